@@ -1,51 +1,109 @@
 from puzzle_runner import Options, PuzzleRunner
+from answer_format import ANSWER_FORMATS
+from day_info import run_list_command
+from http_client import set_offline
+from report import run_report_command
+from session import run_auth_command
+from stats import run_stats_command
 from utils.log_init import set_log_level
 import logging
 from logging import debug, info
 import argparse
+import sys
 
 def main():
     args = parse_args()
     setup_log_level(args.verbosity)
+    if args.offline:
+        set_offline(True)
 
     info("Hello, welcome to Advent Of Code 2019")
-    options = Options(useTestFile=args.test)
+    options = Options(useTestFile=args.test, seed=args.seed,
+                      threads=args.threads, time_limit=args.time_limit, json=args.json,
+                      runs=args.runs, explain=args.explain, answer_format=args.format)
     runner = PuzzleRunner(options)
 
     if args.list:
-        import runners
-        for m in runners.__all__:
-            print("Available day:", m)
+        sys.exit(run_list_command())
     elif args.day:
         runner.run_puzzle(args.day, args.part, args.test)
     elif args.run_all:
         runner.run_all_puzzles(args.part, args.test)
     elif args.add_day:
         runner.add_day(args.add_day)
+    elif args.auth:
+        sys.exit(run_auth_command(args.auth))
+    elif args.stats:
+        sys.exit(run_stats_command(args.board))
+    elif args.report_html:
+        sys.exit(run_report_command(args.report_html))
     else:
         raise Exception("Invalid arguments")
 
+    sys.exit(runner.exit_code)
+
+
+class ArgumentParser(argparse.ArgumentParser):
+    # argparse exits with 2 on usage errors, which is the wrong answer exit code here
+    def error(self, message):
+        self.print_usage(sys.stderr)
+        self.exit(1, f"{self.prog}: error: {message}\n")
+
 
 def parse_args():
-    parser = argparse.ArgumentParser()
+    parser = ArgumentParser()
     parser.add_argument("-v", "--verbosity", action="count", default=0,
                         help="increase output verbosity")
     group = parser.add_mutually_exclusive_group(required=True)
     group.add_argument(
-        "-l", "--list", help="list available days", action="store_true")
+        "-l", "--list", help="list the implemented days, with their titles and solved parts", action="store_true")
     group.add_argument("-r", "--run", help="run the puzzle for a specific day",
                        type=int, dest="day")
     group.add_argument("-a", "--run-all", help="run all puzzles",
                        action="store_true")
     group.add_argument("--add", type=int, metavar="DAY", dest="add_day", help="add a new empty solution for the day")
+    group.add_argument("--auth", choices=["set", "status", "clear"],
+                       help="store, check or remove the session cookie used to download the inputs")
+    group.add_argument("--stats", action="store_true",
+                       help="show your completion times next to the recorded runtimes of the solutions")
+    group.add_argument("--report-html", metavar="DIR",
+                       help="write an HTML page summarizing the recorded answers and runtimes into DIR")
+    parser.add_argument(
+        "--board", type=int, metavar="ID", help="with --stats, also count the completions on a private leaderboard")
     parser.add_argument(
         "-p", "--part", choices=[1, 2], type=int, help="only run a single part of the puzzle(s)")
     parser.add_argument(
         "-t", "--t", type=int, help="use test input TEXT.txt", dest="test", const=0, nargs="?")
+    parser.add_argument(
+        "--seed", type=int, help="seed for the randomized parts of the solvers")
+    parser.add_argument(
+        "--threads", type=int, help="number of threads the solvers may use")
+    parser.add_argument(
+        "--time-limit", type=float, metavar="SECONDS", help="abort each part after the given time")
+    parser.add_argument(
+        "--json", action="store_true", help="print the results as JSON, one object per line")
+    parser.add_argument(
+        "--offline", action="store_true",
+        help="never access the network; also enabled when adventofcode.com can't be resolved")
+    parser.add_argument(
+        "--runs", type=positive_int, default=1, metavar="N",
+        help="run each part N times, and report the min/median/stddev of the timings")
+    parser.add_argument(
+        "--explain", action="store_true", help="show the memory patches and input each Intcode part is run with")
+    parser.add_argument(
+        "--format", choices=ANSWER_FORMATS, default="plain",
+        help="how answers are displayed: as is (the default, ready to be submitted), with grouped digits, or in hex")
 
     return parser.parse_args()
 
 
+def positive_int(value: str) -> int:
+    n = int(value)
+    if n < 1:
+        raise argparse.ArgumentTypeError(f"{value} is not a positive number")
+    return n
+
+
 def setup_log_level(verbosity: int):
     if verbosity == 0:
         set_log_level(logging.WARNING)
@@ -0,0 +1,15 @@
+from typing import Any
+
+# Formats accepted by --format; plain is what gets submitted
+ANSWER_FORMATS = ["plain", "grouped", "hex"]
+
+def format_answer(answer: Any, answer_format: str = "plain") -> str:
+    """Formats an answer for display: as is, with its digits grouped by thousands, or in
+    hexadecimal. Answers that aren't integers are always shown as is."""
+    if not isinstance(answer, int) or isinstance(answer, bool) or answer_format == "plain":
+        return str(answer)
+    if answer_format == "grouped":
+        return f"{answer:,}"
+    if answer_format == "hex":
+        return f"{'-' if answer < 0 else ''}0x{abs(answer):x}"
+    raise ValueError(f"Unknown answer format: {answer_format}")
@@ -0,0 +1,95 @@
+from typing import Any, Callable, Dict, Iterator, Optional, TypeVar
+from contextlib import contextmanager
+from logging import debug
+import json
+import os
+import tempfile
+import threading
+
+T = TypeVar("T")
+
+# Serializes the updates made by the threads of this process; the file lock covers the
+# other processes
+_update_lock = threading.Lock()
+
+@contextmanager
+def file_lock(path: str) -> Iterator[None]:
+    """Holds an exclusive lock on `path`.lock while the block runs"""
+    os.makedirs(os.path.dirname(path), exist_ok=True)
+    with _update_lock, open(f"{path}.lock", "a+") as lock_file:
+        if os.name == "nt":
+            import msvcrt
+            lock_file.seek(0)
+            msvcrt.locking(lock_file.fileno(), msvcrt.LK_LOCK, 1)
+        else:
+            import fcntl
+            fcntl.flock(lock_file.fileno(), fcntl.LOCK_EX)
+        try:
+            yield
+        finally:
+            if os.name == "nt":
+                lock_file.seek(0)
+                msvcrt.locking(lock_file.fileno(), msvcrt.LK_UNLCK, 1)
+            else:
+                fcntl.flock(lock_file.fileno(), fcntl.LOCK_UN)
+
+def load_json(path: str) -> Dict[str, Any]:
+    if not os.path.exists(path):
+        return {}
+    with open(path) as f:
+        return json.load(f)
+
+def update_json(path: str, update: Callable[[Dict[str, Any]], T]) -> T:
+    """Loads the JSON object in the file, lets `update` modify it, and writes it back, all
+    under the file lock. The new content is written to a temporary file first, then renamed,
+    so readers never see a partially written file."""
+    with file_lock(path):
+        content = load_json(path)
+        result = update(content)
+        fd, temp_path = tempfile.mkstemp(dir=os.path.dirname(path), suffix=".tmp")
+        try:
+            with os.fdopen(fd, "w") as f:
+                json.dump(content, f, indent=2, sort_keys=True)
+            os.replace(temp_path, path)
+        except BaseException:
+            os.remove(temp_path)
+            raise
+        return result
+
+class AnswerJournal:
+    """Keeps track of the answers produced by the previous runs, to detect regressions"""
+
+    def get_journal_path(self, day: int) -> str:
+        return f"data/day{day}/answers.json"
+
+    def load(self, day: int) -> Dict[str, str]:
+        return load_json(self.get_journal_path(day))
+
+    def record(self, day: int, part: int, test: Optional[int], answer: str) -> Optional[str]:
+        """Stores the answer, and returns the previous one if it was different"""
+        key = f"part{part}" if not test else f"part{part}_test{test}"
+
+        def update(answers: Dict[str, Any]) -> Optional[str]:
+            previous = answers.get(key)
+            if previous == answer:
+                return None
+            debug("Recording answer %s for day %d %s", answer, day, key)
+            answers[key] = answer
+            return previous
+
+        return update_json(self.get_journal_path(day), update)
+
+class RuntimeJournal:
+    """Keeps the runtime of the last run of each part on the actual input"""
+
+    def get_journal_path(self, day: int) -> str:
+        return f"data/day{day}/runtimes.json"
+
+    def load(self, day: int) -> Dict[str, float]:
+        return load_json(self.get_journal_path(day))
+
+    def record(self, day: int, part: int, elapsed_ms: float) -> None:
+        def update(runtimes: Dict[str, Any]) -> None:
+            runtimes[f"part{part}"] = round(elapsed_ms, 3)
+
+        update_json(self.get_journal_path(day), update)
@@ -0,0 +1,49 @@
+from typing import Any, List, Optional
+import importlib
+import re
+
+import runners
+
+class DayInfo:
+    """What a day's module says about itself, shown by --list"""
+    title: str
+    links: List[str]
+    notes: Optional[str]
+
+    def __init__(self, title: str, links: Optional[List[str]] = None, notes: Optional[str] = None):
+        self.title = title
+        self.links = links or []
+        self.notes = notes
+
+def puzzle_url(day: int) -> str:
+    return f"https://adventofcode.com/2019/day/{day}"
+
+def describe_day(day: int, day_module: Any) -> DayInfo:
+    """The module's description(), or a bare one if it doesn't have any. The link to the
+    puzzle is always listed first."""
+    describe = day_module.__dict__.get("description")
+    info: DayInfo = describe() if describe else DayInfo(f"Day {day}")
+    if puzzle_url(day) not in info.links:
+        info.links.insert(0, puzzle_url(day))
+    return info
+
+def implemented_days() -> List[int]:
+    return sorted(int(m.group(1)) for name in runners.__all__ if (m := re.fullmatch(r"day(\d+)", name)))
+
+def format_day(day: int, day_module: Any) -> List[str]:
+    info = describe_day(day, day_module)
+    parts = [part for part in (1, 2) if f"part{part}" in day_module.__dict__]
+    solved = ", ".join(f"part {part}" for part in parts) if parts else "no part solved"
+    lines = [f"Day {day:2}: {info.title} ({solved})"]
+    lines += [f"        {link}" for link in info.links]
+    if info.notes:
+        lines += [f"        {line}" for line in info.notes.splitlines()]
+    return lines
+
+def run_list_command() -> int:
+    """Implements --list; returns the exit code"""
+    for day in implemented_days():
+        day_module = importlib.import_module(f"runners.day{day}")
+        for line in format_day(day, day_module):
+            print(line)
+    return 0
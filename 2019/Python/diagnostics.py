@@ -0,0 +1,24 @@
+from typing import List
+from logging import debug
+
+class Diagnostics:
+    """Collects the warnings a solver wants to report along with its answer"""
+    warnings: List[str]
+
+    def __init__(self):
+        self.warnings = []
+
+    def warn(self, message: str) -> None:
+        debug("Solver warning: %s", message)
+        self.warnings.append(message)
+
+    def check_input(self, input: List[str]) -> None:
+        """Reports the common input issues that solvers usually silently ignore"""
+        trailing_blank_lines = 0
+        for line in reversed(input):
+            if line.strip():
+                break
+            trailing_blank_lines += 1
+
+        if trailing_blank_lines > 0:
+            self.warn(f"input had {trailing_blank_lines} trailing blank line(s)")
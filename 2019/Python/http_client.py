@@ -0,0 +1,131 @@
+from typing import Any, Dict, Optional
+from logging import debug, info
+import hashlib
+import json
+import os
+import requests
+import socket
+import sys
+import time
+
+USER_AGENT = "github.com/nicodeslandes/AoC2019 puzzle runner"
+
+class OfflineError(Exception):
+    """Raised instead of sending a request in offline mode"""
+    pass
+
+# None until detected, or set with --offline
+_offline: Optional[bool] = None
+
+def set_offline(offline: bool) -> None:
+    global _offline
+    _offline = offline
+
+def is_offline() -> bool:
+    """Whether requests are disabled: with --offline, AOC_OFFLINE set in the environment, or
+    when adventofcode.com can't be resolved"""
+    global _offline
+    if _offline is None:
+        _offline = detect_offline()
+    return _offline
+
+def detect_offline() -> bool:
+    if os.environ.get("AOC_OFFLINE"):
+        return True
+    try:
+        socket.getaddrinfo("adventofcode.com", 443)
+        return False
+    except OSError as e:
+        info("adventofcode.com can't be resolved (%s); running offline", e)
+        return True
+
+class PoliteClient:
+    """HTTP client for adventofcode.com following its automation guidelines: requests are
+    throttled, responses are cached and revalidated with ETag/Last-Modified, and 429/5xx
+    responses are retried with an exponential backoff"""
+
+    def __init__(self, min_interval: float = 5.0, max_retries: int = 4, backoff: float = 2.0,
+                 cache_dir: Optional[str] = None):
+        self.min_interval = min_interval
+        self.max_retries = max_retries
+        self.backoff = backoff
+        self.cache_dir = cache_dir or f"{sys.path[0]}/.data/http_cache"
+        self.last_request: Optional[float] = None
+
+    def get(self, url: str, cookie: Optional[str] = None) -> requests.Response:
+        if is_offline():
+            raise OfflineError(f"offline mode; not requesting {url}")
+
+        cached = self.load_cached(url)
+        headers = {"User-Agent": USER_AGENT}
+        if cached:
+            if cached.get("etag"):
+                headers["If-None-Match"] = cached["etag"]
+            if cached.get("last_modified"):
+                headers["If-Modified-Since"] = cached["last_modified"]
+
+        response = self.send(url, headers, cookie)
+        if response.status_code == 304 and cached:
+            debug("Using the cached response for %s", url)
+            response.status_code = 200
+            response._content = cached["body"].encode()
+        elif response.ok:
+            self.save_cached(url, response)
+        return response
+
+    def send(self, url: str, headers: Dict[str, str], cookie: Optional[str]) -> requests.Response:
+        delay = self.backoff
+        for attempt in range(self.max_retries + 1):
+            self.throttle()
+            response = requests.get(url, headers=headers, cookies=dict(session=cookie) if cookie else None,
+                                    allow_redirects=False, timeout=30)
+            if response.status_code != 429 and response.status_code < 500:
+                return response
+            if attempt == self.max_retries:
+                break
+
+            retry_after = response.headers.get("Retry-After", "")
+            wait = float(retry_after) if retry_after.isdigit() else delay
+            info("HTTP %d for %s; retrying in %.1f s", response.status_code, url, wait)
+            time.sleep(wait)
+            delay *= 2
+        return response
+
+    def throttle(self) -> None:
+        now = time.monotonic()
+        if self.last_request is not None:
+            wait = self.last_request + self.min_interval - now
+            if wait > 0:
+                debug("Throttling request for %.1f s", wait)
+                time.sleep(wait)
+        self.last_request = time.monotonic()
+
+    def get_cache_path(self, url: str) -> str:
+        return f"{self.cache_dir}/{hashlib.sha1(url.encode()).hexdigest()}.json"
+
+    def load_cached(self, url: str) -> Optional[Dict[str, Any]]:
+        path = self.get_cache_path(url)
+        if not os.path.exists(path):
+            return None
+        with open(path) as f:
+            return json.load(f)
+
+    def save_cached(self, url: str, response: requests.Response) -> None:
+        etag = response.headers.get("ETag")
+        last_modified = response.headers.get("Last-Modified")
+        if not etag and not last_modified:
+            return
+
+        os.makedirs(self.cache_dir, exist_ok=True)
+        with open(self.get_cache_path(url), "w") as f:
+            json.dump({"url": url, "etag": etag, "last_modified": last_modified, "body": response.text}, f)
+
+_client: Optional[PoliteClient] = None
+
+def get_client() -> PoliteClient:
+    """Client shared by everything talking to adventofcode.com, so the throttling applies
+    across all the requests"""
+    global _client
+    if _client is None:
+        _client = PoliteClient()
+    return _client
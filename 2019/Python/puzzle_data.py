@@ -1,10 +1,14 @@
 from typing import List, Optional
 from logging import debug, info
 import os
-import requests
-import sys
 
 from requests.models import Response
+from http_client import get_client, is_offline
+from session import SessionStore
+
+class InputMissingError(Exception):
+    """Raised when the puzzle input isn't available locally and can't be downloaded"""
+    pass
 
 class PuzzleData:
     filename: str
@@ -52,11 +56,12 @@ class PuzzleDataLoader:
             content = self.read_test_file(input_cache_name, test)
         else:
             # If there's no local copy, download it
+            if is_offline():
+                raise InputMissingError(f"offline mode; the input for day {day} is expected in {input_cache_name}")
             cookie = self.load_cookie()
-            response: Response = requests.get(f"https://adventofcode.com/2019/day/{day}/input",
-                                   cookies=dict(session=cookie))
+            response: Response = get_client().get(f"https://adventofcode.com/2019/day/{day}/input", cookie)
             if not response.ok:
-                raise Exception(f"Error while downloading input for puzzle {day}: {response.text}")
+                raise InputMissingError(f"Error while downloading input for puzzle {day}: {response.text}")
             content = response.text
 
         self.save_input(content, self.get_cache_dir(day), input_cache_name)
@@ -90,10 +95,8 @@ class PuzzleDataLoader:
         with open(input_cache_name, "w") as f:
             f.write(input)
 
-    def load_cookie(self):
-        cookie_file_dir = f"{sys.path[0]}/.data"
-        if not os.path.exists(cookie_file_dir):
-            os.mkdir(cookie_file_dir)
-
-        with open(f"{cookie_file_dir}/cookie.txt") as cookie_file:
-            return cookie_file.readline().rstrip()
+    def load_cookie(self) -> str:
+        cookie = SessionStore().load()
+        if cookie is None:
+            raise InputMissingError("No session cookie to download the input; store one with --auth set")
+        return cookie
@@ -1,25 +1,63 @@
 
 from re import match
 import runners
-from puzzle_data import PuzzleDataLoader
+from puzzle_data import InputMissingError, PuzzleDataLoader
+from runners.computer import VmError, VmSetup
+from answer_format import format_answer
+from answer_journal import AnswerJournal, RuntimeJournal
+from diagnostics import Diagnostics
+from timing import TimingStats
 from typing import Any, Callable, List, Optional
 from logging import debug, info
 import importlib
+import json
 import re
 import os
+import random
 import shutil
+import signal
 import time
 from inspect import signature
 
 class Options:
     useTestFile: int
+    seed: Optional[int]
+    threads: Optional[int]
+    time_limit: Optional[float]
+    json: bool
+    runs: int
+    explain: bool
+    answer_format: str
 
-    def __init__(self, useTestFile: int):
+    def __init__(self, useTestFile: int, seed: Optional[int] = None, threads: Optional[int] = None,
+                 time_limit: Optional[float] = None, json: bool = False, runs: int = 1, explain: bool = False,
+                 answer_format: str = "plain"):
         self.useTestFile = useTestFile
+        self.seed = seed
+        self.threads = threads
+        self.time_limit = time_limit
+        self.json = json
+        self.runs = runs
+        self.explain = explain
+        self.answer_format = answer_format
+
+class TimeLimitExceeded(Exception):
+    pass
+
+# Exit codes of the runner; when several parts fail, the highest code is reported
+EXIT_OK = 0
+EXIT_WRONG_ANSWER = 2
+EXIT_INPUT_MISSING = 3
+EXIT_VM_ERROR = 4
+EXIT_TIMEOUT = 5
 
 class PuzzleRunner:
     def __init__(self, options: Options):
         self.data_loader = PuzzleDataLoader()
+        self.journal = AnswerJournal()
+        self.runtimes = RuntimeJournal()
+        self.options = options
+        self.exit_code = EXIT_OK
 
     def run_puzzle(self, day: int, part: Optional[int], test: Optional[int]) -> None:
         debug("Starting execution of day %d", day)
@@ -64,26 +102,88 @@ class PuzzleRunner:
         def run_part_if_present(part: int):
             func = day_module.__dict__.get(f'part{part}')
             if func:
-                def run(input: List[str]):
-                    # Check if we can pass an "is_test" argument
+                # Intcode solvers declare how each part sets up the VM; tests run the programs as they are
+                setup: Optional[VmSetup] = None
+                if "setup" in day_module.__dict__:
+                    setup = VmSetup() if test else day_module.setup(part)
+
+                def run(input: List[str], diagnostics: Diagnostics):
+                    # Check if we can pass the optional arguments
                     sig = signature(func)
+                    kwargs: Any = {}
+                    if "diagnostics" in sig.parameters:
+                        kwargs["diagnostics"] = diagnostics
                     if "is_test" in sig.parameters:
-                        return func(input, is_test = test is not None)
-                    else:
-                        return func(input)
-                        
-                self.run(day, part, test, run)
+                        kwargs["is_test"] = test is not None
+                    if "seed" in sig.parameters:
+                        kwargs["seed"] = self.options.seed
+                    if "threads" in sig.parameters:
+                        kwargs["threads"] = self.options.threads
+                    if "setup" in sig.parameters:
+                        kwargs["setup"] = setup or VmSetup()
+                    return func(input, **kwargs)
+
+                # Solvers can optionally describe how they reached their last answer
+                debug_context = day_module.__dict__.get('debug_context')
+                self.run(day, part, test, run, debug_context, setup)
 
         if (part is None or part == 1): run_part_if_present(1)
         if (part is None or part == 2): run_part_if_present(2)
 
-    def run(self, day: int, part: int, test: Optional[int], func: Callable[[List[str]], int]) -> None:
-        puzzle_data = self.data_loader.get_puzzle_data(day, part, test)
+    def run(self, day: int, part: int, test: Optional[int], func: Callable[[List[str], Diagnostics], int],
+            debug_context: Optional[Callable[[], str]] = None, setup: Optional[VmSetup] = None) -> None:
+        try:
+            puzzle_data = self.data_loader.get_puzzle_data(day, part, test)
+        except InputMissingError as e:
+            self.report_failure(day, part, test, "input_missing", f"input missing: {e}", EXIT_INPUT_MISSING)
+            return
         input = puzzle_data.get_data()
         expected_result = puzzle_data.get_expected_result()
+        diagnostics = Diagnostics()
+        diagnostics.check_input(input)
 
-        start = time.perf_counter()
-        result: int = func(input)
+        timings_ms: List[float] = []
+        result: Optional[int] = None
+        for run_index in range(self.options.runs):
+            if self.options.seed is not None:
+                random.seed(self.options.seed)
+
+            # Only the warnings of the first run are reported
+            run_diagnostics = diagnostics if run_index == 0 else Diagnostics()
+            start = time.perf_counter()
+            try:
+                run_result: int = self.run_with_time_limit(func, input, run_diagnostics)
+            except VmError as e:
+                self.report_failure(day, part, test, "vm_error", f"VM error: {e}", EXIT_VM_ERROR)
+                return
+            except TimeLimitExceeded:
+                self.fail(EXIT_TIMEOUT)
+                elapsed_ms = (time.perf_counter() - start) * 1000
+                if self.options.json:
+                    self.print_json(day, part, test, "timeout", None, expected_result, elapsed_ms, diagnostics)
+                else:
+                    print("Day {} part {}{}: ⏱️  time limit exceeded - {:,} ms".format(
+                        day, part, f' test {test}' if test else '', int(elapsed_ms)))
+                    self.print_warnings(diagnostics)
+                return
+
+            timings_ms.append((time.perf_counter() - start) * 1000)
+            if result is None:
+                result = run_result
+            elif run_result != result:
+                diagnostics.warn(f"run {run_index + 1} returned {run_result} instead of {result}")
+
+        elapsed_ms = timings_ms[0]
+        timing = TimingStats(timings_ms) if len(timings_ms) > 1 else None
+        if expected_result is not None and expected_result != str(result):
+            self.fail(EXIT_WRONG_ANSWER)
+        previous_result = self.journal.record(day, part, test, str(result))
+        if not test:
+            self.runtimes.record(day, part, min(timings_ms))
+        if self.options.json:
+            self.print_json(day, part, test, "ok", result, expected_result, elapsed_ms, diagnostics,
+                            previous_result, timing, setup)
+            return
 
         comparison_result = ""
         if expected_result is not None:
@@ -92,9 +192,71 @@ class PuzzleRunner:
             else:
                 comparison_result = f" ❌ ({expected_result} expected)"
 
-        elapsed_ms = (time.perf_counter() - start) * 1000
         print("Day {} part {}{}: {}{} - {:,} ms".format(
-            day, part, f' test {test}' if test else '', result, comparison_result, int(elapsed_ms)))
+            day, part, f' test {test}' if test else '', format_answer(result, self.options.answer_format),
+            comparison_result, int(elapsed_ms)))
+        if timing:
+            print(f"  ⏱️  {timing}")
+        if self.options.explain and setup is not None:
+            print(f"  🔧 VM setup: {setup}")
+        self.print_warnings(diagnostics)
+
+        if previous_result is not None:
+            print(f"  ⚠️  Answer changed since the previous run: {previous_result} -> {result}")
+            if debug_context:
+                for line in debug_context().splitlines():
+                    print(f"    {line}")
+
+    def fail(self, exit_code: int) -> None:
+        self.exit_code = max(self.exit_code, exit_code)
+
+    def report_failure(self, day: int, part: int, test: Optional[int], status: str, message: str,
+                       exit_code: int) -> None:
+        self.fail(exit_code)
+        if self.options.json:
+            print(json.dumps({"day": day, "part": part, "test": test, "status": status, "error": message}))
+        else:
+            print("Day {} part {}{}: ❌ {}".format(day, part, f' test {test}' if test else '', message))
+
+    def print_warnings(self, diagnostics: Diagnostics) -> None:
+        for warning in diagnostics.warnings:
+            print(f"  ⚠️  {warning}")
+
+    def print_json(self, day: int, part: int, test: Optional[int], status: str, result: Optional[int],
+                   expected_result: Optional[str], elapsed_ms: float, diagnostics: Diagnostics,
+                   previous_result: Optional[str] = None, timing: Optional[TimingStats] = None,
+                   setup: Optional[VmSetup] = None) -> None:
+        print(json.dumps({
+            "day": day,
+            "part": part,
+            "test": test,
+            "status": status,
+            "answer": None if result is None else str(result),
+            "expected": expected_result,
+            "previous_answer": previous_result,
+            "elapsed_ms": int(elapsed_ms),
+            "warnings": diagnostics.warnings,
+            "timing": timing.to_json() if timing else None,
+            "setup": {"patches": setup.patches, "inputs": setup.inputs} if setup and self.options.explain else None,
+        }))
+
+    def run_with_time_limit(self, func: Callable[[List[str], Diagnostics], int], input: List[str],
+                            diagnostics: Diagnostics) -> int:
+        time_limit = self.options.time_limit
+        if time_limit is None or not hasattr(signal, "SIGALRM"):
+            # No way to interrupt the solver on Windows
+            return func(input, diagnostics)
+
+        def on_alarm(signum, frame):
+            raise TimeLimitExceeded()
+
+        previous_handler = signal.signal(signal.SIGALRM, on_alarm)
+        signal.setitimer(signal.ITIMER_REAL, time_limit)
+        try:
+            return func(input, diagnostics)
+        finally:
+            signal.setitimer(signal.ITIMER_REAL, 0)
+            signal.signal(signal.SIGALRM, previous_handler)
 
     def run_all_puzzles(self, part: Optional[int], test: Optional[int]):
         info("Running all puzzles")
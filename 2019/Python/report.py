@@ -0,0 +1,100 @@
+from typing import Dict, List, Tuple
+from html import escape
+import os
+import re
+import shutil
+
+from answer_journal import AnswerJournal, RuntimeJournal
+
+# Images saved in a day's data directory are embedded in the report
+VISUALIZATION_EXTENSIONS = (".svg", ".gif", ".png")
+CHART_WIDTH = 400
+CHART_BAR_HEIGHT = 16
+
+STYLE = """
+body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.8em; }
+td.number { text-align: right; font-family: monospace; }
+img { max-width: 600px; display: block; margin: 0.5em 0; }
+"""
+
+def recorded_days() -> List[int]:
+    """Days with a data directory, where the answers and runtimes are journaled"""
+    if not os.path.isdir("data"):
+        return []
+    return sorted(int(m.group(1)) for name in os.listdir("data") if (m := re.fullmatch(r"day(\d+)", name)))
+
+def find_visualizations(day: int) -> List[str]:
+    directory = f"data/day{day}"
+    return sorted(name for name in os.listdir(directory) if name.lower().endswith(VISUALIZATION_EXTENSIONS))
+
+def format_runtime(runtimes: Dict[str, float], key: str) -> str:
+    runtime = runtimes.get(key)
+    return "-" if runtime is None else f"{runtime:,.1f} ms"
+
+def render_timing_chart(timings: List[Tuple[str, float]]) -> str:
+    """Horizontal bar chart of the runtimes, as inline SVG"""
+    if not timings:
+        return "<p>No runtimes recorded yet.</p>"
+    longest = max(ms for _, ms in timings) or 1.0
+    label_width = 110
+    height = CHART_BAR_HEIGHT * len(timings)
+    lines = [f'<svg xmlns="http://www.w3.org/2000/svg" width="{label_width + CHART_WIDTH + 100}" height="{height}">']
+    for i, (label, ms) in enumerate(timings):
+        y = i * CHART_BAR_HEIGHT
+        width = max(1, int(CHART_WIDTH * ms / longest))
+        lines.append(f'<text x="0" y="{y + 12}" font-size="12">{escape(label)}</text>')
+        lines.append(f'<rect x="{label_width}" y="{y + 2}" width="{width}" height="{CHART_BAR_HEIGHT - 4}" fill="#4a7"/>')
+        lines.append(f'<text x="{label_width + width + 4}" y="{y + 12}" font-size="12">{ms:,.1f} ms</text>')
+    lines.append("</svg>")
+    return "\n".join(lines)
+
+def render_report(out_dir: str) -> str:
+    """Builds the page, copying the visualizations it embeds into `out_dir`"""
+    answers_journal = AnswerJournal()
+    runtime_journal = RuntimeJournal()
+    rows: List[str] = []
+    timings: List[Tuple[str, float]] = []
+    visualizations: List[str] = []
+    for day in recorded_days():
+        answers = answers_journal.load(day)
+        runtimes = runtime_journal.load(day)
+        rows.append(
+            f"<tr><td class=\"number\">{day}</td>"
+            + "".join(f"<td class=\"number\">{escape(answers.get(f'part{part}', '-'))}</td>"
+                      f"<td class=\"number\">{format_runtime(runtimes, f'part{part}')}</td>" for part in (1, 2))
+            + "</tr>")
+        for part in (1, 2):
+            if (runtime := runtimes.get(f"part{part}")) is not None:
+                timings.append((f"Day {day} part {part}", runtime))
+
+        for name in find_visualizations(day):
+            target = f"day{day}_{name}"
+            shutil.copyfile(f"data/day{day}/{name}", os.path.join(out_dir, target))
+            visualizations.append(f"<h3>Day {day}: {escape(name)}</h3><img src=\"{escape(target)}\" alt=\"{escape(name)}\">")
+
+    return "\n".join([
+        "<!DOCTYPE html>",
+        "<html><head><meta charset=\"utf-8\"><title>Advent of Code 2019</title>",
+        f"<style>{STYLE}</style></head><body>",
+        "<h1>Advent of Code 2019</h1>",
+        "<h2>Answers</h2>",
+        "<table><tr><th>Day</th><th>Part 1</th><th>Runtime</th><th>Part 2</th><th>Runtime</th></tr>",
+        *rows,
+        "</table>",
+        "<h2>Runtimes</h2>",
+        render_timing_chart(timings),
+        *(["<h2>Visualizations</h2>"] + visualizations if visualizations else []),
+        "</body></html>",
+    ])
+
+def run_report_command(out_dir: str) -> int:
+    """Implements --report-html; returns the exit code"""
+    os.makedirs(out_dir, exist_ok=True)
+    page = render_report(out_dir)
+    path = os.path.join(out_dir, "index.html")
+    with open(path, "w", encoding="utf-8") as f:
+        f.write(page)
+    print(f"Report written to {path}")
+    return 0
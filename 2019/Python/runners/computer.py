@@ -3,6 +3,10 @@ from logging import debug
 from typing import Callable, Dict, Hashable, List, Optional, Tuple
 from enum import Enum
 
+class VmError(Exception):
+    """Raised when the Intcode program can't be executed"""
+    pass
+
 Memory = List[int]
 Result = int
 
@@ -13,6 +17,27 @@ class ExecutionResult(Enum):
     WriteOutput = 4
 
 
+class VmSetup:
+    """What differs between the runs of a program: values patched into the memory before
+    it's started, and the values it's given as input"""
+    patches: Dict[int, int]
+    inputs: List[int]
+
+    def __init__(self, patches: Optional[Dict[int, int]] = None, inputs: Optional[List[int]] = None):
+        self.patches = patches or {}
+        self.inputs = inputs or []
+
+    def apply(self, memory: Memory) -> Memory:
+        for address, value in self.patches.items():
+            memory[address] = value
+        return memory
+
+    def __str__(self) -> str:
+        parts = [f"memory[{address}]={value}" for address, value in sorted(self.patches.items())]
+        if self.inputs:
+            parts.append("input " + ",".join(str(i) for i in self.inputs))
+        return ", ".join(parts) or "no patches or input"
+
 class MemoryLoader:
     @staticmethod
     def load_memory_from_input(input: List[str]) -> Memory:
@@ -38,7 +63,7 @@ class ImmediateParam(Param):
         self._value = value
 
     def get(self) -> int: return self._value
-    def set(self, value: int): raise Exception("Attempted to dereference an immediate parameter!")
+    def set(self, value: int): raise VmError("Attempted to dereference an immediate parameter!")
     
 class InstructionContext:
     current_input: Optional[int] = None
@@ -170,7 +195,7 @@ class Computer:
 
         processor = op_codeRegistry.get(op_code % 100)
         if not processor:
-            raise Exception(f'Unknown opcode: {op_code % 100}')
+            raise VmError(f'Unknown opcode: {op_code % 100}')
         new_pc, result = processor.execute(ctx, self.memory, self.pc, op_code // 100)
 
         debug("Op code %d executed; next pc: %d", op_code, new_pc)
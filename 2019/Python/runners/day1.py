@@ -1,19 +1,26 @@
+from day_info import DayInfo
+from diagnostics import Diagnostics
 from logging import debug
 from typing import List
 
+def description() -> DayInfo:
+    return DayInfo("The Tyranny of the Rocket Equation")
+
 def part1(input: List[str]):
     sum = 0
     for line in input:
         debug("Calculating the fuel for mass %s", line)
-        if line != '':
+        if line.strip() != '':
             sum += calculate_module_fuel(int(line))
     return sum
 
 
-def part2(input: List[str]):
+def part2(input: List[str], diagnostics: Diagnostics):
     sum = 0
-    for line in input:
-        if line == '':
+    for i, line in enumerate(input):
+        if line.strip() == '':
+            if any(l.strip() for l in input[i:]):
+                diagnostics.warn(f"blank line {i + 1} in the middle of the input; ignored the masses after it")
             break
 
         debug("Calculating the fuel for mass %s", line)
@@ -1,30 +1,44 @@
-from runners.computer import Computer, ExecutionResult, InstructionContext, Memory, MemoryLoader
+from day_info import DayInfo
+from runners.computer import Computer, ExecutionResult, InstructionContext, Memory, MemoryLoader, VmSetup
 from typing import List, Optional
 
+def description() -> DayInfo:
+    return DayInfo("1202 Program Alarm",
+                   notes="Part 2 searches for the noun and verb producing 19690720.")
 
-def calc(input: List[str], noun: Optional[int], verb: Optional[int]):
-    memory = MemoryLoader.load_memory_from_input(input)
-    computer = Computer(memory)
+# Last setup the program was run with, for debug_context()
+last_run: Optional[str] = None
+
+def setup(part: int) -> VmSetup:
+    # Part 2 searches for its noun and verb
+    return VmSetup({1: 12}) if part == 1 else VmSetup()
 
-    # Set input
-    if noun: memory[1] = noun
-    if verb: memory[2] = verb
+def calc(input: List[str], setup: VmSetup):
+    global last_run
+    last_run = str(setup)
+    memory = setup.apply(MemoryLoader.load_memory_from_input(input))
+    computer = Computer(memory)
 
     ctx = InstructionContext()
     while computer.run(ctx) != ExecutionResult.Halt:
         pass
 
+    last_run += f" -> {memory[0]}"
     return memory[0]
 
 
-def part1(input: List[str], is_test: bool) -> int:
-    return calc(input, None if is_test else 12, None if is_test else None)
+def part1(input: List[str], setup: VmSetup) -> int:
+    return calc(input, setup)
 
 
-def part2(input: List[str]) -> int:
+def part2(input: List[str], setup: VmSetup) -> int:
     for noun in range(100):
         for verb in range(100):
-            if calc(input, noun, verb) == 19690720:
+            if calc(input, VmSetup({**setup.patches, 1: noun, 2: verb})) == 19690720:
                 return 100 * noun + verb
 
     raise Exception("Unable to find result")
+
+
+def debug_context() -> str:
+    return f"Last run: {last_run}"
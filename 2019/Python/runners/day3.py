@@ -1,6 +1,10 @@
+from day_info import DayInfo
 from logging import debug, info
 from typing import List, Tuple
 
+def description() -> DayInfo:
+    return DayInfo("Crossed Wires")
+
 Position = Tuple[int, int]
 Grid = dict[Position, int]
 
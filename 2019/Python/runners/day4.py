@@ -1,6 +1,10 @@
+from day_info import DayInfo
 from logging import info
 from typing import List
 
+def description() -> DayInfo:
+    return DayInfo("Secure Container")
+
 
 def part1(input: List[str]) -> int:
     input_range = input[0].split("-")
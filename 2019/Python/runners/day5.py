@@ -1,11 +1,20 @@
+from day_info import DayInfo
 from logging import info, lastResort
-from runners.computer import Computer, ExecutionResult, InstructionContext, MemoryLoader
+from runners.computer import Computer, ExecutionResult, InstructionContext, MemoryLoader, VmError, VmSetup
 from typing import List
 
-def part1(input: List[str], is_test: bool) -> int:
-    memory = MemoryLoader.load_memory_from_input(input)
+def description() -> DayInfo:
+    return DayInfo("Sunny with a Chance of Asteroids")
+
+def setup(part: int) -> VmSetup:
+    # ID of the system to test: 1 for the air conditioner, 5 for the thermal radiator controller
+    return VmSetup(inputs=[1 if part == 1 else 5])
+
+def part1(input: List[str], is_test: bool, setup: VmSetup) -> int:
+    memory = setup.apply(MemoryLoader.load_memory_from_input(input))
     computer = Computer(memory)
     ctx = InstructionContext()
+    inputs = iter(setup.inputs)
     last_output = 0
     if is_test:
         computer.run(ctx)
@@ -14,7 +23,9 @@ def part1(input: List[str], is_test: bool) -> int:
     while True:
         result = computer.run(ctx)
         if result == ExecutionResult.ReadInput:
-            ctx.current_input = 1
+            ctx.current_input = next(inputs, None)
+            if ctx.current_input is None:
+                raise VmError("The program needs more input than the setup provides")
         elif result == ExecutionResult.WriteOutput:
             info("Output: %s", ctx.current_outputs)
             last_output = ctx.current_outputs[-1]
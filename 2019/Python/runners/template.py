@@ -1,5 +1,9 @@
+from day_info import DayInfo
 from typing import List
 
+def description() -> DayInfo:
+    return DayInfo("Day title")
+
 def part1(input: List[str]) -> int:
     return 0
 
@@ -0,0 +1,109 @@
+from typing import Optional
+from logging import debug
+import getpass
+import os
+import requests
+import sys
+
+from http_client import OfflineError, get_client
+
+KEYRING_SERVICE = "adventofcode.com"
+KEYRING_USER = "session"
+
+class SessionStore:
+    """Stores the AoC session cookie in the platform keyring, or in a plaintext file when
+    no keyring is available"""
+
+    def get_cookie_file_name(self) -> str:
+        return f"{sys.path[0]}/.data/cookie.txt"
+
+    def get_keyring(self):
+        try:
+            import keyring
+            # Raises if there's no usable backend
+            keyring.get_password(KEYRING_SERVICE, KEYRING_USER)
+            return keyring
+        except Exception as e:
+            debug("Keyring unavailable: %s", e)
+            return None
+
+    def load(self) -> Optional[str]:
+        keyring = self.get_keyring()
+        if keyring:
+            cookie = keyring.get_password(KEYRING_SERVICE, KEYRING_USER)
+            if cookie:
+                return cookie
+
+        cookie_file_name = self.get_cookie_file_name()
+        if not os.path.exists(cookie_file_name):
+            return None
+        with open(cookie_file_name) as cookie_file:
+            return cookie_file.readline().rstrip() or None
+
+    def save(self, cookie: str) -> str:
+        """Stores the cookie, and returns where it was stored"""
+        keyring = self.get_keyring()
+        if keyring:
+            keyring.set_password(KEYRING_SERVICE, KEYRING_USER, cookie)
+            return "the keyring"
+
+        cookie_file_name = self.get_cookie_file_name()
+        os.makedirs(os.path.dirname(cookie_file_name), exist_ok=True)
+        # Readable by the owner only, from the moment the file is created
+        fd = os.open(cookie_file_name, os.O_WRONLY | os.O_CREAT | os.O_TRUNC, 0o600)
+        if os.name != "nt":
+            # Files saved before were created with the default permissions
+            os.fchmod(fd, 0o600)
+        with os.fdopen(fd, "w") as cookie_file:
+            cookie_file.write(cookie + "\n")
+        return cookie_file_name
+
+    def clear(self) -> None:
+        keyring = self.get_keyring()
+        if keyring and keyring.get_password(KEYRING_SERVICE, KEYRING_USER):
+            keyring.delete_password(KEYRING_SERVICE, KEYRING_USER)
+        if os.path.exists(self.get_cookie_file_name()):
+            os.remove(self.get_cookie_file_name())
+
+def validate(cookie: str) -> Optional[bool]:
+    """Checks the cookie is accepted, by requesting the input of the first day.
+    Returns None if adventofcode.com can't be reached."""
+    try:
+        response = get_client().get("https://adventofcode.com/2019/day/1/input", cookie)
+    except (requests.RequestException, OfflineError) as e:
+        debug("Session validation failed: %s", e)
+        return None
+    debug("Session validation: HTTP %d", response.status_code)
+    return response.ok
+
+def run_auth_command(command: str) -> int:
+    """Implements --auth set/status/clear; returns the exit code"""
+    store = SessionStore()
+    if command == "set":
+        cookie = getpass.getpass("Session cookie: ").strip()
+        if not cookie:
+            print("No cookie entered")
+            return 1
+        valid = validate(cookie)
+        if valid is None:
+            print("⚠️  Couldn't reach adventofcode.com to check the cookie; storing it anyway")
+        elif not valid:
+            print("⚠️  The cookie was rejected by adventofcode.com; storing it anyway")
+        print(f"Session cookie stored in {store.save(cookie)}")
+    elif command == "status":
+        cookie = store.load()
+        if cookie is None:
+            print("No session cookie stored")
+            return 1
+        valid = validate(cookie)
+        if valid is None:
+            print("A session cookie is stored, but adventofcode.com can't be reached to check it")
+        elif not valid:
+            print("The stored session cookie is invalid or expired")
+            return 1
+        else:
+            print("The stored session cookie is valid")
+    elif command == "clear":
+        store.clear()
+        print("Session cookie cleared")
+    return 0
@@ -0,0 +1,87 @@
+from typing import Any, Dict, List, Optional
+from logging import debug
+import html
+import re
+
+from answer_journal import RuntimeJournal
+from http_client import OfflineError, get_client
+from session import SessionStore
+
+class PersonalTime:
+    """Time taken to get the star of a part after the puzzle was released, and its rank"""
+    time: str
+    rank: str
+
+    def __init__(self, time: str, rank: str):
+        self.time = time
+        self.rank = rank
+
+def parse_personal_times(page: str) -> Dict[int, List[PersonalTime]]:
+    """Reads the table of the personal leaderboard page; days only have the parts that
+    were completed"""
+    text = html.unescape(re.sub(r"<[^>]*>", "", page))
+    times: Dict[int, List[PersonalTime]] = {}
+    for line in text.splitlines():
+        # Day, then time, rank and score of each part
+        if m := re.match(r"\s*(\d+)\s+(\S+)\s+(\d+)\s+\d+(?:\s+(\S+)\s+(\S+)\s+\S+)?\s*$", line):
+            parts = [PersonalTime(m.group(2), m.group(3))]
+            if m.group(4) and m.group(4) != "-":
+                parts.append(PersonalTime(m.group(4), m.group(5)))
+            times[int(m.group(1))] = parts
+    return times
+
+def count_board_completions(board: Dict[str, Any]) -> Dict[int, int]:
+    """Number of members of a private leaderboard who got both stars, for each day"""
+    completions: Dict[int, int] = {}
+    for member in board.get("members", {}).values():
+        for day, parts in member.get("completion_day_level", {}).items():
+            if "2" in parts:
+                completions[int(day)] = completions.get(int(day), 0) + 1
+    return completions
+
+def format_runtime(runtimes: Dict[str, float], part: int) -> str:
+    runtime = runtimes.get(f"part{part}")
+    return "-" if runtime is None else f"{runtime:,.1f} ms"
+
+def run_stats_command(board_id: Optional[int]) -> int:
+    """Implements --stats; returns the exit code"""
+    cookie = SessionStore().load()
+    if cookie is None:
+        print("No session cookie stored; store one with --auth set")
+        return 1
+
+    client = get_client()
+    try:
+        response = client.get("https://adventofcode.com/2019/leaderboard/self", cookie)
+        if not response.ok:
+            print(f"Couldn't fetch the personal times: HTTP {response.status_code}")
+            return 1
+        times = parse_personal_times(response.text)
+
+        completions: Optional[Dict[int, int]] = None
+        if board_id is not None:
+            response = client.get(f"https://adventofcode.com/2019/leaderboard/private/view/{board_id}.json",
+                                  cookie)
+            if not response.ok:
+                print(f"Couldn't fetch the private leaderboard {board_id}: HTTP {response.status_code}")
+                return 1
+            completions = count_board_completions(response.json())
+    except OfflineError as e:
+        print(f"Can't fetch the stats: {e}")
+        return 1
+
+    debug("Personal times found for %d days", len(times))
+    runtime_journal = RuntimeJournal()
+    header = f"{'Day':>3}  {'Part 1':>10} {'Rank':>6}  {'Part 2':>10} {'Rank':>6}  {'Runtime 1':>12} {'Runtime 2':>12}"
+    if completions is not None:
+        header += f"  {'Board':>5}"
+    print(header)
+    for day in sorted(times):
+        parts = times[day] + [PersonalTime("-", "-")] * (2 - len(times[day]))
+        runtimes = runtime_journal.load(day)
+        line = f"{day:>3}  {parts[0].time:>10} {parts[0].rank:>6}  {parts[1].time:>10} {parts[1].rank:>6}  " \
+               f"{format_runtime(runtimes, 1):>12} {format_runtime(runtimes, 2):>12}"
+        if completions is not None:
+            line += f"  {completions.get(day, 0):>5}"
+        print(line)
+    return 0
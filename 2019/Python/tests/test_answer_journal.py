@@ -0,0 +1,65 @@
+from concurrent.futures import ThreadPoolExecutor
+from typing import Any, Dict
+import json
+import os
+import tempfile
+import unittest
+
+from answer_journal import AnswerJournal, load_json, update_json
+
+THREADS = 8
+UPDATES_PER_THREAD = 50
+
+class ConcurrentJournalTest(unittest.TestCase):
+    """Many threads update the same journal at once: no update may be lost, and the file
+    must stay valid JSON throughout"""
+
+    def setUp(self) -> None:
+        self.previous_dir = os.getcwd()
+        self.temp_dir = tempfile.TemporaryDirectory()
+        os.chdir(self.temp_dir.name)
+
+    def tearDown(self) -> None:
+        os.chdir(self.previous_dir)
+        self.temp_dir.cleanup()
+
+    def test_concurrent_updates(self) -> None:
+        path = os.path.join("data", "journal.json")
+
+        def hammer(thread: int) -> None:
+            for i in range(UPDATES_PER_THREAD):
+                def update(content: Dict[str, Any]) -> None:
+                    content[f"thread{thread}_{i}"] = i
+                    content["count"] = content.get("count", 0) + 1
+                update_json(path, update)
+                # Readers only ever see complete files
+                load_json(path)
+
+        with ThreadPoolExecutor(THREADS) as executor:
+            list(executor.map(hammer, range(THREADS)))
+
+        content = load_json(path)
+        self.assertEqual(THREADS * UPDATES_PER_THREAD, content.pop("count"))
+        expected = {f"thread{t}_{i}": i for t in range(THREADS) for i in range(UPDATES_PER_THREAD)}
+        self.assertEqual(expected, content)
+        self.assertEqual([], [f for f in os.listdir("data") if f.endswith(".tmp")])
+
+    def test_concurrent_answers(self) -> None:
+        journal = AnswerJournal()
+
+        def record(part: int) -> None:
+            for test in range(UPDATES_PER_THREAD):
+                journal.record(7, part, test + 1, f"{part}-{test}")
+
+        with ThreadPoolExecutor(THREADS) as executor:
+            list(executor.map(record, range(1, THREADS + 1)))
+
+        with open(journal.get_journal_path(7)) as f:
+            answers = json.load(f)
+        self.assertEqual(THREADS * UPDATES_PER_THREAD, len(answers))
+        for part in range(1, THREADS + 1):
+            for test in range(UPDATES_PER_THREAD):
+                self.assertEqual(f"{part}-{test}", answers[f"part{part}_test{test + 1}"])
+
+if __name__ == "__main__":
+    unittest.main()
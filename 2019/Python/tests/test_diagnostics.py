@@ -0,0 +1,65 @@
+from contextlib import redirect_stdout
+from typing import List
+import io
+import json
+import unittest
+
+from diagnostics import Diagnostics
+from puzzle_runner import Options, PuzzleRunner
+from tests.test_puzzle_runner import DAY, RunnerTest, answer, day_module
+
+class DiagnosticsTest(unittest.TestCase):
+    def test_warnings_kept_in_order(self) -> None:
+        diagnostics = Diagnostics()
+        self.assertEqual([], diagnostics.warnings)
+        diagnostics.warn("first")
+        diagnostics.warn("second")
+        self.assertEqual(["first", "second"], diagnostics.warnings)
+
+    def test_clean_input(self) -> None:
+        diagnostics = Diagnostics()
+        diagnostics.check_input(["1\n", "\n", "2\n"])
+        diagnostics.check_input([])
+        self.assertEqual([], diagnostics.warnings)
+
+    def test_trailing_blank_lines(self) -> None:
+        diagnostics = Diagnostics()
+        diagnostics.check_input(["1\n", "2\n", "\n", "  \t\n"])
+        self.assertEqual(["input had 2 trailing blank line(s)"], diagnostics.warnings)
+
+    def test_blank_input(self) -> None:
+        diagnostics = Diagnostics()
+        diagnostics.check_input(["\n", "\n", "\n"])
+        self.assertEqual(["input had 3 trailing blank line(s)"], diagnostics.warnings)
+
+def warning_solver(input: List[str], diagnostics: Diagnostics) -> int:
+    diagnostics.warn("took a shortcut")
+    return answer(input)
+
+class ReportedWarningsTest(RunnerTest):
+    """The warnings are shown after the answer, and listed in the JSON output"""
+
+    def run_output(self, options: Options) -> str:
+        with open(f"data/day{DAY}/input.txt", "a") as f:
+            f.write("\n")
+        output = io.StringIO()
+        with redirect_stdout(output):
+            PuzzleRunner(options)._run_puzzle(day_module(part1=warning_solver), DAY, 1, None)
+        return output.getvalue()
+
+    def test_text_output(self) -> None:
+        lines = self.run_output(Options(useTestFile=None)).splitlines()
+        self.assertTrue(lines[0].startswith(f"Day {DAY} part 1: 3 - "))
+        self.assertEqual(["  ⚠️  input had 1 trailing blank line(s)", "  ⚠️  took a shortcut"], lines[1:])
+
+    def test_json_output(self) -> None:
+        result = json.loads(self.run_output(Options(useTestFile=None, json=True)))
+        self.assertEqual("3", result["answer"])
+        self.assertEqual(["input had 1 trailing blank line(s)", "took a shortcut"], result["warnings"])
+
+    def test_first_run_only(self) -> None:
+        result = json.loads(self.run_output(Options(useTestFile=None, json=True, runs=3)))
+        self.assertEqual(["input had 1 trailing blank line(s)", "took a shortcut"], result["warnings"])
+
+if __name__ == "__main__":
+    unittest.main()
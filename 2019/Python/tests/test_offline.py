@@ -0,0 +1,68 @@
+from unittest import mock
+import os
+import socket
+import tempfile
+import unittest
+import urllib.request
+
+import http.client
+import requests
+
+import http_client
+import session
+import stats
+from http_client import OfflineError, get_client, set_offline
+from puzzle_data import InputMissingError, PuzzleDataLoader
+
+def fail(*args, **kwargs):
+    raise AssertionError(f"network access in offline mode: {args}")
+
+class OfflineTest(unittest.TestCase):
+    """In offline mode nothing may reach the network: every way out of the process is
+    stubbed to fail the test, and checked untouched afterwards"""
+
+    def setUp(self) -> None:
+        self.previous_dir = os.getcwd()
+        self.temp_dir = tempfile.TemporaryDirectory()
+        os.chdir(self.temp_dir.name)
+        self.previous_offline = http_client._offline
+        set_offline(True)
+
+        self.stubs = [mock.patch.object(target, name, side_effect=fail) for target, name in [
+            (requests, "get"),
+            (requests.Session, "request"),
+            (urllib.request, "urlopen"),
+            (http.client.HTTPConnection, "connect"),
+            (socket, "getaddrinfo"),
+            (socket, "create_connection"),
+        ]]
+        self.mocks = [stub.start() for stub in self.stubs]
+
+    def tearDown(self) -> None:
+        for stub in self.stubs:
+            stub.stop()
+        for m in self.mocks:
+            m.assert_not_called()
+        http_client._offline = self.previous_offline
+        os.chdir(self.previous_dir)
+        self.temp_dir.cleanup()
+
+    def test_client_refuses_requests(self) -> None:
+        with self.assertRaises(OfflineError):
+            get_client().get("https://adventofcode.com/2019/day/1/input", "cookie")
+
+    def test_missing_input_isnt_downloaded(self) -> None:
+        with self.assertRaises(InputMissingError):
+            PuzzleDataLoader().get_puzzle_data(1, 1, None)
+        self.assertFalse(os.path.exists("data/day1/input.txt"))
+
+    def test_session_isnt_validated(self) -> None:
+        self.assertIsNone(session.validate("cookie"))
+
+    def test_stats_arent_fetched(self) -> None:
+        with mock.patch.object(session.SessionStore, "load", return_value="cookie"), \
+                mock.patch("builtins.print"):
+            self.assertEqual(1, stats.run_stats_command(42))
+
+if __name__ == "__main__":
+    unittest.main()
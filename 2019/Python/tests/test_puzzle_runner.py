@@ -0,0 +1,114 @@
+from contextlib import redirect_stdout
+from types import ModuleType
+from typing import List, Optional
+import io
+import os
+import signal
+import subprocess
+import sys
+import tempfile
+import unittest
+
+import http_client
+from puzzle_runner import (EXIT_INPUT_MISSING, EXIT_OK, EXIT_TIMEOUT, EXIT_VM_ERROR, EXIT_WRONG_ANSWER,
+                           Options, PuzzleRunner)
+from runners.computer import VmError
+
+DAY = 99
+
+def day_module(**parts) -> ModuleType:
+    module = ModuleType(f"runners.day{DAY}")
+    module.__dict__.update(parts)
+    return module
+
+def answer(input: List[str]) -> int:
+    return sum(int(line) for line in input if line.strip())
+
+def vm_error(input: List[str]) -> int:
+    raise VmError("Unknown op code 42 at address 0")
+
+def endless(input: List[str]) -> int:
+    while True:
+        pass
+
+class RunnerTest(unittest.TestCase):
+    """Runs puzzles of a made-up day, whose data is in a temporary directory"""
+
+    def setUp(self) -> None:
+        self.previous_dir = os.getcwd()
+        self.temp_dir = tempfile.TemporaryDirectory()
+        os.chdir(self.temp_dir.name)
+        self.previous_offline = http_client._offline
+        http_client.set_offline(True)
+
+        os.makedirs(f"data/day{DAY}")
+        with open(f"data/day{DAY}/input.txt", "w") as f:
+            f.write("1\n2\n")
+        with open(f"data/day{DAY}/test1_part1.txt", "w") as f:
+            f.write("Result: 3\n\n1\n2\n")
+        with open(f"data/day{DAY}/test2_part1.txt", "w") as f:
+            f.write("Result: 4\n\n1\n2\n")
+
+    def tearDown(self) -> None:
+        http_client._offline = self.previous_offline
+        os.chdir(self.previous_dir)
+        self.temp_dir.cleanup()
+
+    def run_puzzle(self, runner: PuzzleRunner, module: ModuleType, test: Optional[int] = None) -> int:
+        with redirect_stdout(io.StringIO()):
+            runner._run_puzzle(module, DAY, 1, test)
+        return runner.exit_code
+
+class ExitCodeTest(RunnerTest):
+    """The runner's exit code reports the worst failure among the puzzles it ran"""
+
+    def test_ok(self) -> None:
+        runner = PuzzleRunner(Options(useTestFile=None))
+        self.assertEqual(EXIT_OK, self.run_puzzle(runner, day_module(part1=answer)))
+        self.assertEqual(EXIT_OK, self.run_puzzle(runner, day_module(part1=answer), test=1))
+
+    def test_wrong_answer(self) -> None:
+        runner = PuzzleRunner(Options(useTestFile=2))
+        self.assertEqual(EXIT_WRONG_ANSWER, self.run_puzzle(runner, day_module(part1=answer), test=2))
+
+    def test_input_missing(self) -> None:
+        os.remove(f"data/day{DAY}/input.txt")
+        runner = PuzzleRunner(Options(useTestFile=None))
+        self.assertEqual(EXIT_INPUT_MISSING, self.run_puzzle(runner, day_module(part1=answer)))
+
+    def test_vm_error(self) -> None:
+        runner = PuzzleRunner(Options(useTestFile=None))
+        self.assertEqual(EXIT_VM_ERROR, self.run_puzzle(runner, day_module(part1=vm_error)))
+
+    @unittest.skipUnless(hasattr(signal, "SIGALRM"), "no way to interrupt the solver")
+    def test_timeout(self) -> None:
+        runner = PuzzleRunner(Options(useTestFile=None, time_limit=0.05))
+        self.assertEqual(EXIT_TIMEOUT, self.run_puzzle(runner, day_module(part1=endless)))
+
+    def test_worst_failure_wins(self) -> None:
+        runner = PuzzleRunner(Options(useTestFile=None))
+        self.run_puzzle(runner, day_module(part1=vm_error))
+        self.assertEqual(EXIT_VM_ERROR, self.run_puzzle(runner, day_module(part1=answer), test=2))
+        self.assertEqual(EXIT_VM_ERROR, self.run_puzzle(runner, day_module(part1=answer)))
+
+class SolverOptionsTest(RunnerTest):
+    def test_seed_and_threads_passed_to_solvers(self) -> None:
+        received = {}
+
+        def solver(input: List[str], seed: Optional[int], threads: Optional[int]) -> int:
+            received.update(seed=seed, threads=threads)
+            return answer(input)
+
+        runner = PuzzleRunner(Options(useTestFile=None, seed=42, threads=3))
+        self.assertEqual(EXIT_OK, self.run_puzzle(runner, day_module(part1=solver)))
+        self.assertEqual({"seed": 42, "threads": 3}, received)
+
+class CommandLineTest(unittest.TestCase):
+    def test_invalid_arguments(self) -> None:
+        # Not argparse's usual 2, which means a wrong answer
+        advent = os.path.join(os.path.dirname(os.path.dirname(os.path.abspath(__file__))), "advent.py")
+        result = subprocess.run([sys.executable, advent, "--no-such-option"], capture_output=True)
+        self.assertEqual(1, result.returncode)
+
+if __name__ == "__main__":
+    unittest.main()
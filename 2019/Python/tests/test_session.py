@@ -0,0 +1,46 @@
+from unittest import mock
+import os
+import stat
+import tempfile
+import unittest
+
+from session import SessionStore
+
+class SessionStoreTest(unittest.TestCase):
+    def setUp(self) -> None:
+        self.temp_dir = tempfile.TemporaryDirectory()
+        self.cookie_file = os.path.join(self.temp_dir.name, ".data", "cookie.txt")
+        self.stubs = [
+            mock.patch.object(SessionStore, "get_keyring", return_value=None),
+            mock.patch.object(SessionStore, "get_cookie_file_name", return_value=self.cookie_file),
+        ]
+        for stub in self.stubs:
+            stub.start()
+
+    def tearDown(self) -> None:
+        for stub in self.stubs:
+            stub.stop()
+        self.temp_dir.cleanup()
+
+    def test_save_and_load(self) -> None:
+        store = SessionStore()
+        self.assertIsNone(store.load())
+        self.assertEqual(self.cookie_file, store.save("first"))
+        self.assertEqual("first", store.load())
+        store.save("second")
+        self.assertEqual("second", store.load())
+        store.clear()
+        self.assertIsNone(store.load())
+
+    @unittest.skipIf(os.name == "nt", "no POSIX permissions")
+    def test_cookie_file_is_private(self) -> None:
+        SessionStore().save("cookie")
+        self.assertEqual(0o600, stat.S_IMODE(os.stat(self.cookie_file).st_mode))
+
+        # A file saved with the default permissions is restricted on the next save
+        os.chmod(self.cookie_file, 0o644)
+        SessionStore().save("cookie")
+        self.assertEqual(0o600, stat.S_IMODE(os.stat(self.cookie_file).st_mode))
+
+if __name__ == "__main__":
+    unittest.main()
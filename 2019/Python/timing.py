@@ -0,0 +1,50 @@
+from typing import Any, Dict, List
+import statistics
+
+# A run slower than this factor of the median is considered a warm-up run
+WARM_UP_FACTOR = 1.5
+
+class TimingStats:
+    """Summary of the elapsed times of several runs of the same solver"""
+    runs: int
+    min_ms: float
+    median_ms: float
+    stddev_ms: float
+    warm_up_runs: int
+
+    def __init__(self, timings_ms: List[float]):
+        self.runs = len(timings_ms)
+        self.min_ms = min(timings_ms)
+        self.median_ms = statistics.median(timings_ms)
+        self.stddev_ms = statistics.stdev(timings_ms) if len(timings_ms) > 1 else 0.0
+        self.warm_up_runs = count_warm_up_runs(timings_ms)
+
+    def __str__(self) -> str:
+        text = "{} runs: min {:,.1f} ms, median {:,.1f} ms, stddev {:,.1f} ms".format(
+            self.runs, self.min_ms, self.median_ms, self.stddev_ms)
+        if self.warm_up_runs:
+            text += f"; the first {self.warm_up_runs} run(s) look like warm-up"
+        return text
+
+    def to_json(self) -> Dict[str, Any]:
+        return {
+            "runs": self.runs,
+            "min_ms": round(self.min_ms, 3),
+            "median_ms": round(self.median_ms, 3),
+            "stddev_ms": round(self.stddev_ms, 3),
+            "warm_up_runs": self.warm_up_runs,
+        }
+
+def count_warm_up_runs(timings_ms: List[float]) -> int:
+    """Number of leading runs noticeably slower than the median of the second half, which
+    usually come from cold caches or lazy initializations rather than from the solver"""
+    if len(timings_ms) < 3:
+        return 0
+
+    steady_ms = statistics.median(timings_ms[len(timings_ms) // 2:])
+    count = 0
+    for elapsed_ms in timings_ms[:len(timings_ms) // 2]:
+        if elapsed_ms <= steady_ms * WARM_UP_FACTOR:
+            break
+        count += 1
+    return count
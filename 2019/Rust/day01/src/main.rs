@@ -1,18 +1,26 @@
 use std::env;
 use std::fs::File;
 use std::io::Read;
+use std::process;
+
+mod verify;
 
 type Result<T> = ::std::result::Result<T, Box<dyn ::std::error::Error>>;
 
+// Exit codes, shared with the runner
+const EXIT_WRONG_ANSWER: i32 = 2;
+const EXIT_INPUT_MISSING: i32 = 3;
+
 fn main() -> Result<()> {
     let file_name = env::args().nth(1).expect("Enter a file name");
 
     println!("Reading input from {}", file_name);
 
     let mut input = String::new();
-    File::open(file_name)?
-        .read_to_string(&mut input)
-        .expect("Failed to read input file");
+    if let Err(e) = File::open(&file_name).and_then(|mut f| f.read_to_string(&mut input)) {
+        eprintln!("Failed to read input file {}: {}", file_name, e);
+        process::exit(EXIT_INPUT_MISSING);
+    }
 
     let values = input.split_whitespace().collect::<Vec<_>>();
 
@@ -28,6 +36,17 @@ fn main() -> Result<()> {
     }
 
     println!("Result: {}", sum);
+
+    // day01 <input> verify --against <command>
+    if env::args().nth(2).as_deref() == Some("verify") {
+        let command = match (env::args().nth(3).as_deref(), env::args().nth(4)) {
+            (Some("--against"), Some(command)) => command,
+            _ => return Err("Usage: day01 <input> verify --against <command>".into()),
+        };
+        if !verify::verify_against(&command, &file_name, &input, sum as i64)? {
+            process::exit(EXIT_WRONG_ANSWER);
+        }
+    }
     Ok(())
 }
 
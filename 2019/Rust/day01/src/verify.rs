@@ -0,0 +1,77 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+type Result<T> = ::std::result::Result<T, Box<dyn ::std::error::Error>>;
+
+/// Runs an external solver (typically the Python version of the puzzle) on the same
+/// input, and checks it finds the same answer. The input file name replaces `{}` in
+/// the command if present; otherwise the input is sent to the command's stdin.
+/// The answer is the last number written by the command on its output.
+pub fn verify_against(command: &str, file_name: &str, input: &str, answer: i64) -> Result<bool> {
+    let command_line = command.replace("{}", file_name);
+    let mut child = if cfg!(windows) {
+        Command::new("cmd")
+            .args(["/C", &command_line])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?
+    } else {
+        Command::new("sh")
+            .args(["-c", &command_line])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?
+    };
+
+    {
+        let mut stdin = child.stdin.take().unwrap();
+        if !command.contains("{}") {
+            // The command may exit without reading all of its input
+            match stdin.write_all(input.as_bytes()) {
+                Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => return Err(e.into()),
+                _ => (),
+            }
+        }
+    }
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(format!("'{}' failed: {}", command_line, output.status).into());
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let other_answer = last_number(&stdout)
+        .ok_or_else(|| format!("No answer found in the output of '{}'", command_line))?;
+
+    if other_answer == answer {
+        println!("OK: both solvers found {}", answer);
+    } else {
+        println!(
+            "MISMATCH: {} vs {} from '{}'",
+            answer, other_answer, command_line
+        );
+    }
+    Ok(other_answer == answer)
+}
+
+fn last_number(output: &str) -> Option<i64> {
+    output
+        .split(|c: char| !(c.is_ascii_digit() || c == '-'))
+        .rev()
+        .find_map(|w| w.parse().ok())
+}
+
+#[test]
+fn answer_is_last_number() {
+    assert_eq!(Some(5_000_000), last_number("Day 1 part 2: 5000000 ✔️ "));
+    assert_eq!(Some(-3), last_number("Found 2 values\nResult: -3\n"));
+    assert_eq!(None, last_number("No answer"));
+}
+
+#[cfg(unix)]
+#[test]
+fn compare_with_command() {
+    assert!(verify_against("echo 'Result: 42'", "input.txt", "", 42).unwrap());
+    assert!(!verify_against("cat", "input.txt", "12\n13", 12).unwrap());
+    assert!(verify_against("echo {}", "1234", "", 1234).unwrap());
+}
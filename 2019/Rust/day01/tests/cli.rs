@@ -0,0 +1,47 @@
+use std::process::{Command, Output};
+
+fn day01(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_day01"))
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .expect("Failed to run day01")
+}
+
+#[test]
+fn answer_computed() {
+    let output = day01(&["test1.txt"]);
+    assert_eq!(Some(0), output.status.code());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Result: "));
+}
+
+#[test]
+fn answer_from_temp_input() {
+    let path = std::env::temp_dir().join(format!("day01_cli_{}.txt", std::process::id()));
+    std::fs::write(&path, "12\n14\n1969\n100756\n").unwrap();
+    let output = day01(&[path.to_str().unwrap()]);
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(Some(0), output.status.code());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Result: 51316"));
+}
+
+#[test]
+fn input_missing() {
+    let output = day01(&["does_not_exist.txt"]);
+    assert_eq!(Some(3), output.status.code());
+}
+
+#[cfg(unix)]
+#[test]
+fn verify_matching_answer() {
+    let output = day01(&["test1.txt", "verify", "--against", "echo 2"]);
+    assert_eq!(Some(0), output.status.code());
+}
+
+#[cfg(unix)]
+#[test]
+fn verify_wrong_answer() {
+    let output = day01(&["test1.txt", "verify", "--against", "echo 42"]);
+    assert_eq!(Some(2), output.status.code());
+}
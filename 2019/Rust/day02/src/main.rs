@@ -1,9 +1,22 @@
 use std::env;
 use std::fs::File;
 use std::io::Read;
+use std::process;
 
 type Result<T> = ::std::result::Result<T, Box<dyn ::std::error::Error>>;
 
+// Exit code for Intcode VM errors, shared with the runner
+const EXIT_VM_ERROR: i32 = 4;
+
+/// Reports an error of the Intcode VM, and exits with the runner's code for VM errors
+fn vm_error(message: String) -> ! {
+    eprintln!("VM error: {}", message);
+    process::exit(EXIT_VM_ERROR);
+}
+
+/// Number of instructions a noun/verb attempt may execute before it's given up on
+const INSTRUCTION_LIMIT: usize = 100_000;
+
 fn main() -> Result<()> {
     let file_name = env::args().nth(1).expect("Enter a file name");
 
@@ -22,8 +35,8 @@ fn main() -> Result<()> {
 
     for a in 1..100 {
         for b in 1..100 {
-            let result = execute_program(&memory, a, b);
-            if result == 19690720 {
+            let result = execute_with_limit(&memory, a, b, INSTRUCTION_LIMIT);
+            if result == ExecutionResult::Exit(19690720) {
                 println!("Found it! a = {}, b = {}", a, b);
                 break;
             }
@@ -33,24 +46,38 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-fn execute_program(memory: &Vec<usize>, arg1: usize, arg2: usize) -> usize {
+#[derive(Debug, PartialEq)]
+enum ExecutionResult {
+    /// The program halted, with this value at address 0
+    Exit(usize),
+    BudgetExceeded,
+}
+
+/// Runs the program with the given noun and verb, giving up after `max_instructions`
+/// instructions if it hasn't halted by then
+fn execute_with_limit(
+    memory: &[usize],
+    arg1: usize,
+    arg2: usize,
+    max_instructions: usize,
+) -> ExecutionResult {
     let mut ip: usize = 0; // Instruction pointer
-    let mut memory = memory.clone();
+    let mut memory = memory.to_vec();
     // Enter parameters
     memory[1] = arg1;
     memory[2] = arg2;
 
-    loop {
+    for _ in 0..max_instructions {
         match read_op_code(&mut memory, &mut ip) {
             OpCode::Add => execute_instruction(&mut memory, &mut ip, |a, b| a + b),
             OpCode::Mult => execute_instruction(&mut memory, &mut ip, |a, b| a * b),
-            OpCode::Exit => break,
+            OpCode::Exit => return ExecutionResult::Exit(memory[0]),
         }
 
         //println!("Values: {:?}", memory);
     }
 
-    memory[0]
+    ExecutionResult::BudgetExceeded
 }
 
 enum OpCode {
@@ -64,7 +91,7 @@ fn read_op_code(memory: &mut Vec<usize>, ip: &mut usize) -> OpCode {
         1 => OpCode::Add,
         2 => OpCode::Mult,
         99 => OpCode::Exit,
-        _ => panic!("Unknown op code!"),
+        x => vm_error(format!("Unknown op code: {}", x)),
     };
 
     *ip += 1;
@@ -85,3 +112,37 @@ fn execute_instruction(
     memory[index] = operation(x, y);
     *ip += 1;
 }
+
+#[test]
+fn exit_within_limit() {
+    // Example from the puzzle, with noun 9 and verb 10
+    let memory = vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50];
+    assert_eq!(
+        ExecutionResult::Exit(3500),
+        execute_with_limit(&memory, 9, 10, 3)
+    );
+}
+
+#[test]
+fn budget_exceeded() {
+    let memory = vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50];
+    assert_eq!(
+        ExecutionResult::BudgetExceeded,
+        execute_with_limit(&memory, 9, 10, 2)
+    );
+    assert_eq!(
+        ExecutionResult::BudgetExceeded,
+        execute_with_limit(&memory, 9, 10, 0)
+    );
+}
+
+#[test]
+fn input_halts_within_limit() {
+    let memory: Vec<usize> = include_str!("../input.txt")
+        .trim()
+        .split(',')
+        .map(|x| x.parse().unwrap())
+        .collect();
+    let result = execute_with_limit(&memory, 12, 2, INSTRUCTION_LIMIT);
+    assert!(matches!(result, ExecutionResult::Exit(_)), "{:?}", result);
+}
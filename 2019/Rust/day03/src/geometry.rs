@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+#[cfg(test)]
+use std::collections::HashSet;
+
+#[derive(PartialEq, Eq, Hash, Copy, Clone, Debug, PartialOrd, Ord)]
+pub struct Coord {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Coord {
+    pub fn new(x: i32, y: i32) -> Coord {
+        Coord { x, y }
+    }
+
+    pub fn distance_to_origin(&self) -> i32 {
+        self.x.abs() + self.y.abs()
+    }
+
+    pub fn step(&self, direction: Direction, length: i32) -> Coord {
+        match direction {
+            Direction::Up => Coord::new(self.x, self.y + length),
+            Direction::Down => Coord::new(self.x, self.y - length),
+            Direction::Right => Coord::new(self.x + length, self.y),
+            Direction::Left => Coord::new(self.x - length, self.y),
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Direction {
+    Up,
+    Down,
+    Right,
+    Left,
+}
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct Move {
+    pub direction: Direction,
+    pub length: i32,
+}
+
+pub fn parse_wire(line: &str) -> Vec<Move> {
+    line.trim_end()
+        .split(',')
+        .map(|mov| {
+            let mut chars = mov.chars();
+            let direction = match chars.next().expect("Empty move") {
+                'U' => Direction::Up,
+                'D' => Direction::Down,
+                'R' => Direction::Right,
+                'L' => Direction::Left,
+                _ => panic!("Unexpected direction"),
+            };
+            let length = chars.as_str().parse().expect("Failed to parse move");
+            Move { direction, length }
+        })
+        .collect()
+}
+
+/// Axis-aligned segment, including both of its ends
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct Segment {
+    pub start: Coord,
+    pub end: Coord,
+}
+
+impl Segment {
+    pub fn new(start: Coord, end: Coord) -> Segment {
+        assert!(
+            start.x == end.x || start.y == end.y,
+            "Segment {:?}-{:?} isn't axis-aligned",
+            start,
+            end
+        );
+        Segment { start, end }
+    }
+
+    /// Single position segments are considered horizontal
+    pub fn is_horizontal(&self) -> bool {
+        self.start.y == self.end.y
+    }
+
+    pub fn min(&self) -> Coord {
+        self.start.min(self.end)
+    }
+
+    pub fn max(&self) -> Coord {
+        self.start.max(self.end)
+    }
+
+    pub fn len(&self) -> i32 {
+        (self.end.x - self.start.x).abs() + (self.end.y - self.start.y).abs()
+    }
+
+    pub fn contains(&self, pos: Coord) -> bool {
+        let (min, max) = (self.min(), self.max());
+        min.x <= pos.x && pos.x <= max.x && min.y <= pos.y && pos.y <= max.y
+    }
+
+    /// Position where two perpendicular segments cross, if any. Parallel segments
+    /// never intersect; use `overlap` for those.
+    pub fn intersection(&self, other: &Segment) -> Option<Coord> {
+        let (h, v) = match (self.is_horizontal(), other.is_horizontal()) {
+            (true, false) => (self, other),
+            (false, true) => (other, self),
+            _ => return None,
+        };
+        let pos = Coord::new(v.start.x, h.start.y);
+        if h.contains(pos) && v.contains(pos) {
+            Some(pos)
+        } else {
+            None
+        }
+    }
+
+    /// Part shared by two parallel segments on the same line, going in increasing
+    /// coordinates
+    pub fn overlap(&self, other: &Segment) -> Option<Segment> {
+        let same_line = if self.is_horizontal() {
+            other.is_horizontal() && self.start.y == other.start.y
+        } else {
+            !other.is_horizontal() && self.start.x == other.start.x
+        };
+        if !same_line {
+            return None;
+        }
+
+        let from = self.min().max(other.min());
+        let to = self.max().min(other.max());
+        if from <= to {
+            Some(Segment::new(from, to))
+        } else {
+            None
+        }
+    }
+
+    /// All the positions of the segment, from start to end
+    pub fn positions(&self) -> impl Iterator<Item = Coord> {
+        let start = self.start;
+        let (dx, dy) = (
+            (self.end.x - start.x).signum(),
+            (self.end.y - start.y).signum(),
+        );
+        (0..=self.len()).map(move |i| Coord::new(start.x + i * dx, start.y + i * dy))
+    }
+}
+
+/// Positions where the two wires cross, with the combined number of steps each wire
+/// takes to first reach it
+pub type Intersections = HashMap<Coord, i32>;
+
+#[cfg(test)]
+/// Generates random axis-aligned segments in a small area, so that they often meet
+fn random_segments(count: usize, seed: u64) -> Vec<Segment> {
+    let mut state = seed;
+    let mut next = |n: i32| {
+        // xorshift64
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state % n as u64) as i32 - n / 2
+    };
+
+    (0..count)
+        .map(|_| {
+            let start = Coord::new(next(10), next(10));
+            let length = next(10);
+            let end = if next(2) == 0 {
+                Coord::new(start.x + length, start.y)
+            } else {
+                Coord::new(start.x, start.y + length)
+            };
+            Segment::new(start, end)
+        })
+        .collect()
+}
+
+#[test]
+fn shared_positions_match_brute_force() {
+    let segments = random_segments(200, 2019);
+    for a in &segments {
+        let a_positions: HashSet<Coord> = a.positions().collect();
+        for b in &segments {
+            let expected: HashSet<Coord> =
+                b.positions().filter(|p| a_positions.contains(p)).collect();
+
+            let found: HashSet<Coord> = match (a.intersection(b), a.overlap(b)) {
+                (Some(_), Some(_)) => panic!("{:?} and {:?} both cross and overlap", a, b),
+                (Some(pos), None) => vec![pos].into_iter().collect(),
+                (None, Some(overlap)) => overlap.positions().collect(),
+                (None, None) => HashSet::new(),
+            };
+            assert_eq!(expected, found, "{:?} and {:?}", a, b);
+        }
+    }
+}
+
+#[test]
+fn contains_matches_positions() {
+    let segments = random_segments(100, 42);
+    for s in &segments {
+        let positions: HashSet<Coord> = s.positions().collect();
+        assert_eq!(s.len() as usize + 1, positions.len());
+        for x in -10..10 {
+            for y in -10..10 {
+                let pos = Coord::new(x, y);
+                assert_eq!(positions.contains(&pos), s.contains(pos));
+            }
+        }
+    }
+}
+
+#[test]
+fn overlap_is_symmetric() {
+    let segments = random_segments(100, 7);
+    for a in &segments {
+        for b in &segments {
+            assert_eq!(a.overlap(b), b.overlap(a));
+            assert_eq!(a.intersection(b), b.intersection(a));
+        }
+    }
+}
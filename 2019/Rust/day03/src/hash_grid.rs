@@ -0,0 +1,35 @@
+use crate::geometry::{Coord, Intersections, Move};
+use std::collections::HashMap;
+
+/// Number of steps needed to first reach each position of the wire
+fn wire_positions(wire: &[Move]) -> HashMap<Coord, i32> {
+    let mut positions = HashMap::new();
+    let mut current_pos = Coord::new(0, 0);
+    let mut current_length = 0;
+
+    for mov in wire {
+        for _ in 0..mov.length {
+            current_pos = current_pos.step(mov.direction, 1);
+            current_length += 1;
+            positions.entry(current_pos).or_insert(current_length);
+        }
+    }
+    positions
+}
+
+/// Finds the intersections by storing every single position visited by the wires
+pub fn intersections(wire1: &[Move], wire2: &[Move]) -> Intersections {
+    let wire1_positions = wire_positions(wire1);
+    let wire2_positions = wire_positions(wire2);
+    let origin = Coord::new(0, 0);
+
+    wire1_positions
+        .iter()
+        .filter(|(pos, _)| **pos != origin)
+        .filter_map(|(pos, length1)| {
+            wire2_positions
+                .get(pos)
+                .map(|length2| (*pos, length1 + length2))
+        })
+        .collect()
+}
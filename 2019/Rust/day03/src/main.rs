@@ -1,32 +1,45 @@
-use core::cmp::{Eq, PartialEq};
-use core::hash::Hash;
-use std::collections::{HashMap, HashSet};
+use crate::geometry::{parse_wire, Intersections, Move};
 use std::env;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
+use std::time::Instant;
+
+mod geometry;
+mod hash_grid;
+mod sweep;
 
 type Result<T> = ::std::result::Result<T, Box<dyn ::std::error::Error>>;
 
-#[derive(PartialEq, Eq, Hash, Copy, Clone)]
-struct Coord {
-    x: i32,
-    y: i32,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Algorithm {
+    Hash,
+    Sweep,
 }
 
-impl Coord {
-    fn new(x: i32, y: i32) -> Coord {
-        Coord { x, y }
+impl Algorithm {
+    fn parse(name: &str) -> Result<Algorithm> {
+        match name {
+            "hash" => Ok(Algorithm::Hash),
+            "sweep" => Ok(Algorithm::Sweep),
+            _ => Err(format!("Unknown algorithm '{}'; expected hash or sweep", name).into()),
+        }
     }
-}
 
-struct Cell {
-    length: i32,
+    fn intersections(self, wire1: &[Move], wire2: &[Move]) -> Intersections {
+        match self {
+            Algorithm::Hash => hash_grid::intersections(wire1, wire2),
+            Algorithm::Sweep => sweep::intersections(wire1, wire2),
+        }
+    }
 }
 
-type Grid = HashMap<Coord, Cell>;
-
 fn main() -> Result<()> {
     let file_name = env::args().nth(1).expect("Enter a file name");
+    let algorithm = match env::args().skip_while(|a| a != "--algo").nth(1) {
+        Some(name) => Algorithm::parse(&name)?,
+        None => Algorithm::Hash,
+    };
+    let compare = env::args().any(|a| a == "--compare");
 
     println!("Reading input from {}", file_name);
 
@@ -38,28 +51,40 @@ fn main() -> Result<()> {
     reader.read_line(&mut line1)?;
     reader.read_line(&mut line2)?;
 
-    //println!("Line1: {}", line1);
-    //println!("Line2: {}", line2);
-
-    let line1_pos = read_line_positions(line1.trim_end().split(",").collect());
-    let line2_pos = read_line_positions(line2.trim_end().split(",").collect());
+    let wire1 = parse_wire(&line1);
+    let wire2 = parse_wire(&line2);
 
-    //println!("Line1 pos: {:?}", line1_pos);
-    //println!("Line2 pos: {:?}", line2_pos);
-
-    let line1_positions: HashSet<Coord> = line1_pos.keys().map(|x| *x).collect();
-    let line2_positions: HashSet<Coord> = line2_pos.keys().map(|x| *x).collect();
+    if compare {
+        let hash = timed("hash", || Algorithm::Hash.intersections(&wire1, &wire2));
+        let sweep = timed("sweep", || Algorithm::Sweep.intersections(&wire1, &wire2));
+        if hash != sweep {
+            return Err("The hash and sweep algorithms found different intersections".into());
+        }
+        println!(
+            "Both algorithms found the same {} intersections",
+            hash.len()
+        );
+    }
 
-    let closest_intersection = line1_positions
-        .intersection(&line2_positions)
-        .min_by_key(|pos| line1_pos[*pos].length + line2_pos[*pos].length);
+    let intersections = algorithm.intersections(&wire1, &wire2);
 
-    match closest_intersection {
+    match intersections
+        .keys()
+        .min_by_key(|pos| pos.distance_to_origin())
+    {
         Some(pos) => println!(
-            "Closest intersection: {},{}; distance: {}",
+            "Closest intersection to the origin: {},{}; distance: {}",
             pos.x,
             pos.y,
-            line1_pos[&pos].length + line2_pos[&pos].length
+            pos.distance_to_origin()
+        ),
+        _ => println!("No intersection found!"),
+    }
+
+    match intersections.iter().min_by_key(|(_, length)| **length) {
+        Some((pos, length)) => println!(
+            "Closest intersection: {},{}; distance: {}",
+            pos.x, pos.y, length
         ),
         _ => println!("No intersection found!"),
     }
@@ -67,55 +92,60 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-// fn distance_to_origin(pos: (i32, i32)) -> i32 {
-//     pos.0.abs() + pos.1.abs()
-// }
-
-fn read_line_positions(moves: Vec<&str>) -> Grid {
-    let mut grid = Grid::new();
-    let mut current_pos = Coord { x: 0, y: 0 };
-    let mut current_length = 0;
-
-    for mov in moves {
-        let mut chars = mov.chars();
-        let direction = chars.next().expect("Empty move");
-        let movement_length: i32 = chars.as_str().parse().expect("Failed to parse move");
-        let movement: fn(Coord) -> Coord = match direction {
-            'U' => |c| Coord::new(c.x, c.y + 1),
-            'D' => |c| Coord::new(c.x, c.y - 1),
-            'R' => |c| Coord::new(c.x + 1, c.y),
-            'L' => |c| Coord::new(c.x - 1, c.y),
-            _ => panic!("Unexpected direction"),
-        };
-
-        add_positions(
-            &mut grid,
-            &mut current_pos,
-            &mut current_length,
-            movement_length,
-            movement,
-        );
-    }
-    grid
+fn timed<T>(name: &str, f: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let result = f();
+    println!("{}: {:?}", name, start.elapsed());
+    result
 }
 
-fn add_positions(
-    positions: &mut Grid,
-    current_pos: &mut Coord,
-    current_length: &mut i32,
-    length: i32,
-    movement: fn(Coord) -> Coord,
-) {
-    for _ in 0..length {
-        *current_pos = movement(*current_pos);
-        *current_length += 1;
-        if !positions.contains_key(current_pos) {
-            positions.insert(
-                *current_pos,
-                Cell {
-                    length: *current_length,
-                },
-            );
-        }
+#[cfg(test)]
+fn check_algorithms_agree(line1: &str, line2: &str) -> Intersections {
+    let wire1 = parse_wire(line1);
+    let wire2 = parse_wire(line2);
+    let hash = Algorithm::Hash.intersections(&wire1, &wire2);
+    assert_eq!(hash, Algorithm::Sweep.intersections(&wire1, &wire2));
+    hash
+}
+
+#[test]
+fn examples() {
+    let examples = [
+        ("R8,U5,L5,D3", "U7,R6,D4,L4", 6, 30),
+        (
+            "R75,D30,R83,U83,L12,D49,R71,U7,L72",
+            "U62,R66,U55,R34,D71,R55,D58,R83",
+            159,
+            610,
+        ),
+        (
+            "R98,U47,R26,D63,R33,U87,L62,D20,R33,U53,R51",
+            "U98,R91,D20,R16,D67,R40,U7,R15,U6,R7",
+            135,
+            410,
+        ),
+    ];
+    for (line1, line2, distance, length) in examples.iter() {
+        let intersections = check_algorithms_agree(line1, line2);
+        let closest = intersections.keys().map(|p| p.distance_to_origin()).min();
+        assert_eq!(Some(*distance), closest);
+        assert_eq!(Some(*length), intersections.values().cloned().min());
     }
 }
+
+#[test]
+fn overlapping_wires() {
+    // Both wires run along the same lines, in both directions
+    let intersections = check_algorithms_agree("R10,U2,L5,D4", "U2,R8,L3,D1,R6");
+    assert!(intersections.len() > 2);
+}
+
+#[test]
+fn real_input() {
+    let file = match File::open("input.txt") {
+        Ok(file) => file,
+        Err(_) => return,
+    };
+    let lines: Vec<String> = BufReader::new(file).lines().map(|l| l.unwrap()).collect();
+    check_algorithms_agree(&lines[0], &lines[1]);
+}
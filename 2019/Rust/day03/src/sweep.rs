@@ -0,0 +1,151 @@
+use crate::geometry::{Coord, Intersections, Move, Segment};
+use std::collections::{BTreeMap, HashMap};
+
+/// Part of a wire; `steps` is the length of the wire before that segment
+#[derive(Debug, Clone, Copy)]
+struct WireSegment {
+    segment: Segment,
+    steps: i32,
+}
+
+impl WireSegment {
+    /// Number of steps for the wire to reach the position, which must be on the segment
+    fn steps_to(&self, pos: Coord) -> i32 {
+        self.steps + Segment::new(self.segment.start, pos).len()
+    }
+}
+
+fn segments(wire: &[Move]) -> Vec<WireSegment> {
+    let mut result = vec![];
+    let mut current_pos = Coord::new(0, 0);
+    let mut steps = 0;
+    for mov in wire {
+        let end = current_pos.step(mov.direction, mov.length);
+        result.push(WireSegment {
+            segment: Segment::new(current_pos, end),
+            steps,
+        });
+        current_pos = end;
+        steps += mov.length;
+    }
+    result
+}
+
+/// Keeps the lowest step count reached by one wire for each position
+fn record(steps: &mut HashMap<Coord, i32>, pos: Coord, count: i32) {
+    let entry = steps.entry(pos).or_insert(count);
+    *entry = (*entry).min(count);
+}
+
+enum Event {
+    Insert(WireSegment),
+    Query(WireSegment),
+    Remove(WireSegment),
+}
+
+impl Event {
+    fn sort_key(&self) -> (i32, u8) {
+        match self {
+            Event::Insert(s) => (s.segment.min().x, 0),
+            Event::Query(s) => (s.segment.start.x, 1),
+            Event::Remove(s) => (s.segment.max().x, 2),
+        }
+    }
+}
+
+/// Sweeps a vertical line from left to right over the horizontal segments of one wire
+/// and the vertical segments of the other one, to find where they cross
+fn sweep_crossings(
+    horizontal: &[WireSegment],
+    vertical: &[WireSegment],
+    horizontal_steps: &mut HashMap<Coord, i32>,
+    vertical_steps: &mut HashMap<Coord, i32>,
+) {
+    let mut events: Vec<Event> = horizontal
+        .iter()
+        .flat_map(|s| vec![Event::Insert(*s), Event::Remove(*s)])
+        .chain(vertical.iter().map(|s| Event::Query(*s)))
+        .collect();
+    events.sort_by_key(|e| e.sort_key());
+
+    // Horizontal segments crossing the sweep line, indexed by their y coordinate
+    let mut active: BTreeMap<i32, Vec<WireSegment>> = BTreeMap::new();
+    for event in events {
+        match event {
+            Event::Insert(s) => active.entry(s.segment.start.y).or_default().push(s),
+            Event::Remove(s) => {
+                let at_y = active.get_mut(&s.segment.start.y).unwrap();
+                let index = at_y
+                    .iter()
+                    .position(|a| (a.segment, a.steps) == (s.segment, s.steps))
+                    .unwrap();
+                at_y.swap_remove(index);
+            }
+            Event::Query(v) => {
+                let (min_y, max_y) = (v.segment.min().y, v.segment.max().y);
+                for h in active.range(min_y..=max_y).flat_map(|(_, at_y)| at_y) {
+                    if let Some(pos) = h.segment.intersection(&v.segment) {
+                        record(horizontal_steps, pos, h.steps_to(pos));
+                        record(vertical_steps, pos, v.steps_to(pos));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Finds the positions shared by parallel segments lying on the same line
+fn collinear_overlaps(
+    segments1: &[WireSegment],
+    segments2: &[WireSegment],
+    steps1: &mut HashMap<Coord, i32>,
+    steps2: &mut HashMap<Coord, i32>,
+) {
+    let line_of = |s: &WireSegment| {
+        if s.segment.is_horizontal() {
+            (true, s.segment.start.y)
+        } else {
+            (false, s.segment.start.x)
+        }
+    };
+    let mut lines: HashMap<(bool, i32), Vec<WireSegment>> = HashMap::new();
+    for s in segments2 {
+        lines.entry(line_of(s)).or_default().push(*s);
+    }
+
+    for s1 in segments1 {
+        for s2 in lines.get(&line_of(s1)).into_iter().flatten() {
+            if let Some(overlap) = s1.segment.overlap(&s2.segment) {
+                for pos in overlap.positions() {
+                    record(steps1, pos, s1.steps_to(pos));
+                    record(steps2, pos, s2.steps_to(pos));
+                }
+            }
+        }
+    }
+}
+
+/// Finds the intersections from the segments making up the wires, without visiting
+/// each of their positions
+pub fn intersections(wire1: &[Move], wire2: &[Move]) -> Intersections {
+    let (horizontal1, vertical1): (Vec<_>, Vec<_>) = segments(wire1)
+        .into_iter()
+        .partition(|s| s.segment.is_horizontal());
+    let (horizontal2, vertical2): (Vec<_>, Vec<_>) = segments(wire2)
+        .into_iter()
+        .partition(|s| s.segment.is_horizontal());
+
+    let mut steps1 = HashMap::new();
+    let mut steps2 = HashMap::new();
+    sweep_crossings(&horizontal1, &vertical2, &mut steps1, &mut steps2);
+    sweep_crossings(&horizontal2, &vertical1, &mut steps2, &mut steps1);
+    collinear_overlaps(&horizontal1, &horizontal2, &mut steps1, &mut steps2);
+    collinear_overlaps(&vertical1, &vertical2, &mut steps1, &mut steps2);
+
+    let origin = Coord::new(0, 0);
+    steps1
+        .into_iter()
+        .filter(|(pos, _)| *pos != origin)
+        .map(|(pos, s1)| (pos, s1 + steps2[&pos]))
+        .collect()
+}
@@ -2,9 +2,19 @@ use std::cell::Cell;
 use std::env;
 use std::fs::File;
 use std::io::Read;
+use std::process;
 
 type Result<T> = ::std::result::Result<T, Box<dyn ::std::error::Error>>;
 
+// Exit code for Intcode VM errors, shared with the runner
+const EXIT_VM_ERROR: i32 = 4;
+
+/// Reports an error of the Intcode VM, and exits with the runner's code for VM errors
+fn vm_error(message: String) -> ! {
+    eprintln!("VM error: {}", message);
+    process::exit(EXIT_VM_ERROR);
+}
+
 fn main() -> Result<()> {
     let file_name = env::args().nth(1).expect("Enter a file name");
     let input: i32 = env::args()
@@ -124,7 +134,7 @@ fn read_op_code(memory: &mut Vec<Cell<i32>>, ip: &Cell<usize>) -> (OpCode, u32)
         7 => OpCode::LessThan,
         8 => OpCode::Equals,
         99 => OpCode::Exit,
-        x => panic!("Unknown op code: {}", x),
+        x => vm_error(format!("Unknown op code: {}", x)),
     };
 
     ip.set(ip.get() + 1);
@@ -176,7 +186,7 @@ fn get_parameter(
     let parameter_mode = match *parameter_modes % 10 {
         0 => ParameterMode::Position,
         1 => ParameterMode::Immediate,
-        x => panic!(format!("Incorrect parameter mode: {}", x)),
+        x => vm_error(format!("Incorrect parameter mode: {}", x)),
     };
     *parameter_modes /= 10;
 
@@ -204,7 +214,7 @@ impl<'a> Parameter {
     fn set(&self, value: i32) -> () {
         match self {
             Parameter::CellReference(cell) => unsafe { cell.as_ref().unwrap().set(value) },
-            Parameter::ImmediateValue(value) => panic!(format!(
+            Parameter::ImmediateValue(value) => vm_error(format!(
                 "Attempted to write value {} to an immediate parameter",
                 value
             )),
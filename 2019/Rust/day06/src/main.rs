@@ -1,9 +1,12 @@
+use crate::orbit_counts::OrbitCounts;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::env;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
+mod orbit_counts;
+
 type Result<T> = ::std::result::Result<T, Box<dyn ::std::error::Error>>;
 
 #[derive(Debug)]
@@ -14,10 +17,10 @@ struct GraphNode {
 }
 
 impl<'a> GraphNode {
-    fn new(key: &String, parent: Option<String>) -> GraphNode {
+    fn new(key: impl Into<String>, parent: Option<String>) -> GraphNode {
         GraphNode {
-            key: key.clone(),
-            parent: parent,
+            key: key.into(),
+            parent,
             children: HashSet::new(),
         }
     }
@@ -35,45 +38,100 @@ impl<'a> Graph {
         }
     }
 
-    fn add_node_link(&mut self, parent: &String, child: &String) {
-        self.add_or_get_node(child, &Some(parent.clone()));
-        let parent_node = self.add_or_get_node(parent, &None);
+    /// Records that the child orbits the parent, adding the nodes if needed
+    fn add_node_link(&mut self, parent: &str, child: &str) {
+        self.add_or_get_node(child, Some(parent));
+        let parent_node = self.add_or_get_node(parent, None);
 
-        parent_node.children.insert(child.clone());
+        if !parent_node.children.contains(child) {
+            parent_node.children.insert(child.to_string());
+        }
     }
 
-    fn add_or_get_node(&mut self, key: &String, parent: &Option<String>) -> &mut GraphNode {
-        let node = self
-            .nodes
-            .entry(key.clone())
-            .or_insert(GraphNode::new(key, parent.clone()));
+    fn add_or_get_node(&mut self, key: &str, parent: Option<&str>) -> &mut GraphNode {
+        // Only allocate the key when the node is new
+        if !self.nodes.contains_key(key) {
+            self.nodes
+                .insert(key.to_string(), GraphNode::new(key, None));
+        }
+        let node = self.nodes.get_mut(key).unwrap();
 
-        match parent {
-            Some(_p) => node.parent = parent.clone(),
-            _ => (),
-        };
+        if let Some(p) = parent {
+            node.parent = Some(p.to_string());
+        }
 
         node
     }
 
-    fn get_node(&self, key: &String) -> Option<&GraphNode> {
+    fn get_node(&self, key: &str) -> Option<&GraphNode> {
         self.nodes.get(key)
     }
+
+    /// Removes the orbit of the child around the parent, leaving the child as the root of
+    /// a separate graph. Returns false if the child doesn't orbit the parent.
+    fn remove_node_link(&mut self, parent: &str, child: &str) -> bool {
+        match self.nodes.get_mut(child) {
+            Some(node) if node.parent.as_deref() == Some(parent) => node.parent = None,
+            _ => return false,
+        }
+        self.nodes.get_mut(parent).unwrap().children.remove(child);
+        true
+    }
+
+    /// Makes the node orbit `new_parent` instead of its current parent, taking along the
+    /// nodes orbiting it
+    fn move_subtree(&mut self, key: &str, new_parent: &str) -> Result<()> {
+        for k in &[key, new_parent] {
+            if self.get_node(k).is_none() {
+                return Err(format!("Unknown object: {}", k).into());
+            }
+        }
+        if key == new_parent || get_node_parents(self, new_parent).contains(key) {
+            return Err(format!("{} can't orbit {}, which orbits it", key, new_parent).into());
+        }
+
+        if let Some(parent) = self.get_node(key).unwrap().parent.clone() {
+            self.remove_node_link(&parent, key);
+        }
+        self.add_node_link(new_parent, key);
+        Ok(())
+    }
 }
 
 fn main() -> Result<()> {
     let file_name = env::args().nth(1).expect("Enter a file name");
 
     let file = File::open(file_name)?;
-    let reader = BufReader::new(file);
-
-    let mut orbits = Graph::new();
+    let mut orbits = read_orbits(BufReader::new(file))?;
+    //println!("Values: {:?}", orbits);
 
-    for (_, line) in reader.lines().enumerate() {
-        let orbit = parse_line(line.unwrap());
-        orbits.add_node_link(&orbit[0], &orbit[1])
+    // Orbits changed with --move A)B (B orbits A instead) and --detach B, in that order
+    let mut counts = OrbitCounts::compute(&orbits);
+    let args: Vec<String> = env::args().collect();
+    for option in args.windows(2) {
+        match option[0].as_str() {
+            "--move" => {
+                let (parent, child) =
+                    parse_line(&option[1]).ok_or(format!("Invalid orbit: {}", option[1]))?;
+                counts.move_subtree(&mut orbits, child, parent)?;
+                println!(
+                    "{} now orbits {}; total count: {}",
+                    child,
+                    parent,
+                    counts.total()
+                );
+            }
+            "--detach" => {
+                let key = option[1].as_str();
+                let parent = orbits.get_node(key).and_then(|n| n.parent.clone());
+                if !parent.is_some_and(|p| counts.remove_node_link(&mut orbits, &p, key)) {
+                    return Err(format!("{} doesn't orbit anything", key).into());
+                }
+                println!("{} detached; total count: {}", key, counts.total());
+            }
+            _ => (),
+        }
     }
-    //println!("Values: {:?}", orbits);
 
     let mut orbit_counts: HashMap<String, u32> = HashMap::new();
     for key in orbits.nodes.keys() {
@@ -86,15 +144,22 @@ fn main() -> Result<()> {
 
     println!("Total count: {}", orbit_counts.values().sum::<u32>());
 
+    // --what-if A)B: total count if B orbited A instead
+    if let Some(orbit) = env::args().skip_while(|a| a != "--what-if").nth(1) {
+        let (parent, child) = parse_line(&orbit).ok_or(format!("Invalid orbit: {}", orbit))?;
+        let total = counts.total_if_moved(&orbits, child, parent)?;
+        println!("Total count if {} orbited {}: {}", child, parent, total);
+    }
+
     let mut distance_to_root: HashMap<String, u32> = HashMap::new();
     for key in orbits.nodes.keys() {
         compute_distance_to_root_for(&orbits, &mut distance_to_root, key);
     }
 
-    let position_you = "YOU".to_string();
-    let position_san = "SAN".to_string();
+    let position_you = "YOU";
+    let position_san = "SAN";
 
-    let common_parents = find_common_parents(&orbits, &position_you, &position_san);
+    let common_parents = find_common_parents(&orbits, position_you, position_san);
 
     println!("Common parent: {:?}", common_parents);
 
@@ -112,42 +177,40 @@ fn main() -> Result<()> {
     // Or get the node farthest from the origin
     let closest_node = common_parents
         .iter()
-        .max_by_key(|k| distance_to_root.get(&k.to_string()).unwrap())
+        .max_by_key(|k| distance_to_root[**k])
         .expect("No common node found!");
     println!("Leaf: {:?}", closest_node);
 
-    let orbital_transfer_count = distance_to_root[&position_you]
-        - distance_to_root[&closest_node.to_string()]
-        + distance_to_root[&position_san]
-        - distance_to_root[&closest_node.to_string()]
+    let orbital_transfer_count = distance_to_root[position_you] - distance_to_root[*closest_node]
+        + distance_to_root[position_san]
+        - distance_to_root[*closest_node]
         - 2; // Don't count the hop to the closest planet
 
     println!("Hops: {}", orbital_transfer_count);
     Ok(())
 }
-fn find_common_parents<'a>(orbits: &'a Graph, key1: &String, key2: &String) -> HashSet<&'a String> {
-    let key1_parents: HashSet<&String> = get_node_parents(&orbits, key1);
-    let key2_parents: HashSet<&String> = get_node_parents(&orbits, key2);
+
+/// Finds the nodes that both keys orbit, directly or not
+fn find_common_parents<'a>(orbits: &'a Graph, key1: &str, key2: &str) -> HashSet<&'a str> {
+    let key1_parents: HashSet<&str> = get_node_parents(orbits, key1);
+    let key2_parents: HashSet<&str> = get_node_parents(orbits, key2);
 
     println!("Key1 parents: {:?}", key1_parents);
     println!("Key2 parents: {:?}", key2_parents);
 
-    key1_parents
-        .intersection(&key2_parents)
-        .map(|s| *s)
-        .collect()
+    key1_parents.intersection(&key2_parents).copied().collect()
 }
 
-fn get_node_parents<'a>(orbits: &'a Graph, key: &String) -> HashSet<&'a String> {
-    let mut key_parents: HashSet<&'a String> = HashSet::new();
+fn get_node_parents<'a>(orbits: &'a Graph, key: &str) -> HashSet<&'a str> {
+    let mut key_parents: HashSet<&'a str> = HashSet::new();
 
     let mut node = orbits.get_node(key);
     while node.is_some() {
         let parent = &node.unwrap().parent;
         match parent {
             Some(p) => {
-                key_parents.insert(&p);
-                node = orbits.get_node(&p);
+                key_parents.insert(p);
+                node = orbits.get_node(p);
             }
             None => node = None,
         }
@@ -156,12 +219,12 @@ fn get_node_parents<'a>(orbits: &'a Graph, key: &String) -> HashSet<&'a String>
     key_parents
 }
 
-fn compute_orbit_count_for(orbits: &Graph, orbit_counts: &mut HashMap<String, u32>, key: &String) {
+fn compute_orbit_count_for(orbits: &Graph, orbit_counts: &mut HashMap<String, u32>, key: &str) {
     let node = orbits.get_node(key).unwrap();
 
     // First, compute the orbit counts for the children
     for child in node.children.iter() {
-        compute_orbit_count_for(orbits, orbit_counts, &child);
+        compute_orbit_count_for(orbits, orbit_counts, child);
     }
 
     // Then for the node
@@ -179,7 +242,7 @@ fn compute_orbit_count_for(orbits: &Graph, orbit_counts: &mut HashMap<String, u3
 fn compute_distance_to_root_for(
     orbits: &Graph,
     distances_to_root: &mut HashMap<String, u32>,
-    key: &String,
+    key: &str,
 ) -> u32 {
     match distances_to_root.get(key) {
         Some(value) => *value,
@@ -187,17 +250,124 @@ fn compute_distance_to_root_for(
             let node = orbits.get_node(key).unwrap();
 
             let distance_to_root = match &node.parent {
-                Some(p) => 1 + compute_distance_to_root_for(orbits, distances_to_root, &p),
+                Some(p) => 1 + compute_distance_to_root_for(orbits, distances_to_root, p),
                 None => 0,
             };
 
-            distances_to_root.insert(key.clone(), distance_to_root);
+            distances_to_root.insert(key.to_string(), distance_to_root);
             distance_to_root
         }
     }
 }
 
-fn parse_line<'a>(line: String) -> Vec<String> {
-    let split = line.split(")").map(|s| s.to_string());
-    split.collect::<Vec<_>>()
+/// Reads the orbits line by line, reusing the same buffer, so that large inputs never
+/// need to be loaded in memory all at once
+fn read_orbits(mut reader: impl BufRead) -> Result<Graph> {
+    let mut orbits = Graph::new();
+    let mut line = String::new();
+    let mut line_number = 0;
+
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        line_number += 1;
+
+        let orbit = line.trim_end();
+        if orbit.is_empty() {
+            continue;
+        }
+        match parse_line(orbit) {
+            Some((parent, child)) => orbits.add_node_link(parent, child),
+            None => return Err(format!("Invalid orbit on line {}: {}", line_number, orbit).into()),
+        }
+    }
+    Ok(orbits)
+}
+
+fn parse_line(line: &str) -> Option<(&str, &str)> {
+    let separator = line.find(')')?;
+    Some((&line[..separator], &line[separator + 1..]))
+}
+
+#[test]
+fn read_example() {
+    let input = "COM)B\nB)C\nC)D\n\nD)E\r\n";
+    let orbits = read_orbits(input.as_bytes()).unwrap();
+    assert_eq!(5, orbits.nodes.len());
+    assert_eq!(Some("D"), orbits.get_node("E").unwrap().parent.as_deref());
+}
+
+#[test]
+fn add_link() {
+    let mut graph = Graph::new();
+    graph.add_node_link("COM", "B");
+    assert_eq!(Some("COM"), graph.get_node("B").unwrap().parent.as_deref());
+    assert_eq!(None, graph.get_node("COM").unwrap().parent);
+}
+
+#[test]
+fn common_parents() {
+    let orbits = read_orbits("COM)B\nB)C\nB)D\nD)E\n".as_bytes()).unwrap();
+    let expected: HashSet<&str> = vec!["B", "COM"].into_iter().collect();
+    assert_eq!(expected, find_common_parents(&orbits, "C", "E"));
+}
+
+#[test]
+fn edit_links() {
+    let mut orbits = read_orbits("COM)B\nB)C\nC)D\n".as_bytes()).unwrap();
+    assert!(!orbits.remove_node_link("B", "D"));
+    assert!(orbits.remove_node_link("C", "D"));
+    assert_eq!(None, orbits.get_node("D").unwrap().parent);
+    assert!(orbits.get_node("C").unwrap().children.is_empty());
+
+    orbits.move_subtree("C", "COM").unwrap();
+    orbits.move_subtree("D", "C").unwrap();
+    assert_eq!(Some("COM"), orbits.get_node("C").unwrap().parent.as_deref());
+    assert!(!orbits.get_node("B").unwrap().children.contains("C"));
+    assert!(orbits.get_node("C").unwrap().children.contains("D"));
+
+    assert!(orbits.move_subtree("C", "D").is_err());
+    assert!(orbits.move_subtree("C", "C").is_err());
+    assert!(orbits.move_subtree("C", "X").is_err());
+}
+
+#[test]
+fn read_invalid_line() {
+    let error = read_orbits("COM)B\nBC\n".as_bytes()).unwrap_err();
+    assert_eq!("Invalid orbit on line 2: BC", error.to_string());
+}
+
+#[test]
+fn read_million_orbits() {
+    // Orbits generated on the fly, so the input itself is never held in memory
+    struct GeneratedOrbits {
+        next: usize,
+        pending: Vec<u8>,
+    }
+    impl std::io::Read for GeneratedOrbits {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.pending.is_empty() && self.next < 1_000_000 {
+                let parent = if self.next == 0 {
+                    "COM".to_string()
+                } else {
+                    format!("N{}", (self.next - 1) / 2)
+                };
+                self.pending = format!("{})N{}\n", parent, self.next).into_bytes();
+                self.next += 1;
+            }
+            let len = buf.len().min(self.pending.len());
+            buf[..len].copy_from_slice(&self.pending[..len]);
+            self.pending.drain(..len);
+            Ok(len)
+        }
+    }
+
+    let reader = BufReader::new(GeneratedOrbits {
+        next: 0,
+        pending: vec![],
+    });
+    let orbits = read_orbits(reader).unwrap();
+    assert_eq!(1_000_001, orbits.nodes.len());
 }
@@ -0,0 +1,178 @@
+use crate::{get_node_parents, Graph, Result};
+use std::collections::HashMap;
+
+/// Orbit counts of a graph, kept up to date as its orbits change: the depth of each
+/// object (how many objects it orbits, directly or not), and the size of its subtree
+/// (itself and all the objects orbiting it)
+#[derive(Debug, PartialEq, Eq)]
+pub struct OrbitCounts {
+    depths: HashMap<String, u32>,
+    sizes: HashMap<String, u32>,
+    /// Sum of the depths: the number of direct and indirect orbits
+    total: u64,
+}
+
+impl OrbitCounts {
+    /// Counts computed from scratch, going down from the roots of the graph
+    pub fn compute(graph: &Graph) -> OrbitCounts {
+        let mut depths = HashMap::new();
+        let mut order: Vec<&str> = vec![];
+        for root in graph.nodes.values().filter(|n| n.parent.is_none()) {
+            depths.insert(root.key.clone(), 0);
+            order.push(&root.key);
+        }
+        let mut i = 0;
+        while i < order.len() {
+            let depth = depths[order[i]];
+            for child in &graph.nodes[order[i]].children {
+                depths.insert(child.clone(), depth + 1);
+                order.push(child);
+            }
+            i += 1;
+        }
+
+        // The children come after their parent, so sum up the sizes from the end
+        let mut sizes: HashMap<String, u32> = HashMap::new();
+        for key in order.iter().rev() {
+            let children = &graph.nodes[*key].children;
+            let size = 1 + children.iter().map(|c| sizes[c]).sum::<u32>();
+            sizes.insert(key.to_string(), size);
+        }
+
+        let total = depths.values().map(|d| *d as u64).sum();
+        OrbitCounts {
+            depths,
+            sizes,
+            total,
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Total number of orbits if `key` orbited `new_parent` instead, without changing
+    /// anything: all the objects of its subtree move by the same number of orbits
+    pub fn total_if_moved(&self, graph: &Graph, key: &str, new_parent: &str) -> Result<u64> {
+        let depth = |k: &str| {
+            self.depths
+                .get(k)
+                .copied()
+                .ok_or(format!("Unknown object: {}", k))
+        };
+        let shift = depth(new_parent)? as i64 + 1 - depth(key)? as i64;
+        if key == new_parent || get_node_parents(graph, new_parent).contains(key) {
+            return Err(format!("{} can't orbit {}, which orbits it", key, new_parent).into());
+        }
+        Ok((self.total as i64 + shift * self.sizes[key] as i64) as u64)
+    }
+
+    /// Moves the subtree of `key` under `new_parent` in the graph, updating the counts of
+    /// the subtree and of the ancestors it leaves and joins
+    pub fn move_subtree(&mut self, graph: &mut Graph, key: &str, new_parent: &str) -> Result<()> {
+        let old_parent = graph.get_node(key).and_then(|n| n.parent.clone());
+        graph.move_subtree(key, new_parent)?;
+
+        let size = self.sizes[key] as i64;
+        if let Some(old_parent) = old_parent {
+            self.resize_ancestors(graph, &old_parent, -size);
+        }
+        self.resize_ancestors(graph, new_parent, size);
+        let shift = self.depths[new_parent] as i64 + 1 - self.depths[key] as i64;
+        self.shift_subtree(graph, key, shift);
+        Ok(())
+    }
+
+    /// Removes the orbit of the child around the parent, the child's subtree becoming a
+    /// separate graph. Returns false if the child doesn't orbit the parent.
+    pub fn remove_node_link(&mut self, graph: &mut Graph, parent: &str, child: &str) -> bool {
+        if !graph.remove_node_link(parent, child) {
+            return false;
+        }
+        self.resize_ancestors(graph, parent, -(self.sizes[child] as i64));
+        self.shift_subtree(graph, child, -(self.depths[child] as i64));
+        true
+    }
+
+    /// Adds `delta` to the size of `key` and of all the objects it orbits
+    fn resize_ancestors(&mut self, graph: &Graph, key: &str, delta: i64) {
+        let mut node = graph.get_node(key);
+        while let Some(n) = node {
+            let size = self.sizes.get_mut(&n.key).unwrap();
+            *size = (*size as i64 + delta) as u32;
+            node = n.parent.as_deref().and_then(|p| graph.get_node(p));
+        }
+    }
+
+    /// Adds `delta` to the depth of all the objects in the subtree of `key`
+    fn shift_subtree(&mut self, graph: &Graph, key: &str, delta: i64) {
+        let mut pending = vec![key];
+        while let Some(key) = pending.pop() {
+            let depth = self.depths.get_mut(key).unwrap();
+            *depth = (*depth as i64 + delta) as u32;
+            pending.extend(graph.nodes[key].children.iter().map(|c| c.as_str()));
+        }
+        self.total = (self.total as i64 + delta * self.sizes[key] as i64) as u64;
+    }
+}
+
+#[cfg(test)]
+use crate::read_orbits;
+
+#[test]
+fn example_counts() {
+    let input = "COM)B\nB)C\nC)D\nD)E\nE)F\nB)G\nG)H\nD)I\nE)J\nJ)K\nK)L\n";
+    let mut orbits = read_orbits(input.as_bytes()).unwrap();
+    let mut counts = OrbitCounts::compute(&orbits);
+    assert_eq!(42, counts.total());
+
+    // E and the 4 objects orbiting it get 2 orbits closer to COM
+    assert_eq!(
+        42 - 2 * 5,
+        counts.total_if_moved(&orbits, "E", "B").unwrap()
+    );
+    counts.move_subtree(&mut orbits, "E", "B").unwrap();
+    assert_eq!(32, counts.total());
+    assert_eq!(OrbitCounts::compute(&orbits), counts);
+
+    assert!(counts.total_if_moved(&orbits, "B", "L").is_err());
+    assert!(counts.move_subtree(&mut orbits, "B", "L").is_err());
+    assert_eq!(OrbitCounts::compute(&orbits), counts);
+}
+
+#[test]
+fn incremental_matches_full_recompute() {
+    // Random tree of 200 objects, then random moves and removals of orbits
+    let mut seed: u64 = 42;
+    let mut random = |n: usize| {
+        seed = seed
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        (seed >> 33) as usize % n
+    };
+    let key = |i: usize| format!("N{}", i);
+    let mut orbits = Graph::new();
+    for i in 1..200 {
+        orbits.add_node_link(&key(random(i)), &key(i));
+    }
+
+    let mut counts = OrbitCounts::compute(&orbits);
+    let mut moves = 0;
+    for _ in 0..2000 {
+        let (a, b) = (key(random(200)), key(random(200)));
+        if random(10) == 0 {
+            let parent = orbits.get_node(&a).unwrap().parent.clone();
+            if let Some(parent) = parent {
+                assert!(counts.remove_node_link(&mut orbits, &parent, &a));
+            }
+        } else if let Ok(expected) = counts.total_if_moved(&orbits, &a, &b) {
+            counts.move_subtree(&mut orbits, &a, &b).unwrap();
+            assert_eq!(expected, counts.total());
+            moves += 1;
+        } else {
+            assert!(counts.move_subtree(&mut orbits, &a, &b).is_err());
+        }
+        assert_eq!(OrbitCounts::compute(&orbits), counts);
+    }
+    assert!(moves > 1000);
+}
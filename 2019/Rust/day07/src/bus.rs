@@ -0,0 +1,144 @@
+use std::collections::VecDeque;
+
+/// Where a message sent on the bus is delivered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Route {
+    /// The input queue of the computer with this address
+    Computer(usize),
+    /// Out of the bus, where the messages wait to be taken with `take_external`
+    External,
+}
+
+/// Decides where the messages sent on the bus go. Messages carry whatever addressing
+/// the router needs, such as their sender or their destination.
+pub trait Router<M> {
+    /// Routes of the message, which is dropped when there are none
+    fn route(&mut self, message: &M) -> Vec<Route>;
+}
+
+impl<M, F: FnMut(&M) -> Vec<Route>> Router<M> for F {
+    fn route(&mut self, message: &M) -> Vec<Route> {
+        self(message)
+    }
+}
+
+/// Connects computers with addresses 0 to N - 1: each one has a queue of messages
+/// waiting to be read, filled by the router with the messages sent on the bus
+pub struct IntcodeBus<M, R> {
+    queues: Vec<VecDeque<M>>,
+    router: R,
+    external: Vec<M>,
+    /// Number of messages queued and read so far
+    activity: u64,
+    dropped: usize,
+}
+
+impl<M: Clone, R: Router<M>> IntcodeBus<M, R> {
+    pub fn new(computers: usize, router: R) -> IntcodeBus<M, R> {
+        IntcodeBus {
+            queues: (0..computers).map(|_| VecDeque::new()).collect(),
+            router,
+            external: vec![],
+            activity: 0,
+            dropped: 0,
+        }
+    }
+
+    /// Sends the message wherever the router says, copying it for each route
+    pub fn send(&mut self, message: M) {
+        let routes = self.router.route(&message);
+        if routes.is_empty() {
+            self.dropped += 1;
+        }
+        for route in routes {
+            match route {
+                Route::Computer(address) => self.deliver(address, message.clone()),
+                Route::External => self.external.push(message.clone()),
+            }
+        }
+    }
+
+    /// Queues the message for a computer, without going through the router
+    pub fn deliver(&mut self, address: usize, message: M) {
+        match self.queues.get_mut(address) {
+            Some(queue) => {
+                queue.push_back(message);
+                self.activity += 1;
+            }
+            None => self.dropped += 1,
+        }
+    }
+
+    /// Next message waiting for the computer
+    pub fn receive(&mut self, address: usize) -> Option<M> {
+        let message = self.queues.get_mut(address)?.pop_front();
+        if message.is_some() {
+            self.activity += 1;
+        }
+        message
+    }
+
+    /// Messages routed out of the bus since the last call, oldest first
+    pub fn take_external(&mut self) -> Vec<M> {
+        std::mem::take(&mut self.external)
+    }
+
+    pub fn is_quiet(&self) -> bool {
+        self.queues.iter().all(|q| q.is_empty())
+    }
+
+    /// Number of messages waiting to be read, for each computer
+    pub fn queue_lengths(&self) -> Vec<usize> {
+        self.queues.iter().map(|q| q.len()).collect()
+    }
+
+    pub fn activity(&self) -> u64 {
+        self.activity
+    }
+
+    /// Messages with no route, or routed to an address without any computer
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+}
+
+#[test]
+fn messages_follow_the_router() {
+    // Messages are (sender, value); even values are copied out of the bus
+    let mut bus = IntcodeBus::new(3, |&(from, value): &(usize, i64)| {
+        let mut routes = vec![Route::Computer(from + 1)];
+        if value % 2 == 0 {
+            routes.push(Route::External);
+        }
+        routes
+    });
+    bus.send((0, 1));
+    bus.send((0, 2));
+    bus.send((1, 3));
+    bus.send((2, 5));
+
+    assert_eq!(vec![0, 2, 1], bus.queue_lengths());
+    assert_eq!(1, bus.dropped());
+    assert_eq!(Some((0, 1)), bus.receive(1));
+    assert_eq!(Some((0, 2)), bus.receive(1));
+    assert_eq!(None, bus.receive(1));
+    assert_eq!(vec![(0, 2)], bus.take_external());
+    assert!(bus.take_external().is_empty());
+
+    assert!(!bus.is_quiet());
+    assert_eq!(Some((1, 3)), bus.receive(2));
+    assert!(bus.is_quiet());
+    assert_eq!(None, bus.receive(3));
+    // 3 messages queued and read
+    assert_eq!(6, bus.activity());
+}
+
+#[test]
+fn delivery_skips_the_router() {
+    let mut bus = IntcodeBus::new(2, |_: &i64| vec![]);
+    bus.send(1);
+    bus.deliver(1, 2);
+    bus.deliver(2, 3);
+    assert_eq!(vec![0, 1], bus.queue_lengths());
+    assert_eq!(2, bus.dropped());
+}
@@ -1,131 +1,232 @@
+use crate::memory::Memory;
+use crate::topology::Topology;
 use std::cell::Cell;
 use std::env;
 use std::fs::File;
-use std::io::stdout;
 use std::io::Read;
-use std::io::Write;
+use std::process;
+use std::time::{Duration, Instant};
+
+// Same bus as day23, which uses the rest of the module
+#[allow(dead_code)]
+mod bus;
+mod memory;
+mod topology;
 
 type Result<T> = ::std::result::Result<T, Box<dyn ::std::error::Error>>;
 
+// Exit code for Intcode VM errors, shared with the runner
+const EXIT_VM_ERROR: i32 = 4;
+
+/// Reports an error of the Intcode VM, and exits with the runner's code for VM errors
+fn vm_error(message: String) -> ! {
+    eprintln!("VM error: {}", message);
+    process::exit(EXIT_VM_ERROR);
+}
+
 fn main() -> Result<()> {
     let file_name = env::args().nth(1).expect("Enter a file name");
+    let args: Vec<String> = env::args().skip(2).collect();
+    // The amplifiers are in a feedback loop, unless wired differently with --topology
+    let mut topology = Topology::ring(5);
+    let mut mode = SearchMode::Exhaustive;
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--exhaustive" => mode = SearchMode::Exhaustive,
+            "--heuristic" => mode = SearchMode::Heuristic,
+            "--compare" => mode = SearchMode::Compare,
+            "--topology" => {
+                i += 1;
+                let spec = args.get(i).ok_or("Missing topology after --topology")?;
+                topology = Topology::parse(spec)?;
+            }
+            x => return Err(format!("Unknown option: {}", x).into()),
+        }
+        i += 1;
+    }
 
     let mut instructions = String::new();
     File::open(file_name)?
         .read_to_string(&mut instructions)
         .expect("Failed to read input file");
 
-    let memory = instructions
-        .split(",")
-        .map(|x| {
-            Cell::new(
-                x.parse::<i64>()
-                    .expect(format!("Failed to parse {}", x).as_str()),
+    let memory = Memory::parse(&instructions);
+
+    match mode {
+        SearchMode::Exhaustive => {
+            println!(
+                "Max output: {}",
+                exhaustive_search(&memory, &topology).max_output
             )
-        })
-        .collect::<Vec<_>>();
-
-    let mut max_output = 0;
-
-    for i in 5..10 {
-        for j in 5..10 {
-            if j != i {
-                for k in 5..10 {
-                    if k != i && k != j {
-                        for l in 5..10 {
-                            if l != i && l != j && l != k {
-                                for m in 5..10 {
-                                    if m != i && m != j && m != k && m != l {
-                                        let phase_settings = vec![i, j, k, l, m];
-                                        //println!("Trying out {:?}", phase_settings);
-                                        let output = run_amplifiers(&memory, phase_settings);
-                                        //println!("Output: {}", output);
-                                        print!(".");
-                                        stdout().flush().ok();
-                                        if output > max_output {
-                                            max_output = output;
-                                        }
-                                        //run_amplifier(&memory, i, input: i64)
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
+        }
+        SearchMode::Heuristic => {
+            println!(
+                "Max output: {}",
+                heuristic_search(&memory, &topology).max_output
+            )
+        }
+        SearchMode::Compare => {
+            let exhaustive = timed(|| exhaustive_search(&memory, &topology));
+            let heuristic = timed(|| heuristic_search(&memory, &topology));
+            println!("Search      Max output  Phases           Runs  Time");
+            for (name, (result, duration)) in [("exhaustive", exhaustive), ("heuristic", heuristic)]
+            {
+                println!(
+                    "{:<10}  {:>10}  {:<15}  {:>4}  {:?}",
+                    name,
+                    result.max_output,
+                    format!("{:?}", result.phase_settings),
+                    result.runs,
+                    duration
+                );
             }
         }
     }
 
-    println!("\nMax output: {}", max_output);
     Ok(())
 }
 
-fn run_amplifiers(instructions: &Vec<Cell<i64>>, phase_settings: Vec<i64>) -> i64 {
-    let mut current_input = 0;
-    let mut contexts: Vec<_> = phase_settings
-        .iter()
-        .map(|s| ExecutionContext::new(instructions, &vec![*s]))
-        .collect();
-
-    // Initialize the 1st amplifier input
-    while contexts.iter().any(|c| !c.ended) {
-        for i in 0..contexts.len() {
-            if contexts[i].ended {
-                continue;
+enum SearchMode {
+    Exhaustive,
+    Heuristic,
+    Compare,
+}
+
+struct SearchResult {
+    max_output: i64,
+    phase_settings: Vec<i64>,
+    runs: usize,
+}
+
+fn timed<T>(f: impl FnOnce() -> T) -> (T, Duration) {
+    let start = Instant::now();
+    let result = f();
+    (result, start.elapsed())
+}
+
+/// Phase settings of the amplifiers in feedback loop mode, starting at 5
+fn phases(topology: &Topology) -> Vec<i64> {
+    (5..5 + topology.amplifiers() as i64).collect()
+}
+
+/// Tries out all the permutations of the phase settings (5! for 5 amplifiers)
+fn exhaustive_search(memory: &Memory, topology: &Topology) -> SearchResult {
+    let mut result = SearchResult {
+        max_output: i64::MIN,
+        phase_settings: vec![],
+        runs: 0,
+    };
+
+    for phase_settings in permutations(&phases(topology)) {
+        let output = run_amplifiers(memory, topology, &phase_settings);
+        result.runs += 1;
+        if output > result.max_output {
+            result.max_output = output;
+            result.phase_settings = phase_settings;
+        }
+    }
+
+    result
+}
+
+/// Hill-climbing search: starting from each rotation of the phase settings, keep on
+/// swapping the pair of phases that improves the output the most, until we reach a
+/// local maximum
+fn heuristic_search(memory: &Memory, topology: &Topology) -> SearchResult {
+    let mut result = SearchResult {
+        max_output: i64::MIN,
+        phase_settings: vec![],
+        runs: 0,
+    };
+
+    let phases = phases(topology);
+    for start in 0..phases.len() {
+        let mut current: Vec<i64> = (0..phases.len())
+            .map(|i| phases[(start + i) % phases.len()])
+            .collect();
+        let mut current_output = run_amplifiers(memory, topology, &current);
+        result.runs += 1;
+
+        loop {
+            let mut best_neighbour = None;
+            for i in 0..current.len() - 1 {
+                for j in i + 1..current.len() {
+                    let mut neighbour = current.clone();
+                    neighbour.swap(i, j);
+                    let output = run_amplifiers(memory, topology, &neighbour);
+                    result.runs += 1;
+                    if output > best_neighbour.as_ref().map_or(current_output, |(o, _)| *o) {
+                        best_neighbour = Some((output, neighbour));
+                    }
+                }
             }
-            // println!("Running amplifier {}", i);
-            // println!("===================",);
-            let context = &mut contexts[i];
-            // println!(
-            //     "Memory:\n{:?}",
-            //     context.memory.iter().map(|x| x.get()).collect::<Vec<_>>()
-            // );
-
-            let result = run_amplifier(context, current_input);
-            // println!(
-            //     "Memory:\n{:?}",
-            //     context.memory.iter().map(|x| x.get()).collect::<Vec<_>>()
-            // );
-
-            current_input = context.output.remove(0);
-            // println!("Extracting output: {}", current_input);
-
-            match result {
-                ExecutionResult::MoreInputNeeded => {}
-                ExecutionResult::Exit => {
-                    // println!("Amplifier {} ended", i);
-                    break;
+
+            match best_neighbour {
+                Some((output, neighbour)) => {
+                    current_output = output;
+                    current = neighbour;
                 }
+                None => break,
             }
         }
+
+        if current_output > result.max_output {
+            result.max_output = current_output;
+            result.phase_settings = current;
+        }
     }
 
-    current_input
+    result
 }
 
-fn run_amplifier(context: &mut ExecutionContext, input: i64) -> ExecutionResult {
-    context.input.push(input);
-    execute_program(context)
+fn permutations(values: &[i64]) -> Vec<Vec<i64>> {
+    if values.len() <= 1 {
+        return vec![values.to_vec()];
+    }
+
+    let mut result = vec![];
+    for (i, value) in values.iter().enumerate() {
+        let mut rest = values.to_vec();
+        rest.remove(i);
+        for mut permutation in permutations(&rest) {
+            permutation.insert(0, *value);
+            result.push(permutation);
+        }
+    }
+    result
+}
+
+fn run_amplifiers(instructions: &Memory, topology: &Topology, phase_settings: &[i64]) -> i64 {
+    topology.run(instructions, phase_settings).unwrap_or(0)
 }
 
 struct ExecutionContext {
     ip: Cell<usize>,
-    memory: Vec<Cell<i64>>,
+    memory: Memory,
     input: Vec<i64>,
     output: Vec<i64>,
     ended: bool,
 }
 
 impl ExecutionContext {
-    fn new(memory: &Vec<Cell<i64>>, input: &Vec<i64>) -> ExecutionContext {
+    fn new(memory: &Memory, input: &[i64]) -> ExecutionContext {
         ExecutionContext {
             ip: Cell::new(0),
             memory: memory.clone(),
-            input: input.clone(),
+            input: input.to_vec(),
             output: vec![],
             ended: false,
         }
     }
+
+    fn read_input(&mut self) -> Option<i64> {
+        if self.input.is_empty() {
+            None
+        } else {
+            Some(self.input.remove(0))
+        }
+    }
 }
 
 enum ExecutionResult {
@@ -151,18 +252,19 @@ fn execute_program(context: &mut ExecutionContext) -> ExecutionResult {
                 })
             }
             (OpCode::Input, parameter_modes) => {
-                if context.input.is_empty() {
-                    // println!(
-                    //     "Halting program due to input read; ip: {}",
-                    //     context.ip.get()
-                    // );
-                    // Revert the reading of the op-code, so we can read it again when the
-                    // thread is resumed
-                    context.ip.set(context.ip.get() - 1);
-                    return ExecutionResult::MoreInputNeeded;
-                }
-
-                let input_value = context.input.remove(0);
+                let input_value = match context.read_input() {
+                    Some(value) => value,
+                    None => {
+                        // println!(
+                        //     "Halting program due to input read; ip: {}",
+                        //     context.ip.get()
+                        // );
+                        // Revert the reading of the op-code, so we can read it again when the
+                        // thread is resumed
+                        context.ip.set(context.ip.get() - 1);
+                        return ExecutionResult::MoreInputNeeded;
+                    }
+                };
                 // println!("Reading input {}", input_value);
                 execute_instruction1(&context.memory, &context.ip, parameter_modes, |a| {
                     a.set(input_value);
@@ -226,8 +328,8 @@ fn jump_to(ip: &Cell<usize>, address: i64) {
     ip.set(address as usize);
 }
 
-fn read_op_code(memory: &Vec<Cell<i64>>, ip: &Cell<usize>) -> (OpCode, u32) {
-    let value = memory[ip.get()].get();
+fn read_op_code(memory: &Memory, ip: &Cell<usize>) -> (OpCode, u32) {
+    let value = memory.get(ip.get());
     let op_code_value = value % 100;
     let parameter_modes = (value / 100) as u32;
 
@@ -241,7 +343,7 @@ fn read_op_code(memory: &Vec<Cell<i64>>, ip: &Cell<usize>) -> (OpCode, u32) {
         7 => OpCode::LessThan,
         8 => OpCode::Equals,
         99 => OpCode::Exit,
-        x => panic!("Unknown op code: {}", x),
+        x => vm_error(format!("Unknown op code: {}", x)),
     };
 
     ip.set(ip.get() + 1);
@@ -249,7 +351,7 @@ fn read_op_code(memory: &Vec<Cell<i64>>, ip: &Cell<usize>) -> (OpCode, u32) {
 }
 
 fn execute_instruction1(
-    memory: &Vec<Cell<i64>>,
+    memory: &Memory,
     ip: &Cell<usize>,
     parameter_modes: u32,
     mut operation: impl FnMut(Parameter) -> (),
@@ -260,7 +362,7 @@ fn execute_instruction1(
 }
 
 fn execute_instruction2(
-    memory: &Vec<Cell<i64>>,
+    memory: &Memory,
     ip: &Cell<usize>,
     parameter_modes: u32,
     operation: impl Fn(Parameter, Parameter) -> (),
@@ -272,7 +374,7 @@ fn execute_instruction2(
 }
 
 fn execute_instruction3(
-    memory: &Vec<Cell<i64>>,
+    memory: &Memory,
     ip: &Cell<usize>,
     parameter_modes: u32,
     operation: impl Fn(Parameter, Parameter, Parameter) -> (),
@@ -284,44 +386,44 @@ fn execute_instruction3(
     operation(x, y, z);
 }
 
-fn get_parameter(
-    memory: &Vec<Cell<i64>>,
+fn get_parameter<'a>(
+    memory: &'a Memory,
     ip: &Cell<usize>,
     parameter_modes: &mut u32,
-) -> Parameter {
+) -> Parameter<'a> {
     // Get the parameter mode for this parameter
     let parameter_mode = match *parameter_modes % 10 {
         0 => ParameterMode::Position,
         1 => ParameterMode::Immediate,
-        x => panic!(format!("Incorrect parameter mode: {}", x)),
+        x => vm_error(format!("Incorrect parameter mode: {}", x)),
     };
     *parameter_modes /= 10;
 
-    let parameter_value = memory[ip.get()].get();
+    let parameter_value = memory.get(ip.get());
     ip.set(ip.get() + 1);
     match parameter_mode {
-        ParameterMode::Position => Parameter::CellReference(&memory[parameter_value as usize]),
+        ParameterMode::Position => Parameter::Reference(memory, parameter_value as usize),
         ParameterMode::Immediate => Parameter::ImmediateValue(parameter_value),
     }
 }
 
-enum Parameter {
+enum Parameter<'a> {
     ImmediateValue(i64),
-    CellReference(*const Cell<i64>),
+    Reference(&'a Memory, usize),
 }
 
-impl<'a> Parameter {
+impl<'a> Parameter<'a> {
     fn get(&self) -> i64 {
         match self {
-            Parameter::CellReference(cell) => unsafe { cell.as_ref().unwrap().get() },
+            Parameter::Reference(memory, address) => memory.get(*address),
             Parameter::ImmediateValue(value) => *value,
         }
     }
 
     fn set(&self, value: i64) -> () {
         match self {
-            Parameter::CellReference(cell) => unsafe { cell.as_ref().unwrap().set(value) },
-            Parameter::ImmediateValue(value) => panic!(format!(
+            Parameter::Reference(memory, address) => memory.set(*address, value),
+            Parameter::ImmediateValue(value) => vm_error(format!(
                 "Attempted to write value {} to an immediate parameter",
                 value
             )),
@@ -333,3 +435,35 @@ enum ParameterMode {
     Position,
     Immediate,
 }
+
+#[test]
+fn heuristic_matches_exhaustive() {
+    let topology = Topology::ring(5);
+    let memory = Memory::parse("3,52,1001,52,-5,52,3,53,1,52,56,54,1007,54,5,55,1005,55,26,1001,54,-5,54,1105,1,12,1,53,54,53,1008,54,0,55,1001,55,1,55,2,53,55,53,4,53,1001,56,-1,56,1005,56,6,99,0,0,0,0,10");
+
+    let exhaustive = exhaustive_search(&memory, &topology);
+    assert_eq!(120, exhaustive.runs);
+    assert_eq!(18216, exhaustive.max_output);
+    assert_eq!(
+        exhaustive.max_output,
+        heuristic_search(&memory, &topology).max_output
+    );
+}
+
+#[test]
+fn resumes_with_more_input() {
+    // Outputs the sum of its 2 inputs
+    let memory = Memory::parse("3,12,3,13,1,12,13,14,4,14,99");
+    let mut context = ExecutionContext::new(&memory, &[3]);
+
+    assert!(matches!(
+        execute_program(&mut context),
+        ExecutionResult::MoreInputNeeded
+    ));
+    context.input.push(4);
+    assert!(matches!(
+        execute_program(&mut context),
+        ExecutionResult::Exit
+    ));
+    assert_eq!(vec![7], context.output);
+}
@@ -0,0 +1,75 @@
+use std::cell::{Cell, RefCell};
+use std::sync::Arc;
+
+/// Copy-on-write program memory: the initial image is shared between all the
+/// clones, and each of them only stores the values that have been written to it
+#[derive(Clone)]
+pub struct Memory {
+    image: Arc<Vec<i64>>,
+    /// Values written, indexed by address; grown up to the highest address written
+    overlay: RefCell<Vec<Option<i64>>>,
+    /// Lowest address written, so that reads below it go straight to the image
+    lowest_written: Cell<usize>,
+}
+
+impl Memory {
+    pub fn new(values: Vec<i64>) -> Memory {
+        Memory {
+            image: Arc::new(values),
+            overlay: RefCell::new(Vec::new()),
+            lowest_written: Cell::new(usize::MAX),
+        }
+    }
+
+    pub fn parse(string: &str) -> Memory {
+        let values = string
+            .trim()
+            .split(',')
+            .map(|x| {
+                x.parse::<i64>()
+                    .unwrap_or_else(|_| panic!("Failed to parse {}", x))
+            })
+            .collect();
+
+        Memory::new(values)
+    }
+
+    pub fn get(&self, address: usize) -> i64 {
+        let image_value = || self.image.get(address).copied().unwrap_or(0);
+        if address < self.lowest_written.get() {
+            return image_value();
+        }
+        match self.overlay.borrow().get(address) {
+            Some(Some(value)) => *value,
+            _ => image_value(),
+        }
+    }
+
+    pub fn set(&self, address: usize, value: i64) {
+        let mut overlay = self.overlay.borrow_mut();
+        if address >= overlay.len() {
+            overlay.resize(address + 1, None);
+        }
+        overlay[address] = Some(value);
+        self.lowest_written
+            .set(self.lowest_written.get().min(address));
+    }
+}
+
+#[test]
+fn clones_share_the_image() {
+    let memory = Memory::parse("1,2,3");
+    let copy = memory.clone();
+    copy.set(1, 20);
+    copy.set(5, 50);
+
+    assert_eq!(
+        vec![1, 2, 3, 0],
+        (0..4).map(|a| memory.get(a)).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        vec![1, 20, 3, 0, 0, 50],
+        (0..6).map(|a| copy.get(a)).collect::<Vec<_>>()
+    );
+    assert!(Arc::ptr_eq(&memory.image, &copy.image));
+}
@@ -0,0 +1,174 @@
+use crate::bus::{IntcodeBus, Route};
+use crate::memory::Memory;
+use crate::{execute_program, ExecutionContext};
+
+/// Where the signals output by an amplifier go
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    Amplifier(usize),
+    /// The final output of the amplifiers
+    Out,
+}
+
+/// How the amplifiers are wired together. Written as comma-separated `from->to` edges
+/// between amplifier numbers, where `to` can also be `out`, e.g. `0->2, 1->2, 2->out`.
+/// An amplifier can feed several others, and be fed by several.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Topology {
+    amplifiers: usize,
+    edges: Vec<(usize, Target)>,
+}
+
+impl Topology {
+    /// Each amplifier feeds the next one, and the last one gives the result
+    pub fn chain(amplifiers: usize) -> Topology {
+        let mut edges: Vec<_> = (1..amplifiers)
+            .map(|i| (i - 1, Target::Amplifier(i)))
+            .collect();
+        edges.push((amplifiers - 1, Target::Out));
+        Topology { amplifiers, edges }
+    }
+
+    /// A chain whose last amplifier also feeds back into the first one
+    pub fn ring(amplifiers: usize) -> Topology {
+        let mut topology = Topology::chain(amplifiers);
+        topology.edges.push((amplifiers - 1, Target::Amplifier(0)));
+        topology
+    }
+
+    pub fn parse(spec: &str) -> Result<Topology, String> {
+        let parse_amplifier = |s: &str| {
+            s.trim()
+                .parse::<usize>()
+                .map_err(|_| format!("Invalid amplifier: {}", s.trim()))
+        };
+
+        let mut edges = vec![];
+        for edge in spec.split(',') {
+            let mut ends = edge.split("->");
+            let (from, to) = match (ends.next(), ends.next(), ends.next()) {
+                (Some(from), Some(to), None) => (from, to),
+                _ => return Err(format!("Invalid edge: {}", edge.trim())),
+            };
+            let to = match to.trim() {
+                "out" => Target::Out,
+                to => Target::Amplifier(parse_amplifier(to)?),
+            };
+            edges.push((parse_amplifier(from)?, to));
+        }
+
+        if !edges.iter().any(|(_, to)| *to == Target::Out) {
+            return Err("No amplifier is connected to out".to_string());
+        }
+        let amplifiers = edges
+            .iter()
+            .flat_map(|(from, to)| match to {
+                Target::Amplifier(to) => vec![*from, *to],
+                Target::Out => vec![*from],
+            })
+            .max()
+            .unwrap()
+            + 1;
+        Ok(Topology { amplifiers, edges })
+    }
+
+    pub fn amplifiers(&self) -> usize {
+        self.amplifiers
+    }
+
+    /// Amplifiers receiving the initial 0 signal: the ones no other amplifier feeds, or
+    /// the first one if they're all fed
+    fn entries(&self) -> Vec<usize> {
+        let entries: Vec<_> = (0..self.amplifiers)
+            .filter(|&i| !self.edges.iter().any(|(_, to)| *to == Target::Amplifier(i)))
+            .collect();
+        if entries.is_empty() {
+            vec![0]
+        } else {
+            entries
+        }
+    }
+
+    /// Runs a copy of the program for each amplifier, routing their output signals along
+    /// the edges until they all exit or wait for signals that won't come. Returns the
+    /// last signal sent to `out`, if any.
+    pub fn run(&self, instructions: &Memory, phase_settings: &[i64]) -> Option<i64> {
+        // Signals go on the bus along with the amplifier that output them
+        let mut bus = IntcodeBus::new(self.amplifiers, |&(from, _): &(usize, i64)| {
+            self.edges
+                .iter()
+                .filter(|(f, _)| *f == from)
+                .map(|(_, to)| match to {
+                    Target::Amplifier(to) => Route::Computer(*to),
+                    Target::Out => Route::External,
+                })
+                .collect()
+        });
+        let mut contexts: Vec<_> = (0..self.amplifiers)
+            .map(|i| ExecutionContext::new(instructions, phase_settings.get(i..=i).unwrap_or(&[])))
+            .collect();
+        for entry in self.entries() {
+            contexts[entry].input.push(0);
+        }
+
+        let mut result = None;
+        loop {
+            for (amplifier, context) in contexts.iter_mut().enumerate() {
+                // An amplifier that has exited still takes its signals off the bus
+                while let Some((_, signal)) = bus.receive(amplifier) {
+                    context.input.push(signal);
+                }
+                if !context.ended {
+                    execute_program(context);
+                }
+                for signal in context.output.drain(..) {
+                    bus.send((amplifier, signal));
+                }
+            }
+            if let Some((_, signal)) = bus.take_external().pop() {
+                result = Some(signal);
+            }
+
+            // Signals sent to amplifiers wait on the bus until their next turn
+            if bus.is_quiet() {
+                break result;
+            }
+        }
+    }
+}
+
+#[test]
+fn parse_topologies() {
+    assert_eq!(
+        Ok(Topology::chain(3)),
+        Topology::parse("0->1, 1->2, 2->out")
+    );
+    assert_eq!(
+        Ok(Topology::ring(3)),
+        Topology::parse("0->1,1->2,2->out,2->0")
+    );
+    assert_eq!(
+        vec![0, 1],
+        Topology::parse("0->2, 1->2, 2->out").unwrap().entries()
+    );
+    assert_eq!(vec![0], Topology::ring(5).entries());
+
+    assert!(Topology::parse("0->1").is_err());
+    assert!(Topology::parse("0->x, 1->out").is_err());
+    assert!(Topology::parse("0->1->out").is_err());
+}
+
+#[test]
+fn run_topologies() {
+    // Adds 1 to its phase setting and its input, and outputs it
+    let add = Memory::parse("3,15,3,16,1,15,16,17,101,1,17,17,4,17,99,0,0,0");
+    assert_eq!(Some((2 + 1) + 3 + 1), Topology::chain(2).run(&add, &[2, 3]));
+
+    // Fan-in: amplifier 2 gets both outputs, and only adds the first one to its phase
+    let fan_in = Topology::parse("0->2, 1->2, 2->out").unwrap();
+    assert_eq!(Some(10 + 1 + 1), fan_in.run(&add, &[0, 0, 10]));
+
+    // An amplifier waiting for a signal that never comes doesn't block the others
+    let stuck = Topology::parse("0->out, 1->1").unwrap();
+    assert_eq!(Some(1 + 1), stuck.run(&add, &[1, 2]));
+}
@@ -0,0 +1,222 @@
+/// Width and height of the glyphs, as painted by the puzzles
+const GLYPH_WIDTH: usize = 4;
+const GLYPH_HEIGHT: usize = 6;
+/// Glyphs are separated by an empty column
+const GLYPH_STRIDE: usize = GLYPH_WIDTH + 1;
+
+/// Letters used by the puzzle identifiers, and digits for the scores
+const GLYPHS: &[(char, [&str; GLYPH_HEIGHT])] = &[
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+    ('0', [".##.", "#..#", "#.##", "##.#", "#..#", ".##."]),
+    ('1', ["..#.", ".##.", "..#.", "..#.", "..#.", ".###"]),
+    ('2', [".##.", "#..#", "...#", "..#.", ".#..", "####"]),
+    ('3', ["###.", "...#", ".##.", "...#", "...#", "###."]),
+    ('4', ["#..#", "#..#", "####", "...#", "...#", "...#"]),
+    ('5', ["####", "#...", "###.", "...#", "...#", "###."]),
+    ('6', [".##.", "#...", "###.", "#..#", "#..#", ".##."]),
+    ('7', ["####", "...#", "..#.", ".#..", ".#..", ".#.."]),
+    ('8', [".##.", "#..#", ".##.", "#..#", "#..#", ".##."]),
+    ('9', [".##.", "#..#", "#..#", ".###", "...#", ".##."]),
+    (' ', ["....", "....", "....", "....", "....", "...."]),
+];
+
+fn glyph(c: char) -> Option<&'static [&'static str; GLYPH_HEIGHT]> {
+    GLYPHS
+        .iter()
+        .find(|(g, _)| *g == c.to_ascii_uppercase())
+        .map(|(_, rows)| rows)
+}
+
+/// Paints the text with the glyphs; unknown characters are left blank
+pub fn paint(text: &str) -> Vec<Vec<bool>> {
+    let mut rows = vec![vec![]; GLYPH_HEIGHT];
+    for (i, c) in text.chars().enumerate() {
+        let blank = glyph(' ').unwrap();
+        let rows_of_c = glyph(c).unwrap_or(blank);
+        for (row, pixels) in rows.iter_mut().zip(rows_of_c.iter()) {
+            if i > 0 {
+                row.push(false);
+            }
+            row.extend(pixels.chars().map(|p| p == '#'));
+        }
+    }
+    rows
+}
+
+/// Text form of painted rows, as the puzzles show them: `#` for the painted pixels, and
+/// `.` for the others
+pub fn to_grid(rows: &[Vec<bool>]) -> String {
+    rows.iter()
+        .map(|row| row.iter().map(|p| if *p { '#' } else { '.' }).collect())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Rows of pixels written as a grid of `#` and `.`; blank lines are ignored
+pub fn from_grid(grid: &str) -> Vec<Vec<bool>> {
+    grid.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| line.chars().map(|c| c == '#').collect())
+        .collect()
+}
+
+/// Reads the text painted in the rows of pixels, wherever it starts.
+/// Returns None if a glyph isn't recognized.
+pub fn recognize(rows: &[Vec<bool>]) -> Option<String> {
+    if rows.len() != GLYPH_HEIGHT {
+        return None;
+    }
+    let width = rows.iter().map(|r| r.len()).max().unwrap_or(0) as isize;
+    let pixel = |x: isize, y: usize| x >= 0 && rows[y].get(x as usize).cloned().unwrap_or(false);
+    let painted = |x: isize| (0..GLYPH_HEIGHT).any(|y| pixel(x, y));
+    let first_painted = (0..width).find(|x| painted(*x))?;
+
+    // The first glyph may start with empty columns
+    let read_from = |start: isize| {
+        let mut text = String::new();
+        let mut x0 = start;
+        while (x0..width).any(painted) {
+            let (c, _) = GLYPHS.iter().find(|(_, glyph_rows)| {
+                glyph_rows.iter().enumerate().all(|(y, glyph_row)| {
+                    glyph_row
+                        .chars()
+                        .enumerate()
+                        .all(|(dx, p)| (p == '#') == pixel(x0 + dx as isize, y))
+                })
+            })?;
+            text.push(*c);
+            x0 += GLYPH_STRIDE as isize;
+        }
+        Some(text)
+    };
+    (0..GLYPH_WIDTH as isize).find_map(|shift| read_from(first_painted - shift))
+}
+
+/// Renders the text in large letters, for captions under the visualization
+pub fn banner(text: &str) -> String {
+    paint(text)
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|p| if *p { "██" } else { "  " })
+                .collect::<String>()
+                .trim_end()
+                .to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[test]
+fn glyphs_are_distinct() {
+    for (i, (c1, rows1)) in GLYPHS.iter().enumerate() {
+        for (c2, rows2) in &GLYPHS[i + 1..] {
+            assert_ne!(rows1, rows2, "{} and {} look the same", c1, c2);
+        }
+    }
+}
+
+#[test]
+fn recognize_painted_text() {
+    assert_eq!(Some("PGUEPLPR".to_string()), recognize(&paint("PGUEPLPR")));
+    assert_eq!(Some("14538".to_string()), recognize(&paint("14538")));
+
+    // Glyphs don't have to start on the first column
+    let mut rows = paint("HELLO");
+    for row in &mut rows {
+        row.insert(0, false);
+    }
+    assert_eq!(Some("HELLO".to_string()), recognize(&rows));
+
+    // Unknown glyph
+    rows[0][1] = false;
+    assert_eq!(None, recognize(&rows));
+}
+
+/// Pseudo-random texts over the whole alphabet, to test the glyphs in any order
+#[cfg(test)]
+fn sample_texts(count: usize, max_len: usize) -> Vec<String> {
+    let alphabet: Vec<char> = GLYPHS.iter().map(|(c, _)| *c).collect();
+    let mut state: u64 = 0x2019_1225;
+    let mut next = |bound: usize| {
+        state = state
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1);
+        (state >> 33) as usize % bound
+    };
+    (0..count)
+        .map(|_| {
+            let len = 1 + next(max_len);
+            (0..len).map(|_| alphabet[next(alphabet.len())]).collect()
+        })
+        .collect()
+}
+
+#[test]
+fn every_glyph_round_trips() {
+    for (c1, _) in GLYPHS {
+        for (c2, _) in GLYPHS {
+            let text: String = [*c1, *c2].iter().collect();
+            // Blanks can't be told apart from the space around the text
+            let expected = text.trim();
+            if expected.is_empty() {
+                continue;
+            }
+            assert_eq!(
+                Some(expected.to_string()),
+                recognize(&paint(&text)).map(|t| t.trim().to_string()),
+                "{:?}",
+                text
+            );
+        }
+    }
+}
+
+#[test]
+fn random_texts_round_trip() {
+    for text in sample_texts(200, 12) {
+        let expected = text.trim().to_string();
+        let mut rows = from_grid(&to_grid(&paint(&text)));
+        // Wherever the text starts
+        for row in &mut rows {
+            row.insert(0, false);
+        }
+        let recognized = recognize(&rows).map(|t| t.trim().to_string());
+        if expected.is_empty() {
+            assert_eq!(None, recognized);
+        } else {
+            assert_eq!(Some(expected), recognized, "{:?}", text);
+        }
+    }
+}
+
+#[test]
+fn grid_conversions() {
+    let grid = to_grid(&paint("C1"));
+    assert_eq!(".##....#.", grid.lines().next().unwrap());
+    assert_eq!(paint("C1"), from_grid(&format!("\n{}\n", grid)));
+}
+
+#[test]
+fn banner_rendering() {
+    let lines: Vec<_> = banner("L1").lines().map(|l| l.to_string()).collect();
+    assert_eq!(6, lines.len());
+    assert_eq!("██            ██", lines[0]);
+    assert_eq!("████████    ██████", lines[5]);
+}
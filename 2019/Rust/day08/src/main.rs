@@ -1,10 +1,34 @@
+use std::cmp::Ordering;
 use std::env;
 use std::fs::File;
 use std::io::Read;
 
+// Same glyphs as day11 and day13, which use the rest of the module
+#[allow(dead_code)]
+mod font;
+
 type Result<T> = ::std::result::Result<T, Box<dyn ::std::error::Error>>;
 
+const WIDTH: usize = 25;
+const HEIGHT: usize = 6;
+const LAYER_LEN: usize = WIDTH * HEIGHT;
+
+const BLACK: u8 = 0;
+const WHITE: u8 = 1;
+const TRANSPARENT: u8 = 2;
+
 fn main() -> Result<()> {
+    // day08 synthesize <text> [layers]: an image showing the text, as a puzzle input
+    if env::args().nth(1).as_deref() == Some("synthesize") {
+        let text = env::args().nth(2).ok_or("Enter the text of the image")?;
+        let layer_count = match env::args().nth(3) {
+            Some(count) => count.parse()?,
+            None => 100,
+        };
+        println!("{}", synthesize(&text, layer_count)?);
+        return Ok(());
+    }
+
     let file_name = env::args().nth(1).expect("Enter a file name");
 
     let mut pixels = String::new();
@@ -12,24 +36,75 @@ fn main() -> Result<()> {
         .read_to_string(&mut pixels)
         .expect("Failed to read input file");
 
-    let pixels: Vec<i32> = pixels
+    let pixels: Vec<u8> = pixels
+        .trim()
         .chars()
         .map(|c| c.to_string().parse().unwrap())
         .collect();
 
-    const LAYER_LEN: usize = 25 * 6;
+    let rows = decode(&pixels);
+    for row in &rows {
+        let line: String = row.iter().map(|p| if *p { '█' } else { ' ' }).collect();
+        println!("{}", line);
+    }
+    match font::recognize(&rows) {
+        Some(message) => println!("Message: {}", message),
+        None => println!("Couldn't read the message"),
+    }
+
+    Ok(())
+}
 
+/// Rows of the image, true for the white pixels. Each pixel is the one of the first layer
+/// where it isn't transparent.
+fn decode(pixels: &[u8]) -> Vec<Vec<bool>> {
     let layers: Vec<_> = pixels.chunks(LAYER_LEN).collect();
+    let visible: Vec<bool> = (0..LAYER_LEN)
+        .map(|i| {
+            let pixel = layers.iter().map(|l| l[i]).find(|x| *x != TRANSPARENT);
+            pixel == Some(WHITE)
+        })
+        .collect();
+    visible.chunks(WIDTH).map(|row| row.to_vec()).collect()
+}
 
-    for i in 0..LAYER_LEN {
-        // Find the 1 layer that doesn't have a transparent pixel
-        // at this position
-        let pixel = layers.iter().map(|l| l[i]).find(|x| *x != 2).unwrap();
-        print!("{}", if pixel == 0 { " " } else { "█" });
-        if i != 0 && i % 25 == 0 {
-            println!();
+/// Image painting the text in white, spread over the layers: each pixel is transparent
+/// down to a layer depending on its position, and of the opposite colour below it
+fn synthesize(text: &str, layer_count: usize) -> Result<String> {
+    let painted = font::paint(text);
+    if painted[0].len() > WIDTH {
+        return Err(format!("{} doesn't fit in a {}-pixel image", text, WIDTH).into());
+    }
+    if layer_count == 0 {
+        return Err("The image needs at least one layer".into());
+    }
+
+    let mut image = String::new();
+    for layer in 0..layer_count {
+        for i in 0..LAYER_LEN {
+            let white = painted[i / WIDTH].get(i % WIDTH).cloned().unwrap_or(false);
+            let visible_layer = (i * 7) % layer_count;
+            let pixel = match layer.cmp(&visible_layer) {
+                Ordering::Less => TRANSPARENT,
+                Ordering::Equal if white => WHITE,
+                Ordering::Equal => BLACK,
+                Ordering::Greater if white => BLACK,
+                Ordering::Greater => WHITE,
+            };
+            image.push((b'0' + pixel) as char);
         }
     }
+    Ok(image)
+}
 
-    Ok(())
+#[test]
+fn synthesized_images_are_read_back() {
+    let digits = |image: &str| -> Vec<u8> { image.bytes().map(|b| b - b'0').collect() };
+    for (text, layer_count) in &[("CFLUL", 100), ("JZ", 3), ("UP 42", 1)] {
+        let image = synthesize(text, *layer_count).unwrap();
+        assert_eq!(LAYER_LEN * layer_count, image.len());
+        let rows = decode(&digits(&image));
+        assert_eq!(Some(text.to_string()), font::recognize(&rows));
+    }
+    assert!(synthesize("TOOLONG", 10).is_err());
 }
@@ -3,8 +3,21 @@ use std::collections::HashMap;
 use std::env;
 use std::fs::File;
 use std::io::Read;
+use std::process;
+
+mod quine;
+
+const EXIT_WRONG_ANSWER: i32 = 2;
+const EXIT_VM_ERROR: i32 = 4;
 
 type Result<T> = ::std::result::Result<T, Box<dyn ::std::error::Error>>;
+
+/// Reports an error of the Intcode VM, and exits with the runner's code for VM errors
+fn vm_error(message: String) -> ! {
+    eprintln!("VM error: {}", message);
+    process::exit(EXIT_VM_ERROR);
+}
+
 //type Memory = HashMap<usize, Cell<i64>>;
 
 #[derive(Clone)]
@@ -55,29 +68,54 @@ impl Memory {
 // }
 
 fn main() -> Result<()> {
+    // day09 quine-check <program>: checks the program outputs a copy of itself
+    if env::args().nth(1).as_deref() == Some("quine-check") {
+        let file_name = env::args().nth(2).expect("Enter a program file name");
+        let program = parse_program(&read_file(&file_name)?)?;
+        let output = run_program(&program, vec![]);
+        return match quine::quine_check(&program, &output) {
+            Ok(()) => {
+                println!("OK: the program outputs its own {} values", program.len());
+                Ok(())
+            }
+            Err(mismatch) => {
+                eprintln!("Not a quine: {}", mismatch);
+                process::exit(EXIT_WRONG_ANSWER);
+            }
+        };
+    }
+
     let file_name = env::args().nth(1).expect("Enter a file name");
+    let program = parse_program(&read_file(&file_name)?)?;
+    run_program(&program, vec![2]);
+
+    Ok(())
+}
 
+fn read_file(file_name: &str) -> Result<String> {
     let mut instructions = String::new();
-    File::open(file_name)?
-        .read_to_string(&mut instructions)
-        .expect("Failed to read input file");
+    File::open(file_name)?.read_to_string(&mut instructions)?;
+    Ok(instructions)
+}
 
-    let memory: HashMap<usize, Cell<i64>> = instructions
-        .split(",")
+fn parse_program(instructions: &str) -> Result<Vec<i64>> {
+    instructions
+        .trim()
+        .split(',')
         .map(|x| {
-            Cell::new(
-                x.parse::<i64>()
-                    .expect(format!("Failed to parse {}", x).as_str()),
-            )
+            x.trim()
+                .parse::<i64>()
+                .map_err(|_| format!("Failed to parse {}", x).into())
         })
-        .enumerate()
-        .collect();
-    let memory = Memory::new(memory);
+        .collect()
+}
 
-    let mut context = ExecutionContext::new(&memory, &vec![2]);
+/// Runs the program until it exits or needs more input, and returns its output
+fn run_program(program: &[i64], input: Vec<i64>) -> Vec<i64> {
+    let memory = Memory::new(program.iter().map(|v| Cell::new(*v)).enumerate().collect());
+    let mut context = ExecutionContext::new(&memory, &input);
     execute_program(&mut context);
-
-    Ok(())
+    context.output
 }
 
 // fn run_amplifiers(instructions: &Memory, phase_settings: Vec<i64>) -> i64 {
@@ -281,7 +319,7 @@ fn read_op_code(context: &mut ExecutionContext) -> (OpCode, u32) {
         8 => OpCode::Equals,
         9 => OpCode::AdjustRelativeBase,
         99 => OpCode::Exit,
-        x => panic!("Unknown op code: {}", x),
+        x => vm_error(format!("Unknown op code: {}", x)),
     };
 
     context.ip.set(context.ip.get() + 1);
@@ -327,7 +365,7 @@ fn get_parameter(context: &mut ExecutionContext, parameter_modes: &mut u32) -> P
         0 => ParameterMode::Position,
         1 => ParameterMode::Immediate,
         2 => ParameterMode::Relative,
-        x => panic!(format!("Incorrect parameter mode: {}", x)),
+        x => vm_error(format!("Incorrect parameter mode: {}", x)),
     };
     *parameter_modes /= 10;
 
@@ -363,7 +401,7 @@ impl<'a> Parameter {
     fn set(&self, value: i64) -> () {
         match self {
             Parameter::CellReference(cell) => unsafe { cell.as_ref().unwrap().set(value) },
-            Parameter::ImmediateValue(value) => panic!(format!(
+            Parameter::ImmediateValue(value) => vm_error(format!(
                 "Attempted to write value {} to an immediate parameter",
                 value
             )),
@@ -0,0 +1,87 @@
+use std::fmt;
+
+/// First difference between the output a program was expected to produce and its
+/// actual output
+#[derive(Debug, PartialEq, Eq)]
+pub enum Mismatch {
+    Value {
+        index: usize,
+        expected: i64,
+        actual: i64,
+    },
+    Length {
+        expected: usize,
+        actual: usize,
+    },
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Mismatch::Value {
+                index,
+                expected,
+                actual,
+            } => write!(f, "output {} is {}, expected {}", index, actual, expected),
+            Mismatch::Length { expected, actual } => {
+                write!(f, "{} values were output, expected {}", actual, expected)
+            }
+        }
+    }
+}
+
+/// Compares the output of a program with the expected values
+pub fn check_output(expected: &[i64], actual: &[i64]) -> Result<(), Mismatch> {
+    if let Some(index) = expected.iter().zip(actual).position(|(e, a)| e != a) {
+        return Err(Mismatch::Value {
+            index,
+            expected: expected[index],
+            actual: actual[index],
+        });
+    }
+    if expected.len() != actual.len() {
+        return Err(Mismatch::Length {
+            expected: expected.len(),
+            actual: actual.len(),
+        });
+    }
+    Ok(())
+}
+
+/// Checks that a program outputs a copy of itself
+pub fn quine_check(program: &[i64], output: &[i64]) -> Result<(), Mismatch> {
+    check_output(program, output)
+}
+
+#[test]
+fn quine_example() {
+    let program =
+        crate::parse_program("109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99").unwrap();
+    let output = crate::run_program(&program, vec![]);
+    assert_eq!(Ok(()), quine_check(&program, &output));
+}
+
+#[test]
+fn not_a_quine() {
+    let program = crate::parse_program("104,1125899906842624,99").unwrap();
+    let output = crate::run_program(&program, vec![]);
+    assert_eq!(
+        Err(Mismatch::Value {
+            index: 0,
+            expected: 104,
+            actual: 1125899906842624
+        }),
+        quine_check(&program, &output)
+    );
+}
+
+#[test]
+fn output_too_short() {
+    assert_eq!(
+        Err(Mismatch::Length {
+            expected: 3,
+            actual: 2
+        }),
+        check_output(&[1, 2, 3], &[1, 2])
+    );
+}
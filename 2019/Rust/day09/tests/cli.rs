@@ -0,0 +1,25 @@
+use std::process::{Command, Output};
+
+fn run_program(name: &str, program: &str) -> Output {
+    let path = std::env::temp_dir().join(format!("day09_{}_{}.txt", name, std::process::id()));
+    std::fs::write(&path, program).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_day09"))
+        .arg(&path)
+        .output()
+        .expect("Failed to run day09");
+    std::fs::remove_file(&path).unwrap();
+    output
+}
+
+#[test]
+fn program_output() {
+    let output = run_program("output", "104,1125899906842624,99");
+    assert_eq!(Some(0), output.status.code());
+}
+
+#[test]
+fn vm_error() {
+    let output = run_program("vm_error", "42,0,0,0,99");
+    assert_eq!(Some(4), output.status.code());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("VM error: Unknown op code: 42"));
+}
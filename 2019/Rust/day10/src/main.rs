@@ -3,8 +3,11 @@ use std::env;
 use std::f64::consts::PI;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
+use std::time::Instant;
 extern crate ordered_float;
 
+mod visibility;
+
 type Result<T> = ::std::result::Result<T, Box<dyn ::std::error::Error>>;
 type Grid<T> = Vec<Vec<T>>;
 
@@ -15,6 +18,13 @@ struct Coord {
 }
 
 fn main() -> Result<()> {
+    if env::args().nth(1).as_deref() == Some("--bench") {
+        let size = env::args().nth(2).map_or(Ok(500), |s| s.parse())?;
+        let density = env::args().nth(3).map_or(Ok(0.01), |s| s.parse())?;
+        benchmark(size, density);
+        return Ok(());
+    }
+
     let file_name = env::args().nth(1).expect("Enter a file name");
     let file = File::open(file_name)?;
 
@@ -32,24 +42,13 @@ fn main() -> Result<()> {
     let grid_y = asteroids.len();
     println!("Asteroids grid: {}x{}", grid_x, grid_y);
 
-    let mut count = 0;
-    let mut found_asteroid = Coord { x: 0, y: 0 };
-
-    for y in 0..grid_x {
-        for x in 0..grid_y {
-            if !asteroids[y][x] {
-                continue;
-            }
-            let lines_of_sight =
-                compute_line_of_sight_status(&asteroids, grid_x, grid_y, &Coord { x, y });
-            let hit_count = get_visible_asteroids(&lines_of_sight, grid_x, grid_y).len();
-            //println!("Visible count for {},{}: {}", x + 1, y + 1, hit_count);
-            if count < hit_count {
-                count = hit_count;
-                found_asteroid = Coord { x, y };
-            }
-        }
-    }
+    let (found_asteroid, count) =
+        visibility::best_station(&visibility::asteroid_positions(&asteroids))
+            .expect("No asteroids found");
+    println!(
+        "Best station: {:?}; {} asteroids visible",
+        found_asteroid, count
+    );
 
     let mut destroyed_asteroids = 0;
     while destroyed_asteroids < 200 {
@@ -83,6 +82,59 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+/// Finds the best station by marking all the hidden positions for each candidate
+fn best_station_naive(asteroids: &Grid<bool>, grid_x: usize, grid_y: usize) -> (Coord, usize) {
+    let mut count = 0;
+    let mut found_asteroid = Coord { x: 0, y: 0 };
+
+    for y in 0..grid_y {
+        for x in 0..grid_x {
+            if !asteroids[y][x] {
+                continue;
+            }
+            let lines_of_sight =
+                compute_line_of_sight_status(asteroids, grid_x, grid_y, &Coord { x, y });
+            let hit_count = get_visible_asteroids(&lines_of_sight, grid_x, grid_y).len();
+            //println!("Visible count for {},{}: {}", x + 1, y + 1, hit_count);
+            if count < hit_count {
+                count = hit_count;
+                found_asteroid = Coord { x, y };
+            }
+        }
+    }
+    (found_asteroid, count)
+}
+
+/// Compares the naive and hashing algorithms on a generated field
+fn benchmark(size: usize, density: f64) {
+    let grid = visibility::generate_field(size, density, 2019);
+    let asteroids = visibility::asteroid_positions(&grid);
+    println!(
+        "Generated {}x{} field with {} asteroids",
+        size,
+        size,
+        asteroids.len()
+    );
+
+    let start = Instant::now();
+    let (station, count) = visibility::best_station(&asteroids).expect("Empty field");
+    println!(
+        "Hashing: {:?} sees {} asteroids - {:?}",
+        station,
+        count,
+        start.elapsed()
+    );
+
+    let start = Instant::now();
+    let (station, count) = best_station_naive(&grid, size, size);
+    println!(
+        "Naive:   {:?} sees {} asteroids - {:?}",
+        station,
+        count,
+        start.elapsed()
+    );
+}
+
 fn angle_between(a: &Coord, b: &Coord) -> f64 {
     let theta = (b.y as f64 - a.y as f64).atan2(b.x as f64 - a.x as f64);
     let alpha = theta + PI / 2.0;
@@ -0,0 +1,144 @@
+use crate::Coord;
+use crate::Grid;
+use std::collections::HashSet;
+
+/// Lists the positions of all the asteroids in the grid
+pub fn asteroid_positions(grid: &Grid<bool>) -> Vec<Coord> {
+    let mut result = vec![];
+    for (y, row) in grid.iter().enumerate() {
+        for (x, &is_asteroid) in row.iter().enumerate() {
+            if is_asteroid {
+                result.push(Coord { x, y });
+            }
+        }
+    }
+    result
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 {
+        a.abs()
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Direction from a to b, reduced so that all the asteroids on the same line of sight
+/// share the same vector
+pub fn reduced_direction(a: &Coord, b: &Coord) -> (i64, i64) {
+    let dx = b.x as i64 - a.x as i64;
+    let dy = b.y as i64 - a.y as i64;
+    let d = gcd(dx, dy);
+    (dx / d, dy / d)
+}
+
+/// Counts the asteroids visible from the station: only one asteroid can be seen in
+/// each distinct direction, so there's no need to check for occlusions
+pub fn count_visible(asteroids: &[Coord], station: &Coord) -> usize {
+    asteroids
+        .iter()
+        .filter(|a| (a.x, a.y) != (station.x, station.y))
+        .map(|a| reduced_direction(station, a))
+        .collect::<HashSet<_>>()
+        .len()
+}
+
+/// Finds the asteroid that can see the most other asteroids, and how many it sees. On a
+/// tie, the first one in reading order is kept.
+pub fn best_station(asteroids: &[Coord]) -> Option<(Coord, usize)> {
+    asteroids
+        .iter()
+        .map(|station| (station, count_visible(asteroids, station)))
+        .fold(None, |best, (station, count)| match best {
+            Some((_, best_count)) if best_count >= count => best,
+            _ => Some((station, count)),
+        })
+        .map(|(station, count)| (Coord { ..*station }, count))
+}
+
+/// Generates a random field of asteroids, with the given density (between 0 and 1)
+pub fn generate_field(size: usize, density: f64, seed: u64) -> Grid<bool> {
+    let mut state = seed.max(1);
+    let mut next = || {
+        // xorshift64
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state % 1_000_000) as f64 / 1_000_000.0
+    };
+
+    (0..size)
+        .map(|_| (0..size).map(|_| next() < density).collect())
+        .collect()
+}
+
+#[cfg(test)]
+fn parse(text: &str) -> Grid<bool> {
+    text.lines()
+        .map(|line| line.trim().chars().map(|c| c == '#').collect())
+        .collect()
+}
+
+#[test]
+fn small_example() {
+    let grid = parse(
+        ".#..#
+         .....
+         #####
+         ....#
+         ...##",
+    );
+    let (station, count) = best_station(&asteroid_positions(&grid)).unwrap();
+    assert_eq!((3, 4, 8), (station.x, station.y, count));
+}
+
+#[test]
+fn first_of_tied_stations() {
+    // Each asteroid sees the two others
+    let grid = parse(
+        "#.#
+         ...
+         #..",
+    );
+    let (station, count) = best_station(&asteroid_positions(&grid)).unwrap();
+    assert_eq!((0, 0, 2), (station.x, station.y, count));
+}
+
+#[test]
+fn large_example() {
+    let grid = parse(
+        ".#..##.###...#######
+         ##.############..##.
+         .#.######.########.#
+         .###.#######.####.#.
+         #####.##.#.##.###.##
+         ..#####..#.#########
+         ####################
+         #.####....###.#.#.##
+         ##.#################
+         #####.##.###..####..
+         ..######..##.#######
+         ####.##.####...##..#
+         .#####..#.######.###
+         ##...#.##########...
+         #.##########.#######
+         .####.#.###.###.#.##
+         ....##.##.###..#####
+         .#.#.###########.###
+         #.#.#.#####.####.###
+         ###.##.####.##.#..##",
+    );
+    let (station, count) = best_station(&asteroid_positions(&grid)).unwrap();
+    assert_eq!((11, 13, 210), (station.x, station.y, count));
+}
+
+#[test]
+fn matches_line_of_sight_on_generated_field() {
+    let grid = generate_field(30, 0.2, 42);
+    let asteroids = asteroid_positions(&grid);
+    for station in &asteroids {
+        let lines_of_sight = crate::compute_line_of_sight_status(&grid, 30, 30, station);
+        let expected = crate::get_visible_asteroids(&lines_of_sight, 30, 30).len();
+        assert_eq!(expected, count_visible(&asteroids, station));
+    }
+}
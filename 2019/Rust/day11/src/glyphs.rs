@@ -0,0 +1,106 @@
+/// Characters used to draw grids of pixels in the terminal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Glyphs {
+    /// `#` and `.`, one character per pixel
+    Ascii,
+    /// Full blocks, one character per pixel
+    Blocks,
+    /// Braille patterns, packing 2x4 pixels in each character, for large grids
+    Braille,
+}
+
+impl Glyphs {
+    pub fn parse(name: &str) -> Result<Glyphs, String> {
+        match name {
+            "ascii" => Ok(Glyphs::Ascii),
+            "blocks" => Ok(Glyphs::Blocks),
+            "braille" => Ok(Glyphs::Braille),
+            _ => Err(format!(
+                "Unknown glyph set: {} (expected ascii, blocks or braille)",
+                name
+            )),
+        }
+    }
+
+    /// Glyph set given by `--glyphs <name>`, if any
+    pub fn from_args(args: impl Iterator<Item = String>) -> Result<Option<Glyphs>, String> {
+        match args.skip_while(|a| a != "--glyphs").nth(1) {
+            Some(name) => Glyphs::parse(&name).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Draws the rows of pixels, one line of text per row (or per 4 rows in braille)
+    pub fn render(self, rows: &[Vec<bool>]) -> String {
+        let pixel = |x: usize, y: usize| rows.get(y).and_then(|r| r.get(x)).cloned() == Some(true);
+        let width = rows.iter().map(|r| r.len()).max().unwrap_or(0);
+        let mut lines = vec![];
+        match self {
+            Glyphs::Ascii | Glyphs::Blocks => {
+                let (on, off) = match self {
+                    Glyphs::Ascii => ('#', '.'),
+                    _ => ('█', ' '),
+                };
+                for y in 0..rows.len() {
+                    lines.push(
+                        (0..width)
+                            .map(|x| if pixel(x, y) { on } else { off })
+                            .collect::<String>(),
+                    );
+                }
+            }
+            Glyphs::Braille => {
+                // Bit of each dot in the braille pattern, by row and column
+                const DOTS: [[u32; 2]; 4] =
+                    [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+                for y in (0..rows.len()).step_by(4) {
+                    let line = (0..width)
+                        .step_by(2)
+                        .map(|x| {
+                            let mut bits = 0;
+                            for (dy, row) in DOTS.iter().enumerate() {
+                                for (dx, bit) in row.iter().enumerate() {
+                                    if pixel(x + dx, y + dy) {
+                                        bits |= bit;
+                                    }
+                                }
+                            }
+                            std::char::from_u32(0x2800 + bits).unwrap()
+                        })
+                        .collect::<String>();
+                    lines.push(line);
+                }
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+#[test]
+fn glyph_sets() {
+    let rows = vec![
+        vec![true, false, true],
+        vec![false, true, false],
+        vec![true, true, true],
+    ];
+    assert_eq!("#.#\n.#.\n###", Glyphs::Ascii.render(&rows));
+    assert_eq!("█ █\n █ \n███", Glyphs::Blocks.render(&rows));
+    // Dots 1, 5, 3, 6 in the first character, dots 1, 3 in the second
+    assert_eq!("⠵⠅", Glyphs::Braille.render(&rows));
+}
+
+#[test]
+fn glyphs_from_args() {
+    let args = |a: &[&str]| {
+        a.iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+    };
+    assert_eq!(
+        Ok(Some(Glyphs::Braille)),
+        Glyphs::from_args(args(&["input.txt", "--glyphs", "braille"]))
+    );
+    assert_eq!(Ok(None), Glyphs::from_args(args(&["input.txt"])));
+    assert!(Glyphs::from_args(args(&["--glyphs", "emoji"])).is_err());
+}
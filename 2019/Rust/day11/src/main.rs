@@ -1,3 +1,4 @@
+use crate::glyphs::Glyphs;
 use std::cell::Cell;
 use std::collections::HashMap;
 use std::env;
@@ -5,9 +6,22 @@ use std::fs::File;
 use std::io::Read;
 use std::ops::Index;
 use std::ops::IndexMut;
+use std::process;
+
+mod font;
+mod glyphs;
 
 type Result<T> = ::std::result::Result<T, Box<dyn ::std::error::Error>>;
 
+// Exit code for Intcode VM errors, shared with the runner
+const EXIT_VM_ERROR: i32 = 4;
+
+/// Reports an error of the Intcode VM, and exits with the runner's code for VM errors
+fn vm_error(message: String) -> ! {
+    eprintln!("VM error: {}", message);
+    process::exit(EXIT_VM_ERROR);
+}
+
 #[derive(Clone)]
 struct Memory {
     _values: HashMap<usize, Cell<i64>>,
@@ -34,6 +48,22 @@ impl IndexMut<usize> for Memory {
 }
 
 fn main() -> Result<()> {
+    // day11 paint <text>: the hull painted with the text, as a grid of `#` and `.`
+    if env::args().nth(1).as_deref() == Some("paint") {
+        let text = env::args().nth(2).ok_or("Enter the text to paint")?;
+        println!("{}", font::to_grid(&font::paint(&text)));
+        return Ok(());
+    }
+    // day11 read <grid file>: the identifier painted on a hull given as a grid
+    if env::args().nth(1).as_deref() == Some("read") {
+        let file_name = env::args().nth(2).ok_or("Enter a grid file name")?;
+        let mut grid = String::new();
+        File::open(file_name)?.read_to_string(&mut grid)?;
+        let identifier = font::recognize(&font::from_grid(&grid));
+        println!("{}", identifier.ok_or("Couldn't read the identifier")?);
+        return Ok(());
+    }
+
     let file_name = env::args().nth(1).expect("Enter a file name");
 
     let mut instructions = String::new();
@@ -72,12 +102,17 @@ fn main() -> Result<()> {
         grid[x as usize][y as usize] = color == 1;
     }
 
-    for y in 0..y_max + 1 {
-        for x in 0..x_max + 1 {
-            print!("{}", if grid[x][y] { "█" } else { " " })
-        }
+    let rows: Vec<Vec<bool>> = (0..y_max + 1)
+        .map(|y| (0..x_max + 1).map(|x| grid[x][y]).collect())
+        .collect();
+    let glyphs = Glyphs::from_args(env::args())?.unwrap_or(Glyphs::Blocks);
+    println!("{}", glyphs.render(&rows));
 
-        println!()
+    if env::args().any(|a| a == "--banner") {
+        match font::recognize(&rows) {
+            Some(identifier) => println!("\n{}", font::banner(&identifier)),
+            None => println!("Couldn't read the identifier"),
+        }
     }
     Ok(())
 }
@@ -300,7 +335,7 @@ fn read_op_code(context: &mut ExecutionContext) -> (OpCode, u32) {
         8 => OpCode::Equals,
         9 => OpCode::AdjustRelativeBase,
         99 => OpCode::Exit,
-        x => panic!("Unknown op code: {}", x),
+        x => vm_error(format!("Unknown op code: {}", x)),
     };
 
     context.ip += 1;
@@ -346,7 +381,7 @@ fn get_parameter(context: &mut ExecutionContext, parameter_modes: &mut u32) -> P
         0 => ParameterMode::Position,
         1 => ParameterMode::Immediate,
         2 => ParameterMode::Relative,
-        x => panic!(format!("Incorrect parameter mode: {}", x)),
+        x => vm_error(format!("Incorrect parameter mode: {}", x)),
     };
     *parameter_modes /= 10;
 
@@ -381,7 +416,7 @@ impl<'a> Parameter {
     fn set(&self, value: i64) -> () {
         match self {
             Parameter::CellReference(cell) => unsafe { cell.as_ref().unwrap().set(value) },
-            Parameter::ImmediateValue(value) => panic!(format!(
+            Parameter::ImmediateValue(value) => vm_error(format!(
                 "Attempted to write value {} to an immediate parameter",
                 value
             )),
@@ -1,6 +1,5 @@
-extern crate num;
+mod modmath;
 
-use num::integer;
 use std::cmp::Ordering;
 use std::collections::HashSet;
 use std::env;
@@ -25,7 +24,7 @@ struct AxisState {
 }
 
 impl AxisState {
-    fn new(bodies: &Vec<Vec<Body>>, axis: usize) -> AxisState {
+    fn new(bodies: &[Vec<Body>], axis: usize) -> AxisState {
         AxisState {
             positions: bodies.iter().map(|b| b[axis].position).collect(),
             velocities: bodies.iter().map(|b| b[axis].velocity).collect(),
@@ -85,62 +84,112 @@ impl fmt::Display for Body {
 }
 
 fn main() -> Result<()> {
-    let file_name = env::args().nth(1).expect("Enter a file name");
+    let file_name = parse_args(env::args().skip(1))?;
+    let mut bodies = read_bodies(&file_name)?;
+    println!("Loaded {} moons from {}", bodies.len(), file_name);
+
+    println!("Result: {}", find_cycle_length(&mut bodies));
+    Ok(())
+}
+
+/// Returns the moons file to load: either the positional argument, or the
+/// value of the `--moons` option, which overrides it.
+fn parse_args(args: impl Iterator<Item = String>) -> Result<String> {
+    let mut file_name = None;
+    let mut args = args;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--moons" => {
+                file_name = Some(args.next().ok_or("--moons expects a file name")?);
+            }
+            _ if file_name.is_none() => file_name = Some(arg),
+            _ => (),
+        }
+    }
+
+    Ok(file_name.ok_or("Enter a file name")?)
+}
+
+fn read_bodies(file_name: &str) -> Result<Vec<Vec<Body>>> {
     let file = File::open(file_name)?;
+    let mut bodies = vec![];
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            bodies.push(parse_moon(&line)?);
+        }
+    }
+
+    Ok(bodies)
+}
+
+/// Parses a line in the `<x=-1, y=0, z=2>` format into one body per axis
+fn parse_moon(line: &str) -> Result<Vec<Body>> {
+    let line = line.trim();
+    let line = line
+        .strip_prefix('<')
+        .and_then(|l| l.strip_suffix('>'))
+        .ok_or_else(|| format!("Invalid moon: {}", line))?;
+
+    let mut coords = [None; 3];
+    for coord in line.split(',') {
+        let mut parts = coord.split('=');
+        let (name, value) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(name), Some(value), None) => (name.trim(), value.trim()),
+            _ => return Err(format!("Invalid coordinate: {}", coord).into()),
+        };
+        let axis = match name {
+            "x" => 0,
+            "y" => 1,
+            "z" => 2,
+            _ => return Err(format!("Invalid axis: {}", name).into()),
+        };
+        coords[axis] = Some(value.parse::<i32>()?);
+    }
 
-    let mut bodies: Vec<Vec<Body>> = BufReader::new(file)
-        .lines()
-        .map(|line| {
-            let line = line.unwrap();
-            let line = &line[1..(line.len() - 1)];
-            let coords: Vec<i32> = line
-                .split(", ")
-                .map(|c| c.split('=').nth(1).unwrap().parse().unwrap())
-                .collect();
-            vec![
-                Body::new(coords[0]),
-                Body::new(coords[1]),
-                Body::new(coords[2]),
-            ]
-        })
-        .collect();
+    coords
+        .iter()
+        .map(|c| c.map(Body::new))
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| format!("Missing coordinate in moon: {}", line).into())
+}
 
+/// Finds the number of steps until the moons go back to a previous state.
+/// Each axis is independent from the others, so we find the cycle length of
+/// each one separately, and combine them.
+fn find_cycle_length(bodies: &mut [Vec<Body>]) -> u128 {
+    let axis_count = bodies.first().map_or(0, |b| b.len());
     let mut cycle_length_per_axis: Vec<u32> = vec![];
 
-    for axis in 0..3 {
+    for axis in 0..axis_count {
         let mut positions: HashSet<AxisState> = HashSet::new();
         let mut i = 0;
         loop {
             //println!("Step {}", i);
-            let state = AxisState::new(&bodies, axis);
+            let state = AxisState::new(bodies, axis);
 
             if !positions.insert(state) {
                 println!("Found it! Step: {}", i);
-                display(&bodies, axis);
+                display(bodies, axis);
                 cycle_length_per_axis.push(i);
                 break;
             }
-            step(&mut bodies, axis);
+            step(bodies, axis);
 
             if i != 0 && i % 1_000_000 == 0 {
                 println!("Step {}", i);
                 println!("Hash set size: {}", positions.len());
-                display(&bodies, axis);
+                display(bodies, axis);
             }
             i += 1;
         }
     }
-    println!(
-        "Result: {}",
-        cycle_length_per_axis
-            .into_iter()
-            .map(|x| x as u64)
-            .fold(1 as u64, |a, b| integer::lcm(a, b))
-    );
-    Ok(())
+
+    modmath::checked_lcm_all(cycle_length_per_axis.into_iter().map(|x| x as u128))
+        .expect("Cycle length overflows a u128")
 }
 
-fn display(bodies: &Vec<Vec<Body>>, axis: usize) {
+fn display(bodies: &[Vec<Body>], axis: usize) {
     print!("pos: ");
     for body in bodies {
         print!("{} ", body[axis].position);
@@ -154,12 +203,12 @@ fn display(bodies: &Vec<Vec<Body>>, axis: usize) {
     println!();
 }
 
-fn step(bodies: &mut Vec<Vec<Body>>, axis: usize) {
-    for i in 0..bodies.len() {
-        bodies[i][axis].acceleration = 0;
+fn step(bodies: &mut [Vec<Body>], axis: usize) {
+    for body in bodies.iter_mut() {
+        body[axis].acceleration = 0;
     }
 
-    for i in 0..bodies.len() - 1 {
+    for i in 0..bodies.len().saturating_sub(1) {
         for j in i + 1..bodies.len() {
             let (v1, v2) = bodies.split_at_mut(i + 1);
             let body1 = &mut v1[i][axis];
@@ -168,8 +217,40 @@ fn step(bodies: &mut Vec<Vec<Body>>, axis: usize) {
         }
     }
 
-    for i in 0..bodies.len() {
-        bodies[i][axis].apply_acceleration();
-        bodies[i][axis].apply_velocity();
+    for body in bodies.iter_mut() {
+        body[axis].apply_acceleration();
+        body[axis].apply_velocity();
     }
 }
+
+#[test]
+fn parse_moon_with_spaces() {
+    let body = parse_moon(" <x=-1, y= 0,z=2> ").unwrap();
+    assert_eq!(
+        vec![-1, 0, 2],
+        body.iter().map(|b| b.position).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn parse_moon_missing_axis() {
+    assert!(parse_moon("<x=-1, y=0>").is_err());
+}
+
+#[test]
+fn first_example() {
+    let mut bodies = read_bodies("test.txt").unwrap();
+    assert_eq!(2772, find_cycle_length(&mut bodies));
+}
+
+#[test]
+fn second_example() {
+    let mut bodies = read_bodies("test2.txt").unwrap();
+    assert_eq!(4_686_774_924, find_cycle_length(&mut bodies));
+}
+
+#[test]
+fn any_number_of_moons() {
+    let mut bodies = vec![parse_moon("<x=1, y=2, z=3>").unwrap()];
+    assert_eq!(1, find_cycle_length(&mut bodies));
+}
@@ -0,0 +1,51 @@
+pub fn gcd(a: u128, b: u128) -> u128 {
+    let (mut a, mut b) = (a, b);
+    while b != 0 {
+        let r = a % b;
+        a = b;
+        b = r;
+    }
+    a
+}
+
+/// Least common multiple of a and b, or None if it doesn't fit in a u128
+pub fn checked_lcm(a: u128, b: u128) -> Option<u128> {
+    if a == 0 || b == 0 {
+        return Some(0);
+    }
+
+    (a / gcd(a, b)).checked_mul(b)
+}
+
+/// Least common multiple of all the values, or None if it overflows
+pub fn checked_lcm_all(values: impl IntoIterator<Item = u128>) -> Option<u128> {
+    values.into_iter().try_fold(1, checked_lcm)
+}
+
+#[test]
+fn gcd_values() {
+    assert_eq!(6, gcd(12, 18));
+    assert_eq!(7, gcd(7, 0));
+    assert_eq!(1, gcd(u128::MAX, u128::MAX - 1));
+}
+
+#[test]
+fn lcm_at_overflow_boundary() {
+    // 2^64 and 2^64 - 1 are coprime: their lcm is their product, just below u128::MAX
+    let a = 1u128 << 64;
+    let b = (1u128 << 64) - 1;
+    assert_eq!(Some(a * b), checked_lcm(a, b));
+    assert_eq!(None, checked_lcm(a * b, 7));
+    assert_eq!(Some(a * b), checked_lcm(a * b, b));
+}
+
+#[test]
+fn lcm_beyond_u64() {
+    let periods = vec![u64::MAX as u128, (u64::MAX - 1) as u128];
+    let lcm = checked_lcm_all(periods).unwrap();
+    assert!(lcm > u64::MAX as u128);
+    assert_eq!(
+        None,
+        checked_lcm_all(vec![lcm, 7, 11, u64::MAX as u128 - 2])
+    );
+}
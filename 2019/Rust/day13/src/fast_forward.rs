@@ -0,0 +1,104 @@
+use crate::GameEvent;
+
+/// Point of the game from which autoplay is shown
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum Threshold {
+    /// No more than this many blocks remain
+    Blocks(usize),
+    /// The score reached this value
+    Score(i64),
+}
+
+impl Threshold {
+    /// Threshold given with `--until-blocks <count>` or `--until-score <score>`
+    pub fn from_args(args: impl Iterator<Item = String>) -> Result<Option<Threshold>, String> {
+        let args: Vec<String> = args.collect();
+        let option = |name: &str| args.iter().skip_while(|a| *a != name).nth(1);
+        if let Some(count) = option("--until-blocks") {
+            let count = count
+                .parse()
+                .map_err(|_| format!("Invalid block count: {}", count))?;
+            return Ok(Some(Threshold::Blocks(count)));
+        }
+        if let Some(score) = option("--until-score") {
+            let score = score
+                .parse()
+                .map_err(|_| format!("Invalid score: {}", score))?;
+            return Ok(Some(Threshold::Score(score)));
+        }
+        Ok(None)
+    }
+
+    fn reached_by(&self, event: &GameEvent) -> bool {
+        match (self, event) {
+            (Threshold::Blocks(count), GameEvent::BlockDestroyed { remaining, .. }) => {
+                remaining <= count
+            }
+            (Threshold::Score(threshold), GameEvent::ScoreChanged(score)) => score >= threshold,
+            _ => false,
+        }
+    }
+}
+
+/// Follows the game events during autoplay, to tell when the frames should start being
+/// rendered. The game only depends on the joystick inputs, so the endgame shown is the
+/// same, frame for frame, as the one of a fully rendered autoplay.
+pub struct FastForward {
+    threshold: Threshold,
+    live: bool,
+}
+
+impl FastForward {
+    pub fn new(threshold: Threshold) -> FastForward {
+        FastForward {
+            threshold,
+            live: false,
+        }
+    }
+
+    /// Whether the frame producing these events should be rendered
+    pub fn observe(&mut self, events: &[GameEvent]) -> bool {
+        if !self.live {
+            self.live = events.iter().any(|e| self.threshold.reached_by(e));
+        }
+        self.live
+    }
+}
+
+#[cfg(test)]
+fn block_destroyed(remaining: usize) -> GameEvent {
+    GameEvent::BlockDestroyed {
+        position: (1, 1),
+        remaining,
+    }
+}
+
+#[test]
+fn goes_live_once_the_threshold_is_reached() {
+    let mut fast_forward = FastForward::new(Threshold::Blocks(10));
+    assert!(!fast_forward.observe(&[]));
+    assert!(!fast_forward.observe(&[GameEvent::ScoreChanged(5), block_destroyed(11)]));
+    assert!(fast_forward.observe(&[block_destroyed(10), GameEvent::ScoreChanged(9)]));
+    // Stays live until the end of the game
+    assert!(fast_forward.observe(&[]));
+
+    let mut fast_forward = FastForward::new(Threshold::Score(1000));
+    assert!(!fast_forward.observe(&[block_destroyed(0), GameEvent::ScoreChanged(999)]));
+    assert!(fast_forward.observe(&[GameEvent::ScoreChanged(1020)]));
+}
+
+#[test]
+fn thresholds_from_args() {
+    let args = |s: &str| s.split(' ').map(|a| a.to_string()).collect::<Vec<_>>();
+    let threshold = |s: &str| Threshold::from_args(args(s).into_iter());
+    assert_eq!(Ok(None), threshold("input.txt --autoplay"));
+    assert_eq!(
+        Ok(Some(Threshold::Blocks(20))),
+        threshold("input.txt --autoplay --until-blocks 20")
+    );
+    assert_eq!(
+        Ok(Some(Threshold::Score(5000))),
+        threshold("input.txt --until-score 5000")
+    );
+    assert!(threshold("input.txt --until-blocks few").is_err());
+}
@@ -1,18 +1,42 @@
+use crate::fast_forward::{FastForward, Threshold};
 use crate::memory::Memory;
+use crate::screen::Key;
 use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::env;
 use std::fs::File;
 use std::io::Read;
-extern crate ncurses;
-use ncurses::*; // watch for globs
+use std::process;
+use std::thread::sleep;
+use std::time::Duration;
 
+#[macro_use]
+mod strict;
+
+mod fast_forward;
+// Same glyphs as day08 and day11, which use the rest of the module
+#[allow(dead_code)]
+mod font;
 mod memory;
+mod screen;
+mod telemetry;
 
 type Result<T> = ::std::result::Result<T, Box<dyn ::std::error::Error>>;
 
+// Exit code for Intcode VM errors, shared with the runner
+const EXIT_VM_ERROR: i32 = 4;
+
+/// Reports an error of the Intcode VM, and exits with the runner's code for VM errors
+fn vm_error(message: String) -> ! {
+    eprintln!("VM error: {}", message);
+    process::exit(EXIT_VM_ERROR);
+}
+
 fn main() -> Result<()> {
     let file_name = env::args().nth(1).expect("Enter a file name");
+    screen::select_backend(env::args())?;
+    strict::enable_from_args(env::args());
+    telemetry::enable_from_args(env::args());
 
     let mut instructions = String::new();
     File::open(file_name)?
@@ -24,21 +48,33 @@ fn main() -> Result<()> {
     let mut context = ExecutionContext::new(&memory);
     context.memory[0] = 2;
 
-    let locale_conf = LcCategory::all;
-    setlocale(locale_conf, "en_GB.UTF-8");
-    initscr();
+    if env::args().any(|a| a == "--autoplay") {
+        let score = match Threshold::from_args(env::args())? {
+            Some(threshold) => watch_endgame(context, threshold)?,
+            None => autoplay(context, |_, events| {
+                if telemetry::enabled() {
+                    telemetry::frame();
+                }
+                for event in events {
+                    println!("{}", event);
+                }
+            })?,
+        };
+        println!("Final score: {}", score);
+        print_banner(score);
+        print_telemetry();
+        return Ok(());
+    }
+
+    screen::init();
 
     let mut backups: Vec<ExecutionContext> = vec![];
 
     loop {
-        if let ExecutionResult::Exit = execute_program(&mut context) {
-            if context
-                .panel
-                .values()
-                .filter(|t| **t == TileType::Block)
-                .count()
-                < 3
-            {
+        let result = execute_program(&mut context);
+        check_frame(&context.panel);
+        if let ExecutionResult::Exit = result {
+            if context.blocks_remaining < 3 {
                 break;
             }
 
@@ -51,62 +87,134 @@ fn main() -> Result<()> {
             backups.push(context.clone());
         }
         draw_panel(&context.panel, context.score);
-
-        // printw(&format!(
-        //     "Block tiles: {}",
-        //     context
-        //         .panel
-        //         .values()
-        //         .filter(|t| **t == TileType::Block)
-        //         .count()
-        // ));
-        let mut c = getch();
-        while c == 98
-        /*b*/
-        {
+        for event in context.events.drain(..) {
+            screen::print(&format!("{}\n", event));
+        }
+        screen::refresh();
+        // Backspace (or b) rewinds the game one frame at a time
+        let mut key = screen::read_key();
+        while let Key::Backspace | Key::Char('b') = key {
             if let Some(new_context) = backups.pop() {
                 context = new_context;
-                clear();
+                screen::clear();
                 draw_panel(&context.panel, context.score);
             }
-            c = getch();
+            key = screen::read_key();
         }
 
-        //let c = 97;
-        context.next_input = Some(match c {
-            32 /*space*/ => 0,
-            113 /*q*/ => -1,
-            97 /*a*/ => {
-                let ball = context.panel.keys().find(|p| context.panel[p] == TileType::Ball).unwrap();
-                let paddle = context.panel.keys().find(|p| context.panel[p] == TileType::Paddle).unwrap();
-                match ball.0.cmp(&paddle.0) {
-                    Ordering::Equal => 0,
-                    Ordering::Less => -1,
-                    Ordering::Greater => 1
-                 }
-            },
+        context.next_input = Some(match key {
+            Key::Char(' ') | Key::Down => 0,
+            Key::Char('q') | Key::Left => -1,
+            Key::Char('a') => follow_ball(&context.panel),
             _ => 1,
         });
 
-        if context
-            .panel
-            .values()
-            .filter(|t| **t == TileType::Block)
-            .count()
-            == 0
-        {
+        if context.blocks_remaining == 0 {
             break;
         }
-        ncurses::clear();
+        screen::clear();
         //println!("Key: {}", c);
     }
 
-    endwin();
+    screen::end();
     println!("GAME OVER! Final score: {}", context.score);
+    print_banner(context.score);
+    print_telemetry();
 
     Ok(())
 }
 
+/// With --banner, shows the score in large digits, for screenshots
+fn print_banner(score: i64) {
+    if env::args().any(|a| a == "--banner") {
+        println!("\n{}", font::banner(&score.to_string()));
+    }
+}
+
+/// With --telemetry, shows how many instructions ran between the frames
+fn print_telemetry() {
+    if telemetry::enabled() {
+        println!("{}", telemetry::stats_json());
+    }
+}
+
+fn check_frame(panel: &HashMap<(i32, i32), TileType>) {
+    let paddles = panel.values().filter(|t| **t == TileType::Paddle).count();
+    invariant!(paddles == 1, "{} paddles on the screen", paddles);
+}
+
+/// Joystick input moving the paddle towards the ball
+fn follow_ball(panel: &HashMap<(i32, i32), TileType>) -> i64 {
+    let ball = panel.keys().find(|p| panel[p] == TileType::Ball).unwrap();
+    let paddle = panel.keys().find(|p| panel[p] == TileType::Paddle).unwrap();
+    match ball.0.cmp(&paddle.0) {
+        Ordering::Equal => 0,
+        Ordering::Less => -1,
+        Ordering::Greater => 1,
+    }
+}
+
+/// Plays the game always following the ball, passing each frame and the score and block
+/// events it produced to `on_frame`. The final score is only returned if all the blocks
+/// have been destroyed.
+fn autoplay(
+    mut context: ExecutionContext,
+    mut on_frame: impl FnMut(&ExecutionContext, &[GameEvent]),
+) -> Result<i64> {
+    loop {
+        let result = execute_program(&mut context);
+        check_frame(&context.panel);
+        let events: Vec<GameEvent> = context.events.drain(..).collect();
+        on_frame(&context, &events);
+
+        if let ExecutionResult::Exit = result {
+            break;
+        }
+        context.next_input = Some(follow_ball(&context.panel));
+    }
+
+    if context.blocks_remaining != 0 {
+        return Err(format!(
+            "Game over with {} blocks remaining; score {} isn't the answer",
+            context.blocks_remaining, context.score
+        )
+        .into());
+    }
+    Ok(context.score)
+}
+
+/// With --until-blocks or --until-score, runs autoplay without rendering until the
+/// threshold is reached, then shows the rest of the game live
+fn watch_endgame(context: ExecutionContext, threshold: Threshold) -> Result<i64> {
+    const FRAME_DELAY: Duration = Duration::from_millis(20);
+
+    let mut fast_forward = FastForward::new(threshold);
+    let mut live = false;
+    let score = autoplay(context, |context, events| {
+        if !fast_forward.observe(events) {
+            if telemetry::enabled() {
+                telemetry::frame();
+            }
+            return;
+        }
+        if !live {
+            screen::init();
+            live = true;
+        }
+        screen::clear();
+        draw_panel(&context.panel, context.score);
+        for event in events {
+            screen::print(&format!("{}\n", event));
+        }
+        screen::refresh();
+        sleep(FRAME_DELAY);
+    });
+    if live {
+        screen::end();
+    }
+    score
+}
+
 fn draw_panel(panel: &HashMap<(i32, i32), TileType>, score: i64) {
     let x_max = panel.keys().map(|(x, _)| x).max().unwrap();
     let y_max = panel.keys().map(|(x, _)| x).max().unwrap();
@@ -121,13 +229,16 @@ fn draw_panel(panel: &HashMap<(i32, i32), TileType>, score: i64) {
                 TileType::Paddle => '-',
                 TileType::Ball => 'o',
             };
-            addstr(&format!("{}", c));
+            screen::print(&format!("{}", c));
         }
-        addstr("\n");
+        screen::print("\n");
     }
 
-    addstr(&format!("\nScore: {}\n", score));
-    refresh();
+    screen::print(&format!("\nScore: {}\n", score));
+    if telemetry::enabled() {
+        screen::print(&format!("{}\n", telemetry::overlay(telemetry::frame())));
+    }
+    screen::refresh();
 }
 
 #[derive(Clone)]
@@ -140,6 +251,34 @@ struct ExecutionContext {
     next_input: Option<i64>,
     output: Vec<i32>,
     score: i64,
+    blocks_remaining: usize,
+    events: Vec<GameEvent>,
+}
+
+/// Notable changes to the game state, reported as telemetry
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+enum GameEvent {
+    ScoreChanged(i64),
+    BlockDestroyed {
+        position: (i32, i32),
+        remaining: usize,
+    },
+}
+
+impl std::fmt::Display for GameEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            GameEvent::ScoreChanged(score) => write!(f, "score: {}", score),
+            GameEvent::BlockDestroyed {
+                position,
+                remaining,
+            } => write!(
+                f,
+                "block destroyed at {},{}; blocks remaining: {}",
+                position.0, position.1, remaining
+            ),
+        }
+    }
 }
 
 impl ExecutionContext {
@@ -153,6 +292,8 @@ impl ExecutionContext {
             output: vec![],
             next_input: Some(0),
             score: 0,
+            blocks_remaining: 0,
+            events: vec![],
         }
     }
 
@@ -170,7 +311,11 @@ impl ExecutionContext {
             let position = (self.output[0], self.output[1]);
 
             if position == (-1, 0) {
-                self.score = self.output[2] as i64;
+                let score = self.output[2] as i64;
+                if score != self.score {
+                    self.score = score;
+                    self.events.push(GameEvent::ScoreChanged(score));
+                }
             } else {
                 let tile_type = match self.output[2] {
                     0 => TileType::Empty,
@@ -178,10 +323,19 @@ impl ExecutionContext {
                     2 => TileType::Block,
                     3 => TileType::Paddle,
                     4 => TileType::Ball,
-                    x => panic!(format!("Invalid tile type: {}", x)),
+                    x => panic!("Invalid tile type: {}", x),
                 };
 
-                self.panel.insert(position, tile_type);
+                let previous = self.panel.insert(position, tile_type);
+                if previous == Some(TileType::Block) && tile_type != TileType::Block {
+                    self.blocks_remaining -= 1;
+                    self.events.push(GameEvent::BlockDestroyed {
+                        position,
+                        remaining: self.blocks_remaining,
+                    });
+                } else if previous != Some(TileType::Block) && tile_type == TileType::Block {
+                    self.blocks_remaining += 1;
+                }
             }
             self.output.clear();
         }
@@ -205,6 +359,7 @@ enum ExecutionResult {
 fn execute_program(context: &mut ExecutionContext) -> ExecutionResult {
     // println!("Executing program; ip: {}", context.ip.get());
     loop {
+        telemetry::count_instruction();
         match read_op_code(context) {
             (OpCode::Add, parameter_modes) => {
                 let (a, b, c) = extract_parameters3(context, parameter_modes);
@@ -232,15 +387,15 @@ fn execute_program(context: &mut ExecutionContext) -> ExecutionResult {
             }
             (OpCode::Output, parameter_modes) => {
                 let a = extract_parameter(context, parameter_modes);
-                let output = a.get(&context);
+                let output = a.get(context);
                 //println!("Output: {}", output);
                 context.write_output(output);
             }
             (OpCode::JumpIfTrue, parameter_modes) => {
                 let mut jump_address: Option<i64> = None;
                 let (a, b) = extract_parameters2(context, parameter_modes);
-                if a.get(&context) != 0 {
-                    jump_address = Some(b.get(&context));
+                if a.get(context) != 0 {
+                    jump_address = Some(b.get(context));
                 }
 
                 if let Some(address) = jump_address {
@@ -250,8 +405,8 @@ fn execute_program(context: &mut ExecutionContext) -> ExecutionResult {
             (OpCode::JumpIfFalse, parameter_modes) => {
                 let mut jump_address: Option<i64> = None;
                 let (a, b) = extract_parameters2(context, parameter_modes);
-                if a.get(&context) == 0 {
-                    jump_address = Some(b.get(&context));
+                if a.get(context) == 0 {
+                    jump_address = Some(b.get(context));
                 }
 
                 if let Some(address) = jump_address {
@@ -260,7 +415,7 @@ fn execute_program(context: &mut ExecutionContext) -> ExecutionResult {
             }
             (OpCode::LessThan, parameter_modes) => {
                 let (a, b, c) = extract_parameters3(context, parameter_modes);
-                let value = if a.get(&context) < b.get(&context) {
+                let value = if a.get(context) < b.get(context) {
                     1
                 } else {
                     0
@@ -269,7 +424,7 @@ fn execute_program(context: &mut ExecutionContext) -> ExecutionResult {
             }
             (OpCode::Equals, parameter_modes) => {
                 let (a, b, c) = extract_parameters3(context, parameter_modes);
-                let value = if a.get(&context) == b.get(&context) {
+                let value = if a.get(context) == b.get(context) {
                     1
                 } else {
                     0
@@ -278,7 +433,7 @@ fn execute_program(context: &mut ExecutionContext) -> ExecutionResult {
             }
             (OpCode::AdjustRelativeBase, parameter_modes) => {
                 let a = extract_parameter(context, parameter_modes);
-                let adjustment = a.get(&context);
+                let adjustment = a.get(context);
                 context.relative_base = (context.relative_base as i64 + adjustment) as usize;
             }
             (OpCode::Exit, _) => {
@@ -324,7 +479,7 @@ fn read_op_code(context: &mut ExecutionContext) -> (OpCode, u32) {
         8 => OpCode::Equals,
         9 => OpCode::AdjustRelativeBase,
         99 => OpCode::Exit,
-        x => panic!("Unknown op code: {}; ip: {}", x, context.ip),
+        x => vm_error(format!("Unknown op code: {}; ip: {}", x, context.ip)),
     };
 
     context.ip += 1;
@@ -363,7 +518,7 @@ fn get_parameter(context: &mut ExecutionContext, parameter_modes: &mut u32) -> P
         0 => ParameterMode::Position,
         1 => ParameterMode::Immediate,
         2 => ParameterMode::Relative,
-        x => panic!(format!("Incorrect parameter mode: {}", x)),
+        x => vm_error(format!("Incorrect parameter mode: {}", x)),
     };
     *parameter_modes /= 10;
 
@@ -385,7 +540,7 @@ enum Parameter {
     Reference(usize),
 }
 
-impl<'a> Parameter {
+impl Parameter {
     fn get(&self, context: &ExecutionContext) -> i64 {
         match self {
             Parameter::Reference(address) => context.memory[*address],
@@ -393,10 +548,10 @@ impl<'a> Parameter {
         }
     }
 
-    fn set(&self, value: i64, context: &mut ExecutionContext) -> () {
+    fn set(&self, value: i64, context: &mut ExecutionContext) {
         match self {
             Parameter::Reference(address) => context.memory[*address] = value,
-            Parameter::ImmediateValue(value) => panic!(format!(
+            Parameter::ImmediateValue(value) => vm_error(format!(
                 "Attempted to write value {} to an immediate parameter",
                 value
             )),
@@ -0,0 +1,127 @@
+use crossterm::cursor::MoveTo;
+use crossterm::event::{read, Event, KeyCode};
+use crossterm::style::Print;
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode, Clear, ClearType};
+use crossterm::{execute, queue};
+use std::io::{stdout, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Terminal output goes through crossterm, unless ncurses has been selected with
+// `--ui ncurses`, which requires the `ncurses-ui` feature
+static USE_NCURSES: AtomicBool = AtomicBool::new(false);
+
+pub fn select_backend(args: impl Iterator<Item = String>) -> Result<(), String> {
+    match args.skip_while(|a| a != "--ui").nth(1).as_deref() {
+        None | Some("crossterm") => Ok(()),
+        #[cfg(feature = "ncurses-ui")]
+        Some("ncurses") => {
+            USE_NCURSES.store(true, Ordering::Relaxed);
+            Ok(())
+        }
+        Some(x) => Err(format!("Unsupported UI: {}", x)),
+    }
+}
+
+fn use_ncurses() -> bool {
+    cfg!(feature = "ncurses-ui") && USE_NCURSES.load(Ordering::Relaxed)
+}
+
+pub fn init() {
+    if use_ncurses() {
+        #[cfg(feature = "ncurses-ui")]
+        {
+            ncurses::setlocale(ncurses::LcCategory::all, "en_GB.UTF-8");
+            ncurses::initscr();
+            ncurses::keypad(ncurses::stdscr(), true);
+            ncurses::noecho();
+        }
+    } else {
+        execute!(stdout(), Clear(ClearType::All), MoveTo(0, 0)).ok();
+    }
+}
+
+pub fn end() {
+    if use_ncurses() {
+        #[cfg(feature = "ncurses-ui")]
+        ncurses::endwin();
+    }
+}
+
+pub fn clear() {
+    if use_ncurses() {
+        #[cfg(feature = "ncurses-ui")]
+        ncurses::clear();
+    } else {
+        queue!(stdout(), Clear(ClearType::All), MoveTo(0, 0)).ok();
+    }
+}
+
+pub fn print(msg: &str) {
+    if use_ncurses() {
+        #[cfg(feature = "ncurses-ui")]
+        ncurses::addstr(msg);
+    } else {
+        queue!(stdout(), Print(msg)).ok();
+    }
+}
+
+pub fn refresh() {
+    if use_ncurses() {
+        #[cfg(feature = "ncurses-ui")]
+        ncurses::refresh();
+    } else {
+        stdout().flush().ok();
+    }
+}
+
+/// Keys read from the terminal, as soon as they are pressed
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Key {
+    Char(char),
+    Left,
+    Right,
+    Down,
+    Backspace,
+    Enter,
+    Esc,
+    /// The terminal couldn't be read
+    None,
+}
+
+/// Waits for a key press, with the terminal in raw mode
+pub fn read_key() -> Key {
+    if use_ncurses() {
+        #[cfg(feature = "ncurses-ui")]
+        return match ncurses::getch() {
+            ncurses::KEY_LEFT => Key::Left,
+            ncurses::KEY_RIGHT => Key::Right,
+            ncurses::KEY_DOWN => Key::Down,
+            ncurses::KEY_BACKSPACE | 127 => Key::Backspace,
+            10 => Key::Enter,
+            27 => Key::Esc,
+            c if c >= 0 => std::char::from_u32(c as u32).map_or(Key::None, Key::Char),
+            _ => Key::None,
+        };
+    }
+
+    refresh();
+    enable_raw_mode().ok();
+    let key = loop {
+        match read() {
+            Ok(Event::Key(event)) => match event.code {
+                KeyCode::Char(c) => break Key::Char(c),
+                KeyCode::Left => break Key::Left,
+                KeyCode::Right => break Key::Right,
+                KeyCode::Down => break Key::Down,
+                KeyCode::Backspace => break Key::Backspace,
+                KeyCode::Enter => break Key::Enter,
+                KeyCode::Esc => break Key::Esc,
+                _ => (),
+            },
+            Ok(_) => (),
+            Err(_) => break Key::None,
+        }
+    };
+    disable_raw_mode().ok();
+    key
+}
@@ -0,0 +1,29 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static STRICT: AtomicBool = AtomicBool::new(false);
+
+/// Enables the invariant checks if `--strict` is among the arguments
+pub fn enable_from_args(mut args: impl Iterator<Item = String>) {
+    if args.any(|a| a == "--strict") {
+        STRICT.store(true, Ordering::Relaxed);
+    }
+}
+
+pub fn enabled() -> bool {
+    STRICT.load(Ordering::Relaxed)
+}
+
+/// Checks a fact about the puzzle that should always hold, and panics with its location
+/// when it doesn't. Only evaluated in strict mode.
+macro_rules! invariant {
+    ($cond:expr, $($arg:tt)+) => {
+        if $crate::strict::enabled() && !$cond {
+            panic!(
+                "Invariant violated at {}:{}: {}",
+                file!(),
+                line!(),
+                format_args!($($arg)+)
+            );
+        }
+    };
+}
@@ -0,0 +1,69 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+static TELEMETRY: AtomicBool = AtomicBool::new(false);
+
+/// Instructions executed since the last frame
+static SINCE_LAST_FRAME: AtomicU64 = AtomicU64::new(0);
+static FRAMES: AtomicU64 = AtomicU64::new(0);
+static INSTRUCTIONS: AtomicU64 = AtomicU64::new(0);
+static MIN_PER_FRAME: AtomicU64 = AtomicU64::new(u64::MAX);
+static MAX_PER_FRAME: AtomicU64 = AtomicU64::new(0);
+
+/// Enables the instruction counting if `--telemetry` is among the arguments
+pub fn enable_from_args(mut args: impl Iterator<Item = String>) {
+    if args.any(|a| a == "--telemetry") {
+        TELEMETRY.store(true, Ordering::Relaxed);
+    }
+}
+
+pub fn enabled() -> bool {
+    TELEMETRY.load(Ordering::Relaxed)
+}
+
+/// Called by the VM for each instruction it executes
+pub fn count_instruction() {
+    if enabled() {
+        SINCE_LAST_FRAME.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Marks a rendered frame; returns how many instructions ran since the previous one
+pub fn frame() -> u64 {
+    let count = SINCE_LAST_FRAME.swap(0, Ordering::Relaxed);
+    FRAMES.fetch_add(1, Ordering::Relaxed);
+    INSTRUCTIONS.fetch_add(count, Ordering::Relaxed);
+    MIN_PER_FRAME.fetch_min(count, Ordering::Relaxed);
+    MAX_PER_FRAME.fetch_max(count, Ordering::Relaxed);
+    count
+}
+
+/// Line shown under the frame
+pub fn overlay(count: u64) -> String {
+    format!(
+        "Frame {}: {} instructions",
+        FRAMES.load(Ordering::Relaxed),
+        count
+    )
+}
+
+/// Instructions per frame over the whole run, as a JSON object
+pub fn stats_json() -> String {
+    let frames = FRAMES.load(Ordering::Relaxed);
+    let instructions = INSTRUCTIONS.load(Ordering::Relaxed);
+    let (min, mean) = match frames {
+        0 => (0, 0.0),
+        _ => (
+            MIN_PER_FRAME.load(Ordering::Relaxed),
+            instructions as f64 / frames as f64,
+        ),
+    };
+    format!(
+        "{{\"frames\": {}, \"instructions\": {}, \"instructions_per_frame\": \
+         {{\"min\": {}, \"max\": {}, \"mean\": {:.1}}}}}",
+        frames,
+        instructions,
+        min,
+        MAX_PER_FRAME.load(Ordering::Relaxed),
+        mean
+    )
+}
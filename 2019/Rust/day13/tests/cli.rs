@@ -0,0 +1,27 @@
+use std::process::Command;
+
+#[test]
+fn autoplay_from_temp_input_is_headless() {
+    // Run on a copy of the input, from another directory
+    let input = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/input.txt"))
+        .expect("Failed to read input.txt");
+    let path = std::env::temp_dir().join(format!("day13_cli_{}.txt", std::process::id()));
+    std::fs::write(&path, input).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_day13"))
+        .arg(&path)
+        .arg("--autoplay")
+        .current_dir(std::env::temp_dir())
+        .output()
+        .expect("Failed to run day13");
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(Some(0), output.status.code());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("blocks remaining: 0"));
+    assert!(stdout.lines().last().unwrap().starts_with("Final score: "));
+    assert!(
+        !stdout.contains('\x1b'),
+        "ANSI escape codes in the autoplay output"
+    );
+}
@@ -0,0 +1,252 @@
+use crate::Chemical;
+use std::collections::HashMap;
+use std::fmt;
+
+const ORE: &str = "ORE";
+const FUEL: &str = "FUEL";
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Overflow;
+
+impl fmt::Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Quantity overflow while computing the ore requirements")
+    }
+}
+
+impl std::error::Error for Overflow {}
+
+/// Orders the chemicals so that each one comes before all the chemicals it's made of
+fn production_order(chemicals: &HashMap<String, Chemical>) -> Vec<&str> {
+    // Count how many reactions consume each chemical
+    let mut consumers: HashMap<&str, usize> = HashMap::new();
+    for chemical in chemicals.values() {
+        for source in chemical.sources.keys() {
+            *consumers.entry(source).or_default() += 1;
+        }
+    }
+
+    let mut order = vec![];
+    let mut ready = vec![FUEL];
+    while let Some(name) = ready.pop() {
+        order.push(name);
+        if let Some(chemical) = chemicals.get(name) {
+            for source in chemical.sources.keys() {
+                let count = consumers.get_mut(source.as_str()).unwrap();
+                *count -= 1;
+                if *count == 0 {
+                    ready.push(source);
+                }
+            }
+        }
+    }
+    order
+}
+
+/// Number of reactions needed for each chemical to produce the given amount of fuel.
+/// ORE is reported as the quantity needed, since there's no reaction producing it.
+fn required_batches(
+    chemicals: &HashMap<String, Chemical>,
+    fuel: u128,
+) -> Result<HashMap<&str, u128>, Overflow> {
+    let mut needed: HashMap<&str, u128> = HashMap::new();
+    needed.insert(FUEL, fuel);
+
+    let mut batches = HashMap::new();
+    for name in production_order(chemicals) {
+        let qty = needed.get(name).cloned().unwrap_or_default();
+        let chemical = match chemicals.get(name) {
+            Some(chemical) => chemical,
+            None => {
+                batches.insert(name, qty);
+                continue;
+            }
+        };
+
+        let count = qty / chemical.qty + if qty % chemical.qty == 0 { 0 } else { 1 };
+        batches.insert(name, count);
+        for (source, source_qty) in &chemical.sources {
+            let extra = source_qty.checked_mul(count).ok_or(Overflow)?;
+            let total = needed.entry(source).or_default();
+            *total = total.checked_add(extra).ok_or(Overflow)?;
+        }
+    }
+    Ok(batches)
+}
+
+/// Amount of ore needed to produce the given amount of fuel
+pub fn ore_required(chemicals: &HashMap<String, Chemical>, fuel: u128) -> Result<u128, Overflow> {
+    Ok(required_batches(chemicals, fuel)?
+        .get(ORE)
+        .cloned()
+        .unwrap_or_default())
+}
+
+/// Largest amount of fuel that can be produced with the ore budget
+pub fn max_fuel(chemicals: &HashMap<String, Chemical>, budget: u128) -> u128 {
+    let fits = |fuel| matches!(ore_required(chemicals, fuel), Ok(ore) if ore <= budget);
+
+    let mut low = 0;
+    let mut high = 1;
+    while fits(high) {
+        low = high;
+        high = high.saturating_mul(2);
+        if low == u128::MAX {
+            return low;
+        }
+    }
+
+    // fits(low) and !fits(high)
+    while high - low > 1 {
+        let middle = low + (high - low) / 2;
+        if fits(middle) {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    low
+}
+
+/// Reactions that prevent producing one more unit of fuel with the budget
+#[derive(Debug, PartialEq, Eq)]
+pub struct LimitingChain {
+    pub fuel: u128,
+    /// Chemicals from FUEL down to ORE, each needing more reactions for the extra fuel
+    pub chain: Vec<String>,
+    pub extra_ore: u128,
+    pub ore_left: u128,
+}
+
+impl fmt::Display for LimitingChain {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} fuel; one more needs {} more ore through {}, but only {} is left",
+            self.fuel,
+            self.extra_ore,
+            self.chain.join(" <- "),
+            self.ore_left
+        )
+    }
+}
+
+/// Finds the chain of reactions whose extra batches cost the most ore when trying to
+/// produce one more unit of fuel than the budget allows
+pub fn limiting_chain(
+    chemicals: &HashMap<String, Chemical>,
+    budget: u128,
+) -> Result<LimitingChain, Overflow> {
+    let fuel = max_fuel(chemicals, budget);
+    let before = required_batches(chemicals, fuel)?;
+    let after = required_batches(chemicals, fuel.checked_add(1).ok_or(Overflow)?)?;
+    let extra = |name: &str| {
+        after.get(name).cloned().unwrap_or_default() - before.get(name).cloned().unwrap_or_default()
+    };
+
+    // Start from the reaction using ore whose extra batches cost the most ore
+    let ore_cost = |name: &str| {
+        chemicals[name]
+            .sources
+            .get(ORE)
+            .map_or(0, |ore| ore * extra(name))
+    };
+    let mut current = match chemicals
+        .keys()
+        .map(|name| name.as_str())
+        .filter(|name| ore_cost(name) > 0)
+        .max_by_key(|name| (ore_cost(name), *name))
+    {
+        Some(name) => name,
+        None => FUEL,
+    };
+
+    // Then go up to the fuel through the consumers that also needed more batches
+    let mut chain = vec![ORE.to_string(), current.to_string()];
+    while current != FUEL {
+        current = chemicals
+            .values()
+            .filter(|c| c.sources.contains_key(current) && extra(&c.name) > 0)
+            .map(|c| c.name.as_str())
+            .max_by_key(|name| (extra(name), *name))
+            .expect("Chemical with extra batches but no consumer needing more");
+        chain.push(current.to_string());
+    }
+    chain.reverse();
+
+    Ok(LimitingChain {
+        fuel,
+        chain,
+        extra_ore: extra(ORE),
+        ore_left: budget - before[ORE],
+    })
+}
+
+#[cfg(test)]
+fn parse(text: &str) -> HashMap<String, Chemical> {
+    text.lines().map(crate::parse_reaction).collect()
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = "157 ORE => 5 NZVS
+165 ORE => 6 DCFZ
+44 XJWVT, 5 KHKGT, 1 QDVJ, 29 NZVS, 9 GPVTF, 48 HKGWZ => 1 FUEL
+12 HKGWZ, 1 GPVTF, 8 PSHF => 9 QDVJ
+179 ORE => 7 PSHF
+177 ORE => 5 HKGWZ
+7 DCFZ, 7 PSHF => 2 XJWVT
+165 ORE => 2 GPVTF
+3 DCFZ, 7 NZVS, 5 HKGWZ, 10 PSHF => 8 KHKGT";
+
+#[test]
+fn example() {
+    let chemicals = parse(EXAMPLE);
+    assert_eq!(Ok(13312), ore_required(&chemicals, 1));
+    assert_eq!(82892753, max_fuel(&chemicals, 1_000_000_000_000));
+}
+
+#[test]
+fn simple_chain() {
+    let chemicals = parse(
+        "10 ORE => 10 A
+1 ORE => 1 B
+7 A, 1 B => 1 C
+7 A, 1 C => 1 D
+7 A, 1 D => 1 E
+7 A, 1 E => 1 FUEL",
+    );
+    assert_eq!(Ok(31), ore_required(&chemicals, 1));
+}
+
+#[test]
+fn overflow_boundaries() {
+    let chemicals = parse("2 ORE => 1 FUEL");
+    assert_eq!(Ok(u128::MAX - 1), ore_required(&chemicals, u128::MAX / 2));
+    assert_eq!(Err(Overflow), ore_required(&chemicals, u128::MAX / 2 + 1));
+    assert_eq!(u128::MAX / 2, max_fuel(&chemicals, u128::MAX));
+
+    let chemicals = parse("1 ORE => 1 FUEL");
+    assert_eq!(Ok(u128::MAX), ore_required(&chemicals, u128::MAX));
+    assert_eq!(u128::MAX, max_fuel(&chemicals, u128::MAX));
+}
+
+#[test]
+fn overflow_in_intermediate_chemical() {
+    // The total ore fits, but the amount of A doesn't
+    let chemicals = parse("1 ORE => 1000 A\n1000 A => 1 FUEL");
+    assert_eq!(
+        Ok(u128::MAX / 1000),
+        ore_required(&chemicals, u128::MAX / 1000)
+    );
+    assert_eq!(Err(Overflow), ore_required(&chemicals, u128::MAX / 999));
+}
+
+#[test]
+fn limiting_chain_for_budget() {
+    let chemicals = parse("3 ORE => 1 A\n1 ORE => 1 B\n1 A, 1 B => 1 FUEL");
+    let explanation = limiting_chain(&chemicals, 10).unwrap();
+    assert_eq!(2, explanation.fuel);
+    assert_eq!(vec!["FUEL", "A", "ORE"], explanation.chain);
+    assert_eq!(4, explanation.extra_ore);
+    assert_eq!(2, explanation.ore_left);
+}
@@ -0,0 +1,142 @@
+use std::collections::VecDeque;
+
+/// A program that can be driven one exchange at a time
+pub trait Vm {
+    /// Queues values for the program to read
+    fn push_input(&mut self, values: &[i64]);
+    /// Runs the program until it needs more input or exits; returns false once it has exited
+    fn run(&mut self) -> bool;
+    /// Takes the values output since the last call
+    fn take_output(&mut self) -> Vec<i64>;
+}
+
+/// How to tell where a response from the program ends
+#[derive(Debug, Clone)]
+pub enum Delimiter {
+    /// Each response is a fixed number of values
+    Length(usize),
+    /// A response is everything the program outputs before asking for more input
+    InputRequest,
+}
+
+/// Typed response built from the values output by the program
+pub trait FromOutput {
+    fn from_output(output: &[i64]) -> Self;
+}
+
+impl FromOutput for Vec<i64> {
+    fn from_output(output: &[i64]) -> Vec<i64> {
+        output.to_vec()
+    }
+}
+
+/// Request/response exchange with a program: each command is sent as input, and the
+/// program runs until a complete response is available
+pub struct Dialogue<V: Vm> {
+    vm: V,
+    delimiter: Delimiter,
+    pending: VecDeque<i64>,
+    ended: bool,
+}
+
+impl<V: Vm> Dialogue<V> {
+    pub fn new(vm: V, delimiter: Delimiter) -> Dialogue<V> {
+        Dialogue {
+            vm,
+            delimiter,
+            pending: VecDeque::new(),
+            ended: false,
+        }
+    }
+
+    pub fn vm(&self) -> &V {
+        &self.vm
+    }
+
+    /// Whether the program has exited
+    pub fn ended(&self) -> bool {
+        self.ended
+    }
+
+    /// Sends the command and returns the next response: the program runs until it has
+    /// output a complete response, or needs more input. Returns None if the program has
+    /// exited without responding.
+    pub fn send<R: FromOutput>(&mut self, command: &[i64]) -> Option<R> {
+        self.vm.push_input(command);
+        if self.complete_response_len().is_none() && !self.ended {
+            self.ended = !self.vm.run();
+            self.pending.extend(self.vm.take_output());
+        }
+
+        let len = self.complete_response_len().unwrap_or(self.pending.len());
+        if len == 0 && self.ended {
+            return None;
+        }
+        let response: Vec<i64> = self.pending.drain(..len).collect();
+        Some(R::from_output(&response))
+    }
+
+    fn complete_response_len(&self) -> Option<usize> {
+        match self.delimiter {
+            Delimiter::Length(len) if self.pending.len() >= len => Some(len),
+            _ => None,
+        }
+    }
+}
+
+/// Program doubling each value it reads, and exiting when reading 0
+#[cfg(test)]
+struct Doubler {
+    input: VecDeque<i64>,
+    output: Vec<i64>,
+}
+
+#[cfg(test)]
+impl Vm for Doubler {
+    fn push_input(&mut self, values: &[i64]) {
+        self.input.extend(values);
+    }
+
+    fn run(&mut self) -> bool {
+        while let Some(value) = self.input.pop_front() {
+            if value == 0 {
+                return false;
+            }
+            self.output.push(value * 2);
+        }
+        true
+    }
+
+    fn take_output(&mut self) -> Vec<i64> {
+        self.output.drain(..).collect()
+    }
+}
+
+#[cfg(test)]
+fn doubler(delimiter: Delimiter) -> Dialogue<Doubler> {
+    let vm = Doubler {
+        input: VecDeque::new(),
+        output: vec![],
+    };
+    Dialogue::new(vm, delimiter)
+}
+
+#[test]
+fn fixed_length_responses() {
+    let mut dialogue = doubler(Delimiter::Length(2));
+    assert_eq!(Some(vec![2, 4]), dialogue.send(&[1, 2, 3]));
+    // The extra value is kept until the response is complete
+    assert_eq!(Some(vec![6, 8]), dialogue.send(&[4]));
+    assert_eq!(None::<Vec<i64>>, dialogue.send(&[0]));
+    assert!(dialogue.ended());
+}
+
+#[test]
+fn responses_up_to_input_request() {
+    let mut dialogue = doubler(Delimiter::InputRequest);
+    assert_eq!(Some(vec![]), dialogue.send(&[]));
+    assert_eq!(Some(vec![2, 4]), dialogue.send(&[1, 2]));
+    assert_eq!(Some(vec![6]), dialogue.send(&[3, 0, 4]));
+    assert!(dialogue.ended());
+    assert_eq!(None::<Vec<i64>>, dialogue.send(&[5]));
+}
@@ -1,20 +1,42 @@
+use crate::dialogue::{Delimiter, Dialogue, FromOutput, Vm};
+use crate::explore::{Oracle, Space};
+use crate::maze::{Map, Position, Tile};
 use crate::memory::Memory;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::env;
 use std::fs::File;
 use std::io::Read;
+use std::process;
 use std::thread::sleep;
 use std::time::Duration;
 
-#[cfg(unix)]
-extern crate ncurses;
-
+// Same dialogue as day25, which uses the rest of the module
+#[allow(dead_code)]
+mod dialogue;
+mod explore;
+mod maze;
 mod memory;
+#[cfg(all(unix, feature = "ui"))]
+mod screen;
+mod telemetry;
 
 type Result<T> = ::std::result::Result<T, Box<dyn ::std::error::Error>>;
 
+// Exit code for Intcode VM errors, shared with the runner
+const EXIT_VM_ERROR: i32 = 4;
+
+/// Reports an error of the Intcode VM, and exits with the runner's code for VM errors
+fn vm_error(message: String) -> ! {
+    eprintln!("VM error: {}", message);
+    process::exit(EXIT_VM_ERROR);
+}
+type Grid = HashMap<(i32, i32), CellStatus>;
+
 fn main() -> Result<()> {
     let file_name = env::args().nth(1).expect("Enter a file name");
+    #[cfg(all(unix, feature = "ui"))]
+    screen::select_backend(env::args())?;
+    telemetry::enable_from_args(env::args());
 
     let mut instructions = String::new();
     File::open(file_name)?
@@ -25,342 +47,68 @@ fn main() -> Result<()> {
     clear();
     let memory = Memory::parse(&instructions);
 
-    let mut context = ExecutionContext::new(&memory);
-    context.grid.insert((0, 0), CellStatus::Origin);
-
-    let mut next_move = Move::North;
-    let mut current_position: (i32, i32) = (0, 0);
-    let mut loop_count = 0;
-
-    loop {
-        context.next_input = Some(match next_move {
-            Move::North => 1,
-            Move::South => 2,
-            Move::West => 3,
-            Move::East => 4,
-        });
-        let execution_result = execute_program(&mut context);
-        //println!("Result: {:?}", context.result);
-        let target_position = apply_move(current_position, next_move);
-
-        let found_new_cell = !context.grid.contains_key(&target_position);
-        let should_update_cell_status = |ctx: &ExecutionContext, position: (i32, i32)| {
-            match get_cell_status(ctx, position) {
-                CellStatus::Origin | CellStatus::Wall | CellStatus::VisitedAll(_) => None,
-                _ => {
-                    // How many visited-all or Wall positions do we have around the position
-                    let dead_end_count = get_positions_around(position)
-                        .iter()
-                        .filter(|pos| {
-                            let status = get_cell_status(&ctx, **pos);
-                            //println!("Status for {:?}: {:?}", pos, status);
-                            match status {
-                                CellStatus::Origin
-                                | CellStatus::VisitedAll(_)
-                                | CellStatus::Wall
-                                | CellStatus::Oxygen => true,
-                                _ => false,
-                            }
-                        })
-                        .count();
-
-                    //println!("Dead-ends for {:?}: {}", position, dead_end_count);
-                    //draw_grid(&ctx.grid, Some(current_position));
-                    if get_cell_status(ctx, position) != CellStatus::Origin {
-                        // Mark the cell as visited; and compute the length from origin
-                        let min_neighbouring_length = get_positions_around(position)
-                            .iter()
-                            .flat_map(|pos| match get_cell_status(&ctx, *pos) {
-                                CellStatus::Visited(x) | CellStatus::VisitedAll(x) => Some(x),
-                                CellStatus::Origin | CellStatus::Oxygen => Some(0),
-                                _ => None,
-                            })
-                            .min();
-
-                        let new_status = if dead_end_count >= 3 {
-                            CellStatus::VisitedAll(
-                                min_neighbouring_length.expect("No neighbours?") + 1,
-                            )
-                        } else {
-                            CellStatus::Visited(
-                                min_neighbouring_length.expect("No neighbours?") + 1,
-                            )
-                        };
-                        Some(new_status)
-                    } else {
-                        None
-                    }
-                }
-            }
-        };
-
-        match context.result {
-            MoveResult::Moved => {
-                if let Some(status) = should_update_cell_status(&context, target_position) {
-                    context.grid.insert(target_position, status);
-                }
-                current_position = target_position;
-            }
-            MoveResult::HitWall => {
-                context.grid.insert(target_position, CellStatus::Wall);
-                if let Some(status) = should_update_cell_status(&context, current_position) {
-                    context.grid.insert(current_position, status);
-                }
-            }
-            MoveResult::FoundOxygen => {
-                context.grid.insert(target_position, CellStatus::Oxygen);
-                current_position = target_position;
-                draw_grid(&context.grid, None);
-                display_oxygen_location(&context, current_position);
-                break;
-            }
-        };
-
-        let search_for_next_move = || {
-            let unknown_neighbor_move = get_all_moves().into_iter().find(|m| {
-                let pos = apply_move(current_position, *m);
-                get_cell_status(&context, pos) == CellStatus::Unknown
-            });
-
-            // Find an unknown neighbour first
-            if unknown_neighbor_move.is_some() {
-                return unknown_neighbor_move;
-            }
-
-            // Find a neighbour that is not in a final state
-            let non_final_neighbor_move = get_all_moves().into_iter().find(|m| {
-                let pos = apply_move(current_position, *m);
-                // println!(
-                //     "Trying out moving {:?} to {:?}: {:?}",
-                //     next_move,
-                //     new_pos,
-                //     get_cell_status(&context, new_pos)
-                // );
-                match get_cell_status(&context, pos) {
-                    CellStatus::Origin | CellStatus::Wall | CellStatus::VisitedAll(_) => false,
-                    _ => true,
-                }
-            });
-            non_final_neighbor_move
-        };
-
-        let next_move_search_result = search_for_next_move();
-        match next_move_search_result {
-            Some(m) => next_move = m,
-            _ => {
-                println!("All done!");
-                draw_grid(&context.grid, Some(current_position));
-                break;
-            }
-        }
-
-        loop_count += 1;
-        if found_new_cell || loop_count % 1_000_000 == 0 {
-            draw_grid(&context.grid, Some(current_position));
-        }
-
-        if let ExecutionResult::Exit = execution_result {
-            break;
-        }
+    // The droid answers each move with a single status value
+    let mut dialogue = Dialogue::new(ExecutionContext::new(&memory), Delimiter::Length(1));
+    let mut area = Area::new();
+    let graph = explore::explore(&mut area, &mut dialogue, (0, 0));
+    draw_grid(&area.grid, Some((0, 0)));
+    if graph.complete {
+        println!("Map fully explored after {} moves", graph.queries);
     }
 
-    // ======================================================================
-    // Second phase: Find the longest path from the current position
-    // ======================================================================
-    // Reset all the Visited_all positions
-    for (_, v) in context.grid.iter_mut() {
-        match v {
-            //CellStatus::VisitedAll(value) => *v = CellStatus::Visited(*value),
-            CellStatus::VisitedAll(_) | CellStatus::Visited(_) => *v = CellStatus::Unknown,
-            _ => (),
-        }
+    let oxygen_position = area.oxygen;
+    if let Some(oxygen) = oxygen_position {
+        let path = graph.path(&(0, 0), &oxygen).unwrap_or_default();
+        display_oxygen_location(path.len(), oxygen);
+        // The oxygen spreads to the neighbouring cells every minute
+        let max_length = graph.distances(&oxygen).values().max().cloned();
+        println!("Max length: {}", max_length.unwrap());
     }
 
-    draw_grid(&context.grid, Some(current_position));
-    loop {
-        context.next_input = Some(match next_move {
-            Move::North => 1,
-            Move::South => 2,
-            Move::West => 3,
-            Move::East => 4,
-        });
-        let execution_result = execute_program(&mut context);
-        //println!("Result: {:?}", context.result);
-        let target_position = apply_move(current_position, next_move);
-
-        let found_new_cell = !context.grid.contains_key(&target_position);
-        let should_update_cell_status =
-            |ctx: &ExecutionContext, position: (i32, i32)| -> Option<CellStatus> {
-                match get_cell_status(ctx, position) {
-                    CellStatus::Origin | CellStatus::Wall | CellStatus::VisitedAll(_) => None,
-                    _ => {
-                        // How many visited-all or Wall positions do we have around the position
-                        let dead_end_count = get_positions_around(position)
-                            .iter()
-                            .filter(|pos| {
-                                let status = get_cell_status(&ctx, **pos);
-                                //println!("Status for {:?}: {:?}", pos, status);
-                                match status {
-                                    CellStatus::Origin
-                                    | CellStatus::VisitedAll(_)
-                                    | CellStatus::Wall
-                                    | CellStatus::Oxygen => true,
-                                    _ => false,
-                                }
-                            })
-                            .count();
-
-                        //println!("Dead-ends for {:?}: {}", position, dead_end_count);
-                        //draw_grid(&ctx.grid, Some(current_position));
-
-                        if get_cell_status(ctx, position) != CellStatus::Oxygen {
-                            // Mark the cell as visited; and compute the length from origin
-                            let mut min_neighbouring_length = get_positions_around(position)
-                                .iter()
-                                .flat_map(|pos| match get_cell_status(&ctx, *pos) {
-                                    CellStatus::Visited(x) | CellStatus::VisitedAll(x) => Some(x),
-                                    CellStatus::Origin => None,
-                                    CellStatus::Oxygen => Some(0),
-                                    _ => None,
-                                })
-                                .min();
-
-                            if min_neighbouring_length.is_none() {
-                                println!("Couldn't find any neighbours for {:?}", position);
-                                for p in get_positions_around(position) {
-                                    let status = get_cell_status(&ctx, p);
-                                    println!("{:?}: {:?}", p, status);
-                                }
-
-                                min_neighbouring_length = Some(0);
-                            }
-
-                            let new_status = if dead_end_count >= 3 {
-                                CellStatus::VisitedAll(
-                                    min_neighbouring_length.expect("No neighbours?") + 1,
-                                )
-                            } else {
-                                CellStatus::Visited(
-                                    min_neighbouring_length.expect("No neighbours?") + 1,
-                                )
-                            };
-                            Some(new_status)
-                        } else {
-                            None
-                        }
-                    }
-                }
-            };
-
-        match context.result {
-            MoveResult::Moved => {
-                if let Some(status) = should_update_cell_status(&context, target_position) {
-                    context.grid.insert(target_position, status);
-                }
-                current_position = target_position;
-            }
-            MoveResult::HitWall => {
-                context.grid.insert(target_position, CellStatus::Wall);
-                if let Some(status) = should_update_cell_status(&context, current_position) {
-                    context.grid.insert(current_position, status);
-                }
-            }
-            MoveResult::FoundOxygen => {
-                context.grid.insert(target_position, CellStatus::Oxygen);
-                current_position = target_position;
-                draw_grid(&context.grid, None);
-                display_oxygen_location(&context, current_position);
-                break;
-            }
-        };
-
-        draw_grid(&context.grid, None);
-        let search_for_next_move = || {
-            let unknown_neighbor_move = get_all_moves().into_iter().find(|m| {
-                let pos = apply_move(current_position, *m);
-                get_cell_status(&context, pos) == CellStatus::Unknown
-            });
-
-            // Find an unknown neighbour first
-            if unknown_neighbor_move.is_some() {
-                return unknown_neighbor_move;
-            }
-
-            // Find a neighbour that is not in a final state
-            let non_final_neighbor_move = get_all_moves().into_iter().find(|m| {
-                let pos = apply_move(current_position, *m);
-                // println!(
-                //     "Trying out moving {:?} to {:?}: {:?}",
-                //     next_move,
-                //     new_pos,
-                //     get_cell_status(&context, new_pos)
-                // );
-                match get_cell_status(&context, pos) {
-                    CellStatus::Origin | CellStatus::Wall | CellStatus::VisitedAll(_) => false,
-                    _ => true,
-                }
-            });
-            non_final_neighbor_move
-        };
-
-        let next_move_search_result = search_for_next_move();
-        match next_move_search_result {
-            Some(m) => next_move = m,
-            _ => {
-                println!("All done!");
-                draw_grid(&context.grid, Some(current_position));
-                break;
-            }
-        }
+    let map = area.map;
+    if let Some(oxygen) = oxygen_position {
+        report_shortest_path(&map, oxygen);
+    }
+    if telemetry::enabled() {
+        println!("{}", telemetry::stats_json());
+    }
 
-        loop_count += 1;
-        if found_new_cell || loop_count % 1_000_000 == 0 {
-            //draw_grid(&context.grid, Some(current_position));
-        }
+    Ok(())
+}
 
-        if let ExecutionResult::Exit = execution_result {
-            break;
-        }
-    }
+/// Computes the shortest path to the oxygen on the explored map, after pruning the
+/// dead-end corridors
+fn report_shortest_path(map: &Map, oxygen: Position) {
+    let mut cells = map.open_cells();
+    let full_search = maze::shortest_path(&cells, (0, 0), oxygen);
+    let pruned = maze::prune_dead_ends(&mut cells, &[(0, 0), oxygen]);
+    let pruned_search = maze::shortest_path(&cells, (0, 0), oxygen);
 
-    draw_grid(&context.grid, Some(current_position));
     println!(
-        "Current position: {:?}: {:?}",
-        current_position,
-        get_cell_status(&context, current_position)
+        "Explored cells: {} ({} open); pruned {} dead-end cells",
+        map.explored_count(),
+        cells.len() + pruned,
+        pruned
     );
-
-    let max_length = context
-        .grid
-        .values()
-        .map(|s| match s {
-            CellStatus::Visited(x) | CellStatus::VisitedAll(x) => *x,
-            _ => 0,
-        })
-        .max();
-    println!("Max length: {}", max_length.unwrap());
-
-    Ok(())
+    if let (Some((length, visited)), Some((_, pruned_visited))) = (full_search, pruned_search) {
+        println!(
+            "Shortest path to the oxygen: {}; search visited {} cells instead of {}",
+            length, pruned_visited, visited
+        );
+    }
 }
 
 fn get_all_moves() -> Vec<Move> {
     vec![Move::North, Move::West, Move::South, Move::East]
 }
 
-fn display_oxygen_location(context: &ExecutionContext, position: (i32, i32)) {
-    let visited_count = context.grid.values().filter(|s| s.is_visited()).count();
+fn display_oxygen_location(moves: usize, position: Position) {
     println!(
-        "Required movements: {}; current position: {:?}; state: {:?}",
-        visited_count + 1,
-        position,
-        get_cell_status(&context, position)
+        "Required movements: {}; oxygen position: {:?}",
+        moves, position
     );
 }
 
-fn get_cell_status(context: &ExecutionContext, position: (i32, i32)) -> CellStatus {
-    *context.grid.get(&position).unwrap_or(&CellStatus::Unknown)
-}
-
 fn apply_move(position: (i32, i32), m: Move) -> (i32, i32) {
     let (x, y) = position;
     match m {
@@ -370,12 +118,7 @@ fn apply_move(position: (i32, i32), m: Move) -> (i32, i32) {
         Move::East => (x + 1, y),
     }
 }
-fn get_positions_around(position: (i32, i32)) -> Vec<(i32, i32)> {
-    let (x, y) = position;
-    vec![(x + 1, y), (x, y + 1), (x - 1, y), (x, y - 1)]
-}
-
-fn draw_grid(grid: &HashMap<(i32, i32), CellStatus>, current: Option<(i32, i32)>) {
+fn draw_grid(grid: &Grid, current: Option<(i32, i32)>) {
     //clear();
     set_cursor_possition(0, 0);
 
@@ -401,16 +144,11 @@ fn draw_grid(grid: &HashMap<(i32, i32), CellStatus>, current: Option<(i32, i32)>
                 // CellStatus::Wall => "WWWWW".to_string(),
                 // // CellStatus::Visited(_) => format!("░░░░░"),
                 // // CellStatus::VisitedAll(_) => format!("▒▒▒▒▒"),
-                // CellStatus::Visited(i) => format!(" {:3} ", i),
-                // CellStatus::VisitedAll(i) => format!("-{:3}-", i),
                 // CellStatus::Oxygen => "  O  ".to_string(),
                 CellStatus::Origin => "O".to_string(),
                 CellStatus::Unknown => " ".to_string(),
                 CellStatus::Wall => "█".to_string(),
-                // CellStatus::Visited(_) => format!("░░░░░"),
-                // CellStatus::VisitedAll(_) => format!("▒▒▒▒▒"),
-                CellStatus::Visited(i) => format!(" "),
-                CellStatus::VisitedAll(i) => format!("▒"),
+                CellStatus::Open => "▒".to_string(),
                 CellStatus::Oxygen => "O".to_string(),
             };
             print(&format!("{}", c));
@@ -419,11 +157,14 @@ fn draw_grid(grid: &HashMap<(i32, i32), CellStatus>, current: Option<(i32, i32)>
     }
 
     println("");
+    if telemetry::enabled() {
+        println(&telemetry::overlay(telemetry::frame()));
+    }
     refresh();
     //sleep(Duration::from_millis(10));
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum Move {
     North,
     South,
@@ -431,16 +172,35 @@ enum Move {
     East,
 }
 
+impl Move {
+    /// Input value for the droid to make the move
+    fn code(self) -> i64 {
+        match self {
+            Move::North => 1,
+            Move::South => 2,
+            Move::West => 3,
+            Move::East => 4,
+        }
+    }
+
+    fn reverse(self) -> Move {
+        match self {
+            Move::North => Move::South,
+            Move::South => Move::North,
+            Move::West => Move::East,
+            Move::East => Move::West,
+        }
+    }
+}
+
 #[derive(Clone)]
 struct ExecutionContext {
     ip: usize,
     memory: Memory,
     ended: bool,
     relative_base: usize,
-    grid: HashMap<(i32, i32), CellStatus>,
-    next_input: Option<i64>,
-    output: Vec<i32>,
-    result: MoveResult,
+    input: VecDeque<i64>,
+    output: Vec<i64>,
 }
 
 impl ExecutionContext {
@@ -450,31 +210,36 @@ impl ExecutionContext {
             memory: memory.clone(),
             ended: false,
             relative_base: 0,
-            grid: HashMap::new(),
+            input: VecDeque::new(),
             output: vec![],
-            next_input: Some(0),
-            result: MoveResult::Moved,
         }
     }
 
     fn read_input(&mut self) -> Option<i64> {
-        //println!("Reading input: {:?}", self.next_input);
-        let res = self.next_input;
-        self.next_input = None;
-        res
+        //println!("Reading input: {:?}", self.input);
+        self.input.pop_front()
     }
 
     fn write_output(&mut self, value: i64) {
         //println!("Writing output: {}", value);
-        self.output.push(value as i32);
-        self.result = match self.output[0] {
-            0 => MoveResult::HitWall,
-            1 => MoveResult::Moved,
-            2 => MoveResult::FoundOxygen,
-            x => panic!(format!("Invalid result: {}", x)),
-        };
+        self.output.push(value);
+    }
+}
+
+impl Vm for ExecutionContext {
+    fn push_input(&mut self, values: &[i64]) {
+        self.input.extend(values);
+    }
+
+    fn run(&mut self) -> bool {
+        match execute_program(self) {
+            ExecutionResult::MoreInputNeeded => true,
+            ExecutionResult::Exit => false,
+        }
+    }
 
-        self.output.clear();
+    fn take_output(&mut self) -> Vec<i64> {
+        self.output.drain(..).collect()
     }
 }
 
@@ -485,25 +250,90 @@ enum MoveResult {
     FoundOxygen,
 }
 
+impl FromOutput for MoveResult {
+    fn from_output(output: &[i64]) -> MoveResult {
+        match output {
+            [0] => MoveResult::HitWall,
+            [1] => MoveResult::Moved,
+            [2] => MoveResult::FoundOxygen,
+            x => panic!("Invalid result: {:?}", x),
+        }
+    }
+}
+
 #[derive(Debug, Eq, PartialEq, Clone, Copy)]
 enum CellStatus {
     Origin,
     Unknown,
-    Visited(i32),
-    VisitedAll(i32),
+    Open,
     Wall,
     Oxygen,
 }
 
-impl CellStatus {
-    fn is_visited(&self) -> bool {
-        match self {
-            CellStatus::Visited(_) | CellStatus::VisitedAll(_) => true,
-            _ => false,
+/// Area around the droid, discovered by moving it around
+struct Area {
+    map: Map,
+    grid: Grid,
+    oxygen: Option<Position>,
+}
+
+impl Area {
+    fn new() -> Area {
+        let mut map = Map::new();
+        map.record((0, 0), Tile::Open);
+        let mut grid = Grid::new();
+        grid.insert((0, 0), CellStatus::Origin);
+        Area {
+            map,
+            grid,
+            oxygen: None,
         }
     }
 }
 
+impl Space<Move, MoveResult> for Area {
+    type Node = Position;
+
+    fn moves(&self, _: &Position) -> Vec<Move> {
+        get_all_moves()
+    }
+
+    fn reverse(&self, m: &Move) -> Move {
+        m.reverse()
+    }
+
+    fn reached(&mut self, from: &Position, m: &Move, result: MoveResult) -> Option<Position> {
+        let target = apply_move(*from, *m);
+        let found_new_cell = !self.grid.contains_key(&target);
+        let (tile, status) = match result {
+            MoveResult::HitWall => (Tile::Wall, CellStatus::Wall),
+            MoveResult::Moved => (Tile::Open, CellStatus::Open),
+            MoveResult::FoundOxygen => {
+                self.oxygen = Some(target);
+                (Tile::Open, CellStatus::Oxygen)
+            }
+        };
+        self.map.record(target, tile);
+        if found_new_cell {
+            self.grid.insert(target, status);
+        }
+
+        let position = if result == MoveResult::HitWall {
+            *from
+        } else {
+            target
+        };
+        draw_grid(&self.grid, Some(position));
+        Some(position).filter(|_| result != MoveResult::HitWall)
+    }
+}
+
+impl Oracle<Move, MoveResult> for Dialogue<ExecutionContext> {
+    fn query(&mut self, m: &Move) -> Option<MoveResult> {
+        self.send(&[m.code()])
+    }
+}
+
 enum ExecutionResult {
     MoreInputNeeded,
     Exit,
@@ -512,6 +342,7 @@ enum ExecutionResult {
 fn execute_program(context: &mut ExecutionContext) -> ExecutionResult {
     // println!("Executing program; ip: {}", context.ip.get());
     loop {
+        telemetry::count_instruction();
         match read_op_code(context) {
             (OpCode::Add, parameter_modes) => {
                 let (a, b, c) = extract_parameters3(context, parameter_modes);
@@ -631,7 +462,7 @@ fn read_op_code(context: &mut ExecutionContext) -> (OpCode, u32) {
         8 => OpCode::Equals,
         9 => OpCode::AdjustRelativeBase,
         99 => OpCode::Exit,
-        x => panic!("Unknown op code: {}; ip: {}", x, context.ip),
+        x => vm_error(format!("Unknown op code: {}; ip: {}", x, context.ip)),
     };
 
     context.ip += 1;
@@ -670,7 +501,7 @@ fn get_parameter(context: &mut ExecutionContext, parameter_modes: &mut u32) -> P
         0 => ParameterMode::Position,
         1 => ParameterMode::Immediate,
         2 => ParameterMode::Relative,
-        x => panic!(format!("Incorrect parameter mode: {}", x)),
+        x => vm_error(format!("Incorrect parameter mode: {}", x)),
     };
     *parameter_modes /= 10;
 
@@ -703,7 +534,7 @@ impl<'a> Parameter {
     fn set(&self, value: i64, context: &mut ExecutionContext) -> () {
         match self {
             Parameter::Reference(address) => context.memory[*address] = value,
-            Parameter::ImmediateValue(value) => panic!(format!(
+            Parameter::ImmediateValue(value) => vm_error(format!(
                 "Attempted to write value {} to an immediate parameter",
                 value
             )),
@@ -717,26 +548,28 @@ enum ParameterMode {
     Relative,
 }
 
+#[cfg(all(windows, feature = "ui"))]
 extern crate kernel32;
+#[cfg(all(windows, feature = "ui"))]
 extern crate winapi;
 
-#[cfg(windows)]
+#[cfg(all(windows, feature = "ui"))]
 use winapi::wincon::CONSOLE_SCREEN_BUFFER_INFO;
-#[cfg(windows)]
+#[cfg(all(windows, feature = "ui"))]
 use winapi::wincon::COORD;
-#[cfg(windows)]
+#[cfg(all(windows, feature = "ui"))]
 use winapi::wincon::SMALL_RECT;
-#[cfg(windows)]
+#[cfg(all(windows, feature = "ui"))]
 use winapi::DWORD;
-#[cfg(windows)]
+#[cfg(all(windows, feature = "ui"))]
 use winapi::HANDLE;
-#[cfg(windows)]
+#[cfg(all(windows, feature = "ui"))]
 use winapi::WORD;
 
-#[cfg(windows)]
+#[cfg(all(windows, feature = "ui"))]
 static mut CONSOLE_HANDLE: Option<HANDLE> = None;
 
-#[cfg(windows)]
+#[cfg(all(windows, feature = "ui"))]
 fn get_output_handle() -> HANDLE {
     unsafe {
         if let Some(handle) = CONSOLE_HANDLE {
@@ -749,7 +582,7 @@ fn get_output_handle() -> HANDLE {
     }
 }
 
-#[cfg(windows)]
+#[cfg(all(windows, feature = "ui"))]
 fn get_buffer_info() -> winapi::CONSOLE_SCREEN_BUFFER_INFO {
     let handle = get_output_handle();
     if handle == winapi::INVALID_HANDLE_VALUE {
@@ -773,50 +606,48 @@ fn get_buffer_info() -> winapi::CONSOLE_SCREEN_BUFFER_INFO {
     buffer
 }
 
-#[cfg(windows)]
+#[cfg(all(windows, feature = "ui"))]
 fn init() {}
 
-#[cfg(unix)]
+#[cfg(all(unix, feature = "ui"))]
 fn init() {
-    ncurses::initscr();
+    screen::init();
 }
 
-#[cfg(unix)]
+#[cfg(all(unix, feature = "ui"))]
 fn clear() {
-    //ncurses::clear();
-    ncurses::mv(0, 0);
+    screen::move_to(0, 0);
 }
 
-#[cfg(windows)]
+#[cfg(all(windows, feature = "ui"))]
 fn print(msg: &str) {
     print!("{}", msg);
 }
 
-#[cfg(unix)]
+#[cfg(all(unix, feature = "ui"))]
 fn print(msg: &str) {
-    ncurses::printw(msg);
+    screen::print(msg);
 }
 
-#[cfg(windows)]
+#[cfg(all(windows, feature = "ui"))]
 fn println(msg: &str) {
     println!("{}", msg);
 }
 
-#[cfg(unix)]
+#[cfg(all(unix, feature = "ui"))]
 fn println(msg: &str) {
-    ncurses::addstr(msg);
-    ncurses::addstr("\n");
+    screen::println(msg);
 }
 
-#[cfg(windows)]
+#[cfg(all(windows, feature = "ui"))]
 fn refresh() {}
 
-#[cfg(unix)]
+#[cfg(all(unix, feature = "ui"))]
 fn refresh() {
-    ncurses::refresh();
+    screen::refresh();
 }
 
-#[cfg(windows)]
+#[cfg(all(windows, feature = "ui"))]
 fn clear() {
     let handle = get_output_handle();
     if handle == winapi::INVALID_HANDLE_VALUE {
@@ -840,7 +671,7 @@ fn clear() {
     set_cursor_possition(0, 0);
 }
 
-#[cfg(windows)]
+#[cfg(all(windows, feature = "ui"))]
 fn set_cursor_possition(y: i16, x: i16) {
     let handle = get_output_handle();
     if handle == winapi::INVALID_HANDLE_VALUE {
@@ -850,3 +681,30 @@ fn set_cursor_possition(y: i16, x: i16) {
         kernel32::SetConsoleCursorPosition(handle, COORD { X: x, Y: y });
     }
 }
+
+#[cfg(not(feature = "ui"))]
+fn init() {}
+
+#[cfg(not(feature = "ui"))]
+fn clear() {}
+
+#[cfg(not(feature = "ui"))]
+fn print(msg: &str) {
+    print!("{}", msg);
+}
+
+#[cfg(not(feature = "ui"))]
+fn println(msg: &str) {
+    println!("{}", msg);
+}
+
+#[cfg(not(feature = "ui"))]
+fn refresh() {}
+
+#[cfg(all(unix, feature = "ui"))]
+fn set_cursor_possition(y: i16, x: i16) {
+    screen::move_to(y, x);
+}
+
+#[cfg(not(feature = "ui"))]
+fn set_cursor_possition(_y: i16, _x: i16) {}
@@ -0,0 +1,156 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+pub type Position = (i32, i32);
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum Tile {
+    Open,
+    Wall,
+}
+
+/// Tiles found by the droid so far
+pub struct Map {
+    tiles: HashMap<Position, Tile>,
+}
+
+fn neighbours(position: Position) -> [Position; 4] {
+    let (x, y) = position;
+    [(x + 1, y), (x, y + 1), (x - 1, y), (x, y - 1)]
+}
+
+impl Map {
+    pub fn new() -> Map {
+        Map {
+            tiles: HashMap::new(),
+        }
+    }
+
+    pub fn record(&mut self, position: Position, tile: Tile) {
+        self.tiles.insert(position, tile);
+    }
+
+    pub fn explored_count(&self) -> usize {
+        self.tiles.len()
+    }
+
+    pub fn open_cells(&self) -> HashSet<Position> {
+        self.tiles
+            .iter()
+            .filter(|(_, tile)| **tile == Tile::Open)
+            .map(|(position, _)| *position)
+            .collect()
+    }
+}
+
+/// Removes the dead-end corridors: an open cell with a single open neighbour can't be on
+/// the path between two other cells. Returns how many cells were removed.
+pub fn prune_dead_ends(cells: &mut HashSet<Position>, keep: &[Position]) -> usize {
+    let open_neighbours = |cells: &HashSet<Position>, position: Position| {
+        neighbours(position)
+            .iter()
+            .filter(|n| cells.contains(n))
+            .count()
+    };
+
+    let mut candidates: Vec<Position> = cells.iter().cloned().collect();
+    let mut removed = 0;
+    while let Some(position) = candidates.pop() {
+        if keep.contains(&position)
+            || !cells.contains(&position)
+            || open_neighbours(cells, position) > 1
+        {
+            continue;
+        }
+
+        cells.remove(&position);
+        removed += 1;
+        // Removing the cell may have turned its neighbour into a dead end
+        candidates.extend(neighbours(position).iter().filter(|n| cells.contains(n)));
+    }
+    removed
+}
+
+/// Shortest path between the two positions, going through the given cells.
+/// Returns the number of moves, and how many cells the search visited.
+pub fn shortest_path(
+    cells: &HashSet<Position>,
+    from: Position,
+    to: Position,
+) -> Option<(usize, usize)> {
+    let mut distances = HashMap::new();
+    let mut queue = VecDeque::new();
+    distances.insert(from, 0);
+    queue.push_back(from);
+
+    while let Some(position) = queue.pop_front() {
+        let distance = distances[&position];
+        if position == to {
+            return Some((distance, distances.len()));
+        }
+        for n in neighbours(position).iter() {
+            if cells.contains(n) && !distances.contains_key(n) {
+                distances.insert(*n, distance + 1);
+                queue.push_back(*n);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+fn parse(text: &str) -> (Map, Position) {
+    let mut map = Map::new();
+    let mut oxygen = (0, 0);
+    for (y, line) in text.lines().enumerate() {
+        for (x, c) in line.chars().enumerate() {
+            let position = (x as i32, y as i32);
+            match c {
+                '#' => map.record(position, Tile::Wall),
+                '.' => map.record(position, Tile::Open),
+                'O' => {
+                    map.record(position, Tile::Open);
+                    oxygen = position;
+                }
+                _ => (),
+            }
+        }
+    }
+    (map, oxygen)
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = " ##
+#..##
+#.#..#
+#.O.#
+ ###";
+
+#[test]
+fn prune_example_dead_ends() {
+    let (map, oxygen) = parse(EXAMPLE);
+    let mut cells = map.open_cells();
+    let (length, visited) = shortest_path(&cells, (1, 1), oxygen).unwrap();
+
+    // Only the cells between the start and the oxygen are left
+    assert_eq!(4, prune_dead_ends(&mut cells, &[(1, 1), oxygen]));
+    let expected: HashSet<Position> = vec![(1, 1), (1, 2), (1, 3), (2, 3)].into_iter().collect();
+    assert_eq!(expected, cells);
+
+    let (pruned_length, pruned_visited) = shortest_path(&cells, (1, 1), oxygen).unwrap();
+    assert_eq!((3, 3), (length, pruned_length));
+    assert!(pruned_visited < visited);
+}
+
+#[test]
+fn pruning_keeps_loops() {
+    let (map, _) = parse(
+        "#####
+#...#
+#.#.#
+#...#
+#####",
+    );
+    let mut cells = map.open_cells();
+    assert_eq!(0, prune_dead_ends(&mut cells, &[(1, 1)]));
+    assert_eq!(8, cells.len());
+}
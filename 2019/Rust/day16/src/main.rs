@@ -1,64 +1,123 @@
-extern crate num;
-
 use std::env;
 use std::fs::File;
-use std::io;
-use std::io::prelude::*;
+use std::io::BufRead;
 use std::io::BufReader;
-use std::io::Read;
 use std::result::Result;
 
 type MainResult<T> = Result<T, Box<dyn ::std::error::Error>>;
 
+/// Longest signal for which we're willing to run the exact algorithm in part 2
+const MAX_EXACT_LEN: usize = 200_000;
+
 fn main() -> MainResult<()> {
     let file_name = env::args().nth(1).expect("Enter a file name");
-    let file = File::open(file_name)?;
-
-    let mut input_orig = String::new();
-    BufReader::new(file).read_to_string(&mut input_orig)?;
-    let mut input_orig: Vec<i32> = input_orig
-        .chars()
-        .map(|c| c.to_string().parse().unwrap())
-        .collect();
-
-    println!("Input: {:?}", input_orig);
+    let input_orig = read_signal_from_file(&file_name)?;
+    if input_orig.len() < 8 {
+        return Err(format!(
+            "The signal has {} digits; the answer is its first 8",
+            input_orig.len()
+        )
+        .into());
+    }
 
     let mut input = input_orig.clone();
-    // for _ in 0..100 {
-    //     input = calculate_iteration(&input);
-    // }
-    println!("Result: {:?}", input);
-
-    input = vec![];
-    for i in 0..10_000 {
-        input.append(&mut input_orig.clone());
+    for _ in 0..100 {
+        input = calculate_iteration(&input);
     }
+    println!("Result: {}", digits_to_number(&input[..8]));
 
-    let offset = (0..7)
-        .map(|i| input[i])
-        .fold(0, |x: i32, i| x.abs() * 10 + i as i32) as usize;
-    println!("Index: {}; total size: {}", offset, input.len());
+    let message = decode_message(&input_orig, 10_000, 100)?;
+    println!("Result: {}", message);
+    Ok(())
+}
 
-    for _ in 0..100 {
-        print!(".");
-        io::stdout().flush().unwrap();
+fn read_signal_from_file(file_name: &str) -> MainResult<Vec<u8>> {
+    let file = File::open(file_name)?;
+    // Reserve the whole signal upfront, so large inputs don't get copied while growing
+    let capacity = file.metadata()?.len() as usize;
+    read_signal(BufReader::new(file), capacity)
+}
+
+/// Reads the signal digits from the reader, one buffer at a time. Whitespace is ignored.
+fn read_signal(mut reader: impl BufRead, capacity: usize) -> MainResult<Vec<u8>> {
+    let mut signal = Vec::with_capacity(capacity);
+    loop {
+        let buffer = reader.fill_buf()?;
+        if buffer.is_empty() {
+            break;
+        }
 
-        for i in offset..input.len() - 1 {
-            let i = input.len() - 2 - i + offset;
-            input[i] = (input[i] + input[i + 1]) % 10;
+        for &b in buffer {
+            match b {
+                b'0'..=b'9' => signal.push(b - b'0'),
+                b if b.is_ascii_whitespace() => (),
+                b => return Err(format!("Invalid digit '{}' in the signal", b as char).into()),
+            }
         }
+        let len = buffer.len();
+        reader.consume(len);
     }
+    Ok(signal)
+}
 
-    // for v in &input {
-    //     print!("{}", v);
-    // }
+#[cfg(test)]
+fn parse_signal(text: &str) -> MainResult<Vec<u8>> {
+    read_signal(text.as_bytes(), text.len())
+}
 
-    println!();
+fn digits_to_number(digits: &[u8]) -> i64 {
+    digits.iter().fold(0, |x, d| x * 10 + *d as i64)
+}
 
-    let result = (0..8).map(|i| input[i + offset]).fold(0, |x, i| x * 10 + i);
+/// Finds the 8-digit message in the signal repeated `repetitions` times, at the offset
+/// given by its first 7 digits.
+/// When the offset is in the second half of the signal, each digit is only the sum of
+/// the digits after it, which is fast to compute. Otherwise, we fall back to the exact
+/// algorithm if the signal is short enough.
+fn decode_message(signal: &[u8], repetitions: usize, phases: usize) -> MainResult<i64> {
+    if signal.len() < 7 {
+        return Err(format!(
+            "Signal of {} digits is too short for the 7-digit message offset",
+            signal.len()
+        )
+        .into());
+    }
+    let offset = digits_to_number(&signal[..7]) as usize;
+    let total_len = signal.len() * repetitions;
+    if offset + 8 > total_len {
+        return Err(format!(
+            "Message offset {} is past the end of the {}-digit signal",
+            offset, total_len
+        )
+        .into());
+    }
 
-    println!("Result: {}", result);
-    Ok(())
+    let mut input: Vec<u8> = signal.iter().cycle().take(total_len).cloned().collect();
+    if offset * 2 + 1 >= total_len {
+        let mut suffix = input.split_off(offset);
+        for _ in 0..phases {
+            for i in (0..suffix.len() - 1).rev() {
+                suffix[i] = (suffix[i] + suffix[i + 1]) % 10;
+            }
+        }
+        Ok(digits_to_number(&suffix[..8]))
+    } else if total_len <= MAX_EXACT_LEN {
+        println!(
+            "Message offset {} is in the first half of the signal; using the exact algorithm",
+            offset
+        );
+        for _ in 0..phases {
+            input = calculate_exact_iteration(&input);
+        }
+        Ok(digits_to_number(&input[offset..offset + 8]))
+    } else {
+        Err(format!(
+            "Message offset {} is in the first half of the {}-digit signal, \
+             and the signal is too long for the exact algorithm (max {} digits)",
+            offset, total_len, MAX_EXACT_LEN
+        )
+        .into())
+    }
 }
 
 struct Pattern {
@@ -88,14 +147,136 @@ impl Iterator for Pattern {
     }
 }
 
-fn calculate_iteration(input: &Vec<i32>) -> Vec<i32> {
+fn calculate_iteration(input: &[u8]) -> Vec<u8> {
     let mut result = vec![];
     for i in 0..input.len() {
         let pattern = Pattern::new(i + 1);
 
-        let r: i32 = input.iter().zip(pattern).map(|(v, p)| v * p).sum();
-        result.push((r % 10).abs());
+        let r: i32 = input.iter().zip(pattern).map(|(v, p)| *v as i32 * p).sum();
+        result.push((r % 10).unsigned_abs() as u8);
     }
 
     result
 }
+
+/// Same as `calculate_iteration`, but summing whole runs of the pattern at once using
+/// prefix sums, in O(n log n)
+fn calculate_exact_iteration(input: &[u8]) -> Vec<u8> {
+    let mut prefix_sums = vec![0i64; input.len() + 1];
+    for (i, v) in input.iter().enumerate() {
+        prefix_sums[i + 1] = prefix_sums[i] + *v as i64;
+    }
+    let range_sum = |from: usize, to: usize| {
+        prefix_sums[to.min(input.len())] - prefix_sums[from.min(input.len())]
+    };
+
+    (0..input.len())
+        .map(|i| {
+            let run_len = i + 1;
+            let mut r = 0;
+            // The first 1 is at index i, then the pattern repeats every 4 runs
+            let mut start = i;
+            while start < input.len() {
+                r += range_sum(start, start + run_len);
+                r -= range_sum(start + 2 * run_len, start + 3 * run_len);
+                start += 4 * run_len;
+            }
+            (r % 10).unsigned_abs() as u8
+        })
+        .collect()
+}
+
+#[test]
+fn exact_iteration_matches_pattern() {
+    let mut input = parse_signal("80871224585914546619083218645595").unwrap();
+    for _ in 0..10 {
+        let expected = calculate_iteration(&input);
+        input = calculate_exact_iteration(&input);
+        assert_eq!(expected, input);
+    }
+}
+
+#[test]
+fn message_in_second_half() {
+    let signal = parse_signal("03036732577212944063491565474664").unwrap();
+    assert_eq!(84462026, decode_message(&signal, 10_000, 100).unwrap());
+}
+
+#[test]
+fn message_in_first_half_falls_back_to_exact() {
+    // Offset 10 in a 64-digit signal; checked against the pattern-based algorithm
+    let signal = parse_signal("00000105678901234567890123456789").unwrap();
+    let mut input: Vec<u8> = signal.iter().cycle().take(64).cloned().collect();
+    for _ in 0..4 {
+        input = calculate_iteration(&input);
+    }
+    assert_eq!(
+        digits_to_number(&input[10..18]),
+        decode_message(&signal, 2, 4).unwrap()
+    );
+}
+
+#[test]
+fn message_in_first_half_of_long_signal() {
+    let signal = parse_signal("00000105678901234567890123456789").unwrap();
+    let error = decode_message(&signal, 10_000, 100).unwrap_err();
+    assert!(error.to_string().contains("first half"));
+}
+
+#[test]
+fn message_past_the_end() {
+    let signal = parse_signal("99999990000000000000000000000000").unwrap();
+    assert!(decode_message(&signal, 1, 1).is_err());
+}
+
+#[test]
+fn signal_too_short_for_offset() {
+    let signal = parse_signal("123456").unwrap();
+    let error = decode_message(&signal, 10_000, 100).unwrap_err();
+    assert!(error.to_string().contains("too short"));
+}
+
+#[test]
+fn invalid_digit() {
+    assert!(parse_signal("12a4").is_err());
+}
+
+/// Peak resident memory of the process, in bytes
+#[cfg(all(test, target_os = "linux"))]
+fn peak_memory() -> usize {
+    let status = std::fs::read_to_string("/proc/self/status").unwrap();
+    let line = status.lines().find(|l| l.starts_with("VmHWM:")).unwrap();
+    let kb: usize = line.split_whitespace().nth(1).unwrap().parse().unwrap();
+    kb * 1024
+}
+
+// Slow and heavy on the disk; run with `cargo test --release -- --ignored`
+#[test]
+#[ignore]
+#[cfg(target_os = "linux")]
+fn large_signal_parses_within_bounded_memory() {
+    use std::io::Write;
+
+    const SIZE: usize = 500 * 1024 * 1024;
+    let path = env::temp_dir().join("day16_large_signal.txt");
+    {
+        let mut file = std::io::BufWriter::new(File::create(&path).unwrap());
+        let chunk: Vec<u8> = (0..1024 * 1024).map(|i| b'0' + (i % 10) as u8).collect();
+        for _ in 0..SIZE / chunk.len() {
+            file.write_all(&chunk).unwrap();
+        }
+    }
+
+    let before = peak_memory();
+    let signal = read_signal_from_file(path.to_str().unwrap()).unwrap();
+    let used = peak_memory() - before;
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(SIZE, signal.len());
+    assert!(
+        used < SIZE + SIZE / 10,
+        "Parsing used {} bytes for a {} bytes signal",
+        used,
+        SIZE
+    );
+}
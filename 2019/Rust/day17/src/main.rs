@@ -1,47 +1,55 @@
 use crate::memory::Memory;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::env;
 use std::fs::File;
 use std::io::Write;
 use std::io::{stdin, stdout, Read};
+use std::process;
 use std::thread::sleep;
 use std::time::Duration;
 
-#[cfg(unix)]
-extern crate ncurses;
+#[macro_use]
+mod strict;
 
+mod ascii;
 mod memory;
+#[cfg(all(unix, feature = "ui"))]
+mod screen;
+mod telemetry;
 
 type Result<T> = ::std::result::Result<T, Box<dyn ::std::error::Error>>;
 
+// Exit code for Intcode VM errors, shared with the runner
+const EXIT_VM_ERROR: i32 = 4;
+
+/// Reports an error of the Intcode VM, and exits with the runner's code for VM errors
+fn vm_error(message: String) -> ! {
+    eprintln!("VM error: {}", message);
+    process::exit(EXIT_VM_ERROR);
+}
+
 #[derive(Eq, PartialEq, Hash, Clone, Copy)]
 struct Pos(i32, i32);
 
 enum Cell {
-    Robot(RobotStatus),
+    Robot,
     Empty,
     Scaffold,
 }
 
 impl Cell {
     fn is_scaffold(&self) -> bool {
-        match self {
-            Cell::Scaffold => true,
-            _ => false,
-        }
+        matches!(self, Cell::Scaffold)
     }
 }
 
-enum RobotStatus {
-    Up,
-    Down,
-    Left,
-    Right,
-    Falling,
-}
-
 fn main() -> Result<()> {
     let file_name = env::args().nth(1).expect("Enter a file name");
+    #[cfg(all(unix, feature = "ui"))]
+    screen::select_backend(env::args())?;
+    strict::enable_from_args(env::args());
+    telemetry::enable_from_args(env::args());
+    let video_fps = video_fps_from_args(env::args());
 
     let mut instructions = String::new();
     File::open(file_name)?
@@ -55,6 +63,7 @@ fn main() -> Result<()> {
 
     execute_program(&mut context);
     draw_grid(&context.output);
+    check_robot_on_scaffold(&context.output);
     let grid = build_grid(&context.output);
     let x_max = *grid.keys().map(|Pos(x, _)| x).max().unwrap();
     let y_max = *grid.keys().map(|Pos(_, y)| y).max().unwrap();
@@ -84,31 +93,41 @@ fn main() -> Result<()> {
     // Part 2
     context = ExecutionContext::new(&memory);
     context.memory[0] = 2;
-    context.input = String::new();
-    context.input += "A,B,B,C,C,A,B,B,C,A\n";
-    context.input += "R,4,R,12,R,10,L,12\n";
-    context.input += "L,12,R,4,R,12\n";
-    context.input += "L,12,L,8,R,10\n";
-    context.input += "y\n";
+    context.send_line("A,B,B,C,C,A,B,B,C,A");
+    context.send_line("R,4,R,12,R,10,L,12");
+    context.send_line("L,12,R,4,R,12");
+    context.send_line("L,12,L,8,R,10");
+    context.send_line(if video_fps.is_some() { "y" } else { "n" });
+    context.video_fps = video_fps;
 
     loop {
-        match execute_program(&mut context) {
+        let result = execute_program(&mut context);
+        while let Some(line) = context.read_line() {
+            println!("{}", line);
+        }
+        match result {
             ExecutionResult::Exit => break,
             ExecutionResult::MoreInputNeeded => {
                 print!("Input: ");
                 stdout().flush().unwrap();
                 let mut input = String::new();
                 stdin().read_line(&mut input).unwrap();
-                context.input = input.replace("\r", "");
-                context.input_index = 0;
+                context.send_line(&input);
             }
         }
     }
+    match context.dust {
+        Some(dust) => println!("Result: {}", dust),
+        None => println!("No dust value reported"),
+    }
+    if telemetry::enabled() {
+        println!("{}", telemetry::stats_json());
+    }
 
     Ok(())
 }
 
-fn build_grid(chars: &Vec<i32>) -> HashMap<Pos, Cell> {
+fn build_grid(chars: &[i32]) -> HashMap<Pos, Cell> {
     let mut map = HashMap::new();
     let mut x = 0;
     let mut y = 0;
@@ -120,10 +139,10 @@ fn build_grid(chars: &Vec<i32>) -> HashMap<Pos, Cell> {
                 x = 0;
             }
             c => {
-                let cell = match *c as u8 as char {
-                    '.' => Cell::Empty,
-                    '#' => Cell::Scaffold,
-                    x => parse_robot_cell(x),
+                let cell = match ascii::to_char(i64::from(*c)) {
+                    Some('.') => Cell::Empty,
+                    Some('#') => Cell::Scaffold,
+                    x => parse_robot_cell(x.unwrap_or('?')),
                 };
 
                 map.insert(Pos(x, y), cell);
@@ -136,27 +155,99 @@ fn build_grid(chars: &Vec<i32>) -> HashMap<Pos, Cell> {
 }
 
 fn parse_robot_cell(ch: char) -> Cell {
-    let status = match ch {
-        '^' => RobotStatus::Up,
-        '>' => RobotStatus::Left,
-        'v' => RobotStatus::Down,
-        '<' => RobotStatus::Right,
-        'X' => RobotStatus::Falling,
+    match ch {
+        '^' | '>' | 'v' | '<' | 'X' => Cell::Robot,
         x => panic!("Unknown char: {}", x),
+    }
+}
+
+/// Checks that the camera frame shows a single robot, on the scaffold. Lines that
+/// aren't part of the camera view (e.g. the movement prompts) are ignored.
+fn check_robot_on_scaffold(chars: &[i32]) {
+    if !strict::enabled() {
+        return;
+    }
+
+    let text = ascii::decode(chars.iter().map(|c| i64::from(*c)));
+    let lines: Vec<&str> = text
+        .lines()
+        .filter(|l| !l.is_empty() && l.chars().all(|c| ".#^v<>X".contains(c)))
+        .collect();
+    if lines.is_empty() {
+        return;
+    }
+    let cell = |x: i32, y: i32| {
+        if x < 0 || y < 0 {
+            return '.';
+        }
+        lines
+            .get(y as usize)
+            .and_then(|l| l.chars().nth(x as usize))
+            .unwrap_or('.')
     };
-    Cell::Robot(status)
+
+    let robots: Vec<(i32, i32, char)> = lines
+        .iter()
+        .enumerate()
+        .flat_map(|(y, l)| {
+            l.chars()
+                .enumerate()
+                .filter(|(_, c)| "^v<>X".contains(*c))
+                .map(move |(x, c)| (x as i32, y as i32, c))
+        })
+        .collect();
+    invariant!(robots.len() == 1, "{} robots in the frame", robots.len());
+
+    for &(x, y, c) in &robots {
+        invariant!(c != 'X', "Robot fell off the scaffold at {},{}", x, y);
+        let on_scaffold = [(1, 0), (-1, 0), (0, 1), (0, -1)]
+            .iter()
+            .any(|(dx, dy)| cell(x + dx, y + dy) == '#');
+        invariant!(on_scaffold, "Robot at {},{} isn't on the scaffold", x, y);
+    }
+}
+
+/// Frame rate of the live feed if `--video [fps]` is among the arguments; 10 fps by default
+fn video_fps_from_args(args: impl Iterator<Item = String>) -> Option<u64> {
+    let mut args = args.skip_while(|a| a != "--video");
+    args.next()?;
+    Some(
+        args.next()
+            .and_then(|fps| fps.parse().ok())
+            .filter(|&fps| fps > 0)
+            .unwrap_or(10),
+    )
+}
+
+/// Renders a frame of the part 2 live feed, then waits until the next one is due
+fn draw_video_frame(chars: &[i32], fps: u64) {
+    clear();
+    for ch in chars {
+        match ch {
+            10 => println(""),
+            c => print(&ascii::decode(Some(i64::from(*c)))),
+        }
+    }
+    if telemetry::enabled() {
+        println(&telemetry::overlay(telemetry::frame()));
+    }
+    refresh();
+    sleep(Duration::from_millis(1000 / fps));
 }
 
-fn draw_grid(chars: &Vec<i32>) {
+fn draw_grid(chars: &[i32]) {
     clear();
 
     for ch in chars {
         match ch {
-            10 => println(&""),
-            c => print(&format!("{}", *c as u8 as char)),
+            10 => println(""),
+            c => print(&ascii::decode(Some(i64::from(*c)))),
         }
     }
     println("");
+    if telemetry::enabled() {
+        println(&telemetry::overlay(telemetry::frame()));
+    }
     refresh();
     sleep(Duration::from_millis(20));
 }
@@ -167,9 +258,15 @@ struct ExecutionContext {
     memory: Memory,
     ended: bool,
     relative_base: usize,
-    input: String,
-    input_index: usize,
+    input: VecDeque<i64>,
     output: Vec<i32>,
+    /// Text output outside of the live feed, not printed yet
+    lines: ascii::Lines,
+    frame_start: usize,
+    /// Frame rate of the live feed, when it was requested
+    video_fps: Option<u64>,
+    /// Amount of dust collected, reported once the robot is done
+    dust: Option<i64>,
 }
 
 impl ExecutionContext {
@@ -180,33 +277,50 @@ impl ExecutionContext {
             ended: false,
             relative_base: 0,
             output: vec![],
-            input_index: 0,
-            input: String::new(),
+            frame_start: 0,
+            video_fps: None,
+            dust: None,
+            input: VecDeque::new(),
+            lines: ascii::Lines::default(),
         }
     }
 
     fn read_input(&mut self) -> Option<i64> {
-        let index = self.input_index;
-        self.input_index += 1;
-        let res = self.input.chars().nth(index).map(|x| x as i64);
+        self.input.pop_front()
+    }
 
-        //println!("Reading input: {:?}", res);
-        res
+    /// Queues a line of text as input, followed by a new line
+    fn send_line(&mut self, line: &str) {
+        self.input.extend(ascii::encode_line(line));
+    }
+
+    /// Next complete line of text output, outside of the live feed
+    fn read_line(&mut self) -> Option<String> {
+        self.lines.read_line()
     }
 
     fn write_output(&mut self, value: i64) {
-        //println!("{}", value);
         if value > 128 {
-            println!("Result: {}", value);
+            self.dust = Some(value);
             return;
         }
-        print!("{}", value as u8 as char);
         self.output.push(value as i32);
-        if value == 10 && self.output[self.output.len() - 2] == 10 {
-            set_cursor_possition(0, 0);
-            sleep(Duration::from_millis(0));
+        let fps = match self.video_fps {
+            Some(fps) => fps,
+            None => {
+                self.lines.push(value);
+                return;
+            }
+        };
+
+        // Frames of the feed are separated by an empty line
+        let len = self.output.len();
+        if value == 10 && len >= 2 && self.output[len - 2] == 10 {
+            let frame = &self.output[self.frame_start..];
+            check_robot_on_scaffold(frame);
+            draw_video_frame(frame, fps);
+            self.frame_start = len;
         }
-        //self.output.clear();
     }
 }
 
@@ -218,6 +332,7 @@ enum ExecutionResult {
 fn execute_program(context: &mut ExecutionContext) -> ExecutionResult {
     // println!("Executing program; ip: {}", context.ip.get());
     loop {
+        telemetry::count_instruction();
         match read_op_code(context) {
             (OpCode::Add, parameter_modes) => {
                 let (a, b, c) = extract_parameters3(context, parameter_modes);
@@ -245,27 +360,27 @@ fn execute_program(context: &mut ExecutionContext) -> ExecutionResult {
             }
             (OpCode::Output, parameter_modes) => {
                 let a = extract_parameter(context, parameter_modes);
-                let output = a.get(&context);
+                let output = a.get(context);
                 //println!("Output: {}", output);
                 context.write_output(output);
             }
             (OpCode::JumpIfTrue, parameter_modes) => {
                 let (a, b) = extract_parameters2(context, parameter_modes);
-                if a.get(&context) != 0 {
-                    let address = b.get(&context);
+                if a.get(context) != 0 {
+                    let address = b.get(context);
                     jump_to(&mut context.ip, address);
                 }
             }
             (OpCode::JumpIfFalse, parameter_modes) => {
                 let (a, b) = extract_parameters2(context, parameter_modes);
-                if a.get(&context) == 0 {
-                    let address = b.get(&context);
+                if a.get(context) == 0 {
+                    let address = b.get(context);
                     jump_to(&mut context.ip, address);
                 }
             }
             (OpCode::LessThan, parameter_modes) => {
                 let (a, b, c) = extract_parameters3(context, parameter_modes);
-                let value = if a.get(&context) < b.get(&context) {
+                let value = if a.get(context) < b.get(context) {
                     1
                 } else {
                     0
@@ -274,7 +389,7 @@ fn execute_program(context: &mut ExecutionContext) -> ExecutionResult {
             }
             (OpCode::Equals, parameter_modes) => {
                 let (a, b, c) = extract_parameters3(context, parameter_modes);
-                let value = if a.get(&context) == b.get(&context) {
+                let value = if a.get(context) == b.get(context) {
                     1
                 } else {
                     0
@@ -283,7 +398,7 @@ fn execute_program(context: &mut ExecutionContext) -> ExecutionResult {
             }
             (OpCode::AdjustRelativeBase, parameter_modes) => {
                 let a = extract_parameter(context, parameter_modes);
-                let adjustment = a.get(&context);
+                let adjustment = a.get(context);
                 context.relative_base = (context.relative_base as i64 + adjustment) as usize;
             }
             (OpCode::Exit, _) => {
@@ -329,7 +444,7 @@ fn read_op_code(context: &mut ExecutionContext) -> (OpCode, u32) {
         8 => OpCode::Equals,
         9 => OpCode::AdjustRelativeBase,
         99 => OpCode::Exit,
-        x => panic!("Unknown op code: {}; ip: {}", x, context.ip),
+        x => vm_error(format!("Unknown op code: {}; ip: {}", x, context.ip)),
     };
 
     context.ip += 1;
@@ -368,7 +483,7 @@ fn get_parameter(context: &mut ExecutionContext, parameter_modes: &mut u32) -> P
         0 => ParameterMode::Position,
         1 => ParameterMode::Immediate,
         2 => ParameterMode::Relative,
-        x => panic!(format!("Incorrect parameter mode: {}", x)),
+        x => vm_error(format!("Incorrect parameter mode: {}", x)),
     };
     *parameter_modes /= 10;
 
@@ -390,7 +505,7 @@ enum Parameter {
     Reference(usize),
 }
 
-impl<'a> Parameter {
+impl Parameter {
     fn get(&self, context: &ExecutionContext) -> i64 {
         match self {
             Parameter::Reference(address) => context.memory[*address],
@@ -398,10 +513,10 @@ impl<'a> Parameter {
         }
     }
 
-    fn set(&self, value: i64, context: &mut ExecutionContext) -> () {
+    fn set(&self, value: i64, context: &mut ExecutionContext) {
         match self {
             Parameter::Reference(address) => context.memory[*address] = value,
-            Parameter::ImmediateValue(value) => panic!(format!(
+            Parameter::ImmediateValue(value) => vm_error(format!(
                 "Attempted to write value {} to an immediate parameter",
                 value
             )),
@@ -415,26 +530,28 @@ enum ParameterMode {
     Relative,
 }
 
+#[cfg(all(windows, feature = "ui"))]
 extern crate kernel32;
+#[cfg(all(windows, feature = "ui"))]
 extern crate winapi;
 
-#[cfg(windows)]
+#[cfg(all(windows, feature = "ui"))]
 use winapi::wincon::CONSOLE_SCREEN_BUFFER_INFO;
-#[cfg(windows)]
+#[cfg(all(windows, feature = "ui"))]
 use winapi::wincon::COORD;
-#[cfg(windows)]
+#[cfg(all(windows, feature = "ui"))]
 use winapi::wincon::SMALL_RECT;
-#[cfg(windows)]
+#[cfg(all(windows, feature = "ui"))]
 use winapi::DWORD;
-#[cfg(windows)]
+#[cfg(all(windows, feature = "ui"))]
 use winapi::HANDLE;
-#[cfg(windows)]
+#[cfg(all(windows, feature = "ui"))]
 use winapi::WORD;
 
-#[cfg(windows)]
+#[cfg(all(windows, feature = "ui"))]
 static mut CONSOLE_HANDLE: Option<HANDLE> = None;
 
-#[cfg(windows)]
+#[cfg(all(windows, feature = "ui"))]
 fn get_output_handle() -> HANDLE {
     unsafe {
         if let Some(handle) = CONSOLE_HANDLE {
@@ -447,7 +564,7 @@ fn get_output_handle() -> HANDLE {
     }
 }
 
-#[cfg(windows)]
+#[cfg(all(windows, feature = "ui"))]
 fn get_buffer_info() -> winapi::CONSOLE_SCREEN_BUFFER_INFO {
     let handle = get_output_handle();
     if handle == winapi::INVALID_HANDLE_VALUE {
@@ -471,50 +588,48 @@ fn get_buffer_info() -> winapi::CONSOLE_SCREEN_BUFFER_INFO {
     buffer
 }
 
-#[cfg(windows)]
+#[cfg(all(windows, feature = "ui"))]
 fn init() {}
 
-#[cfg(unix)]
+#[cfg(all(unix, feature = "ui"))]
 fn init() {
-    ncurses::initscr();
+    screen::init();
 }
 
-#[cfg(unix)]
+#[cfg(all(unix, feature = "ui"))]
 fn clear() {
-    //ncurses::clear();
-    ncurses::mv(0, 0);
+    screen::move_to(0, 0);
 }
 
-#[cfg(windows)]
+#[cfg(all(windows, feature = "ui"))]
 fn print(msg: &str) {
     print!("{}", msg);
 }
 
-#[cfg(unix)]
+#[cfg(all(unix, feature = "ui"))]
 fn print(msg: &str) {
-    ncurses::printw(msg);
+    screen::print(msg);
 }
 
-#[cfg(windows)]
+#[cfg(all(windows, feature = "ui"))]
 fn println(msg: &str) {
     println!("{}", msg);
 }
 
-#[cfg(unix)]
+#[cfg(all(unix, feature = "ui"))]
 fn println(msg: &str) {
-    ncurses::addstr(msg);
-    ncurses::addstr("\n");
+    screen::println(msg);
 }
 
-#[cfg(windows)]
+#[cfg(all(windows, feature = "ui"))]
 fn refresh() {}
 
-#[cfg(unix)]
+#[cfg(all(unix, feature = "ui"))]
 fn refresh() {
-    ncurses::refresh();
+    screen::refresh();
 }
 
-#[cfg(windows)]
+#[cfg(all(windows, feature = "ui"))]
 fn clear() {
     let handle = get_output_handle();
     if handle == winapi::INVALID_HANDLE_VALUE {
@@ -538,7 +653,7 @@ fn clear() {
     set_cursor_possition(0, 0);
 }
 
-#[cfg(windows)]
+#[cfg(all(windows, feature = "ui"))]
 fn set_cursor_possition(y: i16, x: i16) {
     let handle = get_output_handle();
     if handle == winapi::INVALID_HANDLE_VALUE {
@@ -548,3 +663,62 @@ fn set_cursor_possition(y: i16, x: i16) {
         kernel32::SetConsoleCursorPosition(handle, COORD { X: x, Y: y });
     }
 }
+
+#[cfg(not(feature = "ui"))]
+fn init() {}
+
+#[cfg(not(feature = "ui"))]
+fn clear() {}
+
+#[cfg(not(feature = "ui"))]
+fn print(msg: &str) {
+    print!("{}", msg);
+}
+
+#[cfg(not(feature = "ui"))]
+fn println(msg: &str) {
+    println!("{}", msg);
+}
+
+#[cfg(not(feature = "ui"))]
+fn refresh() {}
+
+#[cfg(test)]
+fn frame(text: &str) -> Vec<i32> {
+    strict::enable_from_args(vec!["--strict".to_string()].into_iter());
+    text.chars().map(|c| c as i32).collect()
+}
+
+#[test]
+fn robot_on_scaffold() {
+    check_robot_on_scaffold(&frame("Main:\n..#..\n..#..\n..^##\n"));
+}
+
+#[test]
+#[should_panic(expected = "Robot fell off the scaffold")]
+fn robot_falling() {
+    check_robot_on_scaffold(&frame("..#..\n..#..\n..#.X\n"));
+}
+
+#[test]
+fn video_fps() {
+    let args = |a: &[&str]| {
+        a.iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+    };
+    assert_eq!(video_fps_from_args(args(&["input.txt"])), None);
+    assert_eq!(
+        video_fps_from_args(args(&["input.txt", "--video"])),
+        Some(10)
+    );
+    assert_eq!(
+        video_fps_from_args(args(&["input.txt", "--video", "30"])),
+        Some(30)
+    );
+    assert_eq!(
+        video_fps_from_args(args(&["--video", "--strict"])),
+        Some(10)
+    );
+}
@@ -17,7 +17,7 @@ impl Memory {
             .split(",")
             .map(|x| {
                 x.parse::<i64>()
-                    .expect(format!("Failed to parse {}", x).as_str())
+                    .unwrap_or_else(|_| panic!("Failed to parse {}", x))
             })
             .enumerate()
             .collect();
@@ -30,7 +30,7 @@ impl Index<usize> for Memory {
     type Output = i64;
 
     fn index(&self, index: usize) -> &Self::Output {
-        &self._values.get(&index).unwrap_or(&0)
+        self._values.get(&index).unwrap_or(&0)
     }
 }
 
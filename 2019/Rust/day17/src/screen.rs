@@ -0,0 +1,70 @@
+use crossterm::cursor::MoveTo;
+use crossterm::style::Print;
+use crossterm::terminal::{Clear, ClearType};
+use crossterm::{execute, queue};
+use std::io::{stdout, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Terminal output goes through crossterm, unless ncurses has been selected with
+// `--ui ncurses`, which requires the `ncurses-ui` feature
+static USE_NCURSES: AtomicBool = AtomicBool::new(false);
+
+pub fn select_backend(args: impl Iterator<Item = String>) -> Result<(), String> {
+    match args.skip_while(|a| a != "--ui").nth(1).as_deref() {
+        None | Some("crossterm") => Ok(()),
+        #[cfg(feature = "ncurses-ui")]
+        Some("ncurses") => {
+            USE_NCURSES.store(true, Ordering::Relaxed);
+            Ok(())
+        }
+        Some(x) => Err(format!("Unsupported UI: {}", x)),
+    }
+}
+
+fn use_ncurses() -> bool {
+    cfg!(feature = "ncurses-ui") && USE_NCURSES.load(Ordering::Relaxed)
+}
+
+pub fn init() {
+    if use_ncurses() {
+        #[cfg(feature = "ncurses-ui")]
+        {
+            ncurses::setlocale(ncurses::LcCategory::all, "en_GB.UTF-8");
+            ncurses::initscr();
+        }
+    } else {
+        execute!(stdout(), Clear(ClearType::All), MoveTo(0, 0)).ok();
+    }
+}
+
+pub fn move_to(y: i16, x: i16) {
+    if use_ncurses() {
+        #[cfg(feature = "ncurses-ui")]
+        ncurses::mv(y as i32, x as i32);
+    } else {
+        queue!(stdout(), MoveTo(x as u16, y as u16)).ok();
+    }
+}
+
+pub fn print(msg: &str) {
+    if use_ncurses() {
+        #[cfg(feature = "ncurses-ui")]
+        ncurses::addstr(msg);
+    } else {
+        queue!(stdout(), Print(msg)).ok();
+    }
+}
+
+pub fn println(msg: &str) {
+    print(msg);
+    print("\n");
+}
+
+pub fn refresh() {
+    if use_ncurses() {
+        #[cfg(feature = "ncurses-ui")]
+        ncurses::refresh();
+    } else {
+        stdout().flush().ok();
+    }
+}
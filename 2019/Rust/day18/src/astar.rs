@@ -0,0 +1,295 @@
+use crate::fasthash::{FastHashMap, FastHashSet};
+use crate::grid::{Content, ContentGrid};
+use crate::iterators::NextMoveIterator;
+use crate::Pos;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::fmt;
+
+/// Path from a point of interest (a start or a key) to a key
+struct Edge {
+    to: usize,
+    distance: u32,
+    /// Keys needed to go through the doors on the way, or picked up on the way
+    required: u32,
+}
+
+/// Shortest paths between the starts and the keys of the grid.
+/// Points are the starts first, then the keys.
+pub struct KeyGraph {
+    start_count: usize,
+    edges: Vec<Vec<Edge>>,
+    all_keys: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SearchState {
+    /// Point where each robot currently is
+    cursors: Vec<usize>,
+    collected: u32,
+}
+
+impl SearchState {
+    /// Key of the state in the shape of the recursive solver's cache keys: the collected
+    /// keys, a dash, then a character for each robot's point
+    pub fn cache_key(&self) -> String {
+        let collected = (0..26u8)
+            .filter(|i| self.collected & (1 << i) != 0)
+            .map(|i| (b'a' + i) as char);
+        let cursors = self.cursors.iter().map(|c| (b'0' + *c as u8) as char);
+        collected.chain(Some('-')).chain(cursors).collect()
+    }
+}
+
+pub type Heuristic = fn(&KeyGraph, &SearchState) -> u32;
+
+/// How well the heuristic estimated the remaining distance during the search. Both
+/// values must be 0 for A* to be guaranteed to find the shortest path.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct HeuristicReport {
+    pub expanded_states: usize,
+    /// Largest amount by which h exceeded the actual remaining distance, on the
+    /// shortest path
+    pub max_overestimate: u32,
+    /// Largest amount by which h decreased more than the distance of a move
+    pub max_inconsistency: u32,
+}
+
+impl fmt::Display for HeuristicReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Expanded {} states; max h overestimate: {}; max inconsistency: {}",
+            self.expanded_states, self.max_overestimate, self.max_inconsistency
+        )
+    }
+}
+
+pub struct Solution {
+    pub distance: u32,
+    pub report: HeuristicReport,
+}
+
+fn key_bit(k: char) -> u32 {
+    1 << (k as u8 - b'a')
+}
+
+impl KeyGraph {
+    /// Builds the graph; start keys are any key that isn't a lowercase letter
+    pub fn new(grid: &ContentGrid) -> KeyGraph {
+        let mut starts = vec![];
+        let mut keys = vec![];
+        for (pos, content) in grid {
+            if let Content::Key(k) = content {
+                if k.is_ascii_lowercase() {
+                    keys.push((*k, *pos));
+                } else {
+                    starts.push((*k, *pos));
+                }
+            }
+        }
+        starts.sort_by_key(|(k, _)| *k);
+        keys.sort_by_key(|(k, _)| *k);
+
+        let points: Vec<(char, Pos)> = starts.iter().chain(&keys).cloned().collect();
+        let index: HashMap<char, usize> = points
+            .iter()
+            .enumerate()
+            .map(|(i, (k, _))| (*k, i))
+            .collect();
+        let edges = points
+            .iter()
+            .map(|(_, pos)| KeyGraph::paths_from(grid, *pos, &index))
+            .collect();
+
+        KeyGraph {
+            start_count: starts.len(),
+            edges,
+            all_keys: keys.iter().fold(0, |mask, (k, _)| mask | key_bit(*k)),
+        }
+    }
+
+    fn paths_from(grid: &ContentGrid, from: Pos, index: &HashMap<char, usize>) -> Vec<Edge> {
+        let mut edges = vec![];
+        let mut visited = FastHashSet::default();
+        let mut queue = VecDeque::new();
+        visited.insert(from);
+        queue.push_back((from, 0, 0));
+
+        while let Some((pos, distance, required)) = queue.pop_front() {
+            for next in NextMoveIterator::new(pos) {
+                if visited.contains(&next) {
+                    continue;
+                }
+                let mut next_required = required;
+                match grid.get(&next) {
+                    None | Some(Content::Wall) => continue,
+                    Some(Content::Door(d)) => next_required |= key_bit(d.to_ascii_lowercase()),
+                    Some(Content::Key(k)) if k.is_ascii_lowercase() => {
+                        edges.push(Edge {
+                            to: index[k],
+                            distance: distance + 1,
+                            required,
+                        });
+                        next_required |= key_bit(*k);
+                    }
+                    _ => (),
+                }
+                visited.insert(next);
+                queue.push_back((next, distance + 1, next_required));
+            }
+        }
+        edges
+    }
+
+    fn key_bit(&self, point: usize) -> u32 {
+        1 << (point - self.start_count)
+    }
+
+    fn distance(&self, from: usize, to: usize) -> Option<u32> {
+        self.edges[from]
+            .iter()
+            .find(|e| e.to == to)
+            .map(|e| e.distance)
+    }
+}
+
+/// Distance to the key farthest from all the robots, ignoring the doors: some robot will
+/// have to walk at least that far to collect it
+pub fn farthest_key(graph: &KeyGraph, state: &SearchState) -> u32 {
+    (graph.start_count..graph.edges.len())
+        .filter(|k| state.collected & graph.key_bit(*k) == 0)
+        .filter_map(|k| {
+            state
+                .cursors
+                .iter()
+                .filter_map(|c| graph.distance(*c, k))
+                .min()
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Pseudo-random states of the grid's search, as keys for the hashing benchmarks
+pub fn sample_states(grid: &ContentGrid, count: usize) -> Vec<SearchState> {
+    let graph = KeyGraph::new(grid);
+    let mut seed: u64 = 42;
+    let mut random = move || {
+        seed = seed
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        (seed >> 32) as u32
+    };
+    (0..count)
+        .map(|_| SearchState {
+            cursors: (0..graph.start_count)
+                .map(|_| random() as usize % graph.edges.len())
+                .collect(),
+            collected: random() & graph.all_keys,
+        })
+        .collect()
+}
+
+/// Finds the shortest path collecting all the keys with A*, checking the heuristic along
+/// the way
+pub fn shortest_path(grid: &ContentGrid, heuristic: Heuristic) -> Option<Solution> {
+    let graph = KeyGraph::new(grid);
+    let start = SearchState {
+        cursors: (0..graph.start_count).collect(),
+        collected: 0,
+    };
+
+    let mut report = HeuristicReport::default();
+    let mut best: FastHashMap<SearchState, u32> = FastHashMap::default();
+    let mut previous: FastHashMap<SearchState, SearchState> = FastHashMap::default();
+    let mut queue = BinaryHeap::new();
+    best.insert(start.clone(), 0);
+    queue.push(Reverse((heuristic(&graph, &start), 0, start)));
+
+    while let Some(Reverse((_, g, state))) = queue.pop() {
+        if best[&state] < g {
+            continue;
+        }
+        report.expanded_states += 1;
+
+        if state.collected == graph.all_keys {
+            // Along the shortest path, the remaining distance is known exactly
+            let mut current = Some(&state);
+            while let Some(s) = current {
+                let remaining = g - best[s];
+                let overestimate = heuristic(&graph, s).saturating_sub(remaining);
+                report.max_overestimate = report.max_overestimate.max(overestimate);
+                current = previous.get(s);
+            }
+            return Some(Solution {
+                distance: g,
+                report,
+            });
+        }
+
+        let h = heuristic(&graph, &state);
+        for (robot, point) in state.cursors.iter().enumerate() {
+            for edge in &graph.edges[*point] {
+                let bit = graph.key_bit(edge.to);
+                if state.collected & bit != 0 || edge.required & !state.collected != 0 {
+                    continue;
+                }
+
+                let mut next = state.clone();
+                next.cursors[robot] = edge.to;
+                next.collected |= bit;
+                let next_g = g + edge.distance;
+                let next_h = heuristic(&graph, &next);
+                let inconsistency = h.saturating_sub(edge.distance + next_h);
+                report.max_inconsistency = report.max_inconsistency.max(inconsistency);
+
+                if !matches!(best.get(&next), Some(d) if *d <= next_g) {
+                    best.insert(next.clone(), next_g);
+                    previous.insert(next.clone(), state.clone());
+                    queue.push(Reverse((next_g + next_h, next_g, next)));
+                }
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+fn solve_example(file_name: &str, split: bool, heuristic: Heuristic) -> Solution {
+    let (mut grid, initial_pos) = crate::grid::parse_grid(file_name).unwrap();
+    if split {
+        crate::grid::split_entrance(&mut grid, initial_pos, &['@', '$', '%', '#']);
+    }
+    shortest_path(&grid, heuristic).unwrap()
+}
+
+#[test]
+fn examples_with_admissible_heuristic() {
+    let examples = vec![
+        ("test.txt", false, 8),
+        ("test2.txt", false, 86),
+        ("test3.txt", false, 132),
+        ("test4.txt", false, 136),
+        ("test5.txt", false, 81),
+        ("test6.txt", true, 8),
+        ("test7.txt", true, 24),
+        ("test8.txt", true, 32),
+        ("test9.txt", true, 72),
+    ];
+    for (file_name, split, expected) in examples {
+        let solution = solve_example(file_name, split, farthest_key);
+        assert_eq!(expected, solution.distance, "{}", file_name);
+        assert_eq!(0, solution.report.max_overestimate, "{}", file_name);
+        assert_eq!(0, solution.report.max_inconsistency, "{}", file_name);
+    }
+}
+
+#[test]
+fn overestimating_heuristic_is_reported() {
+    fn doubled(graph: &KeyGraph, state: &SearchState) -> u32 {
+        farthest_key(graph, state) * 2
+    }
+    let report = solve_example("test2.txt", false, doubled).report;
+    assert!(report.max_overestimate > 0);
+    assert!(report.max_inconsistency > 0);
+}
@@ -0,0 +1,124 @@
+use std::collections::hash_map::RandomState;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryInto;
+use std::hash::{BuildHasher, BuildHasherDefault, Hash, Hasher};
+use std::time::Instant;
+
+/// Hasher of the Firefox and rustc hash maps: a multiply and rotate per word. Much faster
+/// than SipHash on small keys, and deterministic, but with no protection against
+/// collision attacks, which don't matter for puzzle inputs.
+#[derive(Default, Clone, Copy)]
+pub struct FxHasher {
+    hash: u64,
+}
+
+const SEED: u64 = 0x51_7c_c1_b7_27_22_0a_95;
+
+impl FxHasher {
+    fn add(&mut self, word: u64) {
+        self.hash = (self.hash.rotate_left(5) ^ word).wrapping_mul(SEED);
+    }
+}
+
+impl Hasher for FxHasher {
+    fn write(&mut self, mut bytes: &[u8]) {
+        while bytes.len() >= 8 {
+            self.add(u64::from_le_bytes(bytes[..8].try_into().unwrap()));
+            bytes = &bytes[8..];
+        }
+        if bytes.len() >= 4 {
+            self.add(u32::from_le_bytes(bytes[..4].try_into().unwrap()) as u64);
+            bytes = &bytes[4..];
+        }
+        for byte in bytes {
+            self.add(*byte as u64);
+        }
+    }
+
+    fn write_u8(&mut self, i: u8) {
+        self.add(i as u64);
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.add(i as u64);
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.add(i as u64);
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.add(i);
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.add(i as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        // The multiplications only carry upwards, and the maps pick the bucket from the
+        // low bits
+        self.hash.rotate_left(26)
+    }
+}
+
+/// Hashing of the solver maps: FxHash, or the standard SipHash with the `std-hash` feature
+#[cfg(not(feature = "std-hash"))]
+pub type BuildFastHasher = BuildHasherDefault<FxHasher>;
+#[cfg(feature = "std-hash")]
+pub type BuildFastHasher = RandomState;
+
+pub type FastHashMap<K, V> = HashMap<K, V, BuildFastHasher>;
+pub type FastHashSet<T> = HashSet<T, BuildFastHasher>;
+
+/// Time taken to insert the keys in a map, then to look them all up, in ms
+fn time_map<K: Hash + Eq + Clone, S: BuildHasher + Default>(keys: &[K]) -> (f64, f64) {
+    let start = Instant::now();
+    let mut map: HashMap<K, usize, S> = HashMap::default();
+    for (i, k) in keys.iter().enumerate() {
+        map.insert(k.clone(), i);
+    }
+    let inserted = Instant::now();
+    let found = keys.iter().filter(|k| map.contains_key(k)).count();
+    assert_eq!(keys.len(), found);
+    let ms = |from: Instant, to: Instant| (to - from).as_secs_f64() * 1000.0;
+    (ms(start, inserted), ms(inserted, Instant::now()))
+}
+
+/// Prints the time taken by SipHash and FxHash maps on keys like the solvers'
+pub fn compare_hashers<K: Hash + Eq + Clone>(name: &str, keys: &[K]) {
+    let (sip_insert, sip_lookup) = time_map::<K, RandomState>(keys);
+    let (fx_insert, fx_lookup) = time_map::<K, BuildHasherDefault<FxHasher>>(keys);
+    println!("{} ({} keys):", name, keys.len());
+    println!(
+        "  SipHash: insert {:.1} ms, lookup {:.1} ms",
+        sip_insert, sip_lookup
+    );
+    println!(
+        "  FxHash:  insert {:.1} ms, lookup {:.1} ms ({:.1}x faster)",
+        fx_insert,
+        fx_lookup,
+        (sip_insert + sip_lookup) / (fx_insert + fx_lookup)
+    );
+}
+
+#[test]
+fn deterministic_hashes() {
+    let hash = |value: &(Vec<usize>, u32)| {
+        let mut hasher = FxHasher::default();
+        value.hash(&mut hasher);
+        hasher.finish()
+    };
+    let a = (vec![1, 2, 3], 7);
+    assert_eq!(hash(&a), hash(&a.clone()));
+    assert_ne!(hash(&a), hash(&(vec![1, 2, 3], 6)));
+    assert_ne!(hash(&a), hash(&(vec![3, 2, 1], 7)));
+
+    // Strings differing only past the first word
+    let hash_str = |s: &str| {
+        let mut hasher = FxHasher::default();
+        hasher.write(s.as_bytes());
+        hasher.finish()
+    };
+    assert_ne!(hash_str("abcdefghij"), hash_str("abcdefghik"));
+}
@@ -1,9 +1,8 @@
+use crate::iterators::NextMoveIterator;
+use crate::maze::{self, Cell, Maze};
 use crate::MainResult;
 use crate::Pos;
 use std::collections::HashMap;
-use std::fs::File;
-use std::io::prelude::*;
-use std::io::BufReader;
 
 #[derive(Debug)]
 pub enum Content {
@@ -17,42 +16,72 @@ pub type Grid<T> = HashMap<Pos, T>;
 pub type ContentGrid = Grid<Content>;
 
 pub fn parse_grid(file_name: &str) -> MainResult<(ContentGrid, Pos)> {
-    let file = File::open(file_name)?;
-    let mut reader = BufReader::new(file);
-    let mut grid: ContentGrid = ContentGrid::new();
+    let maze = parse_maze(&std::fs::read_to_string(file_name)?);
+    let current_pos = maze
+        .labels
+        .get(&'@')
+        .and_then(|p| p.last())
+        .copied()
+        .unwrap_or(Pos(0, 0));
+    Ok((maze.grid, current_pos))
+}
 
-    let mut y = 0;
-    let mut current_pos = Pos(0, 0);
-    loop {
-        let mut line = String::new();
-        let read = reader.read_line(&mut line)?;
-        if read == 0 {
-            break;
-        }
-        for (x, ch) in line.chars().enumerate() {
-            let pos = Pos(x, y);
-            match ch {
-                '#' => {
-                    grid.insert(pos, Content::Wall);
-                }
-                '.' => {
-                    grid.insert(pos, Content::Passage);
-                }
-                '@' => {
-                    current_pos = pos;
-                    grid.insert(pos, Content::Key('@'));
-                }
-                x if x.is_lowercase() => {
-                    grid.insert(pos, Content::Key(x));
-                }
-                x if x.is_uppercase() => {
-                    grid.insert(pos, Content::Door(x));
-                }
-                _ => (),
+/// Parses the maze; the entrance, keys and doors are labeled with their character
+pub fn parse_maze(text: &str) -> Maze<Content, char> {
+    maze::parse(text, |_, ch, _| match ch {
+        '#' => Cell::Plain(Content::Wall),
+        '.' => Cell::Plain(Content::Passage),
+        '@' => Cell::Labeled(Content::Key('@'), '@'),
+        x if x.is_lowercase() => Cell::Labeled(Content::Key(x), x),
+        x if x.is_uppercase() => Cell::Labeled(Content::Door(x), x),
+        _ => Cell::Ignored,
+    })
+}
+
+/// Closes the entrance and puts a start key in each of its diagonal corners, so that
+/// 4 robots explore their own quadrant
+pub fn split_entrance(grid: &mut ContentGrid, initial_pos: Pos, start_keys: &[char]) {
+    // Close the path around initial_pos
+    for p in NextMoveIterator::new(initial_pos) {
+        grid.insert(p, Content::Wall);
+    }
+    grid.insert(initial_pos, Content::Wall);
+
+    let mut start_keys_content: Vec<_> = start_keys.iter().copied().map(Content::Key).collect();
+    for xd in -1..=1 {
+        for yd in -1..=1 {
+            if xd * yd != 0 {
+                let start_key_pos = Pos(
+                    ((initial_pos.0 as isize) + xd) as usize,
+                    (initial_pos.1 as isize + yd) as usize,
+                );
+                grid.insert(
+                    start_key_pos,
+                    start_keys_content.pop().expect("Ran out of start keys!"),
+                );
             }
         }
-        y += 1;
     }
+}
 
-    Ok((grid, current_pos))
+#[test]
+fn labeled_examples() {
+    for &(file_name, keys, doors, entrance) in &[
+        ("test.txt", 2, 1, Pos(5, 1)),
+        ("test2.txt", 6, 5, Pos(15, 1)),
+        ("test3.txt", 7, 5, Pos(6, 3)),
+        ("test4.txt", 16, 8, Pos(8, 4)),
+        ("test5.txt", 9, 5, Pos(1, 1)),
+        ("test6.txt", 4, 3, Pos(3, 3)),
+        ("test7.txt", 4, 3, Pos(7, 3)),
+        ("test8.txt", 12, 11, Pos(6, 3)),
+        ("test9.txt", 15, 13, Pos(6, 4)),
+    ] {
+        let maze = parse_maze(&std::fs::read_to_string(file_name).unwrap());
+        let count = |f: fn(&char) -> bool| maze.labels.keys().filter(|l| f(l)).count();
+        assert_eq!(keys, count(char::is_ascii_lowercase), "{}", file_name);
+        assert_eq!(doors, count(char::is_ascii_uppercase), "{}", file_name);
+        assert_eq!(vec![entrance], maze.labels[&'@'], "{}", file_name);
+        assert!(maze.labels.values().all(|p| p.len() == 1), "{}", file_name);
+    }
 }
@@ -1,3 +1,4 @@
+use crate::fasthash::FastHashMap;
 use crate::grid::*;
 use crate::iterators::*;
 use linked_hash_set::LinkedHashSet;
@@ -14,8 +15,11 @@ use std::rc::Rc;
 use std::result::Result;
 use std::time::Instant;
 
+mod astar;
+mod fasthash;
 mod grid;
 mod iterators;
+mod maze;
 
 type MainResult<T> = Result<T, Box<dyn ::std::error::Error>>;
 
@@ -68,7 +72,7 @@ struct State {
     path_map: HashMap<Key, HashMap<Key, Rc<RefCell<KeyPath>>>>,
     iteration_count: u32,
     key_cursors: HashMap<Key, usize>,
-    cache: HashMap<String, u32>,
+    cache: FastHashMap<String, u32>,
 }
 
 impl State {
@@ -100,7 +104,7 @@ impl State {
             path_map,
             iteration_count: 0,
             key_cursors,
-            cache: HashMap::new(),
+            cache: FastHashMap::default(),
         }
     }
 }
@@ -118,28 +122,32 @@ fn main() -> MainResult<()> {
     let (mut grid, initial_pos) = parse_grid(&file_name)?;
 
     // Update the grid for part 2
-    // Close the path around initial_pos
-    for p in get_neighbouring_positions(initial_pos) {
-        grid.insert(p, Content::Wall);
-    }
-    grid.insert(initial_pos, Content::Wall);
-
     let start_keys: Vec<_> = vec!['@', '$', '%', '#'];
-    let mut start_keys_content: Vec<_> = start_keys.iter().copied().map(Content::Key).collect();
+    split_entrance(&mut grid, initial_pos, &start_keys);
     let start = Instant::now();
-    for xd in -1..=1 {
-        for yd in -1..=1 {
-            if xd * yd != 0 {
-                let start_key_pos = Pos(
-                    ((initial_pos.0 as isize) + xd) as usize,
-                    (initial_pos.1 as isize + yd) as usize,
-                );
-                grid.insert(
-                    start_key_pos,
-                    start_keys_content.pop().expect("Ran out of start keys!"),
-                );
-            }
-        }
+
+    // day18 <input> --bench-hash: times the solver maps with SipHash and FxHash
+    if env::args().nth(2).as_deref() == Some("--bench-hash") {
+        let states = astar::sample_states(&grid, 500_000);
+        fasthash::compare_hashers("A* states", &states);
+        let cache_keys: Vec<String> = states.iter().map(|s| s.cache_key()).collect();
+        fasthash::compare_hashers("Cache keys", &cache_keys);
+        return Ok(());
+    }
+
+    // day18 <input> --astar: searches with A*, and reports on the heuristic's quality
+    if env::args().nth(2).as_deref() == Some("--astar") {
+        let solution = astar::shortest_path(&grid, astar::farthest_key)
+            .ok_or("Some keys can't be collected")?;
+        println!(
+            "Min distance found in {} ms with A*: {}",
+            (Instant::now() - start)
+                .as_millis()
+                .to_formatted_string(&Locale::en),
+            solution.distance
+        );
+        println!("{}", solution.report);
+        return Ok(());
     }
 
     display_content_grid(&grid, None);
@@ -0,0 +1,57 @@
+use crate::Pos;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Content of each cell of a maze, along with the positions of its labeled cells
+pub struct Maze<C, L> {
+    pub grid: HashMap<Pos, C>,
+    /// Positions of each label, in reading order
+    pub labels: HashMap<L, Vec<Pos>>,
+}
+
+/// What a character of the maze stands for, as decided by the labeling callback
+pub enum Cell<C, L> {
+    Plain(C),
+    Labeled(C, L),
+    /// Not part of the maze, e.g. blank space or the letters of a portal name
+    Ignored,
+}
+
+/// Characters of the maze text, by position
+pub type CharGrid = HashMap<Pos, char>;
+
+/// Parses the text of a maze. `label` is called for each character, with the whole
+/// character grid so that it can read labels spanning several characters.
+pub fn parse<C, L: Eq + Hash>(
+    text: &str,
+    mut label: impl FnMut(Pos, char, &CharGrid) -> Cell<C, L>,
+) -> Maze<C, L> {
+    let mut chars = CharGrid::new();
+    for (y, line) in text.lines().enumerate() {
+        for (x, ch) in line.chars().enumerate() {
+            chars.insert(Pos(x, y), ch);
+        }
+    }
+
+    let mut maze = Maze {
+        grid: HashMap::new(),
+        labels: HashMap::new(),
+    };
+    for (y, line) in text.lines().enumerate() {
+        for (x, ch) in line.chars().enumerate() {
+            let pos = Pos(x, y);
+            match label(pos, ch, &chars) {
+                Cell::Plain(content) => {
+                    maze.grid.insert(pos, content);
+                }
+                Cell::Labeled(content, l) => {
+                    maze.grid.insert(pos, content);
+                    maze.labels.entry(l).or_insert_with(Vec::new).push(pos);
+                }
+                Cell::Ignored => (),
+            }
+        }
+    }
+
+    maze
+}
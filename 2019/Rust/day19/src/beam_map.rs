@@ -0,0 +1,265 @@
+use crate::fasthash::FastHashSet;
+use crate::glyphs::Glyphs;
+use std::fs;
+
+/// Side of the square that must fit in the beam
+pub const SQUARE_SIZE: i64 = 100;
+
+/// Scanned region of the beam, with the square found in it
+pub struct BeamMap {
+    /// Top-left corner of the region, and its size
+    pub origin: (i64, i64),
+    pub size: (i64, i64),
+    pub hits: FastHashSet<(i64, i64)>,
+    /// Top-left corner of the square
+    pub square: Option<(i64, i64)>,
+}
+
+impl BeamMap {
+    fn contains(&self, (x, y): (i64, i64)) -> bool {
+        x >= self.origin.0
+            && y >= self.origin.1
+            && x < self.origin.0 + self.size.0
+            && y < self.origin.1 + self.size.1
+    }
+
+    fn on_square_outline(&self, (x, y): (i64, i64)) -> bool {
+        match self.square {
+            Some((x0, y0)) => {
+                let (x1, y1) = (x0 + SQUARE_SIZE - 1, y0 + SQUARE_SIZE - 1);
+                let inside = x >= x0 && x <= x1 && y >= y0 && y <= y1;
+                inside && (x == x0 || x == x1 || y == y0 || y == y1)
+            }
+            None => false,
+        }
+    }
+
+    fn label(&self) -> String {
+        match self.square {
+            Some((x, y)) => format!(
+                "{0}x{0} square at {1},{2}: {3}",
+                SQUARE_SIZE,
+                x,
+                y,
+                x * 10_000 + y
+            ),
+            None => format!("No {0}x{0} square in the scanned region", SQUARE_SIZE),
+        }
+    }
+
+    /// Draws the region with each pixel covering `scale`x`scale` positions. A pixel is
+    /// painted when the beam reaches one of its positions; the outline of the square is
+    /// drawn by inverting the pixels it crosses.
+    pub fn render(&self, scale: i64, glyphs: Glyphs) -> String {
+        let scale = scale.max(1);
+        let block = |px: i64, py: i64| {
+            let (x0, y0) = (self.origin.0 + px * scale, self.origin.1 + py * scale);
+            (y0..y0 + scale).flat_map(move |y| (x0..x0 + scale).map(move |x| (x, y)))
+        };
+        let rows: Vec<Vec<bool>> = (0..(self.size.1 + scale - 1) / scale)
+            .map(|py| {
+                (0..(self.size.0 + scale - 1) / scale)
+                    .map(|px| {
+                        let beam = block(px, py).any(|p| self.hits.contains(&p));
+                        let outline = block(px, py).any(|p| self.on_square_outline(p));
+                        beam != outline
+                    })
+                    .collect()
+            })
+            .collect();
+        format!("{}\n{}", glyphs.render(&rows), self.label())
+    }
+
+    /// SVG picture of the region, one pixel per position, with the label underneath
+    pub fn to_svg(&self) -> String {
+        let (width, height) = self.size;
+        let font_size = (width / 25).max(8);
+        let mut lines = vec![
+            format!(
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"{2} {3} {0} {1}\">",
+                width,
+                height + 2 * font_size,
+                self.origin.0,
+                self.origin.1
+            ),
+            format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"#0f0f23\"/>",
+                self.origin.0, self.origin.1, width, height
+            ),
+        ];
+
+        // One rectangle per run of positions in the beam on each row
+        for y in self.origin.1..self.origin.1 + height {
+            let mut x = self.origin.0;
+            while x < self.origin.0 + width {
+                if !self.hits.contains(&(x, y)) {
+                    x += 1;
+                    continue;
+                }
+                let start = x;
+                while self.contains((x, y)) && self.hits.contains(&(x, y)) {
+                    x += 1;
+                }
+                lines.push(format!(
+                    "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"1\" fill=\"#ffff66\"/>",
+                    start,
+                    y,
+                    x - start
+                ));
+            }
+        }
+
+        if let Some((x, y)) = self.square {
+            lines.push(format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{2}\" height=\"{2}\" fill=\"none\" stroke=\"#ff0000\"/>",
+                x, y, SQUARE_SIZE
+            ));
+        }
+        lines.push(format!(
+            "<text x=\"{}\" y=\"{}\" font-size=\"{}\" font-family=\"monospace\">{}</text>",
+            self.origin.0,
+            self.origin.1 + height + 3 * font_size / 2,
+            font_size,
+            self.label()
+        ));
+        lines.push("</svg>".to_string());
+        lines.join("\n") + "\n"
+    }
+}
+
+/// Text form of the probe results of a region: its bounds, then the runs of positions in
+/// the beam on each row, such as `850: 700-712,720-730`
+pub fn serialize_scan(
+    origin: (i64, i64),
+    size: (i64, i64),
+    hits: &FastHashSet<(i64, i64)>,
+) -> String {
+    let mut lines = vec![format!(
+        "region: {} {} {} {}",
+        origin.0, origin.1, size.0, size.1
+    )];
+    for y in origin.1..origin.1 + size.1 {
+        let mut runs = vec![];
+        let mut run: Option<(i64, i64)> = None;
+        for x in origin.0..origin.0 + size.0 {
+            match (hits.contains(&(x, y)), run) {
+                (true, Some((start, _))) => run = Some((start, x)),
+                (true, None) => run = Some((x, x)),
+                (false, Some((start, end))) => {
+                    runs.push(format!("{}-{}", start, end));
+                    run = None;
+                }
+                (false, None) => (),
+            }
+        }
+        if let Some((start, end)) = run {
+            runs.push(format!("{}-{}", start, end));
+        }
+        if !runs.is_empty() {
+            lines.push(format!("{}: {}", y, runs.join(",")));
+        }
+    }
+    lines.join("\n") + "\n"
+}
+
+/// Probe results of the region, if the text holds a scan of that same region
+pub fn deserialize_scan(
+    text: &str,
+    origin: (i64, i64),
+    size: (i64, i64),
+) -> Option<FastHashSet<(i64, i64)>> {
+    let mut lines = text.lines();
+    let region = format!("region: {} {} {} {}", origin.0, origin.1, size.0, size.1);
+    if lines.next()? != region {
+        return None;
+    }
+
+    let mut hits = FastHashSet::default();
+    for line in lines {
+        let mut parts = line.splitn(2, ':');
+        let y: i64 = parts.next()?.trim().parse().ok()?;
+        for run in parts.next()?.trim().split(',') {
+            let mut bounds = run.splitn(2, '-');
+            let start: i64 = bounds.next()?.parse().ok()?;
+            let end: i64 = bounds.next()?.parse().ok()?;
+            hits.extend((start..=end).map(|x| (x, y)));
+        }
+    }
+    Some(hits)
+}
+
+/// Probe results cached in `file_name`, if it holds a scan of the region
+pub fn load_scan(
+    file_name: &str,
+    origin: (i64, i64),
+    size: (i64, i64),
+) -> Option<FastHashSet<(i64, i64)>> {
+    deserialize_scan(&fs::read_to_string(file_name).ok()?, origin, size)
+}
+
+pub fn save_scan(
+    file_name: &str,
+    origin: (i64, i64),
+    size: (i64, i64),
+    hits: &FastHashSet<(i64, i64)>,
+) -> std::io::Result<()> {
+    fs::write(file_name, serialize_scan(origin, size, hits))
+}
+
+#[cfg(test)]
+fn triangle_beam() -> BeamMap {
+    // The beam widens by one position per row
+    let hits = (0..8)
+        .flat_map(|y: i64| (0..=y).map(move |x| (x, y)))
+        .collect();
+    BeamMap {
+        origin: (0, 0),
+        size: (8, 8),
+        hits,
+        square: None,
+    }
+}
+
+#[test]
+fn scan_round_trip() {
+    let map = triangle_beam();
+    let text = serialize_scan(map.origin, map.size, &map.hits);
+    assert!(text.starts_with("region: 0 0 8 8\n0: 0-0\n1: 0-1\n"));
+    assert_eq!(
+        Some(map.hits.clone()),
+        deserialize_scan(&text, map.origin, map.size)
+    );
+    // Scan of another region
+    assert_eq!(None, deserialize_scan(&text, (1, 0), map.size));
+}
+
+#[test]
+fn render_with_square() {
+    let mut map = triangle_beam();
+    map.square = Some((0, 7));
+    let expected = [
+        "#.......", "##......", "###.....", "####....", "#####...", "######..", "#######.",
+        "........",
+    ]
+    .join("\n");
+    let text = map.render(1, Glyphs::Ascii);
+    assert!(text.starts_with(&expected));
+    assert!(text.ends_with("100x100 square at 0,7: 7"));
+
+    // Each pixel covers 2x2 positions
+    map.square = None;
+    assert!(map
+        .render(2, Glyphs::Ascii)
+        .starts_with("#...\n##..\n###.\n####\n"));
+}
+
+#[test]
+fn svg_export() {
+    let mut map = triangle_beam();
+    map.square = Some((0, 7));
+    let svg = map.to_svg();
+    assert!(svg.starts_with("<svg"));
+    assert!(svg.contains("<rect x=\"0\" y=\"7\" width=\"8\" height=\"1\" fill=\"#ffff66\"/>"));
+    assert!(svg.contains("stroke=\"#ff0000\""));
+    assert!(svg.contains("100x100 square at 0,7: 7</text>"));
+}
@@ -0,0 +1,285 @@
+use crate::memory::Memory;
+use crate::{jump_to, to_address, ExecutionContext, ExecutionResult, Fault, OpCode};
+use std::rc::Rc;
+
+/// Largest instruction: an op code and 3 parameters
+const MAX_INSTRUCTION_SIZE: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operand {
+    Immediate(i64),
+    Position(usize),
+    /// Offset from the relative base, which is only known at run time
+    Relative(i64),
+}
+
+/// Instruction whose op code and parameter modes have been decoded ahead of execution
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CompiledInstruction {
+    op_code: OpCode,
+    /// Unused operands are `Immediate(0)`
+    operands: [Operand; 3],
+}
+
+impl CompiledInstruction {
+    fn size(&self) -> usize {
+        1 + self.op_code.parameter_count()
+    }
+}
+
+/// Instruction starting at each address of the program image, if it's a valid one. It's
+/// shared by all the VMs running the program; a VM writing into an instruction gets its
+/// own copy, without that instruction.
+pub type Code = Rc<Vec<Option<CompiledInstruction>>>;
+
+fn decode(memory: &Memory, address: usize) -> Result<CompiledInstruction, Fault> {
+    let value = memory[address];
+    let op_code = OpCode::from_value(value % 100).ok_or(Fault::UnknownOpCode(value % 100))?;
+    let mut modes = value / 100;
+    let mut operands = [Operand::Immediate(0); 3];
+    for (i, operand) in operands
+        .iter_mut()
+        .enumerate()
+        .take(op_code.parameter_count())
+    {
+        let parameter = memory[address + 1 + i];
+        *operand = match modes % 10 {
+            0 => Operand::Position(to_address(Some(parameter))?),
+            1 => Operand::Immediate(parameter),
+            2 => Operand::Relative(parameter),
+            mode => return Err(Fault::InvalidParameterMode(mode)),
+        };
+        modes /= 10;
+    }
+    Ok(CompiledInstruction { op_code, operands })
+}
+
+/// Decodes the instruction at every address of the program; the addresses holding data
+/// are decoded too, in case the program jumps there
+pub fn compile(memory: &Memory) -> Code {
+    Rc::new(
+        (0..memory.image_len())
+            .map(|address| decode(memory, address).ok())
+            .collect(),
+    )
+}
+
+/// Address of a parameter that can be written to
+fn address(context: &ExecutionContext, operand: Operand) -> Result<usize, Fault> {
+    match operand {
+        Operand::Immediate(_) => Err(Fault::WriteToImmediate),
+        Operand::Position(address) => Ok(address),
+        Operand::Relative(offset) => to_address(context.relative_base.checked_add(offset)),
+    }
+}
+
+/// Checks that the address of a parameter is valid, without accessing it
+fn check(context: &ExecutionContext, operand: Operand) -> Result<(), Fault> {
+    match operand {
+        Operand::Immediate(_) => Ok(()),
+        _ => address(context, operand).map(|_| ()),
+    }
+}
+
+fn read(context: &ExecutionContext, operand: Operand) -> Result<i64, Fault> {
+    if let Operand::Immediate(value) = operand {
+        return Ok(value);
+    }
+    let address = address(context, operand)?;
+    if let Some(profile) = &context.profile {
+        profile.borrow_mut().record_read(address);
+    }
+    Ok(context.memory[address])
+}
+
+fn write(context: &mut ExecutionContext, operand: Operand, value: i64) -> Result<(), Fault> {
+    let address = address(context, operand)?;
+    if let Some(profile) = &context.profile {
+        profile.borrow_mut().record_write(address);
+    }
+    context.memory[address] = value;
+    invalidate(context, address);
+    Ok(())
+}
+
+/// Drops the compiled instructions that the write to `address` has modified
+fn invalidate(context: &mut ExecutionContext, address: usize) {
+    let code = match &mut context.code {
+        Some(code) => code,
+        None => return,
+    };
+    let first = address.saturating_sub(MAX_INSTRUCTION_SIZE - 1);
+    let covers = |code: &[Option<CompiledInstruction>], start: usize| match code.get(start) {
+        Some(Some(instruction)) => start + instruction.size() > address,
+        _ => false,
+    };
+    if (first..=address).any(|start| covers(code, start)) {
+        let code = Rc::make_mut(code);
+        for start in first..=address {
+            if covers(code, start) {
+                code[start] = None;
+            }
+        }
+    }
+}
+
+/// Fetches the compiled instruction at the instruction pointer, decoding it again if it
+/// has been modified
+fn fetch(context: &mut ExecutionContext) -> Result<CompiledInstruction, Fault> {
+    let ip = context.ip;
+    if let Some(Some(instruction)) = context.code.as_ref().and_then(|c| c.get(ip)) {
+        return Ok(*instruction);
+    }
+    let instruction = decode(&context.memory, ip)?;
+    if let Some(code) = &mut context.code {
+        if ip < code.len() {
+            Rc::make_mut(code)[ip] = Some(instruction);
+        }
+    }
+    Ok(instruction)
+}
+
+/// Same as `execute_instructions`, running the pre-decoded instructions of
+/// `context.code` instead of decoding each one as it's executed
+pub fn execute(context: &mut ExecutionContext, max_instructions: u64) -> ExecutionResult {
+    for _ in 0..max_instructions {
+        let ip = context.ip;
+        match execute_instruction(context) {
+            Ok(None) => context.executed += 1,
+            Ok(Some(result)) => return result,
+            Err(fault) => {
+                context.ip = ip;
+                return ExecutionResult::Fault(fault);
+            }
+        }
+    }
+
+    ExecutionResult::BudgetExceeded
+}
+
+fn execute_instruction(context: &mut ExecutionContext) -> Result<Option<ExecutionResult>, Fault> {
+    let ip = context.ip;
+    let instruction = fetch(context)?;
+    if let Some(profile) = &context.profile {
+        profile
+            .borrow_mut()
+            .start_instruction(ip, instruction.op_code);
+    }
+
+    context.ip += instruction.size();
+    let [a, b, c] = instruction.operands;
+    match instruction.op_code {
+        OpCode::Add => {
+            let value = read(context, a)?.wrapping_add(read(context, b)?);
+            write(context, c, value)?;
+        }
+        OpCode::Mult => {
+            let value = read(context, a)?.wrapping_mul(read(context, b)?);
+            write(context, c, value)?;
+        }
+        OpCode::Input => {
+            address(context, a)?;
+            match context.read_input() {
+                Some(value) => write(context, a, value)?,
+                None => {
+                    // Execute the instruction again when the program is resumed
+                    context.ip = ip;
+                    return Ok(Some(ExecutionResult::MoreInputNeeded));
+                }
+            }
+        }
+        OpCode::Output => {
+            let value = read(context, a)?;
+            context.write_output(value);
+        }
+        // The address of the target must be valid even when the jump isn't taken
+        OpCode::JumpIfTrue => {
+            check(context, b)?;
+            if read(context, a)? != 0 {
+                let address = read(context, b)?;
+                jump_to(&mut context.ip, address)?;
+            }
+        }
+        OpCode::JumpIfFalse => {
+            check(context, b)?;
+            if read(context, a)? == 0 {
+                let address = read(context, b)?;
+                jump_to(&mut context.ip, address)?;
+            }
+        }
+        OpCode::LessThan => {
+            let value = (read(context, a)? < read(context, b)?) as i64;
+            write(context, c, value)?;
+        }
+        OpCode::Equals => {
+            let value = (read(context, a)? == read(context, b)?) as i64;
+            write(context, c, value)?;
+        }
+        OpCode::AdjustRelativeBase => {
+            let adjustment = read(context, a)?;
+            context.relative_base = context.relative_base.wrapping_add(adjustment);
+        }
+        OpCode::Exit => {
+            context.ended = true;
+            context.executed += 1;
+            return Ok(Some(ExecutionResult::Exit));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+fn compiled_context(memory: &Memory) -> ExecutionContext {
+    let mut context = ExecutionContext::new(memory);
+    context.code = Some(compile(memory));
+    context
+}
+
+#[test]
+fn same_results_as_interpreter() {
+    // Reads a value, and outputs the product of the integers from 1 to that value
+    let memory = Memory::parse("3,20,1101,1,0,21,2,21,20,21,1001,20,-1,20,1005,20,6,4,21,99");
+    for n in 1..6 {
+        let mut interpreted = ExecutionContext::new(&memory);
+        interpreted.input = vec![n].into();
+        let mut compiled = compiled_context(&memory);
+        compiled.input = vec![n].into();
+        assert_eq!(
+            ExecutionResult::Exit,
+            crate::execute_with_limit(&mut interpreted, 1000)
+        );
+        assert_eq!(ExecutionResult::Exit, execute(&mut compiled, 1000));
+        assert_eq!(interpreted.output, compiled.output);
+    }
+
+    let mut context = compiled_context(&memory);
+    assert_eq!(ExecutionResult::MoreInputNeeded, execute(&mut context, 10));
+    assert_eq!(0, context.ip);
+}
+
+#[test]
+fn self_modifying_code() {
+    // Writes 2 into the parameter of the output instruction that follows
+    let memory = Memory::parse("1101,1,1,5,104,0,99");
+    let code = compile(&memory);
+    let mut context = ExecutionContext::new(&memory);
+    context.code = Some(code.clone());
+    assert_eq!(ExecutionResult::Exit, execute(&mut context, 10));
+    assert_eq!(2, context.output);
+
+    // The other VMs still share the original instructions
+    assert_eq!(Some(Operand::Immediate(0)), code[4].map(|i| i.operands[0]));
+    let mut other = ExecutionContext::new(&memory);
+    other.code = Some(code);
+    execute(&mut other, 10);
+    assert_eq!(2, other.output);
+}
+
+#[test]
+fn compiled_instruction_limit() {
+    // Outputs its input, then loops forever
+    let mut context = compiled_context(&Memory::parse("3,0,4,0,1105,1,4"));
+    context.input = vec![7].into();
+    assert_eq!(ExecutionResult::BudgetExceeded, execute(&mut context, 100));
+    assert_eq!((7, 4), (context.output, context.ip));
+}
@@ -0,0 +1,351 @@
+use crate::memory::Memory;
+use crate::{compiled, execute_with_limit, ExecutionContext, ExecutionResult};
+use std::fs;
+use std::io;
+use std::mem::discriminant;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+/// Number of instructions each fuzzed program may execute
+const FUZZ_INSTRUCTION_LIMIT: u64 = 10_000;
+
+/// Values that tend to break address computations
+const EXTREME_VALUES: [i64; 6] = [i64::MIN, i64::MAX, -1, -100, i64::MAX / 2, 1 << 62];
+
+/// xorshift64* generator, reproducible from its seed
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        // 0 is the only state the generator can't leave
+        Rng(seed ^ 0x9e37_79b9_7f4a_7c15)
+    }
+
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+}
+
+/// Value likely to make an interesting program: mostly instructions and small addresses,
+/// sometimes extreme values
+fn random_value(rng: &mut Rng) -> i64 {
+    match rng.below(10) {
+        0..=3 => {
+            const OP_CODES: [i64; 10] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 99];
+            let op_code = OP_CODES[rng.below(OP_CODES.len() as u64) as usize];
+            // Mostly valid parameter modes
+            let modes = (0..3).fold(0, |modes, _| {
+                let mode = if rng.below(20) == 0 { 3 } else { rng.below(3) };
+                modes * 10 + mode as i64
+            });
+            modes * 100 + op_code
+        }
+        4..=7 => rng.below(68) as i64 - 4,
+        8 => EXTREME_VALUES[rng.below(EXTREME_VALUES.len() as u64) as usize],
+        _ => rng.next() as i64,
+    }
+}
+
+pub fn random_program(rng: &mut Rng, len: usize) -> Vec<i64> {
+    (0..len).map(|_| random_value(rng)).collect()
+}
+
+/// Copy of the program with a few values replaced, nudged or swapped
+pub fn mutate(rng: &mut Rng, program: &[i64]) -> Vec<i64> {
+    let mut program = program.to_vec();
+    if program.is_empty() {
+        return program;
+    }
+    for _ in 0..1 + rng.below(4) {
+        let len = program.len() as u64;
+        let i = rng.below(len) as usize;
+        match rng.below(3) {
+            0 => program[i] = random_value(rng),
+            1 => program[i] = program[i].wrapping_add(rng.below(3) as i64 - 1),
+            _ => program.swap(i, rng.below(len) as usize),
+        }
+    }
+    program
+}
+
+fn run(
+    memory: &Memory,
+    input: &[i64],
+    compiled: bool,
+) -> Result<(ExecutionResult, ExecutionContext), String> {
+    let mut context = ExecutionContext::new(memory);
+    context.input = input.iter().copied().collect();
+    if compiled {
+        context.code = Some(compiled::compile(memory));
+    }
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        execute_with_limit(&mut context, FUZZ_INSTRUCTION_LIMIT)
+    }));
+    let executor = if compiled { "compiled" } else { "interpreter" };
+    let result = result.map_err(|e| {
+        let message = e
+            .downcast_ref::<String>()
+            .cloned()
+            .or_else(|| e.downcast_ref::<&str>().map(|s| s.to_string()))
+            .unwrap_or_default();
+        format!("{} panicked: {}", executor, message)
+    })?;
+
+    if context.executed > FUZZ_INSTRUCTION_LIMIT {
+        return Err(format!(
+            "{} executed {} instructions, beyond the limit of {}",
+            executor, context.executed, FUZZ_INSTRUCTION_LIMIT
+        ));
+    }
+    // A negative address cast to a usize lands above i64::MAX
+    if let Some(address) = context.memory.highest_address() {
+        if address > i64::MAX as usize {
+            return Err(format!(
+                "{} wrote to negative address {}",
+                executor, address as i64
+            ));
+        }
+    }
+    Ok((result, context))
+}
+
+/// Runs the program with both executors, checking that neither panics, exceeds the
+/// instruction limit or accesses a negative address, and that they agree on the outcome.
+/// They may report different faults for an instruction with several invalid parameters.
+pub fn check(program: &[i64], input: &[i64]) -> Result<(), String> {
+    let memory = Memory::new(program.iter().cloned().enumerate().collect());
+    let (interpreted_result, interpreted) = run(&memory, input, false)?;
+    let (compiled_result, compiled) = run(&memory, input, true)?;
+    if discriminant(&interpreted_result) != discriminant(&compiled_result) {
+        return Err(format!(
+            "interpreter returned {:?}, compiled {:?}",
+            interpreted_result, compiled_result
+        ));
+    }
+    let state = |c: &ExecutionContext| (c.ip, c.relative_base, c.output, c.executed);
+    if state(&interpreted) != state(&compiled) || interpreted.memory != compiled.memory {
+        return Err(format!(
+            "interpreter ended with (ip, relative base, output, executed) = {:?}, compiled {:?}",
+            state(&interpreted),
+            state(&compiled)
+        ));
+    }
+    Ok(())
+}
+
+/// Program failing the checks, with the input it was run with
+#[derive(Debug)]
+pub struct Failure {
+    pub program: Vec<i64>,
+    pub input: Vec<i64>,
+    pub message: String,
+}
+
+impl Failure {
+    /// Message, then the program and its input, in the format read back by `parse`
+    pub fn report(&self) -> String {
+        let join = |values: &[i64]| {
+            values
+                .iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        format!(
+            "{}\nProgram: {}\nInput: {}",
+            self.message,
+            join(&self.program),
+            join(&self.input)
+        )
+    }
+
+    /// Failure written by `report`, such as a regression case
+    pub fn parse(text: &str) -> Result<Failure, String> {
+        let values = |line: &str| -> Result<Vec<i64>, String> {
+            line.split(',')
+                .map(|v| v.trim())
+                .filter(|v| !v.is_empty())
+                .map(|v| v.parse().map_err(|_| format!("Invalid value: {}", v)))
+                .collect()
+        };
+        let mut message = vec![];
+        let (mut program, mut input) = (None, vec![]);
+        for line in text.lines() {
+            if let Some(line) = line.strip_prefix("Program:") {
+                program = Some(values(line)?);
+            } else if let Some(line) = line.strip_prefix("Input:") {
+                input = values(line)?;
+            } else {
+                message.push(line);
+            }
+        }
+        Ok(Failure {
+            program: program.ok_or("No program in the failure")?,
+            input,
+            message: message.join("\n").trim().to_string(),
+        })
+    }
+
+    /// Name of the regression case: the start of the message, and a hash telling apart
+    /// the programs failing the same way
+    pub fn case_name(&self) -> String {
+        let first_line = self.message.lines().next().unwrap_or("");
+        let words: Vec<String> = first_line
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .take(6)
+            .map(|w| w.to_ascii_lowercase())
+            .collect();
+        // FNV-1a, so the name doesn't change between runs
+        let hash = self
+            .program
+            .iter()
+            .chain(&[i64::MIN])
+            .chain(&self.input)
+            .fold(0xcbf2_9ce4_8422_2325u64, |hash, v| {
+                (hash ^ *v as u64).wrapping_mul(0x0100_0000_01b3)
+            });
+        format!("{}-{:08x}", words.join("-"), hash as u32)
+    }
+
+    /// Writes the failure as a regression case in `dir`, and returns its path
+    pub fn save_regression(&self, dir: &Path) -> io::Result<PathBuf> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(format!("{}.txt", self.case_name()));
+        fs::write(&path, format!("{}\n", self.report()))?;
+        Ok(path)
+    }
+}
+
+/// Runs a regression case through the checks, which it passes once the fault is fixed
+pub fn replay(case: &str) -> Result<(), String> {
+    let failure = Failure::parse(case)?;
+    check(&failure.program, &failure.input)
+}
+
+/// Checks `iterations` programs, alternating between random ones and mutations of the
+/// given program
+pub fn fuzz(program: &[i64], iterations: usize, seed: u64) -> Result<(), Failure> {
+    let mut rng = Rng::new(seed);
+    for i in 0..iterations {
+        let candidate = if i % 2 == 0 || program.is_empty() {
+            let len = 1 + rng.below(64) as usize;
+            random_program(&mut rng, len)
+        } else {
+            mutate(&mut rng, program)
+        };
+        let input: Vec<i64> = (0..rng.below(4)).map(|_| random_value(&mut rng)).collect();
+        if let Err(message) = check(&candidate, &input) {
+            return Err(Failure {
+                program: candidate,
+                input,
+                message,
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+fn run_interpreted(program: &str) -> ExecutionResult {
+    let mut context = ExecutionContext::new(&Memory::parse(program));
+    execute_with_limit(&mut context, FUZZ_INSTRUCTION_LIMIT)
+}
+
+#[test]
+fn faults_instead_of_panics() {
+    use crate::Fault;
+
+    let cases = [
+        ("1,-1,0,0,99", Fault::NegativeAddress(-1)),
+        ("109,-5,204,0,99", Fault::NegativeAddress(-5)),
+        ("1105,1,-3", Fault::NegativeAddress(-3)),
+        ("42", Fault::UnknownOpCode(42)),
+        ("-1", Fault::UnknownOpCode(-1)),
+        ("304,0,99", Fault::InvalidParameterMode(3)),
+        ("11101,1,1,0,99", Fault::WriteToImmediate),
+        (
+            "109,9223372036854775807,22201,1,1,0,99",
+            Fault::AddressOverflow,
+        ),
+    ];
+    for (program, fault) in cases.iter() {
+        assert_eq!(
+            ExecutionResult::Fault(*fault),
+            run_interpreted(program),
+            "{}",
+            program
+        );
+        let values: Vec<i64> = program.split(',').map(|v| v.parse().unwrap()).collect();
+        assert_eq!(Ok(()), check(&values, &[]), "{}", program);
+    }
+}
+
+#[test]
+fn arithmetic_wraps() {
+    let mut context = ExecutionContext::new(&Memory::parse("1101,9223372036854775807,1,0,99"));
+    assert_eq!(ExecutionResult::Exit, execute_with_limit(&mut context, 10));
+    assert_eq!(i64::MIN, context.memory[0]);
+}
+
+#[test]
+fn instruction_limit_respected() {
+    let mut context = ExecutionContext::new(&Memory::parse("1105,1,0"));
+    assert_eq!(
+        ExecutionResult::BudgetExceeded,
+        execute_with_limit(&mut context, FUZZ_INSTRUCTION_LIMIT)
+    );
+    assert_eq!(FUZZ_INSTRUCTION_LIMIT, context.executed);
+}
+
+#[test]
+fn fuzz_random_and_mutated_programs() {
+    // Reads a value, and outputs the product of the integers from 1 to that value
+    let program = [
+        3, 20, 1101, 1, 0, 21, 2, 21, 20, 21, 1001, 20, -1, 20, 1005, 20, 6, 4, 21, 99,
+    ];
+    if let Err(failure) = fuzz(&program, 1000, 2019) {
+        panic!("{}", failure.report());
+    }
+}
+
+#[test]
+fn regression_cases() {
+    let failure = Failure {
+        program: vec![1, -1, 0, 0, 99],
+        input: vec![7],
+        message: "interpreter panicked: index out of bounds".to_string(),
+    };
+    let parsed = Failure::parse(&failure.report()).unwrap();
+    assert_eq!(
+        (
+            failure.program.clone(),
+            failure.input.clone(),
+            failure.message.clone()
+        ),
+        (parsed.program, parsed.input, parsed.message)
+    );
+
+    let name = failure.case_name();
+    assert!(
+        name.starts_with("interpreter-panicked-index-out-of-bounds-"),
+        "{}",
+        name
+    );
+    assert_eq!(name, Failure::parse(&failure.report()).unwrap().case_name());
+    let other = Failure {
+        input: vec![],
+        ..failure
+    };
+    assert_ne!(name, other.case_name());
+
+    assert_eq!(Ok(()), replay("Fixed\nProgram: 1,-1,0,0,99\nInput:"));
+    assert!(Failure::parse("No program").is_err());
+}
@@ -1,20 +1,50 @@
+use crate::beam_map::BeamMap;
+use crate::fasthash::FastHashSet;
+use crate::glyphs::Glyphs;
 use crate::memory::Memory;
-use std::collections::HashSet;
+use crate::profile::Profile;
+use std::cell::RefCell;
+use std::collections::VecDeque;
 use std::env;
+use std::fmt;
 use std::fs::File;
-use std::io::Read;
-
-#[cfg(unix)]
-extern crate ncurses;
-
+use std::io::{Read, Write};
+use std::panic;
+use std::path::Path;
+use std::process;
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+mod beam_map;
+mod compiled;
+// Same hashing as day18, which uses the rest of the module
+#[allow(dead_code)]
+mod fasthash;
+mod fuzz;
+mod glyphs;
 mod memory;
+mod profile;
 
 type Result<T> = ::std::result::Result<T, Box<dyn ::std::error::Error>>;
 
+/// Where the fuzzer saves the failures it finds, relative to the crate
+const REGRESSIONS_DIR: &str = "tests/regressions";
+
+/// Number of instructions a single drone probe may execute before it's considered stuck
+const PROBE_INSTRUCTION_LIMIT: u64 = 1_000_000;
+
+// Exit code for Intcode VM errors, shared with the runner
+const EXIT_VM_ERROR: i32 = 4;
+
 #[derive(Eq, PartialEq, Hash, Clone, Copy)]
 struct Pos(i32, i32);
 
 fn main() -> Result<()> {
+    // day19 regression <case>...: replays regression cases saved by the fuzzer
+    if env::args().nth(1).as_deref() == Some("regression") {
+        return replay_regressions(env::args().skip(2));
+    }
+
     let file_name = env::args().nth(1).expect("Enter a file name");
 
     let mut instructions = String::new();
@@ -24,67 +54,206 @@ fn main() -> Result<()> {
 
     //init();
     let memory = Memory::parse(&instructions);
+    if let Some(iterations) = env::args().skip_while(|a| a != "--fuzz").nth(1) {
+        return run_fuzzer(&memory, iterations.parse()?);
+    }
+    // Gathered over all the probes
+    let profile = if env::args().any(|a| a == "--profile") {
+        Some(Rc::new(RefCell::new(Profile::default())))
+    } else {
+        None
+    };
+
+    // Decoded once, and shared by all the probes
+    let code = if env::args().any(|a| a == "--compiled") {
+        Some(compiled::compile(&memory))
+    } else {
+        None
+    };
+
+    // Draws the plain beam instead of the annotated scan
+    let glyphs = Glyphs::from_args(env::args())?;
 
     let mut context = ExecutionContext::new(&memory);
 
-    let mut run = |x: i64, y: i64| {
+    let mut run = |x: i64, y: i64| -> Result<i64> {
         context = ExecutionContext::new(&memory);
-        context.input = vec![x, y];
-        execute_program(&mut context);
-        context.output
+        context.input = vec![x, y].into();
+        context.profile = profile.clone();
+        context.code = code.clone();
+        match execute_with_limit(&mut context, PROBE_INSTRUCTION_LIMIT) {
+            ExecutionResult::BudgetExceeded => Err(format!(
+                "Probe at {}x{} didn't halt after {} instructions",
+                x, y, PROBE_INSTRUCTION_LIMIT
+            )
+            .into()),
+            ExecutionResult::Fault(fault) => {
+                eprintln!(
+                    "VM error: probe at {}x{} failed at ip {}: {}",
+                    x, y, context.ip, fault
+                );
+                process::exit(EXIT_VM_ERROR);
+            }
+            _ => Ok(context.output),
+        }
     };
 
-    let mut hits: HashSet<(i64, i64)> = HashSet::new();
-
     let x_min = 700;
     let x_max = 1100;
 
     let y_min = 850;
     let y_max = 1100;
 
-    for y in y_min..y_max {
-        //let mut line_hits = 0;
-        //print!("{}", y);
-        for x in x_min..x_max {
-            //println!("Result {}x{}: {}", x, y, run(x, y));
-            //print!("{}", if run(x, y) == 1 { '#' } else { '.' });
-            if run(x, y) == 1 {
-                hits.insert((x, y));
-                //line_hits += 1;
-            };
+    let region = ((x_min, y_min), (x_max - x_min, y_max - y_min));
+    // day19 <input> --bench-hash: times the beam cache with SipHash and FxHash
+    if env::args().any(|a| a == "--bench-hash") {
+        let positions: Vec<(i64, i64)> = (y_min..y_max)
+            .flat_map(|y| (x_min..x_max).map(move |x| (x, y)))
+            .collect();
+        fasthash::compare_hashers("Beam positions", &positions);
+        return Ok(());
+    }
+    let cache_file = env::args().skip_while(|a| a != "--beam-cache").nth(1);
+    let cached = cache_file
+        .as_ref()
+        .and_then(|f| beam_map::load_scan(f, region.0, region.1));
+    let scan = cached.is_none();
+    let mut hits: FastHashSet<(i64, i64)> = cached.unwrap_or_default();
+
+    if scan {
+        for y in y_min..y_max {
+            //let mut line_hits = 0;
+            //print!("{}", y);
+            for x in x_min..x_max {
+                //println!("Result {}x{}: {}", x, y, run(x, y));
+                //print!("{}", if run(x, y)? == 1 { '#' } else { '.' });
+                if run(x, y)? == 1 {
+                    hits.insert((x, y));
+                    //line_hits += 1;
+                };
+            }
+            //println!("{}", line_hits);
+        }
+        if let Some(file_name) = &cache_file {
+            beam_map::save_scan(file_name, region.0, region.1, &hits)?;
         }
-        //println!("{}", line_hits);
     }
 
-    let mut result: Option<(i64, i64)> = None;
-    for y in y_min..y_max {
-        print!("{}", y);
-        for x in x_min..x_max {
-            if hits.contains(&(x, y)) {
-                if !hits.contains(&(x + 1, y)) {
-                    if hits.contains(&(x - 99, y + 99)) {
-                        if result.is_none() {
-                            result = Some((x, y));
+    // Right edge of the beam, with the bottom-left corner of a 100x100 square in the beam
+    let fits = |x: i64, y: i64| {
+        hits.contains(&(x, y)) && !hits.contains(&(x + 1, y)) && hits.contains(&(x - 99, y + 99))
+    };
+    let result = (y_min..y_max)
+        .flat_map(|y| (x_min..x_max).map(move |x| (x, y)))
+        .find(|(x, y)| fits(*x, *y));
+
+    match glyphs {
+        Some(glyphs) => {
+            let rows: Vec<Vec<bool>> = (y_min..y_max)
+                .map(|y| (x_min..x_max).map(|x| hits.contains(&(x, y))).collect())
+                .collect();
+            println!("{}", glyphs.render(&rows));
+        }
+        None => {
+            for y in y_min..y_max {
+                print!("{}", y);
+                for x in x_min..x_max {
+                    if hits.contains(&(x, y)) {
+                        if !hits.contains(&(x + 1, y)) {
+                            if fits(x, y) {
+                                print!("!");
+                            } else {
+                                print!("X");
+                            }
+                        } else {
+                            print!("#");
                         }
-                        print!("!");
                     } else {
-                        print!("X");
+                        print!(".")
                     }
-                } else {
-                    print!("#");
                 }
-            } else {
-                print!(".")
+
+                println!();
             }
         }
-
-        println!();
     }
 
     let edge = result.unwrap();
     let origin = (edge.0 - 99, edge.1);
     println!("Result: {:?}", origin.0 * 10_000 + origin.1);
 
+    // Positions covered by each character of the map
+    let scale = match env::args().skip_while(|a| a != "--scale").nth(1) {
+        Some(scale) => scale.parse()?,
+        None => 4,
+    };
+    let map = BeamMap {
+        origin: region.0,
+        size: region.1,
+        hits,
+        square: Some(origin),
+    };
+    if env::args().any(|a| a == "--beam-map") {
+        println!("\n{}", map.render(scale, glyphs.unwrap_or(Glyphs::Ascii)));
+    }
+    if let Some(file_name) = env::args().skip_while(|a| a != "--beam-svg").nth(1) {
+        File::create(&file_name)?.write_all(map.to_svg().as_bytes())?;
+        println!("Beam map exported to {}", file_name);
+    }
+
+    if let Some(profile) = profile {
+        println!("\n{}", profile.borrow().report(10));
+    }
+
+    Ok(())
+}
+
+/// Fuzzes the VM with random programs and mutations of the puzzle input, from the seed
+/// given by `--seed` or a random one
+fn run_fuzzer(memory: &Memory, iterations: usize) -> Result<()> {
+    let seed = match env::args().skip_while(|a| a != "--seed").nth(1) {
+        Some(seed) => seed.parse()?,
+        None => SystemTime::now().duration_since(UNIX_EPOCH)?.as_nanos() as u64,
+    };
+    println!("Fuzzing {} programs from seed {}", iterations, seed);
+    let program: Vec<i64> = (0..memory.image_len()).map(|a| memory[a]).collect();
+
+    // The panics are reported with the program that caused them
+    panic::set_hook(Box::new(|_| {}));
+    let result = fuzz::fuzz(&program, iterations, seed);
+    let _ = panic::take_hook();
+    match result {
+        Ok(()) => println!("No failure found"),
+        Err(failure) => {
+            println!("{}", failure.report());
+            // Kept as a test case, which passes once the fault is fixed
+            let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join(REGRESSIONS_DIR);
+            let path = failure.save_regression(&dir)?;
+            println!("Regression case saved to {}", path.display());
+        }
+    }
+    Ok(())
+}
+
+/// Checks each regression case, reporting the ones still failing
+fn replay_regressions(files: impl Iterator<Item = String>) -> Result<()> {
+    panic::set_hook(Box::new(|_| {}));
+    let mut failed = 0;
+    for file in files {
+        let mut case = String::new();
+        File::open(&file)?.read_to_string(&mut case)?;
+        match fuzz::replay(&case) {
+            Ok(()) => println!("{}: ok", file),
+            Err(message) => {
+                println!("{}: {}", file, message);
+                failed += 1;
+            }
+        }
+    }
+    let _ = panic::take_hook();
+    if failed > 0 {
+        return Err(format!("{} regression case(s) failing", failed).into());
+    }
     Ok(())
 }
 
@@ -93,10 +262,14 @@ struct ExecutionContext {
     ip: usize,
     memory: Memory,
     ended: bool,
-    relative_base: usize,
-    input: Vec<i64>,
-    input_index: usize,
+    relative_base: i64,
+    input: VecDeque<i64>,
     output: i64,
+    /// Instructions executed so far
+    executed: u64,
+    profile: Option<Rc<RefCell<Profile>>>,
+    /// Pre-decoded instructions; when set, the program is run by the compiled executor
+    code: Option<compiled::Code>,
 }
 
 impl ExecutionContext {
@@ -107,21 +280,15 @@ impl ExecutionContext {
             ended: false,
             relative_base: 0,
             output: 0,
-            input_index: 0,
-            input: vec![],
+            input: VecDeque::new(),
+            executed: 0,
+            profile: None,
+            code: None,
         }
     }
 
     fn read_input(&mut self) -> Option<i64> {
-        if self.input_index >= self.input.len() {
-            self.input_index = 0;
-            self.input.clear();
-            None
-        } else {
-            let res = self.input[self.input_index];
-            self.input_index += 1;
-            Some(res)
-        }
+        self.input.pop_front()
     }
 
     fn write_output(&mut self, value: i64) {
@@ -131,93 +298,159 @@ impl ExecutionContext {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 enum ExecutionResult {
     MoreInputNeeded,
     Exit,
+    /// The instruction limit was reached; the program can be resumed where it stopped
+    BudgetExceeded,
+    /// The instruction at the instruction pointer can't be executed
+    Fault(Fault),
 }
 
-fn execute_program(context: &mut ExecutionContext) -> ExecutionResult {
-    // println!("Executing program; ip: {}", context.ip.get());
-    loop {
-        match read_op_code(context) {
-            (OpCode::Add, parameter_modes) => {
-                let (a, b, c) = extract_parameters3(context, parameter_modes);
-                c.set(a.get(context) + b.get(context), context);
-            }
-            (OpCode::Mult, parameter_modes) => {
-                let (a, b, c) = extract_parameters3(context, parameter_modes);
-                c.set(a.get(context) * b.get(context), context);
-            }
-            (OpCode::Input, parameter_modes) => {
-                match context.read_input() {
-                    Some(value) => {
-                        // println!("Reading input {}", value);
-                        let a = extract_parameter(context, parameter_modes);
-                        a.set(value, context);
-                    }
-                    None => {
-                        //println!("Halting program due to input read; ip: {}", context.ip);
-                        // Revert the reading of the op-code, so we can read it again when the
-                        // thread is resumed
-                        context.ip -= 1;
-                        return ExecutionResult::MoreInputNeeded;
-                    }
-                }
-            }
-            (OpCode::Output, parameter_modes) => {
-                let a = extract_parameter(context, parameter_modes);
-                let output = a.get(&context);
-                //println!("Output: {}", output);
-                context.write_output(output);
-            }
-            (OpCode::JumpIfTrue, parameter_modes) => {
-                let (a, b) = extract_parameters2(context, parameter_modes);
-                if a.get(&context) != 0 {
-                    let address = b.get(&context);
-                    jump_to(&mut context.ip, address);
-                }
+/// Reason why an instruction can't be executed
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Fault {
+    UnknownOpCode(i64),
+    InvalidParameterMode(i64),
+    NegativeAddress(i64),
+    /// Relative address beyond the range of an i64
+    AddressOverflow,
+    WriteToImmediate,
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Fault::UnknownOpCode(op_code) => write!(f, "Unknown op code: {}", op_code),
+            Fault::InvalidParameterMode(mode) => write!(f, "Incorrect parameter mode: {}", mode),
+            Fault::NegativeAddress(address) => write!(f, "Negative address: {}", address),
+            Fault::AddressOverflow => write!(f, "Address overflow"),
+            Fault::WriteToImmediate => write!(f, "Write to an immediate parameter"),
+        }
+    }
+}
+
+/// Memory address designated by a value, which can't be negative; None if computing it
+/// overflowed
+fn to_address(value: Option<i64>) -> std::result::Result<usize, Fault> {
+    match value {
+        Some(address) if address >= 0 => Ok(address as usize),
+        Some(address) => Err(Fault::NegativeAddress(address)),
+        None => Err(Fault::AddressOverflow),
+    }
+}
+
+/// Executes the program, stopping after `max_instructions` instructions if it hasn't
+/// halted or run out of input by then
+fn execute_with_limit(context: &mut ExecutionContext, max_instructions: u64) -> ExecutionResult {
+    let result = if context.code.is_some() {
+        compiled::execute(context, max_instructions)
+    } else {
+        execute_instructions(context, max_instructions)
+    };
+    if let Some(profile) = &context.profile {
+        profile.borrow_mut().stop();
+    }
+    result
+}
+
+fn execute_instructions(context: &mut ExecutionContext, max_instructions: u64) -> ExecutionResult {
+    for _ in 0..max_instructions {
+        let ip = context.ip;
+        match execute_instruction(context) {
+            Ok(None) => context.executed += 1,
+            Ok(Some(result)) => return result,
+            Err(fault) => {
+                // Leave the instruction pointer on the faulty instruction
+                context.ip = ip;
+                return ExecutionResult::Fault(fault);
             }
-            (OpCode::JumpIfFalse, parameter_modes) => {
-                let (a, b) = extract_parameters2(context, parameter_modes);
-                if a.get(&context) == 0 {
-                    let address = b.get(&context);
-                    jump_to(&mut context.ip, address);
+        }
+    }
+
+    ExecutionResult::BudgetExceeded
+}
+
+/// Executes the instruction at the instruction pointer; returns the result of the
+/// execution if the program stops there
+fn execute_instruction(
+    context: &mut ExecutionContext,
+) -> std::result::Result<Option<ExecutionResult>, Fault> {
+    match read_op_code(context)? {
+        (OpCode::Add, parameter_modes) => {
+            let (a, b, c) = extract_parameters3(context, parameter_modes)?;
+            c.set(a.get(context).wrapping_add(b.get(context)), context)?;
+        }
+        (OpCode::Mult, parameter_modes) => {
+            let (a, b, c) = extract_parameters3(context, parameter_modes)?;
+            c.set(a.get(context).wrapping_mul(b.get(context)), context)?;
+        }
+        (OpCode::Input, parameter_modes) => {
+            let a = extract_parameter(context, parameter_modes)?;
+            a.address()?;
+            match context.read_input() {
+                Some(value) => a.set(value, context)?,
+                None => {
+                    // Revert the reading of the op-code, so we can read it again when the
+                    // thread is resumed
+                    context.ip -= 2;
+                    return Ok(Some(ExecutionResult::MoreInputNeeded));
                 }
             }
-            (OpCode::LessThan, parameter_modes) => {
-                let (a, b, c) = extract_parameters3(context, parameter_modes);
-                let value = if a.get(&context) < b.get(&context) {
-                    1
-                } else {
-                    0
-                };
-                c.set(value, context);
-            }
-            (OpCode::Equals, parameter_modes) => {
-                let (a, b, c) = extract_parameters3(context, parameter_modes);
-                let value = if a.get(&context) == b.get(&context) {
-                    1
-                } else {
-                    0
-                };
-                c.set(value, context);
-            }
-            (OpCode::AdjustRelativeBase, parameter_modes) => {
-                let a = extract_parameter(context, parameter_modes);
-                let adjustment = a.get(&context);
-                context.relative_base = (context.relative_base as i64 + adjustment) as usize;
+        }
+        (OpCode::Output, parameter_modes) => {
+            let a = extract_parameter(context, parameter_modes)?;
+            let output = a.get(context);
+            context.write_output(output);
+        }
+        (OpCode::JumpIfTrue, parameter_modes) => {
+            let (a, b) = extract_parameters2(context, parameter_modes)?;
+            if a.get(context) != 0 {
+                let address = b.get(context);
+                jump_to(&mut context.ip, address)?;
             }
-            (OpCode::Exit, _) => {
-                context.ended = true;
-                return ExecutionResult::Exit;
+        }
+        (OpCode::JumpIfFalse, parameter_modes) => {
+            let (a, b) = extract_parameters2(context, parameter_modes)?;
+            if a.get(context) == 0 {
+                let address = b.get(context);
+                jump_to(&mut context.ip, address)?;
             }
         }
-
-        // println!("Values: {:?}", memory);
+        (OpCode::LessThan, parameter_modes) => {
+            let (a, b, c) = extract_parameters3(context, parameter_modes)?;
+            let value = if a.get(context) < b.get(context) {
+                1
+            } else {
+                0
+            };
+            c.set(value, context)?;
+        }
+        (OpCode::Equals, parameter_modes) => {
+            let (a, b, c) = extract_parameters3(context, parameter_modes)?;
+            let value = if a.get(context) == b.get(context) {
+                1
+            } else {
+                0
+            };
+            c.set(value, context)?;
+        }
+        (OpCode::AdjustRelativeBase, parameter_modes) => {
+            let a = extract_parameter(context, parameter_modes)?;
+            let adjustment = a.get(context);
+            context.relative_base = context.relative_base.wrapping_add(adjustment);
+        }
+        (OpCode::Exit, _) => {
+            context.ended = true;
+            context.executed += 1;
+            return Ok(Some(ExecutionResult::Exit));
+        }
     }
+    Ok(None)
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum OpCode {
     Add,
     Mult,
@@ -231,80 +464,105 @@ enum OpCode {
     AdjustRelativeBase,
 }
 
-fn jump_to(ip: &mut usize, address: i64) {
-    *ip = address as usize;
+impl OpCode {
+    fn from_value(value: i64) -> Option<OpCode> {
+        Some(match value {
+            1 => OpCode::Add,
+            2 => OpCode::Mult,
+            3 => OpCode::Input,
+            4 => OpCode::Output,
+            5 => OpCode::JumpIfTrue,
+            6 => OpCode::JumpIfFalse,
+            7 => OpCode::LessThan,
+            8 => OpCode::Equals,
+            9 => OpCode::AdjustRelativeBase,
+            99 => OpCode::Exit,
+            _ => return None,
+        })
+    }
+
+    fn parameter_count(self) -> usize {
+        match self {
+            OpCode::Exit => 0,
+            OpCode::Input | OpCode::Output | OpCode::AdjustRelativeBase => 1,
+            OpCode::JumpIfTrue | OpCode::JumpIfFalse => 2,
+            OpCode::Add | OpCode::Mult | OpCode::LessThan | OpCode::Equals => 3,
+        }
+    }
+}
+
+fn jump_to(ip: &mut usize, address: i64) -> std::result::Result<(), Fault> {
+    *ip = to_address(Some(address))?;
+    Ok(())
 }
 
-fn read_op_code(context: &mut ExecutionContext) -> (OpCode, u32) {
+fn read_op_code(context: &mut ExecutionContext) -> std::result::Result<(OpCode, i64), Fault> {
     let value = context.memory[context.ip];
     let op_code_value = value % 100;
-    let parameter_modes = (value / 100) as u32;
-
-    let op_code = match op_code_value {
-        1 => OpCode::Add,
-        2 => OpCode::Mult,
-        3 => OpCode::Input,
-        4 => OpCode::Output,
-        5 => OpCode::JumpIfTrue,
-        6 => OpCode::JumpIfFalse,
-        7 => OpCode::LessThan,
-        8 => OpCode::Equals,
-        9 => OpCode::AdjustRelativeBase,
-        99 => OpCode::Exit,
-        x => panic!("Unknown op code: {}; ip: {}", x, context.ip),
-    };
+    let parameter_modes = value / 100;
+
+    let op_code = OpCode::from_value(op_code_value).ok_or(Fault::UnknownOpCode(op_code_value))?;
+    if let Some(profile) = &context.profile {
+        profile.borrow_mut().start_instruction(context.ip, op_code);
+    }
 
     context.ip += 1;
-    (op_code, parameter_modes)
+    Ok((op_code, parameter_modes))
 }
 
-fn extract_parameter(context: &mut ExecutionContext, parameter_modes: u32) -> Parameter {
+fn extract_parameter(
+    context: &mut ExecutionContext,
+    parameter_modes: i64,
+) -> std::result::Result<Parameter, Fault> {
     let mut param_modes = parameter_modes;
     get_parameter(context, &mut param_modes)
 }
 
 fn extract_parameters2(
     context: &mut ExecutionContext,
-    parameter_modes: u32,
-) -> (Parameter, Parameter) {
+    parameter_modes: i64,
+) -> std::result::Result<(Parameter, Parameter), Fault> {
     let mut param_modes = parameter_modes;
-    let x = get_parameter(context, &mut param_modes);
-    let y = get_parameter(context, &mut param_modes);
-    (x, y)
+    let x = get_parameter(context, &mut param_modes)?;
+    let y = get_parameter(context, &mut param_modes)?;
+    Ok((x, y))
 }
 
 fn extract_parameters3(
     context: &mut ExecutionContext,
-    parameter_modes: u32,
-) -> (Parameter, Parameter, Parameter) {
+    parameter_modes: i64,
+) -> std::result::Result<(Parameter, Parameter, Parameter), Fault> {
     let mut param_modes = parameter_modes;
-    let x = get_parameter(context, &mut param_modes);
-    let y = get_parameter(context, &mut param_modes);
-    let z = get_parameter(context, &mut param_modes);
-    (x, y, z)
+    let x = get_parameter(context, &mut param_modes)?;
+    let y = get_parameter(context, &mut param_modes)?;
+    let z = get_parameter(context, &mut param_modes)?;
+    Ok((x, y, z))
 }
 
-fn get_parameter(context: &mut ExecutionContext, parameter_modes: &mut u32) -> Parameter {
+fn get_parameter(
+    context: &mut ExecutionContext,
+    parameter_modes: &mut i64,
+) -> std::result::Result<Parameter, Fault> {
     // Get the parameter mode for this parameter
     let parameter_mode = match *parameter_modes % 10 {
         0 => ParameterMode::Position,
         1 => ParameterMode::Immediate,
         2 => ParameterMode::Relative,
-        x => panic!(format!("Incorrect parameter mode: {}", x)),
+        x => return Err(Fault::InvalidParameterMode(x)),
     };
     *parameter_modes /= 10;
 
     let parameter_value = context.memory[context.ip];
     context.ip += 1;
 
-    match parameter_mode {
-        ParameterMode::Position => Parameter::Reference(parameter_value as usize),
+    Ok(match parameter_mode {
+        ParameterMode::Position => Parameter::Reference(to_address(Some(parameter_value))?),
         ParameterMode::Immediate => Parameter::ImmediateValue(parameter_value),
         ParameterMode::Relative => {
-            let address = (parameter_value + context.relative_base as i64) as usize;
+            let address = to_address(parameter_value.checked_add(context.relative_base))?;
             Parameter::Reference(address)
         }
-    }
+    })
 }
 
 enum Parameter {
@@ -315,19 +573,31 @@ enum Parameter {
 impl<'a> Parameter {
     fn get(&self, context: &ExecutionContext) -> i64 {
         match self {
-            Parameter::Reference(address) => context.memory[*address],
+            Parameter::Reference(address) => {
+                if let Some(profile) = &context.profile {
+                    profile.borrow_mut().record_read(*address);
+                }
+                context.memory[*address]
+            }
             Parameter::ImmediateValue(value) => *value,
         }
     }
 
-    fn set(&self, value: i64, context: &mut ExecutionContext) -> () {
+    /// Address of the value, for parameters that can be written to
+    fn address(&self) -> std::result::Result<usize, Fault> {
         match self {
-            Parameter::Reference(address) => context.memory[*address] = value,
-            Parameter::ImmediateValue(value) => panic!(format!(
-                "Attempted to write value {} to an immediate parameter",
-                value
-            )),
+            Parameter::Reference(address) => Ok(*address),
+            Parameter::ImmediateValue(_) => Err(Fault::WriteToImmediate),
+        }
+    }
+
+    fn set(&self, value: i64, context: &mut ExecutionContext) -> std::result::Result<(), Fault> {
+        let address = self.address()?;
+        if let Some(profile) = &context.profile {
+            profile.borrow_mut().record_write(address);
         }
+        context.memory[address] = value;
+        Ok(())
     }
 }
 
@@ -337,6 +607,28 @@ enum ParameterMode {
     Relative,
 }
 
+#[test]
+fn instruction_limit() {
+    // Outputs its input, then loops forever
+    let memory = Memory::parse("3,0,4,0,1105,1,4");
+    let mut context = ExecutionContext::new(&memory);
+    context.input = vec![7].into();
+    assert_eq!(
+        ExecutionResult::BudgetExceeded,
+        execute_with_limit(&mut context, 100)
+    );
+    assert_eq!((7, 4), (context.output, context.ip));
+
+    let mut context = ExecutionContext::new(&Memory::parse("104,3,99"));
+    assert_eq!(ExecutionResult::Exit, execute_with_limit(&mut context, 2));
+    let mut context = ExecutionContext::new(&Memory::parse("104,3,99"));
+    assert_eq!(
+        ExecutionResult::BudgetExceeded,
+        execute_with_limit(&mut context, 1)
+    );
+    assert_eq!(ExecutionResult::Exit, execute_with_limit(&mut context, 1));
+}
+
 // extern crate kernel32;
 
 // #[cfg(windows)]
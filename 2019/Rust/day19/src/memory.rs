@@ -2,7 +2,7 @@ use std::collections::HashMap;
 use std::ops::Index;
 use std::ops::IndexMut;
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Memory {
     _values: HashMap<usize, i64>,
 }
@@ -24,6 +24,15 @@ impl Memory {
 
         Memory::new(memory)
     }
+
+    /// Number of values up to the highest address set
+    pub fn image_len(&self) -> usize {
+        self.highest_address().map_or(0, |a| a + 1)
+    }
+
+    pub fn highest_address(&self) -> Option<usize> {
+        self._values.keys().max().cloned()
+    }
 }
 
 impl Index<usize> for Memory {
@@ -0,0 +1,149 @@
+use crate::OpCode;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Execution statistics gathered over one or more runs of a program
+#[derive(Default)]
+pub struct Profile {
+    /// Number of times the instruction at each address was executed
+    executions: HashMap<usize, u64>,
+    /// Number of executions and total time of each op code
+    op_codes: HashMap<OpCode, (u64, Duration)>,
+    reads: HashMap<usize, u64>,
+    writes: HashMap<usize, u64>,
+    /// Op code of the instruction being executed, and when it started
+    current: Option<(OpCode, Instant)>,
+}
+
+impl Profile {
+    pub fn start_instruction(&mut self, ip: usize, op_code: OpCode) {
+        self.stop();
+        *self.executions.entry(ip).or_insert(0) += 1;
+        self.current = Some((op_code, Instant::now()));
+    }
+
+    /// Ends the timing of the current instruction; called when the program stops
+    pub fn stop(&mut self) {
+        if let Some((op_code, start)) = self.current.take() {
+            let entry = self
+                .op_codes
+                .entry(op_code)
+                .or_insert((0, Duration::default()));
+            entry.0 += 1;
+            entry.1 += start.elapsed();
+        }
+    }
+
+    pub fn record_read(&mut self, address: usize) {
+        *self.reads.entry(address).or_insert(0) += 1;
+    }
+
+    pub fn record_write(&mut self, address: usize) {
+        *self.writes.entry(address).or_insert(0) += 1;
+    }
+
+    pub fn instruction_count(&self) -> u64 {
+        self.executions.values().sum()
+    }
+
+    /// Ranked report of the `top` most executed addresses, the time spent in each op code,
+    /// and the `top` most accessed memory cells
+    pub fn report(&self, top: usize) -> String {
+        let total = self.instruction_count().max(1);
+        let mut lines = vec![format!("{} instructions executed", total)];
+
+        lines.push("\nHottest instructions:".to_string());
+        lines.push(format!(
+            "{:>8}  {:>12}  {:>6}",
+            "Address", "Executions", "%"
+        ));
+        let mut executions: Vec<_> = self.executions.iter().collect();
+        executions.sort_by_key(|(address, count)| (std::cmp::Reverse(**count), **address));
+        for (address, count) in executions.into_iter().take(top) {
+            lines.push(format!(
+                "{:>8}  {:>12}  {:>5.1}%",
+                address,
+                count,
+                100.0 * *count as f64 / total as f64
+            ));
+        }
+
+        lines.push("\nTime per op code:".to_string());
+        lines.push(format!(
+            "{:<20}  {:>12}  {:>12}  {:>8}",
+            "Op code", "Executions", "Total", "Average"
+        ));
+        let mut op_codes: Vec<_> = self.op_codes.iter().collect();
+        op_codes.sort_by_key(|(_, (_, time))| std::cmp::Reverse(*time));
+        for (op_code, (count, time)) in op_codes {
+            lines.push(format!(
+                "{:<20}  {:>12}  {:>12}  {:>6}ns",
+                format!("{:?}", op_code),
+                count,
+                format!("{:.1?}", time),
+                time.as_nanos() / *count as u128
+            ));
+        }
+
+        lines.push("\nMemory hot spots:".to_string());
+        lines.push(format!(
+            "{:>8}  {:>12}  {:>12}",
+            "Address", "Reads", "Writes"
+        ));
+        let mut addresses: Vec<usize> = self
+            .reads
+            .keys()
+            .chain(self.writes.keys())
+            .copied()
+            .collect();
+        addresses.sort_unstable();
+        addresses.dedup();
+        let accesses = |address: &usize| {
+            (
+                self.reads.get(address).copied().unwrap_or(0),
+                self.writes.get(address).copied().unwrap_or(0),
+            )
+        };
+        addresses.sort_by_key(|a| {
+            let (reads, writes) = accesses(a);
+            std::cmp::Reverse(reads + writes)
+        });
+        for address in addresses.into_iter().take(top) {
+            let (reads, writes) = accesses(&address);
+            lines.push(format!("{:>8}  {:>12}  {:>12}", address, reads, writes));
+        }
+
+        lines.join("\n")
+    }
+}
+
+#[test]
+fn profile_loop() {
+    use crate::memory::Memory;
+    use crate::{execute_with_limit, ExecutionContext};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    // Counts [12] down from 3 to 0, then exits
+    let memory = Memory::parse("1001,12,-1,12,1005,12,0,99,0,0,0,0,3");
+    let profile = Rc::new(RefCell::new(Profile::default()));
+    let mut context = ExecutionContext::new(&memory);
+    context.profile = Some(profile.clone());
+    execute_with_limit(&mut context, 100);
+
+    let profile = profile.borrow();
+    assert_eq!(7, profile.instruction_count());
+    assert_eq!(Some(&3), profile.executions.get(&0));
+    assert_eq!(Some(&3), profile.executions.get(&4));
+    assert_eq!(Some(&1), profile.executions.get(&7));
+    assert_eq!(Some(&6), profile.reads.get(&12));
+    assert_eq!(Some(&3), profile.writes.get(&12));
+    assert_eq!(3, profile.op_codes[&OpCode::JumpIfTrue].0);
+    assert_eq!(1, profile.op_codes[&OpCode::Exit].0);
+    assert!(profile.current.is_none());
+
+    let report = profile.report(1);
+    assert!(report.starts_with("7 instructions executed"));
+    assert!(report.contains("\n       0             3   42.9%"));
+    assert!(report.contains("\n      12             6             3"));
+}
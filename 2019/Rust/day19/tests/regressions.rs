@@ -0,0 +1,33 @@
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Cases saved by `day19 --fuzz` in tests/regressions, each one replayed on its own so
+/// a failure names the case
+#[test]
+fn fuzz_regressions_pass() {
+    let dir = PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/regressions"));
+    let mut cases: Vec<PathBuf> = std::fs::read_dir(&dir)
+        .expect("Failed to list the regression cases")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|e| e == "txt"))
+        .collect();
+    cases.sort();
+    assert!(!cases.is_empty(), "No regression case in {}", dir.display());
+
+    let failures: Vec<String> = cases
+        .iter()
+        .filter_map(|case| {
+            let output = Command::new(env!("CARGO_BIN_EXE_day19"))
+                .arg("regression")
+                .arg(case)
+                .output()
+                .expect("Failed to run day19");
+            if output.status.success() {
+                None
+            } else {
+                Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+            }
+        })
+        .collect();
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
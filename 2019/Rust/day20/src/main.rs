@@ -1,17 +1,18 @@
 #![allow(dead_code)]
 
 use crate::iterators::*;
+use crate::maze::{Cell, Maze};
 use num_format::{Locale, ToFormattedString};
 use std::collections::HashMap;
 use std::env;
 use std::fs::File;
 use std::io::prelude::*;
-use std::io::BufReader;
 use std::iter::FromIterator;
 use std::result::Result;
 use std::time::Instant;
 
 mod iterators;
+mod maze;
 
 type MainResult<T> = Result<T, Box<dyn ::std::error::Error>>;
 
@@ -45,118 +46,20 @@ type StateGrid3D = HashMap<usize, StateGrid>;
 
 fn main() -> MainResult<()> {
     let file_name = env::args().nth(1).expect("Enter a file name");
-    let file = File::open(file_name)?;
-
-    let mut character_grid: HashMap<Pos, char> = HashMap::new();
-    let mut grid: HashMap<Pos, Content> = HashMap::new();
-
-    let mut reader = BufReader::new(&file);
-    let mut y = 0;
-    loop {
-        let mut line = String::new();
-        let read = reader.read_line(&mut line)?;
-        if read == 0 {
-            break;
-        }
-
-        for (x, ch) in line.chars().enumerate() {
-            let pos = Pos(x, y);
-            character_grid.insert(pos, ch);
-        }
-
-        y += 1;
-    }
-
-    let x_max = character_grid.keys().map(|Pos(x, _)| *x).max().unwrap();
-    let y_max = character_grid.keys().map(|Pos(_, y)| *y).max().unwrap();
-
-    let read_portal_name = |pos1: Pos, pos2: Pos| {
-        let mut chars = vec![character_grid[&pos1], character_grid[&pos2]];
-        chars.sort();
-        String::from_iter(chars.into_iter())
-    };
-
-    let mut portals: Vec<(String, Pos)> = vec![];
-
-    let mut gen_portal = |pos1: Pos, pos2: Pos| {
-        let name = read_portal_name(pos1, pos2);
-        portals.push((name.clone(), pos1));
-        Content::Portal(name)
-    };
-
-    let mut try_read_portal = |pos| {
-        // A portal position should contain an alphabetic character, and be adjacent to a passage
-        match character_grid.get(&pos) {
-            None => None,
-            Some(ch) => {
-                let Pos(x, y) = pos;
-
-                let is_passage = |x, y| match character_grid.get(&Pos(x, y)) {
-                    Some('.') => true,
-                    _ => false,
-                };
-
-                if !ch.is_alphabetic() {
-                    None
-                } else {
-                    if is_passage(x - 1, y) {
-                        Some(gen_portal(pos, Pos(x + 1, y)))
-                    } else if is_passage(x + 1, y) {
-                        Some(gen_portal(pos, Pos(x - 1, y)))
-                    } else if is_passage(x, y - 1) {
-                        Some(gen_portal(pos, Pos(x, y + 1)))
-                    } else if is_passage(x, y + 1) {
-                        Some(gen_portal(pos, Pos(x, y - 1)))
-                    } else {
-                        None
-                    }
-                }
-            }
-        }
-    };
-    for y in 1..y_max {
-        for x in 1..x_max {
-            let pos = Pos(x, y);
-            let content = match character_grid.get(&pos) {
-                Some('.') => Content::Passage,
-                Some('#') => Content::Wall,
-                Some(ch) if ch.is_alphabetic() => {
-                    if let Some(portal) = try_read_portal(pos) {
-                        portal
-                    } else {
-                        continue;
-                    }
-                }
-                _ => continue,
-            };
+    let mut text = String::new();
+    File::open(file_name)?.read_to_string(&mut text)?;
 
-            grid.insert(pos, content);
-        }
-    }
+    let maze = parse_maze(&text);
+    let grid = &maze.grid;
 
-    display_content_grid(&grid, None);
+    display_content_grid(grid, None);
     let start = Instant::now();
 
-    let current = grid
-        .iter()
-        .find(|(_, v)| match v {
-            Content::Portal(s) => s == "AA",
-            _ => false,
-        })
-        .map(|(pos, _)| {
-            NextMoveIterator::new(*pos).find(|p| match grid.get(p) {
-                Some(Content::Passage) => true,
-                _ => false,
-            })
-        })
-        .unwrap()
-        .unwrap();
-
-    let current = Pos3D::new(current, 0);
+    let current = start_position(&maze);
 
     println!("Start position: {:?}", current);
 
-    let distance = get_distance_to_exit(current, &grid, (x_max, y_max), &portals);
+    let distance = get_distance_to_exit(current, grid, dimensions(&text), &maze.labels);
     println!(
         "Min distance found in {} ms: {}",
         (Instant::now() - start)
@@ -167,23 +70,68 @@ fn main() -> MainResult<()> {
     Ok(())
 }
 
+/// Parses the maze; each portal is found on the letter of its name that's next to a
+/// passage, and labeled with its name
+fn parse_maze(text: &str) -> Maze<Content, String> {
+    maze::parse(text, |pos, ch, chars| {
+        let is_passage = |x: usize, y: usize| chars.get(&Pos(x, y)) == Some(&'.');
+        let read_portal_name = |other: Pos| {
+            let mut name = vec![ch, chars[&other]];
+            name.sort();
+            String::from_iter(name.into_iter())
+        };
+
+        let Pos(x, y) = pos;
+        match ch {
+            '.' => Cell::Plain(Content::Passage),
+            '#' => Cell::Plain(Content::Wall),
+            ch if ch.is_alphabetic() => {
+                // A portal position should contain an alphabetic character, and be adjacent
+                // to a passage
+                let other = if is_passage(x.wrapping_sub(1), y) {
+                    Pos(x + 1, y)
+                } else if is_passage(x + 1, y) {
+                    Pos(x - 1, y)
+                } else if is_passage(x, y.wrapping_sub(1)) {
+                    Pos(x, y + 1)
+                } else if is_passage(x, y + 1) {
+                    Pos(x, y - 1)
+                } else {
+                    return Cell::Ignored;
+                };
+                let name = read_portal_name(other);
+                Cell::Labeled(Content::Portal(name.clone()), name)
+            }
+            _ => Cell::Ignored,
+        }
+    })
+}
+
+/// Bounds of the maze, as when the newlines were part of the character grid
+fn dimensions(text: &str) -> (usize, usize) {
+    let x_max = text.lines().map(|l| l.len()).max().unwrap();
+    let y_max = text.lines().count() - 1;
+    (x_max, y_max)
+}
+
+/// Passage next to the AA portal, on the outermost level
+fn start_position(maze: &Maze<Content, String>) -> Pos3D {
+    let pos = NextMoveIterator::new(maze.labels["AA"][0])
+        .find(|p| match maze.grid.get(p) {
+            Some(Content::Passage) => true,
+            _ => false,
+        })
+        .unwrap();
+    Pos3D::new(pos, 0)
+}
+
 fn get_distance_to_exit(
     start: Pos3D,
     grid: &ContentGrid,
     dim: (usize, usize),
-    portals: &Vec<(String, Pos)>,
+    portals_by_key: &HashMap<String, Vec<Pos>>,
 ) -> u32 {
     // Connect each portal to its destination
-    let mut portals_by_key: HashMap<String, Vec<Pos>> = HashMap::new();
-    for (portal_name, pos) in portals {
-        match portals_by_key.get_mut(portal_name) {
-            None => {
-                portals_by_key.insert(portal_name.clone(), vec![*pos]);
-            }
-            Some(v) => v.push(*pos),
-        }
-    }
-
     let get_portal_destination = |name: &String, from: Pos| {
         let portal_positions = &portals_by_key[name];
         let other_end = if portal_positions[0] == from {
@@ -374,3 +322,42 @@ fn set_cursor_position(y: i16, x: i16) {
         kernel32::SetConsoleCursorPosition(handle, COORD { X: x, Y: y });
     }
 }
+
+#[cfg(test)]
+fn parse_example(file_name: &str) -> (String, Maze<Content, String>) {
+    let text = std::fs::read_to_string(file_name).unwrap();
+    let maze = parse_maze(&text);
+    (text, maze)
+}
+
+#[test]
+fn portal_labels() {
+    for &(file_name, portal_count) in &[
+        ("test.txt", 5),
+        ("test2.txt", 12),
+        ("test3.txt", 15),
+        ("test4.txt", 6),
+    ] {
+        let (_, maze) = parse_example(file_name);
+        assert_eq!(portal_count, maze.labels.len(), "{}", file_name);
+        for (name, positions) in &maze.labels {
+            let expected = if name == "AA" || name == "ZZ" { 1 } else { 2 };
+            assert_eq!(expected, positions.len(), "{} in {}", name, file_name);
+        }
+    }
+}
+
+#[test]
+fn recursive_distances() {
+    // The 2nd example has no solution once the maze is recursive
+    for &(file_name, distance) in &[("test.txt", 26), ("test3.txt", 396), ("test4.txt", 12)] {
+        let (text, maze) = parse_example(file_name);
+        let start = start_position(&maze);
+        assert_eq!(
+            distance,
+            get_distance_to_exit(start, &maze.grid, dimensions(&text), &maze.labels),
+            "{}",
+            file_name
+        );
+    }
+}
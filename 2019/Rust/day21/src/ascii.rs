@@ -0,0 +1,101 @@
+use std::collections::VecDeque;
+
+/// Character of an output value; None for the values beyond the ASCII range, which the
+/// programs use to report results such as the amount of dust collected
+pub fn to_char(value: i64) -> Option<char> {
+    if (0..128).contains(&value) {
+        Some(value as u8 as char)
+    } else {
+        None
+    }
+}
+
+/// Input values of a line of text: its characters, then a new line
+pub fn encode_line(line: &str) -> Vec<i64> {
+    line.chars()
+        .filter(|c| *c != '\r' && *c != '\n')
+        .chain(Some('\n'))
+        .map(|c| c as i64)
+        .collect()
+}
+
+/// Text of output values, leaving out the ones that aren't characters
+pub fn decode(values: impl IntoIterator<Item = i64>) -> String {
+    values.into_iter().filter_map(to_char).collect()
+}
+
+/// Commands of a script file, one per line; blank lines and lines starting with `#` are
+/// left out
+pub fn script_commands(text: &str) -> VecDeque<String> {
+    text.lines()
+        .map(|line| line.trim_end())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.to_string())
+        .collect()
+}
+
+/// Output of a program, split into lines
+#[derive(Clone, Debug, Default)]
+pub struct Lines {
+    /// Start of the line being output
+    partial: String,
+    complete: VecDeque<String>,
+}
+
+impl Lines {
+    /// Adds an output value; returns false if it isn't a character
+    pub fn push(&mut self, value: i64) -> bool {
+        match to_char(value) {
+            Some('\n') => {
+                let line = std::mem::take(&mut self.partial);
+                self.complete.push_back(line);
+                true
+            }
+            Some(c) => {
+                self.partial.push(c);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Oldest complete line not read yet, without its new line
+    pub fn read_line(&mut self) -> Option<String> {
+        self.complete.pop_front()
+    }
+}
+
+#[test]
+fn line_conversions() {
+    assert_eq!(vec![78, 79, 84, 10], encode_line("NOT"));
+    assert_eq!(vec![65, 10], encode_line("A\r\n"));
+    assert_eq!("Hi\n", decode(vec![72, 105, 10, 1234]));
+    assert_eq!(None, to_char(128));
+    assert_eq!(None, to_char(-1));
+}
+
+#[test]
+fn lines_framing() {
+    let mut lines = Lines::default();
+    for value in encode_line("Walking...").into_iter().chain(encode_line("")) {
+        assert!(lines.push(value));
+    }
+    // Not a complete line yet
+    "Input".chars().for_each(|c| assert!(lines.push(c as i64)));
+    assert!(!lines.push(19_355_364));
+
+    assert_eq!(Some("Walking...".to_string()), lines.read_line());
+    assert_eq!(Some(String::new()), lines.read_line());
+    assert_eq!(None, lines.read_line());
+    lines.push(10);
+    assert_eq!(Some("Input".to_string()), lines.read_line());
+}
+
+#[test]
+fn script_commands_skip_comments() {
+    let script = "# Walk\nnorth\n\ntake mug  \r\n  # done\ninv";
+    assert_eq!(
+        vec!["north", "take mug", "  # done", "inv"],
+        Vec::from(script_commands(script))
+    );
+}
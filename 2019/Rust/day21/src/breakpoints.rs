@@ -0,0 +1,151 @@
+use std::collections::HashSet;
+use std::fmt;
+
+/// Why the program stopped at a breakpoint
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BreakReason {
+    /// The instruction at the breakpoint's address is about to be executed
+    Instruction,
+    /// A watched memory cell was written to by the instruction
+    Write { address: usize, old: i64, new: i64 },
+}
+
+impl fmt::Display for BreakReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BreakReason::Instruction => write!(f, "breakpoint"),
+            BreakReason::Write { address, old, new } => {
+                write!(f, "memory[{}] written: {} -> {}", address, old, new)
+            }
+        }
+    }
+}
+
+/// Instruction addresses to break on, and memory cells to watch
+#[derive(Clone, Default)]
+pub struct Breakpoints {
+    instructions: HashSet<usize>,
+    watched: HashSet<usize>,
+    /// Address of the last breakpoint hit, which must not stop the program again when
+    /// it's resumed
+    resumed_from: Option<usize>,
+    /// Whether each breakpoint and watchpoint is removed once it has stopped the program
+    once: bool,
+}
+
+impl Breakpoints {
+    /// Breakpoints given with `--break ADDRESS` and watchpoints with `--watch ADDRESS`;
+    /// both can be repeated. With `--once`, each one only stops the program once.
+    pub fn from_args(args: impl Iterator<Item = String>) -> Breakpoints {
+        let mut breakpoints = Breakpoints::default();
+        let args: Vec<String> = args.collect();
+        breakpoints.once = args.iter().any(|a| a == "--once");
+        for pair in args.windows(2) {
+            let address = match pair[1].parse() {
+                Ok(address) => address,
+                Err(_) => continue,
+            };
+            match pair[0].as_str() {
+                "--break" => breakpoints.add_breakpoint(address),
+                "--watch" => breakpoints.add_watchpoint(address),
+                _ => (),
+            }
+        }
+        breakpoints
+    }
+
+    pub fn add_breakpoint(&mut self, address: usize) {
+        self.instructions.insert(address);
+    }
+
+    pub fn remove_breakpoint(&mut self, address: usize) {
+        self.instructions.remove(&address);
+    }
+
+    pub fn add_watchpoint(&mut self, address: usize) {
+        self.watched.insert(address);
+    }
+
+    pub fn remove_watchpoint(&mut self, address: usize) {
+        self.watched.remove(&address);
+    }
+
+    /// Records that the program stopped at `ip` for the reason; with `--once`, the
+    /// breakpoint or watchpoint that stopped it is removed
+    pub fn stopped(&mut self, ip: usize, reason: &BreakReason) {
+        if self.once {
+            match *reason {
+                BreakReason::Instruction => self.remove_breakpoint(ip),
+                BreakReason::Write { address, .. } => self.remove_watchpoint(address),
+            }
+        }
+    }
+
+    /// Whether the program should stop before executing the instruction at `ip`
+    pub fn should_break(&mut self, ip: usize) -> bool {
+        if self.resumed_from.take() == Some(ip) {
+            return false;
+        }
+        if self.instructions.contains(&ip) {
+            self.resumed_from = Some(ip);
+            return true;
+        }
+        false
+    }
+
+    pub fn is_watched(&self, address: usize) -> bool {
+        self.watched.contains(&address)
+    }
+}
+
+#[test]
+fn breakpoint_resumes() {
+    let mut breakpoints = Breakpoints::default();
+    breakpoints.add_breakpoint(4);
+    assert!(!breakpoints.should_break(0));
+    assert!(breakpoints.should_break(4));
+    // Resuming executes the instruction, but it stops again the next time it's reached
+    assert!(!breakpoints.should_break(4));
+    assert!(!breakpoints.should_break(8));
+    assert!(breakpoints.should_break(4));
+
+    breakpoints.remove_breakpoint(4);
+    breakpoints.resumed_from = None;
+    assert!(!breakpoints.should_break(4));
+}
+
+#[test]
+fn breakpoints_from_args() {
+    let args = [
+        "input.txt",
+        "--break",
+        "12",
+        "--watch",
+        "100",
+        "--break",
+        "x",
+    ];
+    let breakpoints = Breakpoints::from_args(args.iter().map(|a| a.to_string()));
+    assert!(breakpoints.instructions.contains(&12));
+    assert_eq!(1, breakpoints.instructions.len());
+    assert!(breakpoints.is_watched(100));
+    assert!(!breakpoints.once);
+}
+
+#[test]
+fn stop_once() {
+    let args = ["input.txt", "--break", "4", "--watch", "100", "--once"];
+    let mut breakpoints = Breakpoints::from_args(args.iter().map(|a| a.to_string()));
+    assert!(breakpoints.should_break(4));
+    breakpoints.stopped(4, &BreakReason::Instruction);
+    breakpoints.resumed_from = None;
+    assert!(!breakpoints.should_break(4));
+
+    let write = BreakReason::Write {
+        address: 100,
+        old: 0,
+        new: 1,
+    };
+    breakpoints.stopped(8, &write);
+    assert!(!breakpoints.is_watched(100));
+}
@@ -0,0 +1,260 @@
+use crate::memory::Memory;
+use std::fmt;
+
+/// Instructions shown before the divergence, and after it in each run
+const CONTEXT_STEPS: usize = 5;
+
+/// An executed instruction, and the memory cell it wrote to, if any
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Step {
+    pub ip: usize,
+    pub write: Option<(usize, i64)>,
+}
+
+impl Step {
+    pub fn new(ip: usize) -> Step {
+        Step { ip, write: None }
+    }
+}
+
+/// How two runs stop behaving the same
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Divergence {
+    /// The runs execute different instructions
+    ControlFlow,
+    /// The same instruction writes a different value, or to a different cell
+    Write,
+    /// One of the runs stopped, while the other one went on
+    Length,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Divergence::ControlFlow => write!(f, "control flow"),
+            Divergence::Write => write!(f, "memory write"),
+            Divergence::Length => write!(f, "end of run"),
+        }
+    }
+}
+
+/// Index of the first step where the traces differ, and how
+pub fn first_divergence(a: &[Step], b: &[Step]) -> Option<(usize, Divergence)> {
+    for (index, (step_a, step_b)) in a.iter().zip(b).enumerate() {
+        if step_a.ip != step_b.ip {
+            return Some((index, Divergence::ControlFlow));
+        }
+        if step_a.write != step_b.write {
+            return Some((index, Divergence::Write));
+        }
+    }
+    if a.len() != b.len() {
+        Some((a.len().min(b.len()), Divergence::Length))
+    } else {
+        None
+    }
+}
+
+/// Disassembly of the instruction at `address`, in the syntax of the day23 assembler
+pub fn disassemble(memory: &Memory, address: usize) -> String {
+    let value = memory[address];
+    let (mnemonic, parameter_count) = match value % 100 {
+        1 => ("add", 3),
+        2 => ("mul", 3),
+        3 => ("in", 1),
+        4 => ("out", 1),
+        5 => ("jnz", 2),
+        6 => ("jz", 2),
+        7 => ("lt", 3),
+        8 => ("eq", 3),
+        9 => ("arb", 1),
+        99 => ("hlt", 0),
+        _ => return format!(".data {}", value),
+    };
+    let mut modes = value / 100;
+    let parameters: Vec<String> = (1..=parameter_count)
+        .map(|offset| {
+            let parameter = memory[address + offset];
+            let mode = modes % 10;
+            modes /= 10;
+            match mode {
+                0 => format!("[{}]", parameter),
+                2 => format!("[rb{:+}]", parameter),
+                _ => parameter.to_string(),
+            }
+        })
+        .collect();
+    format!("{} {}", mnemonic, parameters.join(", "))
+        .trim_end()
+        .to_string()
+}
+
+/// Memory of a run before the step `index`, rebuilt from the writes of its trace
+fn memory_at(initial: &Memory, trace: &[Step], index: usize) -> Memory {
+    let mut memory = initial.clone();
+    for (address, value) in trace[..index].iter().filter_map(|s| s.write) {
+        memory[address] = value;
+    }
+    memory
+}
+
+/// Disassembled steps `from..to` of a trace, each line giving the step index, the
+/// address of the instruction and the write it made
+fn format_steps(initial: &Memory, trace: &[Step], from: usize, to: usize) -> Vec<String> {
+    let to = to.min(trace.len());
+    let mut memory = memory_at(initial, trace, from.min(to));
+    let mut lines = vec![];
+    for (index, step) in trace.iter().enumerate().take(to).skip(from) {
+        let mut line = format!(
+            "{:>9} {:>5}: {}",
+            index,
+            step.ip,
+            disassemble(&memory, step.ip)
+        );
+        if let Some((address, value)) = step.write {
+            line += &format!("  ; [{}] = {}", address, value);
+            memory[address] = value;
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// A run to compare: the memory it started with, and the instructions it executed
+pub struct Run<'a> {
+    pub name: &'a str,
+    pub memory: &'a Memory,
+    pub trace: &'a [Step],
+}
+
+/// Describes where two runs diverge, with the instructions leading to it, and those
+/// each run executed from there
+pub fn report(a: &Run, b: &Run) -> String {
+    let (index, divergence) = match first_divergence(a.trace, b.trace) {
+        Some(divergence) => divergence,
+        None => return format!("Runs are identical ({} steps)", a.trace.len()),
+    };
+
+    let mut lines = vec![format!(
+        "Runs diverge at step {} ({}); {} ran {} steps, {} ran {}",
+        index,
+        divergence,
+        a.name,
+        a.trace.len(),
+        b.name,
+        b.trace.len()
+    )];
+    if index > 0 {
+        lines.push("Common steps:".to_string());
+        let from = index.saturating_sub(CONTEXT_STEPS);
+        lines.extend(format_steps(a.memory, a.trace, from, index));
+    }
+    for run in &[a, b] {
+        lines.push(format!("{}:", run.name));
+        let steps = format_steps(run.memory, run.trace, index, index + CONTEXT_STEPS);
+        if steps.is_empty() {
+            lines.push("          (ended)".to_string());
+        }
+        lines.extend(steps);
+    }
+    lines.join("\n")
+}
+
+/// Memory cells set by `--patch-a ADDRESS=VALUE` (or `--patch-b`) options
+pub fn patches_from_args(args: &[String], option: &str) -> Result<Vec<(usize, i64)>, String> {
+    args.windows(2)
+        .filter(|pair| pair[0] == option)
+        .map(|pair| {
+            let invalid = || format!("Invalid patch: {}", pair[1]);
+            let (address, value) = pair[1].split_once('=').ok_or_else(invalid)?;
+            Ok((
+                address.trim().parse().map_err(|_| invalid())?,
+                value.trim().parse().map_err(|_| invalid())?,
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+fn steps(ips: &[usize]) -> Vec<Step> {
+    ips.iter().map(|ip| Step::new(*ip)).collect()
+}
+
+#[test]
+fn divergences() {
+    let a = steps(&[0, 4, 8, 12]);
+    assert_eq!(None, first_divergence(&a, &a));
+    assert_eq!(
+        Some((2, Divergence::ControlFlow)),
+        first_divergence(&a, &steps(&[0, 4, 20, 24]))
+    );
+    assert_eq!(Some((3, Divergence::Length)), first_divergence(&a[..3], &a));
+
+    let mut b = a.clone();
+    b[1].write = Some((100, 1));
+    assert_eq!(Some((1, Divergence::Write)), first_divergence(&a, &b));
+}
+
+#[test]
+fn disassembly() {
+    let memory = Memory::parse("1101,5,-3,100,21208,2,7,-1,204,3,99,12345");
+    assert_eq!("add 5, -3, [100]", disassemble(&memory, 0));
+    assert_eq!("eq [rb+2], 7, [rb-1]", disassemble(&memory, 4));
+    assert_eq!("out [rb+3]", disassemble(&memory, 8));
+    assert_eq!("hlt", disassemble(&memory, 10));
+    assert_eq!(".data 12345", disassemble(&memory, 11));
+}
+
+#[test]
+fn report_uses_the_memory_of_each_step() {
+    // The first instruction patches the operand of the second one
+    let memory = Memory::parse("1101,0,7,6,1101,0,0,100,99");
+    let mut a = steps(&[0, 4, 8]);
+    a[0].write = Some((6, 7));
+    a[1].write = Some((100, 7));
+    let mut b = a.clone();
+    b[1].write = Some((100, 8));
+
+    let report = report(
+        &Run {
+            name: "A",
+            memory: &memory,
+            trace: &a,
+        },
+        &Run {
+            name: "B",
+            memory: &memory,
+            trace: &b,
+        },
+    );
+    let lines: Vec<&str> = report.lines().collect();
+    assert_eq!(
+        "Runs diverge at step 1 (memory write); A ran 3 steps, B ran 3",
+        lines[0]
+    );
+    assert_eq!("        1     4: add 0, 7, [100]  ; [100] = 7", lines[4]);
+    assert_eq!("        1     4: add 0, 7, [100]  ; [100] = 8", lines[7]);
+}
+
+#[test]
+fn patches() {
+    let args: Vec<String> = [
+        "prog",
+        "--patch-a",
+        "10=5",
+        "--patch-b",
+        "3=-1",
+        "--patch-a",
+        "7=0",
+    ]
+    .iter()
+    .map(|a| a.to_string())
+    .collect();
+    assert_eq!(
+        Ok(vec![(10, 5), (7, 0)]),
+        patches_from_args(&args, "--patch-a")
+    );
+    assert_eq!(Ok(vec![(3, -1)]), patches_from_args(&args, "--patch-b"));
+    let args = vec!["--patch-a".to_string(), "10".to_string()];
+    assert!(patches_from_args(&args, "--patch-a").is_err());
+}
@@ -1,70 +1,204 @@
+use crate::breakpoints::{BreakReason, Breakpoints};
+use crate::compare::{Run, Step};
 use crate::memory::Memory;
-use std::collections::HashSet;
+use std::collections::VecDeque;
 use std::env;
 use std::fs::File;
 use std::io::stdin;
 use std::io::Read;
+use std::process;
 
-#[cfg(unix)]
-extern crate ncurses;
-
+mod ascii;
+mod breakpoints;
+mod compare;
 mod memory;
+mod retry;
+mod search;
 
 type Result<T> = ::std::result::Result<T, Box<dyn ::std::error::Error>>;
 
+// Exit code for Intcode VM errors, shared with the runner
+const EXIT_VM_ERROR: i32 = 4;
+
+/// Reports an error of the Intcode VM, and exits with the runner's code for VM errors
+fn vm_error(message: String) -> ! {
+    eprintln!("VM error: {}", message);
+    process::exit(EXIT_VM_ERROR);
+}
+
 #[derive(Eq, PartialEq, Hash, Clone, Copy)]
 struct Pos(i32, i32);
 
 fn main() -> Result<()> {
+    // day21 compare-runs <input> <script-a> <script-b>: where two runs stop behaving the same
+    if env::args().nth(1).as_deref() == Some("compare-runs") {
+        return compare_runs(&env::args().collect::<Vec<_>>());
+    }
+    // day21 search <input> [candidates]: tries springscripts until one gets the droid across
+    if env::args().nth(1).as_deref() == Some("search") {
+        return search_scripts(&env::args().collect::<Vec<_>>());
+    }
+
     let file_name = env::args().nth(1).expect("Enter a file name");
-    let script_file = env::args().nth(2).expect("Enter a script file name");
+    // Commands fed to the program before reading the standard input, from `--script`,
+    // or the file given after the program
+    let script_file = env::args()
+        .skip_while(|a| a != "--script")
+        .nth(1)
+        .or_else(|| env::args().nth(2).filter(|a| !a.starts_with("--")));
 
     let mut instructions = String::new();
     File::open(file_name)?
         .read_to_string(&mut instructions)
         .expect("Failed to read input file");
 
-    let mut script = String::new();
-    File::open(script_file)?
-        .read_to_string(&mut script)
-        .expect("Failed to read script file");
+    let mut script = VecDeque::new();
+    if let Some(script_file) = script_file {
+        let mut text = String::new();
+        File::open(script_file)?
+            .read_to_string(&mut text)
+            .expect("Failed to read script file");
+        script = ascii::script_commands(&text);
+    }
 
     //init();
     let memory = Memory::parse(&instructions);
 
     let mut context = ExecutionContext::new(&memory);
+    context.breakpoints = Breakpoints::from_args(env::args());
 
     loop {
-        if let ExecutionResult::Exit = execute_program(&mut context) {
-            break;
+        let result = execute_program(&mut context);
+        while let Some(line) = context.read_line() {
+            println!("{}", line);
+        }
+        if let Some(damage) = context.damage.take() {
+            println!("Result: {}", damage);
+        }
+        match result {
+            ExecutionResult::Exit => break,
+            ExecutionResult::Breakpoint { ip, reason } => {
+                println!(
+                    "[{} at ip {}; relative base {}]",
+                    reason, ip, context.relative_base
+                );
+                context.breakpoints.stopped(ip, &reason);
+                continue;
+            }
+            ExecutionResult::MoreInputNeeded => (),
         }
 
-        // let mut input_str = String::new();
-        // stdin().read_line(&mut input_str)?;
-        // context.input = input_str
-        //     .chars()
-        //     .filter(|c| *c != '\r')
-        //     .map(|c| c as i64)
-        //     .collect();
-
-        context.input = script
-            .chars()
-            .filter(|ch| *ch != '\r')
-            .map(|c| c as i64)
-            .collect();
+        match script.pop_front() {
+            Some(line) => context.send_line(&line),
+            None => {
+                let mut line = String::new();
+                if stdin().read_line(&mut line)? == 0 {
+                    // End of the input, before the end of the program
+                    break;
+                }
+                context.send_line(&line);
+            }
+        }
     }
     Ok(())
 }
 
+/// Runs the program with two scripts, and optionally memory patches given with
+/// `--patch-a ADDRESS=VALUE` and `--patch-b ADDRESS=VALUE`, and reports the first
+/// instruction where they diverge
+fn compare_runs(args: &[String]) -> Result<()> {
+    let file_name = args.get(2).ok_or("Enter a file name")?;
+    let script_files = [
+        args.get(3).ok_or("Enter the script of the first run")?,
+        args.get(4).ok_or("Enter the script of the second run")?,
+    ];
+
+    let mut instructions = String::new();
+    File::open(file_name)?.read_to_string(&mut instructions)?;
+    let program = Memory::parse(instructions.trim());
+
+    let mut memories = vec![];
+    let mut traces = vec![];
+    for (script_file, option) in script_files.iter().zip(&["--patch-a", "--patch-b"]) {
+        let mut script = String::new();
+        File::open(script_file)?.read_to_string(&mut script)?;
+        let mut memory = program.clone();
+        for (address, value) in compare::patches_from_args(args, option)? {
+            memory[address] = value;
+        }
+        traces.push(trace_run(&memory, &script));
+        memories.push(memory);
+    }
+
+    let run = |i: usize| Run {
+        name: script_files[i],
+        memory: &memories[i],
+        trace: &traces[i],
+    };
+    println!("{}", compare::report(&run(0), &run(1)));
+    Ok(())
+}
+
+/// Tries the scripts of the candidates file, separated by blank lines, or the WALK
+/// scripts jumping over holes if there's no file, and reports how each one fared
+fn search_scripts(args: &[String]) -> Result<()> {
+    let file_name = args.get(2).ok_or("Enter a file name")?;
+    let mut instructions = String::new();
+    File::open(file_name)?.read_to_string(&mut instructions)?;
+    let program = Memory::parse(instructions.trim());
+
+    let candidates = match args.get(3) {
+        Some(candidates_file) => {
+            let mut text = String::new();
+            File::open(candidates_file)?.read_to_string(&mut text)?;
+            search::parse_candidates(&text)
+        }
+        None => search::walk_candidates(),
+    };
+    let success = match search::search(&program, &candidates) {
+        Ok(success) => success,
+        Err(failures) => {
+            println!("{}", failures);
+            return Err("No script got the droid across".into());
+        }
+    };
+    println!("{}", success.script);
+    println!("Result: {}", success.damage);
+    Ok(())
+}
+
+/// Instructions executed by the program given a script, until it exits or asks for more
+/// input than the script has
+fn trace_run(memory: &Memory, script: &str) -> Vec<Step> {
+    let mut context = ExecutionContext::new(memory);
+    context.echo_input = false;
+    context.trace = Some(vec![]);
+    for line in ascii::script_commands(script) {
+        context.send_line(&line);
+    }
+    execute_program(&mut context);
+    context.trace.unwrap_or_default()
+}
+
 #[derive(Clone)]
 struct ExecutionContext {
     ip: usize,
     memory: Memory,
     ended: bool,
     relative_base: usize,
-    input: Vec<i64>,
-    input_index: usize,
+    breakpoints: Breakpoints,
+    /// Watchpoint hit by the instruction being executed
+    watch_hit: Option<BreakReason>,
+    input: VecDeque<i64>,
     output: i64,
+    /// Text output, not printed yet
+    lines: ascii::Lines,
+    /// Amount of hull damage, reported once the droid made it across
+    damage: Option<i64>,
+    /// Whether the input is printed as it's read
+    echo_input: bool,
+    /// Instructions executed so far, when recording them
+    trace: Option<Vec<Step>>,
 }
 
 impl ExecutionContext {
@@ -74,30 +208,38 @@ impl ExecutionContext {
             memory: memory.clone(),
             ended: false,
             relative_base: 0,
+            breakpoints: Breakpoints::default(),
+            watch_hit: None,
             output: 0,
-            input_index: 0,
-            input: vec![],
+            input: VecDeque::new(),
+            lines: ascii::Lines::default(),
+            damage: None,
+            echo_input: true,
+            trace: None,
         }
     }
 
     fn read_input(&mut self) -> Option<i64> {
-        if self.input_index >= self.input.len() {
-            self.input_index = 0;
-            self.input.clear();
-            None
-        } else {
-            let res = self.input[self.input_index];
+        let res = self.input.pop_front()?;
+        if self.echo_input {
             print_char(res);
-            self.input_index += 1;
-            Some(res)
         }
+        Some(res)
+    }
+
+    /// Queues a line of text as input, followed by a new line
+    fn send_line(&mut self, line: &str) {
+        self.input.extend(ascii::encode_line(line));
+    }
+
+    /// Next complete line of text output
+    fn read_line(&mut self) -> Option<String> {
+        self.lines.read_line()
     }
 
     fn write_output(&mut self, value: i64) {
-        if value > 255 {
-            println!("Result: {}", value);
-        } else {
-            print_char(value);
+        if !self.lines.push(value) {
+            self.damage = Some(value);
         }
         self.output = value;
         //self.output.clear();
@@ -105,22 +247,35 @@ impl ExecutionContext {
 }
 
 fn print_char(c: i64) {
-    if c == 10 {
-        println!();
-    } else {
-        print!("{}", c as u8 as char);
-    }
+    print!("{}", ascii::decode(Some(c)));
 }
 
 #[derive(Debug)]
 enum ExecutionResult {
     MoreInputNeeded,
     Exit,
+    /// Stopped at a breakpoint or watchpoint; `ip` is the address of the instruction that
+    /// is about to be executed, or that wrote to the watched cell
+    Breakpoint {
+        ip: usize,
+        reason: BreakReason,
+    },
 }
 
 fn execute_program(context: &mut ExecutionContext) -> ExecutionResult {
     // println!("Executing program; ip: {}", context.ip.get());
     loop {
+        let instruction_ip = context.ip;
+        if context.breakpoints.should_break(instruction_ip) {
+            return ExecutionResult::Breakpoint {
+                ip: instruction_ip,
+                reason: BreakReason::Instruction,
+            };
+        }
+        if let Some(trace) = &mut context.trace {
+            trace.push(Step::new(instruction_ip));
+        }
+
         match read_op_code(context) {
             (OpCode::Add, parameter_modes) => {
                 let (a, b, c) = extract_parameters3(context, parameter_modes);
@@ -142,6 +297,9 @@ fn execute_program(context: &mut ExecutionContext) -> ExecutionResult {
                         // Revert the reading of the op-code, so we can read it again when the
                         // thread is resumed
                         context.ip -= 1;
+                        if let Some(trace) = &mut context.trace {
+                            trace.pop();
+                        }
                         return ExecutionResult::MoreInputNeeded;
                     }
                 }
@@ -196,6 +354,12 @@ fn execute_program(context: &mut ExecutionContext) -> ExecutionResult {
         }
 
         // println!("Values: {:?}", memory);
+        if let Some(reason) = context.watch_hit.take() {
+            return ExecutionResult::Breakpoint {
+                ip: instruction_ip,
+                reason,
+            };
+        }
     }
 }
 
@@ -232,7 +396,7 @@ fn read_op_code(context: &mut ExecutionContext) -> (OpCode, u32) {
         8 => OpCode::Equals,
         9 => OpCode::AdjustRelativeBase,
         99 => OpCode::Exit,
-        x => panic!("Unknown op code: {}; ip: {}", x, context.ip),
+        x => vm_error(format!("Unknown op code: {}; ip: {}", x, context.ip)),
     };
 
     context.ip += 1;
@@ -271,7 +435,7 @@ fn get_parameter(context: &mut ExecutionContext, parameter_modes: &mut u32) -> P
         0 => ParameterMode::Position,
         1 => ParameterMode::Immediate,
         2 => ParameterMode::Relative,
-        x => panic!(format!("Incorrect parameter mode: {}", x)),
+        x => vm_error(format!("Incorrect parameter mode: {}", x)),
     };
     *parameter_modes /= 10;
 
@@ -303,8 +467,21 @@ impl<'a> Parameter {
 
     fn set(&self, value: i64, context: &mut ExecutionContext) -> () {
         match self {
-            Parameter::Reference(address) => context.memory[*address] = value,
-            Parameter::ImmediateValue(value) => panic!(format!(
+            Parameter::Reference(address) => {
+                let old = context.memory[*address];
+                context.memory[*address] = value;
+                if let Some(step) = context.trace.as_mut().and_then(|t| t.last_mut()) {
+                    step.write = Some((*address, value));
+                }
+                if context.breakpoints.is_watched(*address) {
+                    context.watch_hit = Some(BreakReason::Write {
+                        address: *address,
+                        old,
+                        new: value,
+                    });
+                }
+            }
+            Parameter::ImmediateValue(value) => vm_error(format!(
                 "Attempted to write value {} to an immediate parameter",
                 value
             )),
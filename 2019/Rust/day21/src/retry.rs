@@ -0,0 +1,73 @@
+use std::fmt;
+
+/// How many times a sequence may be tried
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+}
+
+impl RetryPolicy {
+    pub fn new(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy { max_attempts }
+    }
+}
+
+/// Every attempt failed; the errors are in the order of the attempts
+#[derive(Debug)]
+pub struct RetryError<E> {
+    pub errors: Vec<E>,
+}
+
+impl<E: fmt::Display> fmt::Display for RetryError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Failed after {} attempt(s)", self.errors.len())?;
+        for (i, error) in self.errors.iter().enumerate() {
+            write!(f, "\n  attempt {}: {}", i + 1, error)?;
+        }
+        Ok(())
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for RetryError<E> {}
+
+/// Calls `attempt` with the attempt number, starting from 1, until it succeeds or the
+/// policy's attempts are exhausted
+pub fn retry<T, E>(
+    policy: &RetryPolicy,
+    mut attempt: impl FnMut(u32) -> Result<T, E>,
+) -> Result<T, RetryError<E>> {
+    let mut errors = vec![];
+    for n in 1..=policy.max_attempts {
+        match attempt(n) {
+            Ok(result) => return Ok(result),
+            Err(error) => errors.push(error),
+        }
+    }
+    Err(RetryError { errors })
+}
+
+#[test]
+fn succeeds_after_failures() {
+    let mut attempts = vec![];
+    let result = retry(&RetryPolicy::new(5), |n| {
+        attempts.push(n);
+        if n < 3 {
+            Err(n)
+        } else {
+            Ok("done")
+        }
+    });
+    assert_eq!("done", result.unwrap());
+    assert_eq!(vec![1, 2, 3], attempts);
+}
+
+#[test]
+fn reports_every_failure() {
+    let result: Result<(), _> = retry(&RetryPolicy::new(3), |n| Err(format!("error {}", n)));
+    let error = result.unwrap_err();
+    assert_eq!(vec!["error 1", "error 2", "error 3"], error.errors);
+    assert_eq!(
+        "Failed after 3 attempt(s)\n  attempt 1: error 1\n  attempt 2: error 2\n  attempt 3: error 3",
+        error.to_string()
+    );
+}
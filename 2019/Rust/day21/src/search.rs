@@ -0,0 +1,104 @@
+use crate::ascii;
+use crate::memory::Memory;
+use crate::retry::{retry, RetryError, RetryPolicy};
+use crate::{execute_program, ExecutionContext};
+
+/// Springscript that got the droid across, and the hull damage it reported
+#[derive(Debug, PartialEq, Eq)]
+pub struct Success {
+    pub script: String,
+    pub damage: i64,
+}
+
+/// Scripts of a candidates file: blocks of commands separated by blank lines
+pub fn parse_candidates(text: &str) -> Vec<String> {
+    text.split("\n\n")
+        .map(|block| Vec::from(ascii::script_commands(block)).join("\n"))
+        .filter(|script| !script.is_empty())
+        .collect()
+}
+
+/// WALK scripts jumping when D is ground and any of a set of the sensors A, B and C sees
+/// a hole, for each non-empty set
+pub fn walk_candidates() -> Vec<String> {
+    (1..8u32)
+        .map(|set| {
+            let sensors: Vec<_> = ['A', 'B', 'C']
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| set & 1 << i != 0)
+                .map(|(_, s)| s)
+                .collect();
+            let mut commands = vec![format!("NOT {} J", sensors[0])];
+            for sensor in &sensors[1..] {
+                commands.push(format!("NOT {} T", sensor));
+                commands.push("OR T J".to_string());
+            }
+            commands.push("AND D J".to_string());
+            commands.push("WALK".to_string());
+            commands.join("\n")
+        })
+        .collect()
+}
+
+/// Tries the scripts in turn, each on a copy of the droid booted up to its prompt, until
+/// one gets it across. Each failure is the script, and the last line the droid output,
+/// the ground where it fell.
+pub fn search(memory: &Memory, candidates: &[String]) -> Result<Success, RetryError<String>> {
+    let mut booted = ExecutionContext::new(memory);
+    booted.echo_input = false;
+    execute_program(&mut booted);
+    while booted.read_line().is_some() {}
+
+    retry(&RetryPolicy::new(candidates.len() as u32), |n| {
+        let script = &candidates[n as usize - 1];
+        let mut droid = booted.clone();
+        for line in script.lines() {
+            droid.send_line(line);
+        }
+        execute_program(&mut droid);
+        if let Some(damage) = droid.damage {
+            return Ok(Success {
+                script: script.clone(),
+                damage,
+            });
+        }
+        let mut last_line = String::new();
+        while let Some(line) = droid.read_line() {
+            if !line.is_empty() {
+                last_line = line;
+            }
+        }
+        Err(format!("{} -> {}", script.replace('\n', "; "), last_line))
+    })
+}
+
+#[test]
+fn candidates_file() {
+    let text = "# Jumps over single holes\nNOT A J\nWALK\n\n\n# Never jumps\nWALK\n";
+    assert_eq!(
+        vec!["NOT A J\nWALK".to_string(), "WALK".to_string()],
+        parse_candidates(text)
+    );
+    let candidates = walk_candidates();
+    assert_eq!(7, candidates.len());
+    assert_eq!("NOT A J\nAND D J\nWALK", candidates[0]);
+    assert_eq!(
+        "NOT A J\nNOT B T\nOR T J\nNOT C T\nOR T J\nAND D J\nWALK",
+        candidates[6]
+    );
+}
+
+#[test]
+fn search_input() {
+    let memory = Memory::parse(include_str!("../input.txt").trim());
+
+    // Without jumping, the droid falls into the first hole
+    let failure = search(&memory, &["WALK".to_string()]).unwrap_err();
+    assert_eq!(1, failure.errors.len());
+    assert!(failure.errors[0].starts_with("WALK -> #"));
+
+    let success = search(&memory, &walk_candidates()).unwrap();
+    assert!(success.damage > 127);
+    assert!(success.script.ends_with("AND D J\nWALK"));
+}
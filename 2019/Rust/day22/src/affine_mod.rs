@@ -0,0 +1,182 @@
+/// Map x -> a.x + b modulo n. The coefficients are kept in 0..n; products of two of them
+/// must fit in an i128, so the modulus must be below 2^63.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AffineMod {
+    pub a: i128,
+    pub b: i128,
+    pub modulus: i128,
+}
+
+impl AffineMod {
+    pub fn new(a: i128, b: i128, modulus: i128) -> AffineMod {
+        AffineMod {
+            a: a.rem_euclid(modulus),
+            b: b.rem_euclid(modulus),
+            modulus,
+        }
+    }
+
+    pub fn identity(modulus: i128) -> AffineMod {
+        AffineMod::new(1, 0, modulus)
+    }
+
+    pub fn apply(&self, x: i128) -> i128 {
+        (self.a * x.rem_euclid(self.modulus) + self.b) % self.modulus
+    }
+
+    /// This map followed by the other one
+    pub fn compose(&self, other: &AffineMod) -> AffineMod {
+        // a'.(a.x + b) + b'
+        AffineMod::new(
+            other.a * self.a % self.modulus,
+            other.a * self.b % self.modulus + other.b,
+            self.modulus,
+        )
+    }
+
+    /// This map applied n times, computed by repeated squaring
+    pub fn pow(&self, n: u128) -> AffineMod {
+        let mut result = AffineMod::identity(self.modulus);
+        let mut square = *self;
+        let mut n = n;
+        while n > 0 {
+            if n & 1 == 1 {
+                result = result.compose(&square);
+            }
+            square = square.compose(&square);
+            n >>= 1;
+        }
+        result
+    }
+
+    /// Map undoing this one, or None if it isn't a bijection (when a and the modulus
+    /// aren't coprime)
+    pub fn invert(&self) -> Option<AffineMod> {
+        // x = a^-1.y - a^-1.b
+        let inv_a = modular_inverse(self.a, self.modulus)?;
+        Some(AffineMod::new(
+            inv_a,
+            -inv_a * self.b % self.modulus,
+            self.modulus,
+        ))
+    }
+
+    /// Values x such that a.x + b = x, as the smallest one and the distance between two
+    /// consecutive ones; None if there are none
+    pub fn fixed_points(&self) -> Option<(i128, i128)> {
+        // (a - 1).x = -b: dividing by g = gcd(a - 1, n), it has a single solution modulo
+        // n / g if g divides b, and none otherwise
+        let n = self.modulus;
+        let c = (self.a - 1).rem_euclid(n);
+        let d = (-self.b).rem_euclid(n);
+        let g = gcd(c, n);
+        if d % g != 0 {
+            return None;
+        }
+        let step = n / g;
+        let x = (d / g) * modular_inverse(c / g, step)? % step;
+        Some((x, step))
+    }
+}
+
+fn gcd(a: i128, b: i128) -> i128 {
+    let (mut a, mut b) = (a, b);
+    while b != 0 {
+        let r = a % b;
+        a = b;
+        b = r;
+    }
+    a
+}
+
+/// Inverse of x modulo n, using the extended Euclidean algorithm
+pub fn modular_inverse(x: i128, n: i128) -> Option<i128> {
+    let (mut r0, mut r1) = (x.rem_euclid(n), n);
+    let (mut s0, mut s1) = (1i128, 0i128);
+    while r1 != 0 {
+        let q = r0 / r1;
+        let (r, s) = (r0 - q * r1, s0 - q * s1);
+        r0 = r1;
+        r1 = r;
+        s0 = s1;
+        s1 = s;
+    }
+    if r0 == 1 {
+        Some(s0.rem_euclid(n))
+    } else {
+        None
+    }
+}
+
+/// Every map for each modulus up to 12
+#[cfg(test)]
+fn all_small_maps() -> impl Iterator<Item = AffineMod> {
+    (1..=12).flat_map(|n| (0..n).flat_map(move |a| (0..n).map(move |b| AffineMod::new(a, b, n))))
+}
+
+#[test]
+fn compose_matches_sequential_application() {
+    for f in all_small_maps() {
+        let n = f.modulus;
+        for g in (0..n).flat_map(|a| (0..n).map(move |b| AffineMod::new(a, b, n))) {
+            let h = f.compose(&g);
+            for x in 0..n {
+                assert_eq!(g.apply(f.apply(x)), h.apply(x), "{:?} then {:?}", f, g);
+            }
+        }
+    }
+}
+
+#[test]
+fn pow_matches_repeated_application() {
+    for f in all_small_maps() {
+        for k in 0..20 {
+            let fk = f.pow(k);
+            for x in 0..f.modulus {
+                let expected = (0..k).fold(x, |y, _| f.apply(y));
+                assert_eq!(expected, fk.apply(x), "{:?}^{}", f, k);
+            }
+        }
+    }
+}
+
+#[test]
+fn invert_exactly_the_bijections() {
+    for f in all_small_maps() {
+        let n = f.modulus;
+        let mut images: Vec<i128> = (0..n).map(|x| f.apply(x)).collect();
+        images.sort_unstable();
+        let bijective = images == (0..n).collect::<Vec<_>>();
+        match f.invert() {
+            Some(inverse) => {
+                assert!(bijective, "{:?}", f);
+                assert_eq!(AffineMod::identity(n), f.compose(&inverse));
+                assert_eq!(AffineMod::identity(n), inverse.compose(&f));
+            }
+            None => assert!(!bijective, "{:?}", f),
+        }
+    }
+}
+
+#[test]
+fn fixed_points_match_brute_force() {
+    for f in all_small_maps() {
+        let n = f.modulus;
+        let expected: Vec<i128> = (0..n).filter(|x| f.apply(*x) == *x).collect();
+        let found: Vec<i128> = match f.fixed_points() {
+            Some((first, step)) => (first..n).step_by(step as usize).collect(),
+            None => vec![],
+        };
+        assert_eq!(expected, found, "{:?}", f);
+    }
+}
+
+#[test]
+fn large_modulus() {
+    // The deck of part 2: x -> 2.x, applied 100 times, is x -> 2^100.x
+    let n = 119315717514047;
+    let f = AffineMod::new(2, 0, n);
+    let expected = (0..100).fold(1, |y, _| y * 2 % n);
+    assert_eq!(expected, f.pow(100).apply(1));
+    assert_eq!(1, f.invert().unwrap().pow(100).apply(expected));
+}
@@ -0,0 +1,181 @@
+use crate::affine_mod::AffineMod;
+use crate::Operation;
+use std::convert::TryFrom;
+
+/// Shuffle moving the card at position x to position a.x + b, modulo the deck length
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LinearShuffle {
+    pub map: AffineMod,
+}
+
+impl LinearShuffle {
+    pub fn identity(deck_len: usize) -> LinearShuffle {
+        LinearShuffle {
+            map: AffineMod::identity(deck_len as i128),
+        }
+    }
+
+    pub fn from_operation(op: &Operation, deck_len: usize) -> LinearShuffle {
+        let (a, b) = match *op {
+            Operation::DealIntoNewStack => (-1, -1),
+            Operation::Cut(n) => (1, -(n as i128)),
+            Operation::DealWithIncrement(incr) => (incr as i128, 0),
+        };
+        LinearShuffle {
+            map: AffineMod::new(a, b, deck_len as i128),
+        }
+    }
+
+    /// Composes all the operations, in order
+    pub fn from_operations(operations: &[Operation], deck_len: usize) -> LinearShuffle {
+        operations
+            .iter()
+            .fold(LinearShuffle::identity(deck_len), |shuffle, op| {
+                shuffle.then(&LinearShuffle::from_operation(op, deck_len))
+            })
+    }
+
+    /// Position of the card that was at the given position before the shuffle
+    pub fn apply(&self, position: usize) -> usize {
+        self.map.apply(position as i128) as usize
+    }
+
+    /// This shuffle followed by the other one
+    pub fn then(&self, other: &LinearShuffle) -> LinearShuffle {
+        LinearShuffle {
+            map: self.map.compose(&other.map),
+        }
+    }
+
+    /// Shuffle putting the cards back where they were, or None if the shuffle isn't
+    /// reversible (when a and the deck length aren't coprime)
+    pub fn inverse(&self) -> Option<LinearShuffle> {
+        Some(LinearShuffle {
+            map: self.map.invert()?,
+        })
+    }
+
+    /// Operations realizing this shuffle: dealing with increment a moves the card at x to
+    /// a.x, then cutting -b cards moves it to a.x + b.
+    /// Fails if the cut doesn't fit in an i32, which can happen on large decks.
+    pub fn to_operations(self) -> Result<Vec<Operation>, String> {
+        let AffineMod { a, b, modulus } = self.map;
+        let mut operations = vec![];
+        if a != 1 {
+            operations.push(Operation::DealWithIncrement(a as usize));
+        }
+        if b != 0 {
+            let cut = i32::try_from(modulus - b)
+                .map_err(|_| format!("Can't cut {} cards: the deck is too large", modulus - b))?;
+            operations.push(Operation::Cut(cut));
+        }
+        Ok(operations)
+    }
+}
+
+/// Shuffles an actual deck of cards, following the puzzle's description
+#[cfg(test)]
+fn deal(deck: &[usize], op: &Operation) -> Vec<usize> {
+    match *op {
+        Operation::DealIntoNewStack => deck.iter().rev().cloned().collect(),
+        Operation::Cut(n) => {
+            let n = (n as i64).rem_euclid(deck.len() as i64) as usize;
+            deck[n..].iter().chain(&deck[..n]).cloned().collect()
+        }
+        Operation::DealWithIncrement(incr) => {
+            let mut table = vec![0; deck.len()];
+            for (i, card) in deck.iter().enumerate() {
+                table[i * incr % deck.len()] = *card;
+            }
+            table
+        }
+    }
+}
+
+#[cfg(test)]
+fn deal_all(deck: &[usize], operations: &[Operation]) -> Vec<usize> {
+    operations
+        .iter()
+        .fold(deck.to_vec(), |deck, op| deal(&deck, op))
+}
+
+#[test]
+fn example_shuffle() {
+    // Cards in the deck after the shuffle: 9 2 5 8 1 4 7 0 3 6
+    let operations = vec![
+        Operation::DealIntoNewStack,
+        Operation::Cut(-2),
+        Operation::DealWithIncrement(7),
+        Operation::Cut(8),
+        Operation::Cut(-4),
+        Operation::DealWithIncrement(7),
+        Operation::Cut(3),
+        Operation::DealWithIncrement(9),
+        Operation::DealWithIncrement(3),
+        Operation::Cut(-1),
+    ];
+    let shuffle = LinearShuffle::from_operations(&operations, 10);
+    let deck = deal_all(&(0..10).collect::<Vec<_>>(), &operations);
+    assert_eq!(vec![9, 2, 5, 8, 1, 4, 7, 0, 3, 6], deck);
+    for card in 0..10 {
+        assert_eq!(card, deck[shuffle.apply(card)]);
+    }
+}
+
+#[test]
+fn inverse_operations_undo_the_shuffle() {
+    let operations = vec![
+        Operation::DealWithIncrement(55),
+        Operation::Cut(-6791),
+        Operation::DealIntoNewStack,
+        Operation::DealWithIncrement(9),
+        Operation::Cut(5412),
+        Operation::DealIntoNewStack,
+        Operation::DealWithIncrement(21),
+    ];
+    let deck: Vec<usize> = (0..10007).collect();
+    let shuffled = deal_all(&deck, &operations);
+
+    let shuffle = LinearShuffle::from_operations(&operations, deck.len());
+    let inverse = shuffle.inverse().unwrap().to_operations().unwrap();
+    assert_eq!(deck, deal_all(&shuffled, &inverse));
+    assert_eq!(
+        LinearShuffle::identity(deck.len()),
+        shuffle.then(&LinearShuffle::from_operations(&inverse, deck.len()))
+    );
+}
+
+#[test]
+fn inverse_operations_for_every_small_deck_shuffle() {
+    // All the reversible deals and cuts on a deck of 12 cards
+    let deck: Vec<usize> = (0..12).collect();
+    for incr in (1..12).filter(|i| crate::affine_mod::modular_inverse(*i, 12).is_some()) {
+        for cut in -11..12 {
+            let operations = vec![
+                Operation::DealIntoNewStack,
+                Operation::DealWithIncrement(incr as usize),
+                Operation::Cut(cut),
+            ];
+            let shuffle = LinearShuffle::from_operations(&operations, deck.len());
+            let inverse = shuffle.inverse().unwrap().to_operations().unwrap();
+            assert_eq!(deck, deal_all(&deal_all(&deck, &operations), &inverse));
+        }
+    }
+}
+
+#[test]
+fn irreversible_shuffle() {
+    let shuffle = LinearShuffle::from_operation(&Operation::DealWithIncrement(4), 10);
+    assert_eq!(None, shuffle.inverse());
+}
+
+#[test]
+fn cut_too_large_for_operations() {
+    let shuffle = LinearShuffle::from_operation(&Operation::Cut(-1), 119315717514047);
+    assert_eq!(
+        Some("Can't cut 119315717514046 cards: the deck is too large".to_string()),
+        shuffle.to_operations().err()
+    );
+    let shuffle = LinearShuffle::from_operation(&Operation::Cut(-1), 10007);
+    assert!(shuffle.to_operations().is_ok());
+}
@@ -2,6 +2,7 @@ extern crate generic_matrix;
 extern crate num;
 extern crate regex;
 
+use crate::linear::LinearShuffle;
 use crate::num::Integer;
 use crate::num::Signed;
 use generic_matrix::Matrix;
@@ -10,15 +11,21 @@ use num::One;
 use num::Zero;
 use regex::Regex;
 use std::env;
+use std::fmt;
 use std::fs::File;
 use std::io::prelude::*;
 use std::io::BufReader;
 use std::result::Result;
 
+mod affine_mod;
+mod linear;
+
 type MainResult<T> = Result<T, Box<dyn ::std::error::Error>>;
 const DECK_LENGTH: usize = 119315717514047;
 const CARD_INDEX: usize = 2020;
 const LOOPS: usize = 101741582076661;
+const PART1_DECK_LENGTH: usize = 10007;
+const PART1_CARD: usize = 2019;
 
 // const DECK_LENGTH: usize = 10007;
 // const CARD_INDEX: usize = 2019;
@@ -31,10 +38,49 @@ enum Operation {
     Cut(i32),
 }
 
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Operation::DealWithIncrement(incr) => write!(f, "deal with increment {}", incr),
+            Operation::DealIntoNewStack => write!(f, "deal into new stack"),
+            Operation::Cut(n) => write!(f, "cut {}", n),
+        }
+    }
+}
+
 fn main() -> MainResult<()> {
     let file_name = env::args().nth(1).expect("Enter a file name");
     let operations = read_operations(&file_name)?;
 
+    // day22 <input> --inverse <deck length>: prints the operations undoing the shuffle
+    if env::args().nth(2).as_deref() == Some("--inverse") {
+        let deck_len: usize = env::args().nth(3).ok_or("Enter the deck length")?.parse()?;
+        // The affine maps multiply positions together, which must fit in an i128
+        if deck_len == 0 || deck_len as u128 >= 1 << 63 {
+            return Err(
+                format!("Invalid deck length: {} (expected 1 to 2^63 - 1)", deck_len).into(),
+            );
+        }
+        let inverse = LinearShuffle::from_operations(&operations, deck_len)
+            .inverse()
+            .ok_or("The shuffle can't be reversed for this deck length")?;
+        for op in inverse.to_operations()? {
+            println!("{}", op);
+        }
+        return Ok(());
+    }
+
+    let part1 = LinearShuffle::from_operations(&operations, PART1_DECK_LENGTH);
+    println!(
+        "Position of card {}: {}",
+        PART1_CARD,
+        part1.apply(PART1_CARD)
+    );
+    match part1.map.fixed_points() {
+        Some((first, step)) => println!("Cards left in place: {}, then every {}", first, step),
+        None => println!("No card is left in place"),
+    }
+
     //println!("Operations: {:?}", operations);
 
     // f = a.x + b
@@ -102,6 +148,14 @@ fn main() -> MainResult<()> {
     let res = y[(0, 0)].clone();
     println!("Result: {}", res);
 
+    // Same result with the modular affine map of the shuffle: undo it LOOPS times
+    let repeated = LinearShuffle::from_operations(&operations, DECK_LENGTH)
+        .map
+        .pow(LOOPS as u128);
+    if let Some(inverse) = repeated.invert() {
+        println!("Result (affine map): {}", inverse.apply(CARD_INDEX as i128));
+    }
+
     Ok(())
 }
 
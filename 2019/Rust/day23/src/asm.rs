@@ -0,0 +1,304 @@
+use crate::code::{OpCode, ParameterMode};
+use crate::memory::Value;
+use std::collections::HashMap;
+
+/// Short mnemonics of the op codes; the names shown when disassembling instructions
+/// (`Add`, `JumpIfTrue`...) are accepted as well, in any case
+const MNEMONICS: [(&str, OpCode); 10] = [
+    ("add", OpCode::Add),
+    ("mul", OpCode::Mult),
+    ("in", OpCode::Input),
+    ("out", OpCode::Output),
+    ("jnz", OpCode::JumpIfTrue),
+    ("jz", OpCode::JumpIfFalse),
+    ("lt", OpCode::LessThan),
+    ("eq", OpCode::Equals),
+    ("arb", OpCode::AdjustRelativeBase),
+    ("hlt", OpCode::Exit),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    /// 1-based line of the source
+    pub line: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for AsmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+/// A value in the source, which may be the address of a label
+enum Operand<'a> {
+    Number(Value),
+    Label(&'a str),
+}
+
+enum Item<'a> {
+    Instruction(OpCode, Vec<(ParameterMode, Operand<'a>)>),
+    Data(Vec<Operand<'a>>),
+}
+
+pub fn mnemonic(op_code: OpCode) -> &'static str {
+    MNEMONICS.iter().find(|(_, o)| *o == op_code).unwrap().0
+}
+
+fn lookup_op_code(mnemonic: &str) -> Option<OpCode> {
+    let mnemonic = mnemonic.to_lowercase();
+    MNEMONICS
+        .iter()
+        .find(|(m, op_code)| *m == mnemonic || format!("{:?}", op_code).to_lowercase() == mnemonic)
+        .map(|(_, op_code)| *op_code)
+}
+
+fn parse_operand(text: &str) -> Result<Operand<'_>, String> {
+    if let Ok(value) = text.parse() {
+        return Ok(Operand::Number(value));
+    }
+    let is_label = text
+        .chars()
+        .enumerate()
+        .all(|(i, c)| c == '_' || c.is_ascii_alphabetic() || (i > 0 && c.is_ascii_digit()));
+    if is_label && !text.is_empty() {
+        Ok(Operand::Label(text))
+    } else {
+        Err(format!("Invalid operand: {}", text))
+    }
+}
+
+/// Parameters are written `5` or `label` (immediate), `[5]` or `[label]` (position), and
+/// `[rb+5]` or `[rb-5]` (relative)
+fn parse_parameter(text: &str) -> Result<(ParameterMode, Operand<'_>), String> {
+    let inner = match text.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+        Some(inner) => inner.trim(),
+        None => return Ok((ParameterMode::Immediate, parse_operand(text)?)),
+    };
+    match inner.strip_prefix("rb") {
+        Some("") => Ok((ParameterMode::Relative, Operand::Number(0))),
+        Some(offset) if offset.starts_with('+') || offset.starts_with('-') => {
+            let offset = offset
+                .trim_start_matches('+')
+                .parse()
+                .map_err(|_| format!("Invalid relative parameter: {}", text))?;
+            Ok((ParameterMode::Relative, Operand::Number(offset)))
+        }
+        _ => Ok((ParameterMode::Position, parse_operand(inner)?)),
+    }
+}
+
+fn split_operands(text: &str) -> Vec<&str> {
+    if text.is_empty() {
+        vec![]
+    } else {
+        text.split(',').map(str::trim).collect()
+    }
+}
+
+/// Assembles a program into the values of its memory image. Each line holds an optional
+/// `label:`, then either an instruction such as `add [x], 1, [x]`, or `.data` followed by
+/// values; comments start with `;`.
+pub fn assemble(source: &str) -> Result<Vec<Value>, AsmError> {
+    let mut items = vec![];
+    let mut labels = HashMap::new();
+    let mut address = 0;
+    for (index, line) in source.lines().enumerate() {
+        let error = |message| AsmError {
+            line: index + 1,
+            message,
+        };
+        let mut line = line.split(';').next().unwrap().trim();
+        if let Some(colon) = line.find(':') {
+            let label = line[..colon].trim();
+            match parse_operand(label) {
+                Ok(Operand::Label(_)) => (),
+                _ => return Err(error(format!("Invalid label: {}", label))),
+            }
+            if labels.insert(label, address).is_some() {
+                return Err(error(format!("Duplicate label: {}", label)));
+            }
+            line = line[colon + 1..].trim();
+        }
+        if line.is_empty() {
+            continue;
+        }
+
+        let (mnemonic, rest) = match line.find(char::is_whitespace) {
+            Some(space) => (&line[..space], line[space..].trim()),
+            None => (line, ""),
+        };
+        let operands = split_operands(rest);
+        let item = if mnemonic == ".data" {
+            let values = operands
+                .into_iter()
+                .map(parse_operand)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(error)?;
+            address += values.len();
+            Item::Data(values)
+        } else {
+            let op_code = lookup_op_code(mnemonic)
+                .ok_or_else(|| error(format!("Unknown instruction: {}", mnemonic)))?;
+            if operands.len() != op_code.parameter_count() {
+                return Err(error(format!(
+                    "{} takes {} parameters",
+                    mnemonic,
+                    op_code.parameter_count()
+                )));
+            }
+            let parameters = operands
+                .into_iter()
+                .map(parse_parameter)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(error)?;
+            if let (true, Some((ParameterMode::Immediate, _))) =
+                (op_code.writes(), parameters.last())
+            {
+                return Err(error(format!(
+                    "{} can't write to an immediate parameter",
+                    mnemonic
+                )));
+            }
+            address += 1 + parameters.len();
+            Item::Instruction(op_code, parameters)
+        };
+        items.push((index + 1, item));
+    }
+
+    let mut image = vec![];
+    for (line, item) in items {
+        let resolve = |operand: &Operand| match operand {
+            Operand::Number(value) => Ok(*value),
+            Operand::Label(label) => labels
+                .get(label)
+                .map(|&address| address as Value)
+                .ok_or_else(|| AsmError {
+                    line,
+                    message: format!("Unknown label: {}", label),
+                }),
+        };
+        match item {
+            Item::Instruction(op_code, parameters) => {
+                let modes = parameters.iter().rev().fold(0, |modes, (mode, _)| {
+                    modes * 10
+                        + match mode {
+                            ParameterMode::Position => 0,
+                            ParameterMode::Immediate => 1,
+                            ParameterMode::Relative => 2,
+                        }
+                });
+                image.push(modes * 100 + op_code.value());
+                for (_, operand) in &parameters {
+                    image.push(resolve(operand)?);
+                }
+            }
+            Item::Data(values) => {
+                for operand in &values {
+                    image.push(resolve(operand)?);
+                }
+            }
+        }
+    }
+    Ok(image)
+}
+
+/// Comma-separated memory image, in the format of the puzzle inputs
+pub fn to_image(values: &[Value]) -> String {
+    values
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[test]
+fn assemble_program() {
+    let source = "
+        ; Adds 2 and 3 into sum, outputs it, and exits
+        add 2, 3, [sum]
+        out [sum]
+        hlt
+        sum: .data 0
+    ";
+    assert_eq!("1101,2,3,7,4,7,99,0", to_image(&assemble(source).unwrap()));
+
+    let relative = "arb 10\nin [rb-2]\nOutput [rb]\nExit";
+    assert_eq!(
+        vec![109, 10, 203, -2, 204, 0, 99],
+        assemble(relative).unwrap()
+    );
+}
+
+#[test]
+fn assemble_labels() {
+    // Counts down from 3, outputting each value
+    let source = "
+        loop: out [counter]
+              add [counter], -1, [counter]
+              jnz [counter], loop
+              hlt
+        counter: .data 3
+    ";
+    let image = assemble(source).unwrap();
+    assert_eq!("4,10,1001,10,-1,10,1005,10,0,99,3", to_image(&image));
+}
+
+#[test]
+fn disassembly_round_trip() {
+    use crate::code::Computer;
+    use crate::memory::Memory;
+    use std::collections::BTreeMap;
+
+    let source = "
+        loop: out [counter]
+              add [counter], -1, [counter]
+              jnz [counter], loop
+              hlt
+        counter: .data 3
+    ";
+    let image = assemble(source).unwrap();
+    let mut computer = Computer::new(0, Memory::new(image.clone()), || None, ());
+    let mut disassembly = BTreeMap::new();
+    while let Some(instruction) = computer.decode() {
+        computer.step().unwrap();
+        // Drop the address in front of the instruction
+        let text = instruction.to_string();
+        let text = text.split_once(": ").unwrap().1.to_string();
+        disassembly.insert(instruction.address, text);
+    }
+
+    let mut reassembled = disassembly.values().cloned().collect::<Vec<_>>().join("\n");
+    reassembled.push_str("\n.data 3");
+    assert_eq!(image, assemble(&reassembled).unwrap());
+}
+
+#[test]
+fn assembly_errors() {
+    let error = |source| assemble(source).unwrap_err();
+    assert_eq!(
+        AsmError {
+            line: 2,
+            message: "Unknown instruction: nop".to_string()
+        },
+        error("hlt\nnop")
+    );
+    assert_eq!(
+        "line 1: add takes 3 parameters",
+        error("add 1, 2").to_string()
+    );
+    assert_eq!(
+        "line 1: Unknown label: nowhere",
+        error("jnz 1, nowhere").to_string()
+    );
+    assert_eq!(
+        "line 1: in can't write to an immediate parameter",
+        error("in 5").to_string()
+    );
+    assert_eq!(
+        "line 2: Duplicate label: a",
+        error("a: hlt\na: hlt").to_string()
+    );
+}
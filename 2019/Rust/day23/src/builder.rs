@@ -0,0 +1,213 @@
+use crate::code::{Computer, EmptyInput};
+use crate::io::{parse_values, InputSource, OutputSink};
+use crate::memory::{Memory, Value};
+use std::collections::VecDeque;
+use std::time::Instant;
+
+/// How the text given to `IntcodeBuilder::input` is turned into input values
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IoMode {
+    /// Comma or whitespace separated numbers
+    Numeric,
+    /// Each character is sent as its ASCII code
+    Ascii,
+}
+
+impl IoMode {
+    /// Input values of the text, or an error naming the first invalid number
+    pub fn encode(self, text: &str) -> Result<VecDeque<Value>, String> {
+        match self {
+            IoMode::Numeric => parse_values(text),
+            IoMode::Ascii => Ok(text.bytes().map(|b| b as Value).collect()),
+        }
+    }
+}
+
+/// Sets up a computer: its memory patches, preloaded input, I/O and execution limits
+pub struct IntcodeBuilder {
+    id: usize,
+    memory: Memory,
+    io_mode: IoMode,
+    input: VecDeque<Value>,
+    input_source: Box<dyn InputSource>,
+    output: Box<dyn OutputSink>,
+    empty_input: EmptyInput,
+    budget: Option<u64>,
+    deadline: Option<Instant>,
+}
+
+impl IntcodeBuilder {
+    pub fn new(memory: Memory) -> IntcodeBuilder {
+        IntcodeBuilder {
+            id: 0,
+            memory,
+            io_mode: IoMode::Numeric,
+            input: VecDeque::new(),
+            input_source: Box::new(|| None),
+            output: Box::new(()),
+            empty_input: EmptyInput::Block,
+            budget: None,
+            deadline: None,
+        }
+    }
+
+    pub fn id(mut self, id: usize) -> IntcodeBuilder {
+        self.id = id;
+        self
+    }
+
+    /// Overwrites a value of the initial memory
+    pub fn poke(mut self, address: usize, value: Value) -> IntcodeBuilder {
+        self.memory[address] = value;
+        self
+    }
+
+    /// Sets the noun and verb, at addresses 1 and 2
+    pub fn noun_verb(self, noun: Value, verb: Value) -> IntcodeBuilder {
+        self.poke(1, noun).poke(2, verb)
+    }
+
+    pub fn io_mode(mut self, io_mode: IoMode) -> IntcodeBuilder {
+        self.io_mode = io_mode;
+        self
+    }
+
+    /// Queues input values, read before anything else
+    pub fn input_values(mut self, values: &[Value]) -> IntcodeBuilder {
+        self.input.extend(values);
+        self
+    }
+
+    /// Queues input, encoded according to the I/O mode. Fails if a number is invalid in
+    /// numeric mode.
+    pub fn input(mut self, text: &str) -> Result<IntcodeBuilder, String> {
+        self.input.extend(self.io_mode.encode(text)?);
+        Ok(self)
+    }
+
+    /// Where to read the input from, once the preloaded input has been read
+    pub fn input_source(mut self, input_source: impl InputSource + 'static) -> IntcodeBuilder {
+        self.input_source = Box::new(input_source);
+        self
+    }
+
+    pub fn output(mut self, output: impl OutputSink + 'static) -> IntcodeBuilder {
+        self.output = Box::new(output);
+        self
+    }
+
+    /// What to do when the input is exhausted; the program waits for more by default
+    pub fn on_empty_input(mut self, empty_input: EmptyInput) -> IntcodeBuilder {
+        self.empty_input = empty_input;
+        self
+    }
+
+    /// Stops the program after the given number of instructions
+    pub fn budget(mut self, budget: u64) -> IntcodeBuilder {
+        self.budget = Some(budget);
+        self
+    }
+
+    pub fn deadline(mut self, deadline: Option<Instant>) -> IntcodeBuilder {
+        self.deadline = deadline;
+        self
+    }
+
+    pub fn build(self) -> Computer {
+        let mut preloaded = self.input;
+        let mut input_source = self.input_source;
+        let mut output = self.output;
+        let mut computer = Computer::new(
+            self.id,
+            self.memory,
+            move || preloaded.pop_front().or_else(|| input_source.read()),
+            move |value| output.write(value),
+        );
+        computer.set_empty_input(self.empty_input);
+        computer.set_deadline(self.deadline);
+        computer.set_instruction_budget(self.budget);
+        computer
+    }
+}
+
+#[test]
+fn preloaded_input() {
+    // Echoes two values
+    let echo = Memory::parse("3,0,4,0,3,0,4,0,99");
+    let mut computer = IntcodeBuilder::new(echo.clone())
+        .input_values(&[7, 8])
+        .build();
+    assert_eq!(vec![7, 8], computer.outputs().collect::<Vec<_>>());
+
+    let mut computer = IntcodeBuilder::new(echo.clone())
+        .input("7, 8")
+        .unwrap()
+        .build();
+    assert_eq!(vec![7, 8], computer.outputs().collect::<Vec<_>>());
+
+    let mut computer = IntcodeBuilder::new(echo.clone())
+        .io_mode(IoMode::Ascii)
+        .input("hi")
+        .unwrap()
+        .build();
+    assert_eq!(vec![104, 105], computer.outputs().collect::<Vec<_>>());
+
+    assert_eq!(
+        Some("Invalid input value: 8x".to_string()),
+        IntcodeBuilder::new(echo.clone()).input("7, 8x").err()
+    );
+
+    // The input source is only read once the preloaded input is exhausted
+    let mut computer = IntcodeBuilder::new(echo)
+        .input_values(&[1])
+        .input_source(|| Some(2))
+        .build();
+    assert_eq!(vec![1, 2], computer.outputs().collect::<Vec<_>>());
+}
+
+#[test]
+fn poked_memory() {
+    use crate::code::ExecutionResult;
+
+    let mut computer = IntcodeBuilder::new(Memory::parse("104,0,99"))
+        .poke(1, 42)
+        .build();
+    assert_eq!(Ok(Some(42)), computer.run_until_output());
+
+    // Adds the noun and verb into address 0
+    let mut computer = IntcodeBuilder::new(Memory::parse("1,0,0,0,99,10,20"))
+        .noun_verb(5, 6)
+        .build();
+    assert_eq!(Ok(ExecutionResult::Exit), computer.run_until_input_needed());
+    assert_eq!(30, computer.memory()[0]);
+}
+
+#[test]
+fn instruction_budget() {
+    use crate::code::ExecutionResult;
+
+    // Jumps back to itself forever
+    let mut computer = IntcodeBuilder::new(Memory::parse("1105,1,0"))
+        .budget(10)
+        .build();
+    for _ in 0..10 {
+        assert_eq!(
+            Ok(ExecutionResult::Executed),
+            computer.execute_single_instruction()
+        );
+    }
+    assert_eq!(
+        Ok(ExecutionResult::OutOfBudget),
+        computer.run_until_input_needed()
+    );
+}
+
+#[test]
+fn empty_input_policy() {
+    // Reads a value and outputs it
+    let echo = Memory::parse("3,0,4,0,99");
+    let mut computer = IntcodeBuilder::new(echo)
+        .on_empty_input(EmptyInput::Default(-1))
+        .build();
+    assert_eq!(vec![-1], computer.outputs().collect::<Vec<_>>());
+}
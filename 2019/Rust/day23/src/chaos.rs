@@ -0,0 +1,85 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+/// Failure injection for the computers I/O: inputs are randomly delayed (the computer
+/// is told no packet is available yet) and outputs randomly duplicated. It's a
+/// robustness tool, to check the host logic doesn't rely on a specific ordering of
+/// reads and writes, or on each packet being sent once; it's enabled with
+/// `--chaos <seed>`. A computer's input and output share the same random sequence.
+pub struct FlakyIo {
+    state: Cell<u64>,
+    delay_rate: f64,
+    duplicate_rate: f64,
+}
+
+impl FlakyIo {
+    pub fn new(seed: u64, delay_rate: f64, duplicate_rate: f64) -> Rc<FlakyIo> {
+        Rc::new(FlakyIo {
+            // xorshift gets stuck on 0
+            state: Cell::new(seed ^ 0x9E37_79B9_7F4A_7C15),
+            delay_rate,
+            duplicate_rate,
+        })
+    }
+
+    /// Next pseudo-random number in [0, 1), using xorshift64
+    fn next_random(&self) -> f64 {
+        let mut x = self.state.get();
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.state.set(x);
+        (x >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Randomly reports no input available. Inputs are whole packets rather than single
+    /// values, as the computers can't be told in the middle of a packet that its next
+    /// value isn't there.
+    pub fn wrap_input<T>(self: Rc<Self>, input: impl Fn() -> Option<T>) -> impl Fn() -> Option<T> {
+        move || {
+            if self.next_random() < self.delay_rate {
+                None
+            } else {
+                input()
+            }
+        }
+    }
+
+    /// Randomly writes the outputs twice. Outputs are whole packets rather than single
+    /// values, whose duplicates would shift the rest of the stream.
+    pub fn wrap_output<T: Copy>(self: Rc<Self>, mut output: impl FnMut(T)) -> impl FnMut(T) {
+        move |value| {
+            output(value);
+            if self.next_random() < self.duplicate_rate {
+                output(value);
+            }
+        }
+    }
+}
+
+#[test]
+fn same_seed_same_faults() {
+    let run = |seed| {
+        let input = FlakyIo::new(seed, 0.5, 0.0).wrap_input(|| Some(1));
+        (0..100).map(|_| input()).collect::<Vec<_>>()
+    };
+
+    assert_eq!(run(42), run(42));
+    assert_ne!(run(42), run(43));
+    let delayed = run(42).iter().filter(|x| x.is_none()).count();
+    assert!(delayed > 30 && delayed < 70, "{} delays", delayed);
+}
+
+#[test]
+fn duplicated_outputs() {
+    use crate::memory::Value;
+
+    let written = Rc::new(Cell::new(0));
+    let counter = written.clone();
+    let mut output = FlakyIo::new(1, 0.0, 1.0).wrap_output(move |_: [Value; 3]| {
+        counter.set(counter.get() + 1);
+    });
+    output([1, 2, 3]);
+    output([4, 5, 6]);
+    assert_eq!(4, written.get());
+}
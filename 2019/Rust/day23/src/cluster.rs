@@ -0,0 +1,118 @@
+use crate::code::Computer;
+use crate::code::ExecutionResult;
+use std::collections::VecDeque;
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum VmStatus {
+    Ready,
+    WaitingForInput,
+    OutOfBudget,
+    TimedOut,
+    Exited,
+    /// Stopped on an invalid instruction
+    Failed,
+}
+
+struct Slot {
+    computer: Computer,
+    budget: Option<u64>,
+    executed: u64,
+    status: VmStatus,
+}
+
+/// Cooperative scheduler running many VMs on the current thread. Each VM in turn gets
+/// to execute a slice of instructions, in a round-robin fashion. VMs waiting for input
+/// are paused, and given a chance again on the next round.
+pub struct Scheduler {
+    queue: VecDeque<Slot>,
+    done: Vec<Slot>,
+    slice_size: u64,
+}
+
+impl Scheduler {
+    pub fn new(slice_size: u64) -> Scheduler {
+        Scheduler {
+            queue: VecDeque::new(),
+            done: vec![],
+            slice_size,
+        }
+    }
+
+    /// Adds a VM to the end of the queue; it'll stop after executing `budget`
+    /// instructions, if any
+    pub fn add(&mut self, computer: Computer, budget: Option<u64>) {
+        self.queue.push_back(Slot {
+            computer,
+            budget,
+            executed: 0,
+            status: VmStatus::Ready,
+        });
+    }
+
+    /// Runs a slice on the VM at the front of the queue, and returns the number of
+    /// instructions it executed
+    pub fn run_slice(&mut self) -> u64 {
+        let mut slot = match self.queue.pop_front() {
+            Some(slot) => slot,
+            None => return 0,
+        };
+
+        let mut executed = 0;
+        slot.status = loop {
+            if executed == self.slice_size {
+                break VmStatus::Ready;
+            }
+            if slot.budget.is_some_and(|b| slot.executed >= b) {
+                break VmStatus::OutOfBudget;
+            }
+
+            match slot.computer.execute_single_instruction() {
+                Ok(ExecutionResult::Executed) | Ok(ExecutionResult::Output(_)) => {
+                    executed += 1;
+                    slot.executed += 1;
+                }
+                Ok(ExecutionResult::MoreInputNeeded) => break VmStatus::WaitingForInput,
+                Ok(ExecutionResult::Exit) => break VmStatus::Exited,
+                Ok(ExecutionResult::TimedOut) => break VmStatus::TimedOut,
+                Ok(ExecutionResult::OutOfBudget) => break VmStatus::OutOfBudget,
+                Err(_) => break VmStatus::Failed,
+            }
+        };
+
+        match slot.status {
+            VmStatus::Ready | VmStatus::WaitingForInput => self.queue.push_back(slot),
+            VmStatus::OutOfBudget | VmStatus::TimedOut | VmStatus::Exited | VmStatus::Failed => {
+                self.done.push(slot)
+            }
+        }
+        executed
+    }
+
+    /// Runs all the VMs until they're either done, or all waiting for input.
+    /// Returns the total number of instructions executed.
+    pub fn run(&mut self) -> u64 {
+        let mut total = 0;
+        loop {
+            let mut round = 0;
+            for _ in 0..self.queue.len() {
+                round += self.run_slice();
+            }
+            total += round;
+
+            if round == 0 {
+                break total;
+            }
+        }
+    }
+
+    pub fn statuses(&self) -> Vec<(usize, VmStatus)> {
+        let mut statuses: Vec<_> = self
+            .queue
+            .iter()
+            .chain(self.done.iter())
+            .map(|s| (s.computer.id(), s.status))
+            .collect();
+        statuses.sort_by_key(|(id, _)| *id);
+        statuses
+    }
+}
@@ -1,24 +1,62 @@
-use crate::memory::Memory;
+use crate::io::{InputSource, OutputSink};
+use crate::memory::{Memory, Value};
+use std::future::Future;
+#[cfg(test)]
+use std::sync::mpsc::{Receiver, Sender};
+use std::time::Instant;
+
+/// Number of instructions executed between two checks of the deadline, as reading the
+/// clock on every instruction would slow the VM down noticeably
+const DEADLINE_CHECK_INTERVAL: u32 = 1024;
+
+/// What a computer does when it needs an input value that isn't available
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EmptyInput {
+    /// Stops with `ExecutionResult::MoreInputNeeded`, and reads the input again when resumed
+    #[default]
+    Block,
+    /// Reads this value instead, like the network computers reading -1 when no packet is
+    /// waiting
+    Default(Value),
+    /// Fails with `IntcodeError::InputExhausted`, for programs whose input is all known
+    /// in advance
+    Error,
+}
 
 pub struct Computer {
     id: usize,
     context: ExecutionContext,
-    input: Box<dyn Fn() -> Option<i64>>,
-    output: Box<dyn Fn(i64, i64) -> ()>,
+    input: Box<dyn InputSource>,
+    output: Box<dyn OutputSink>,
+    deadline: Option<Instant>,
+    instructions_until_check: u32,
+    timed_out: bool,
+    instruction_budget: Option<u64>,
+    executed: u64,
+    /// Value provided by `execute_async`, read before the input source
+    pending_input: Option<Value>,
+    empty_input: EmptyInput,
 }
 
-impl<'a> Computer {
+impl Computer {
     pub fn new(
         id: usize,
         memory: Memory,
-        input: Box<dyn Fn() -> Option<i64>>,
-        output: Box<dyn Fn(i64, i64) -> ()>,
+        input: impl InputSource + 'static,
+        output: impl OutputSink + 'static,
     ) -> Computer {
         Computer {
             id,
             context: ExecutionContext::new(memory),
-            input,
-            output,
+            input: Box::new(input),
+            output: Box::new(output),
+            deadline: None,
+            instructions_until_check: DEADLINE_CHECK_INTERVAL,
+            timed_out: false,
+            instruction_budget: None,
+            executed: 0,
+            pending_input: None,
+            empty_input: EmptyInput::Block,
         }
     }
 
@@ -26,129 +64,314 @@ impl<'a> Computer {
         self.id
     }
 
-    fn read_input(&mut self) -> Option<i64> {
+    /// Sets the wall-clock time after which the program stops executing instructions and
+    /// reports `ExecutionResult::TimedOut` instead. The deadline is only checked every
+    /// `DEADLINE_CHECK_INTERVAL` instructions.
+    pub fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.deadline = deadline;
+        self.instructions_until_check = 0;
+        self.timed_out = false;
+    }
+
+    /// Sets the number of instructions the program may execute from now on, after which
+    /// it reports `ExecutionResult::OutOfBudget`
+    pub fn set_instruction_budget(&mut self, budget: Option<u64>) {
+        self.instruction_budget = budget;
+        self.executed = 0;
+    }
+
+    /// Sets what happens when the program reads an input that the input source doesn't
+    /// have. With `EmptyInput::Default` or `EmptyInput::Error`, `execute_async` never awaits
+    /// its provider.
+    pub fn set_empty_input(&mut self, empty_input: EmptyInput) {
+        self.empty_input = empty_input;
+    }
+
+    fn is_past_deadline(&mut self) -> bool {
+        if self.timed_out {
+            return true;
+        }
+        if let Some(deadline) = self.deadline {
+            if self.instructions_until_check == 0 {
+                self.instructions_until_check = DEADLINE_CHECK_INTERVAL;
+                self.timed_out = Instant::now() >= deadline;
+            }
+            self.instructions_until_check -= 1;
+        }
+        self.timed_out
+    }
+
+    fn read_input(&mut self) -> Option<Value> {
         //println!("Computer {} is reading its input", self.id);
-        let read = (*self.input)();
+        let read = self.pending_input.take().or_else(|| self.input.read());
         //println!("Computer {} read result: {:?}", self.id, read);
-        read.or(Some(-1))
-    }
-    fn write_output(&mut self, addr: i64, value: i64) {
-        //println!(
-        //    "Computer {} is writing {} to address {}",
-        //    self.id, value, addr
-        //);
-        (*self.output)(addr, value);
-    }
-
-    // pub fn execute(&mut self) -> ExecutionResult {
-    //     //println!("Executing program; ip: {}", self.context.ip);
-    //     let result = loop {
-    //         match self.execute_single_instruction() {
-    //             ExecutionResult::Executed => (),
-    //             x => break x,
-    //         };
-    //     };
-
-    //     //println!("Result: {:?}", result);
-    //     result
-    // }
-
-    pub fn execute_single_instruction(&mut self) -> ExecutionResult {
-        match self.context.read_op_code() {
+        read
+    }
+    fn write_output(&mut self, value: Value) {
+        //println!("Computer {} is writing {}", self.id, value);
+        self.output.write(value);
+    }
+
+    /// Runs the program until it outputs a value, and returns it. Returns None if the
+    /// program exits or runs out of input before that.
+    pub fn run_until_output(&mut self) -> Result<Option<Value>, IntcodeError> {
+        loop {
+            match self.execute_single_instruction()? {
+                ExecutionResult::Executed => (),
+                ExecutionResult::Output(value) => return Ok(Some(value)),
+                ExecutionResult::MoreInputNeeded
+                | ExecutionResult::Exit
+                | ExecutionResult::TimedOut
+                | ExecutionResult::OutOfBudget => return Ok(None),
+            }
+        }
+    }
+
+    pub fn relative_base(&self) -> Value {
+        self.context.relative_base
+    }
+
+    pub fn memory(&self) -> &Memory {
+        &self.context.memory
+    }
+
+    /// Decodes the instruction at the instruction pointer, without executing it. Returns
+    /// None if the program has exited, or the op code or a parameter mode is invalid.
+    pub fn decode(&self) -> Option<Instruction> {
+        if self.context.ended {
+            return None;
+        }
+        Instruction::decode(&self.context.memory, self.context.ip)
+    }
+
+    /// Executes a single instruction, and returns what it did: the instruction, the memory
+    /// cells it read and wrote, and its result. Nothing is allocated, so external tools
+    /// can follow a program one instruction at a time at little cost.
+    pub fn step(&mut self) -> Result<StepInfo<'_>, IntcodeError> {
+        let ended = self.context.ended;
+        let result = self.execute_single_instruction()?;
+        let executed = !ended
+            && matches!(
+                result,
+                ExecutionResult::Executed | ExecutionResult::Output(_) | ExecutionResult::Exit
+            );
+        let accesses = &self.context.accesses;
+        Ok(if executed {
+            StepInfo {
+                instruction: accesses.op_code.map(|op| (self.context.instruction_ip, op)),
+                reads: &accesses.reads[..accesses.read_count],
+                write: accesses.write,
+                result,
+            }
+        } else {
+            StepInfo {
+                instruction: None,
+                reads: &[],
+                write: None,
+                result,
+            }
+        })
+    }
+
+    /// Executes the instruction at the instruction pointer. If it's invalid, the computer
+    /// is left as it was before the instruction, and the error is returned.
+    pub fn execute_single_instruction(&mut self) -> Result<ExecutionResult, IntcodeError> {
+        if self.context.ended {
+            return Ok(ExecutionResult::Exit);
+        }
+        if self.is_past_deadline() {
+            return Ok(ExecutionResult::TimedOut);
+        }
+        if matches!(self.instruction_budget, Some(budget) if self.executed >= budget) {
+            return Ok(ExecutionResult::OutOfBudget);
+        }
+
+        let ip = self.context.ip;
+        let result = self.execute_instruction();
+        match result {
+            Err(_) => self.context.ip = ip,
+            Ok(ExecutionResult::MoreInputNeeded) => (),
+            Ok(_) => self.executed += 1,
+        }
+        result
+    }
+
+    fn execute_instruction(&mut self) -> Result<ExecutionResult, IntcodeError> {
+        match self.context.read_op_code()? {
             (OpCode::Add, parameter_modes) => {
-                let (a, b, c) = self.context.extract_parameters3(parameter_modes);
-                c.set(
-                    a.get(&mut self.context) + b.get(&mut self.context),
-                    &mut self.context,
-                );
+                let (a, b, c) = self.context.extract_parameters3(parameter_modes)?;
+                let sum = a
+                    .get(&mut self.context)
+                    .checked_add(b.get(&mut self.context));
+                c.set(self.context.check_overflow(sum)?, &mut self.context)?;
             }
             (OpCode::Mult, parameter_modes) => {
-                let (a, b, c) = self.context.extract_parameters3(parameter_modes);
-                c.set(
-                    a.get(&self.context) * b.get(&self.context),
-                    &mut self.context,
-                );
+                let (a, b, c) = self.context.extract_parameters3(parameter_modes)?;
+                let product = a
+                    .get(&mut self.context)
+                    .checked_mul(b.get(&mut self.context));
+                c.set(self.context.check_overflow(product)?, &mut self.context)?;
             }
             (OpCode::Input, parameter_modes) => {
-                match self.read_input() {
-                    Some(value) => {
+                // Decode the parameter first, so no input is lost if it's invalid
+                let a = self.context.extract_parameter(parameter_modes)?;
+                a.check_writable(&self.context)?;
+                match (self.read_input(), self.empty_input) {
+                    (Some(value), _) | (None, EmptyInput::Default(value)) => {
                         // println!("Reading input {}", value);
-                        let a = self.context.extract_parameter(parameter_modes);
-                        a.set(value, &mut self.context);
+                        a.set(value, &mut self.context)?;
                     }
-                    None => {
+                    (None, EmptyInput::Block) => {
                         //println!("Halting program due to input read; ip: {}", context.ip);
-                        // Revert the reading of the op-code, so we can read it again when the
-                        // thread is resumed
-                        self.context.ip -= 1;
-                        return ExecutionResult::MoreInputNeeded;
+                        // Revert the reading of the instruction, so we can read it again when
+                        // the thread is resumed
+                        self.context.ip = self.context.instruction_ip;
+                        return Ok(ExecutionResult::MoreInputNeeded);
+                    }
+                    (None, EmptyInput::Error) => {
+                        return Err(IntcodeError::InputExhausted {
+                            ip: self.context.instruction_ip,
+                            instruction: self.context.memory[self.context.instruction_ip],
+                        })
                     }
                 }
             }
             (OpCode::Output, parameter_modes) => {
-                let a = self.context.extract_parameter(parameter_modes);
-                let output = a.get(&self.context);
-                let pending_outputs = &mut self.context.pending_outputs;
-                pending_outputs.push(output);
-                if pending_outputs.len() >= 3 {
-                    let addr = pending_outputs[0];
-                    let x = pending_outputs[1];
-                    let y = pending_outputs[2];
-                    pending_outputs.clear();
-
-                    self.write_output(addr, x);
-                    self.write_output(addr, y);
-                }
+                let a = self.context.extract_parameter(parameter_modes)?;
+                let output = a.get(&mut self.context);
+                self.write_output(output);
+                return Ok(ExecutionResult::Output(output));
             }
             (OpCode::JumpIfTrue, parameter_modes) => {
-                let (a, b) = self.context.extract_parameters2(parameter_modes);
-                if a.get(&self.context) != 0 {
-                    let address = b.get(&self.context);
-                    self.context.jump_to(address);
+                let (a, b) = self.context.extract_parameters2(parameter_modes)?;
+                if a.get(&mut self.context) != 0 {
+                    let address = b.get(&mut self.context);
+                    self.context.jump_to(address)?;
                 }
             }
             (OpCode::JumpIfFalse, parameter_modes) => {
-                let (a, b) = self.context.extract_parameters2(parameter_modes);
-                if a.get(&self.context) == 0 {
-                    let address = b.get(&self.context);
-                    self.context.jump_to(address);
+                let (a, b) = self.context.extract_parameters2(parameter_modes)?;
+                if a.get(&mut self.context) == 0 {
+                    let address = b.get(&mut self.context);
+                    self.context.jump_to(address)?;
                 }
             }
             (OpCode::LessThan, parameter_modes) => {
-                let (a, b, c) = self.context.extract_parameters3(parameter_modes);
-                let value = if a.get(&self.context) < b.get(&self.context) {
+                let (a, b, c) = self.context.extract_parameters3(parameter_modes)?;
+                let value = if a.get(&mut self.context) < b.get(&mut self.context) {
                     1
                 } else {
                     0
                 };
-                c.set(value, &mut self.context);
+                c.set(value, &mut self.context)?;
             }
             (OpCode::Equals, parameter_modes) => {
-                let (a, b, c) = self.context.extract_parameters3(parameter_modes);
-                let value = if a.get(&self.context) == b.get(&self.context) {
+                let (a, b, c) = self.context.extract_parameters3(parameter_modes)?;
+                let value = if a.get(&mut self.context) == b.get(&mut self.context) {
                     1
                 } else {
                     0
                 };
-                c.set(value, &mut self.context);
+                c.set(value, &mut self.context)?;
             }
             (OpCode::AdjustRelativeBase, parameter_modes) => {
-                let a = self.context.extract_parameter(parameter_modes);
-                let adjustment = a.get(&self.context);
-                self.context.relative_base =
-                    (self.context.relative_base as i64 + adjustment) as usize;
+                let a = self.context.extract_parameter(parameter_modes)?;
+                let adjustment = a.get(&mut self.context);
+                let context = &mut self.context;
+                context.relative_base = context.relative_base.checked_add(adjustment).ok_or(
+                    IntcodeError::AddressOverflow {
+                        ip: context.instruction_ip,
+                        instruction: context.memory[context.instruction_ip],
+                    },
+                )?;
             }
             (OpCode::Exit, _) => {
                 self.context.ended = true;
-                return ExecutionResult::Exit;
+                return Ok(ExecutionResult::Exit);
             }
         };
 
-        return ExecutionResult::Executed;
+        Ok(ExecutionResult::Executed)
     }
 }
 
-enum OpCode {
+/// Ways to run a computer on its own, as `--run` does, and to see where it stopped. The
+/// network steps its computers one instruction at a time instead.
+impl Computer {
+    /// Runs the program until it needs an input that isn't available yet, exits or times out.
+    /// Outputs are still sent to the output handler along the way.
+    pub fn run_until_input_needed(&mut self) -> Result<ExecutionResult, IntcodeError> {
+        loop {
+            match self.execute_single_instruction()? {
+                ExecutionResult::Executed | ExecutionResult::Output(_) => (),
+                x => return Ok(x),
+            }
+        }
+    }
+
+    /// Runs the program until it exits, times out or fails. Whenever it needs an input that
+    /// the input source doesn't have, the value returned by `provider` is awaited; the
+    /// execution stops with `MoreInputNeeded` if it resolves to None.
+    pub async fn execute_async<F, Fut>(
+        &mut self,
+        mut provider: F,
+    ) -> Result<ExecutionResult, IntcodeError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Option<Value>>,
+    {
+        loop {
+            match self.run_until_input_needed()? {
+                ExecutionResult::MoreInputNeeded => match provider().await {
+                    Some(value) => self.pending_input = Some(value),
+                    None => return Ok(ExecutionResult::MoreInputNeeded),
+                },
+                result => return Ok(result),
+            }
+        }
+    }
+
+    pub fn ip(&self) -> usize {
+        self.context.ip
+    }
+}
+
+/// Shortcuts for the tests driving a computer by hand
+#[cfg(test)]
+impl Computer {
+    /// Computer reading its input from `rx`, and sending its output values to `tx`
+    pub fn with_channels(
+        id: usize,
+        memory: Memory,
+        rx: Receiver<Value>,
+        tx: Sender<Value>,
+    ) -> Computer {
+        Computer::new(id, memory, rx, tx)
+    }
+
+    /// Lazily runs the program, yielding each of its outputs. The iteration stops when
+    /// the program exits, runs out of input, times out or fails.
+    pub fn outputs(&mut self) -> impl Iterator<Item = Value> + '_ {
+        std::iter::from_fn(move || self.run_until_output().ok().flatten())
+    }
+}
+
+/// Polls the future until it completes; enough for futures that don't depend on a reactor,
+/// such as `execute_async` with a provider reading the standard input
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    use std::task::{Context, Poll, Waker};
+
+    let mut future = Box::pin(future);
+    let mut context = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(value) = future.as_mut().poll(&mut context) {
+            return value;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpCode {
     Add,
     Mult,
     Exit,
@@ -161,135 +384,794 @@ enum OpCode {
     AdjustRelativeBase,
 }
 
+impl OpCode {
+    fn from_value(value: Value) -> Option<OpCode> {
+        Some(match value {
+            1 => OpCode::Add,
+            2 => OpCode::Mult,
+            3 => OpCode::Input,
+            4 => OpCode::Output,
+            5 => OpCode::JumpIfTrue,
+            6 => OpCode::JumpIfFalse,
+            7 => OpCode::LessThan,
+            8 => OpCode::Equals,
+            9 => OpCode::AdjustRelativeBase,
+            99 => OpCode::Exit,
+            _ => return None,
+        })
+    }
+
+    /// Value of the op code in an instruction, without the parameter modes
+    pub fn value(self) -> Value {
+        match self {
+            OpCode::Add => 1,
+            OpCode::Mult => 2,
+            OpCode::Input => 3,
+            OpCode::Output => 4,
+            OpCode::JumpIfTrue => 5,
+            OpCode::JumpIfFalse => 6,
+            OpCode::LessThan => 7,
+            OpCode::Equals => 8,
+            OpCode::AdjustRelativeBase => 9,
+            OpCode::Exit => 99,
+        }
+    }
+
+    /// Whether the instruction writes to the address given by its last parameter
+    pub fn writes(self) -> bool {
+        matches!(
+            self,
+            OpCode::Add | OpCode::Mult | OpCode::LessThan | OpCode::Equals | OpCode::Input
+        )
+    }
+
+    pub fn parameter_count(self) -> usize {
+        match self {
+            OpCode::Exit => 0,
+            OpCode::Input | OpCode::Output | OpCode::AdjustRelativeBase => 1,
+            OpCode::JumpIfTrue | OpCode::JumpIfFalse => 2,
+            OpCode::Add | OpCode::Mult | OpCode::LessThan | OpCode::Equals => 3,
+        }
+    }
+}
+
+/// What `Computer::step` did, borrowed from the computer until the next step
+pub struct StepInfo<'a> {
+    /// Address and op code of the instruction; None if nothing was executed
+    pub instruction: Option<(usize, OpCode)>,
+    /// Addresses of the memory cells read by the parameters, in order
+    pub reads: &'a [usize],
+    /// Address of the memory cell written, and the value written to it
+    pub write: Option<(usize, Value)>,
+    pub result: ExecutionResult,
+}
+
+impl StepInfo<'_> {
+    pub fn output(&self) -> Option<Value> {
+        match self.result {
+            ExecutionResult::Output(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// An instruction as found in memory, before it's executed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction {
+    pub address: usize,
+    pub op_code: OpCode,
+    pub parameters: Vec<(ParameterMode, Value)>,
+}
+
+impl Instruction {
+    /// Decodes the instruction at `address`. Returns None if the op code or a parameter
+    /// mode is invalid.
+    pub fn decode(memory: &Memory, address: usize) -> Option<Instruction> {
+        let op_code = OpCode::from_value(memory[address] % 100)?;
+        let mut modes = memory[address] / 100;
+        let mut parameters = vec![];
+        for i in 0..op_code.parameter_count() {
+            let mode = match modes % 10 {
+                0 => ParameterMode::Position,
+                1 => ParameterMode::Immediate,
+                2 => ParameterMode::Relative,
+                _ => return None,
+            };
+            modes /= 10;
+            parameters.push((mode, memory[address + 1 + i]));
+        }
+        Some(Instruction {
+            address,
+            op_code,
+            parameters,
+        })
+    }
+}
+
+impl std::fmt::Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:>5}: {:?}", self.address, self.op_code)?;
+        for (i, (mode, value)) in self.parameters.iter().enumerate() {
+            let separator = if i == 0 { " " } else { ", " };
+            match mode {
+                ParameterMode::Position => write!(f, "{}[{}]", separator, value)?,
+                ParameterMode::Immediate => write!(f, "{}{}", separator, value)?,
+                ParameterMode::Relative => write!(f, "{}[rb{:+}]", separator, value)?,
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(Clone)]
 struct ExecutionContext {
     ip: usize,
+    /// Address of the instruction being executed
+    instruction_ip: usize,
     memory: Memory,
     ended: bool,
-    relative_base: usize,
-    input: Vec<i64>,
-    input_index: usize,
-    output: i64,
-    pending_outputs: Vec<i64>,
+    relative_base: Value,
+    accesses: Accesses,
+}
+
+/// Op code and memory accesses of the instruction being executed, for `Computer::step`
+#[derive(Clone, Copy, Default)]
+struct Accesses {
+    op_code: Option<OpCode>,
+    /// No instruction reads more than 2 memory cells
+    reads: [usize; 2],
+    read_count: usize,
+    write: Option<(usize, Value)>,
 }
 
 impl ExecutionContext {
     fn new(memory: Memory) -> ExecutionContext {
         ExecutionContext {
             ip: 0,
+            instruction_ip: 0,
             memory,
             ended: false,
             relative_base: 0,
-            output: 0,
-            input_index: 0,
-            input: vec![],
-            pending_outputs: vec![],
+            accesses: Accesses::default(),
         }
     }
 
-    fn jump_to(&mut self, address: i64) {
-        self.ip = address as usize;
+    fn jump_to(&mut self, address: Value) -> Result<(), IntcodeError> {
+        self.ip = self.to_address(Some(address))?;
+        Ok(())
     }
 
-    fn read_op_code(&mut self) -> (OpCode, u32) {
+    /// Memory address designated by a value, which can't be negative; None if computing it
+    /// overflowed
+    fn to_address(&self, value: Option<Value>) -> Result<usize, IntcodeError> {
+        match value {
+            Some(address) if address >= 0 => Ok(address as usize),
+            Some(address) => Err(IntcodeError::NegativeAddress {
+                ip: self.instruction_ip,
+                instruction: self.memory[self.instruction_ip],
+                address,
+            }),
+            None => Err(IntcodeError::AddressOverflow {
+                ip: self.instruction_ip,
+                instruction: self.memory[self.instruction_ip],
+            }),
+        }
+    }
+
+    fn read_op_code(&mut self) -> Result<(OpCode, u32), IntcodeError> {
+        self.instruction_ip = self.ip;
+        self.accesses = Accesses::default();
         let value = self.memory[self.ip];
         let op_code_value = value % 100;
         let parameter_modes = (value / 100) as u32;
 
-        let op_code = match op_code_value {
-            1 => OpCode::Add,
-            2 => OpCode::Mult,
-            3 => OpCode::Input,
-            4 => OpCode::Output,
-            5 => OpCode::JumpIfTrue,
-            6 => OpCode::JumpIfFalse,
-            7 => OpCode::LessThan,
-            8 => OpCode::Equals,
-            9 => OpCode::AdjustRelativeBase,
-            99 => OpCode::Exit,
-            x => panic!("Unknown op code: {}; ip: {}", x, self.ip),
-        };
+        let op_code = OpCode::from_value(op_code_value).ok_or(IntcodeError::UnknownOpCode {
+            ip: self.ip,
+            instruction: value,
+        })?;
 
         self.ip += 1;
-        (op_code, parameter_modes)
+        self.accesses.op_code = Some(op_code);
+        Ok((op_code, parameter_modes))
     }
-    fn extract_parameter(&mut self, parameter_modes: u32) -> Parameter {
+    fn extract_parameter(&mut self, parameter_modes: u32) -> Result<Parameter, IntcodeError> {
         let mut param_modes = parameter_modes;
         self.get_parameter(&mut param_modes)
     }
 
-    fn extract_parameters2(&mut self, parameter_modes: u32) -> (Parameter, Parameter) {
+    /// Result of an arithmetic instruction, or an error if it overflowed
+    fn check_overflow(&self, result: Option<Value>) -> Result<Value, IntcodeError> {
+        result.ok_or(IntcodeError::Overflow {
+            ip: self.instruction_ip,
+            instruction: self.memory[self.instruction_ip],
+        })
+    }
+
+    fn extract_parameters2(
+        &mut self,
+        parameter_modes: u32,
+    ) -> Result<(Parameter, Parameter), IntcodeError> {
         let mut param_modes = parameter_modes;
-        let x = self.get_parameter(&mut param_modes);
-        let y = self.get_parameter(&mut param_modes);
-        (x, y)
+        let x = self.get_parameter(&mut param_modes)?;
+        let y = self.get_parameter(&mut param_modes)?;
+        Ok((x, y))
     }
 
-    fn extract_parameters3(&mut self, parameter_modes: u32) -> (Parameter, Parameter, Parameter) {
+    fn extract_parameters3(
+        &mut self,
+        parameter_modes: u32,
+    ) -> Result<(Parameter, Parameter, Parameter), IntcodeError> {
         let mut param_modes = parameter_modes;
-        let x = self.get_parameter(&mut param_modes);
-        let y = self.get_parameter(&mut param_modes);
-        let z = self.get_parameter(&mut param_modes);
-        (x, y, z)
+        let x = self.get_parameter(&mut param_modes)?;
+        let y = self.get_parameter(&mut param_modes)?;
+        let z = self.get_parameter(&mut param_modes)?;
+        Ok((x, y, z))
     }
 
-    fn get_parameter(&mut self, parameter_modes: &mut u32) -> Parameter {
+    fn get_parameter(&mut self, parameter_modes: &mut u32) -> Result<Parameter, IntcodeError> {
         // Get the parameter mode for this parameter
         let parameter_mode = match *parameter_modes % 10 {
             0 => ParameterMode::Position,
             1 => ParameterMode::Immediate,
             2 => ParameterMode::Relative,
-            x => panic!(format!("Incorrect parameter mode: {}", x)),
+            _ => {
+                return Err(IntcodeError::InvalidParameterMode {
+                    ip: self.instruction_ip,
+                    instruction: self.memory[self.instruction_ip],
+                })
+            }
         };
         *parameter_modes /= 10;
 
         let parameter_value = self.memory[self.ip];
         self.ip += 1;
 
-        match parameter_mode {
-            ParameterMode::Position => Parameter::Reference(parameter_value as usize),
+        Ok(match parameter_mode {
+            ParameterMode::Position => {
+                Parameter::Reference(self.to_address(Some(parameter_value))?)
+            }
             ParameterMode::Immediate => Parameter::ImmediateValue(parameter_value),
             ParameterMode::Relative => {
-                let address = (parameter_value + self.relative_base as i64) as usize;
-                Parameter::Reference(address)
+                let address = parameter_value.checked_add(self.relative_base);
+                Parameter::Reference(self.to_address(address)?)
             }
-        }
+        })
     }
 }
 
 #[derive(Debug, Eq, PartialEq)]
 pub enum ExecutionResult {
     Executed,
+    Output(Value),
     MoreInputNeeded,
     Exit,
+    TimedOut,
+    OutOfBudget,
+}
+
+/// Invalid instruction found while executing a program; `ip` is the address of the
+/// instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IntcodeError {
+    UnknownOpCode {
+        ip: usize,
+        instruction: Value,
+    },
+    InvalidParameterMode {
+        ip: usize,
+        instruction: Value,
+    },
+    WriteToImmediate {
+        ip: usize,
+        instruction: Value,
+    },
+    /// The result of an addition or multiplication doesn't fit in a memory cell
+    Overflow {
+        ip: usize,
+        instruction: Value,
+    },
+    /// The program reads an input that isn't available, with `EmptyInput::Error`
+    InputExhausted {
+        ip: usize,
+        instruction: Value,
+    },
+    /// A parameter or a jump designates a negative address
+    NegativeAddress {
+        ip: usize,
+        instruction: Value,
+        address: Value,
+    },
+    /// The relative base, or a relative address, is beyond the range of a memory cell
+    AddressOverflow {
+        ip: usize,
+        instruction: Value,
+    },
+    /// A write beyond `DENSE_LIMIT` to a memory using only the dense backend
+    BeyondDenseLimit {
+        ip: usize,
+        instruction: Value,
+        address: usize,
+    },
+}
+
+impl std::fmt::Display for IntcodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            IntcodeError::UnknownOpCode { ip, instruction } => {
+                write!(f, "Unknown op code in {} at ip {}", instruction, ip)
+            }
+            IntcodeError::InvalidParameterMode { ip, instruction } => {
+                write!(f, "Invalid parameter mode in {} at ip {}", instruction, ip)
+            }
+            IntcodeError::WriteToImmediate { ip, instruction } => write!(
+                f,
+                "Write to an immediate parameter in {} at ip {}",
+                instruction, ip
+            ),
+            IntcodeError::Overflow { ip, instruction } => {
+                write!(f, "Overflow in {} at ip {}", instruction, ip)
+            }
+            IntcodeError::InputExhausted { ip, instruction } => {
+                write!(f, "No input left for {} at ip {}", instruction, ip)
+            }
+            IntcodeError::NegativeAddress {
+                ip,
+                instruction,
+                address,
+            } => write!(
+                f,
+                "Negative address {} in {} at ip {}",
+                address, instruction, ip
+            ),
+            IntcodeError::AddressOverflow { ip, instruction } => {
+                write!(f, "Address overflow in {} at ip {}", instruction, ip)
+            }
+            IntcodeError::BeyondDenseLimit {
+                ip,
+                instruction,
+                address,
+            } => write!(
+                f,
+                "Write to {}, beyond the dense memory limit, in {} at ip {}",
+                address, instruction, ip
+            ),
+        }
+    }
 }
 
+impl std::error::Error for IntcodeError {}
+
 enum Parameter {
-    ImmediateValue(i64),
+    ImmediateValue(Value),
     Reference(usize),
 }
 
-impl<'a> Parameter {
-    fn get(&self, context: &ExecutionContext) -> i64 {
+impl Parameter {
+    fn get(&self, context: &mut ExecutionContext) -> Value {
         match self {
-            Parameter::Reference(address) => context.memory[*address],
+            Parameter::Reference(address) => {
+                let accesses = &mut context.accesses;
+                accesses.reads[accesses.read_count] = *address;
+                accesses.read_count += 1;
+                context.memory[*address]
+            }
             Parameter::ImmediateValue(value) => *value,
         }
     }
 
-    fn set(&self, value: i64, context: &mut ExecutionContext) -> () {
+    fn check_writable(&self, context: &ExecutionContext) -> Result<(), IntcodeError> {
         match self {
-            Parameter::Reference(address) => context.memory[*address] = value,
-            Parameter::ImmediateValue(value) => panic!(format!(
-                "Attempted to write value {} to an immediate parameter",
-                value
-            )),
+            Parameter::Reference(address) if !context.memory.can_write(*address) => {
+                Err(IntcodeError::BeyondDenseLimit {
+                    ip: context.instruction_ip,
+                    instruction: context.memory[context.instruction_ip],
+                    address: *address,
+                })
+            }
+            Parameter::Reference(_) => Ok(()),
+            Parameter::ImmediateValue(_) => Err(IntcodeError::WriteToImmediate {
+                ip: context.instruction_ip,
+                instruction: context.memory[context.instruction_ip],
+            }),
+        }
+    }
+
+    fn set(&self, value: Value, context: &mut ExecutionContext) -> Result<(), IntcodeError> {
+        self.check_writable(context)?;
+        if let Parameter::Reference(address) = self {
+            context.memory[*address] = value;
+            context.accesses.write = Some((*address, value));
         }
+        Ok(())
     }
 }
 
-enum ParameterMode {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParameterMode {
     Position,
     Immediate,
     Relative,
 }
+
+#[test]
+fn run_until_output_and_input() {
+    use std::collections::VecDeque;
+
+    let inputs: VecDeque<Value> = vec![42].into_iter().collect();
+    let mut computer = Computer::new(0, Memory::parse("3,0,4,0,3,0,99"), inputs, ());
+
+    assert_eq!(Ok(Some(42)), computer.run_until_output());
+    assert_eq!(Ok(None), computer.run_until_output());
+    assert_eq!(
+        Ok(ExecutionResult::MoreInputNeeded),
+        computer.run_until_input_needed()
+    );
+}
+
+#[test]
+fn outputs_iterator() {
+    let mut computer = Computer::new(0, Memory::parse("104,1,104,2,104,3,99"), || None, ());
+
+    assert_eq!(vec![1, 2, 3], computer.outputs().collect::<Vec<_>>());
+    assert_eq!(None, computer.outputs().next());
+}
+
+#[test]
+fn packets_written_to_output_sink() {
+    use crate::io::Chunks;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    let written = Rc::new(RefCell::new(vec![]));
+    let sink = written.clone();
+    let mut computer = Computer::new(
+        0,
+        Memory::parse("104,5,104,6,104,7,99"),
+        || None,
+        Chunks::new(move |packet: [Value; 3]| sink.borrow_mut().push(packet)),
+    );
+
+    assert_eq!(Ok(ExecutionResult::Exit), computer.run_until_input_needed());
+    assert_eq!(vec![[5, 6, 7]], *written.borrow());
+}
+
+#[test]
+fn channel_io() {
+    use std::sync::mpsc::channel;
+
+    let (input, rx) = channel();
+    let (tx, written) = channel();
+    let mut computer =
+        Computer::with_channels(0, Memory::parse("3,0,4,0,3,0,4,0,3,0,4,0,99"), rx, tx);
+
+    input.send(2).unwrap();
+    assert_eq!(
+        Ok(ExecutionResult::MoreInputNeeded),
+        computer.run_until_input_needed()
+    );
+    input.send(3).unwrap();
+    input.send(4).unwrap();
+    assert_eq!(Ok(ExecutionResult::Exit), computer.run_until_input_needed());
+    assert_eq!(vec![2, 3, 4], written.try_iter().collect::<Vec<_>>());
+}
+
+#[test]
+fn overflow_is_an_error() {
+    let max = Value::MAX.to_string();
+    for program in &[
+        format!("1101,{},1,7,99,0,0,0", max),
+        format!("1102,{},2,7,99,0,0,0", max),
+    ] {
+        let mut computer = Computer::new(0, Memory::parse(program), || None, ());
+        assert!(matches!(
+            computer.run_until_input_needed(),
+            Err(IntcodeError::Overflow { ip: 0, .. })
+        ));
+        assert_eq!(0, computer.ip());
+    }
+}
+
+#[test]
+fn address_overflow_is_an_error() {
+    let max = Value::MAX.to_string();
+    for (program, ip) in &[
+        // The relative base overflows
+        (format!("109,{},109,1,99", max), 2),
+        // The relative address overflows
+        (format!("109,{},1201,1,0,0,99", max), 2),
+    ] {
+        let mut computer = Computer::new(0, Memory::parse(program), || None, ());
+        let result = computer.run_until_input_needed();
+        assert!(
+            matches!(result, Err(IntcodeError::AddressOverflow { ip: i, .. }) if i == *ip),
+            "{}: {:?}",
+            program,
+            result
+        );
+        assert_eq!(*ip, computer.ip());
+    }
+}
+
+#[test]
+fn negative_address_is_an_error() {
+    for (program, address) in &[
+        // Jumps to -3
+        ("1105,1,-3", -3),
+        // Reads from address -1
+        ("4,-1,99", -1),
+        // Writes to rb-5, with a relative base of 0
+        ("21101,1,1,-5,99", -5),
+    ] {
+        let mut computer = Computer::new(0, Memory::parse(program), || None, ());
+        let result = computer.run_until_input_needed();
+        assert!(
+            matches!(
+                result,
+                Err(IntcodeError::NegativeAddress { ip: 0, address: a, .. }) if a == *address
+            ),
+            "{}: {:?}",
+            program,
+            result
+        );
+        assert_eq!(0, computer.ip());
+    }
+}
+
+#[test]
+fn write_beyond_dense_limit_is_an_error() {
+    use crate::memory::{BackendKind, DENSE_LIMIT};
+
+    // Writes 2 to 100000, then to 65536
+    let program = "1101,1,1,100000,1101,1,1,65536,99";
+    let mut memory = Memory::parse(program);
+    memory.set_backend(BackendKind::Dense);
+    let mut computer = Computer::new(0, memory, || None, ());
+    assert_eq!(
+        Err(IntcodeError::BeyondDenseLimit {
+            ip: 0,
+            instruction: 1101,
+            address: 100_000
+        }),
+        computer.run_until_input_needed()
+    );
+    assert_eq!(0, computer.ip());
+
+    let mut computer = Computer::new(0, Memory::parse(program), || None, ());
+    assert_eq!(Ok(ExecutionResult::Exit), computer.run_until_input_needed());
+    assert_eq!(2, computer.memory()[100_000]);
+    assert_eq!(2, computer.memory()[DENSE_LIMIT]);
+}
+
+#[test]
+fn step_through_program() {
+    // Adds 2 and 3 into address 7, outputs it, and exits
+    let mut computer = Computer::new(0, Memory::parse("1101,2,3,7,4,7,99,0"), || None, ());
+
+    assert_eq!(
+        "    0: Add 2, 3, [7]",
+        computer.decode().unwrap().to_string()
+    );
+    let step = computer.step().unwrap();
+    assert_eq!(ExecutionResult::Executed, step.result);
+    assert_eq!(Some((0, OpCode::Add)), step.instruction);
+    assert_eq!((&[][..], Some((7, 5))), (step.reads, step.write));
+    assert_eq!(
+        (4, 0, 5),
+        (
+            computer.ip(),
+            computer.relative_base(),
+            computer.memory()[7]
+        )
+    );
+
+    assert_eq!(Some(5), computer.step().unwrap().output());
+    let step = computer.step().unwrap();
+    assert_eq!(ExecutionResult::Exit, step.result);
+    assert_eq!(Some((6, OpCode::Exit)), step.instruction);
+    let step = computer.step().unwrap();
+    assert_eq!(
+        (None, ExecutionResult::Exit),
+        (step.instruction, step.result)
+    );
+}
+
+#[test]
+fn steps_report_memory_accesses() {
+    // With rb = 9, adds [rb+0] and [rb+1] into [rb+10], outputs [10], then waits for an
+    // input to store in [9]
+    let program = "109,9,22201,0,1,10,4,10,3,9,99";
+    let mut computer = Computer::new(0, Memory::parse(program), || None, ());
+
+    let step = computer.step().unwrap();
+    assert_eq!(Some((0, OpCode::AdjustRelativeBase)), step.instruction);
+    assert_eq!((&[][..], None), (step.reads, step.write));
+
+    let step = computer.step().unwrap();
+    assert_eq!(&[9, 10], step.reads);
+    assert_eq!(Some((19, 9 + 99)), step.write);
+
+    let step = computer.step().unwrap();
+    assert_eq!((&[10][..], None), (step.reads, step.write));
+    assert_eq!(Some(99), step.output());
+
+    // Nothing is executed while the input is missing
+    let step = computer.step().unwrap();
+    assert_eq!(ExecutionResult::MoreInputNeeded, step.result);
+    assert_eq!(
+        (None, &[][..], None),
+        (step.instruction, step.reads, step.write)
+    );
+}
+
+/// Runs `program` to completion both by stepping through decoded instructions and with
+/// the plain interpreter, and checks that they leave the same memory behind
+#[cfg(test)]
+fn run_stepped_and_plain(program: &str) -> Memory {
+    let mut stepped = Computer::new(0, Memory::parse(program), || None, ());
+    while stepped.step().unwrap().result != ExecutionResult::Exit {}
+
+    let mut plain = Computer::new(0, Memory::parse(program), || None, ());
+    assert_eq!(Ok(ExecutionResult::Exit), plain.run_until_input_needed());
+
+    assert_eq!(
+        Vec::<(usize, Value, Value)>::new(),
+        stepped.memory().diff(plain.memory())
+    );
+    assert_eq!(stepped.ip(), plain.ip());
+    plain.memory().clone()
+}
+
+#[test]
+fn store_into_next_instruction() {
+    // Day 2 example: the first Add turns the 99 at address 4 into a Mul
+    let memory = run_stepped_and_plain("1,1,1,4,99,5,6,0,99");
+    assert_eq!(30, memory[0]);
+    assert_eq!(2, memory[4]);
+
+    // The next instruction is decoded from its new content, not the one at load time
+    let mut computer = Computer::new(0, Memory::parse("1101,100,-1,4,104,7,99"), || None, ());
+    computer.step().unwrap();
+    assert_eq!(OpCode::Exit, computer.decode().unwrap().op_code);
+    assert_eq!(Ok(None), computer.run_until_output());
+    run_stepped_and_plain("1101,100,-1,4,104,7,99");
+}
+
+#[test]
+fn store_into_current_instruction() {
+    // The Add overwrites its own second parameter, after having read it
+    let mut computer = Computer::new(0, Memory::parse("1,5,0,2,99,7"), || None, ());
+    assert_eq!(
+        "    0: Add [5], [0], [2]",
+        computer.decode().unwrap().to_string()
+    );
+    assert_eq!(Some((2, 8)), computer.step().unwrap().write);
+    assert_eq!((4, 8), (computer.ip(), computer.memory()[2]));
+    assert_eq!(8, run_stepped_and_plain("1,5,0,2,99,7")[2]);
+
+    // The Add overwrites its own op code with an Exit, which runs once jumped back to
+    let memory = run_stepped_and_plain("1101,98,1,0,1105,1,0");
+    assert_eq!(99, memory[0]);
+}
+
+#[test]
+fn invalid_instructions_are_errors() {
+    use std::collections::VecDeque;
+
+    let run = |program: &str| {
+        let input: VecDeque<Value> = vec![1].into_iter().collect();
+        let mut computer = Computer::new(0, Memory::parse(program), input, ());
+        let result = computer.run_until_input_needed();
+        (result, computer.ip())
+    };
+
+    let unknown = IntcodeError::UnknownOpCode {
+        ip: 3,
+        instruction: 42,
+    };
+    assert_eq!((Err(unknown), 3), run("1105,1,3,42"));
+
+    let invalid_mode = IntcodeError::InvalidParameterMode {
+        ip: 0,
+        instruction: 301,
+    };
+    assert_eq!((Err(invalid_mode), 0), run("301,0,0,0,99"));
+
+    // The input isn't consumed when the instruction reading it is invalid
+    let immediate_write = IntcodeError::WriteToImmediate {
+        ip: 0,
+        instruction: 103,
+    };
+    assert_eq!((Err(immediate_write.clone()), 0), run("103,0,99"));
+    assert_eq!(
+        "Write to an immediate parameter in 103 at ip 0",
+        immediate_write.to_string()
+    );
+}
+
+#[test]
+fn deadline_stops_infinite_loop() {
+    use std::time::Duration;
+
+    // Jumps back to itself forever
+    let mut computer = Computer::new(0, Memory::parse("1105,1,0"), || None, ());
+
+    computer.set_deadline(Some(Instant::now() + Duration::from_millis(50)));
+    assert_eq!(
+        Ok(ExecutionResult::TimedOut),
+        computer.run_until_input_needed()
+    );
+    assert_eq!(
+        Ok(ExecutionResult::TimedOut),
+        computer.execute_single_instruction()
+    );
+
+    computer.set_deadline(None);
+    assert_eq!(
+        Ok(ExecutionResult::Executed),
+        computer.execute_single_instruction()
+    );
+}
+
+#[test]
+fn async_input() {
+    use std::cell::RefCell;
+    use std::pin::Pin;
+    use std::rc::Rc;
+    use std::task::{Context, Poll};
+
+    /// Resolves to its value on the second poll, like an input arriving later
+    struct Delayed(Option<Value>, bool);
+
+    impl Future for Delayed {
+        type Output = Option<Value>;
+
+        fn poll(mut self: Pin<&mut Self>, context: &mut Context) -> Poll<Option<Value>> {
+            if self.1 {
+                Poll::Ready(self.0)
+            } else {
+                self.1 = true;
+                context.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+
+    // Echoes 3 values, sent as a packet
+    let written = Rc::new(RefCell::new(vec![]));
+    let mut computer = Computer::new(
+        0,
+        Memory::parse("3,0,4,0,3,0,4,0,3,0,4,0,99"),
+        || None,
+        written.clone(),
+    );
+    let mut inputs = vec![8, 5, 6].into_iter();
+    let result = block_on(computer.execute_async(|| Delayed(inputs.next(), false)));
+    assert_eq!(Ok(ExecutionResult::Exit), result);
+    assert_eq!(vec![8, 5, 6], *written.borrow());
+
+    let mut computer = Computer::new(0, Memory::parse("3,0,99"), || None, ());
+    let result = block_on(computer.execute_async(|| async { None }));
+    assert_eq!(Ok(ExecutionResult::MoreInputNeeded), result);
+}
+
+#[test]
+fn empty_input_policies() {
+    // Echoes two values
+    let run = |empty_input| {
+        let mut computer = Computer::new(0, Memory::parse("3,0,4,0,3,0,4,0,99"), || None, ());
+        computer.set_empty_input(empty_input);
+        let result = computer.run_until_input_needed();
+        (result, computer.ip())
+    };
+
+    assert_eq!(
+        (Ok(ExecutionResult::MoreInputNeeded), 0),
+        run(EmptyInput::Block)
+    );
+    assert_eq!((Ok(ExecutionResult::Exit), 9), run(EmptyInput::Default(-1)));
+    let exhausted = IntcodeError::InputExhausted {
+        ip: 0,
+        instruction: 3,
+    };
+    assert_eq!((Err(exhausted), 0), run(EmptyInput::Error));
+}
@@ -0,0 +1,401 @@
+use crate::code::{Instruction, OpCode, ParameterMode};
+use crate::listing::find_instructions_from;
+use crate::memory::{Memory, Value};
+use std::collections::{BTreeMap, BTreeSet};
+
+const INDENT: &str = "    ";
+
+/// Enclosing loop of the code being structured: jumps to its head are `continue`, and
+/// jumps to the address following it are `break`
+#[derive(Clone, Copy)]
+struct Loop {
+    head: usize,
+    exit: usize,
+}
+
+struct Decompiler {
+    instructions: BTreeMap<usize, Instruction>,
+    /// Targets of the jumps written as `goto`, which get a label
+    labels: BTreeSet<usize>,
+    lines: Vec<String>,
+}
+
+fn operand(mode: ParameterMode, value: Value) -> String {
+    match mode {
+        ParameterMode::Position => format!("m[{}]", value),
+        ParameterMode::Immediate => value.to_string(),
+        ParameterMode::Relative => format!("m[rb{:+}]", value),
+    }
+}
+
+/// `a + b`, written `a - n` when b is a negative constant
+fn sum(a: &str, b: (ParameterMode, Value)) -> String {
+    match b {
+        (ParameterMode::Immediate, value) if value < 0 => format!("{} - {}", a, -value),
+        (mode, value) => format!("{} + {}", a, operand(mode, value)),
+    }
+}
+
+/// Condition under which a conditional jump is taken, or None if it's always taken
+fn jump_condition(instruction: &Instruction) -> Option<String> {
+    let (mode, value) = instruction.parameters[0];
+    let taken_if_zero = instruction.op_code == OpCode::JumpIfFalse;
+    if mode == ParameterMode::Immediate && (value == 0) == taken_if_zero {
+        return None;
+    }
+    let comparison = if taken_if_zero { "==" } else { "!=" };
+    Some(format!("{} {} 0", operand(mode, value), comparison))
+}
+
+/// Condition under which a conditional jump is not taken
+fn negate(condition: &str) -> String {
+    match condition.strip_suffix(" == 0") {
+        Some(value) => format!("{} != 0", value),
+        None => format!("{} == 0", condition.trim_end_matches(" != 0")),
+    }
+}
+
+fn jump_target(instruction: &Instruction) -> Option<usize> {
+    match (instruction.op_code, instruction.parameters.get(1)) {
+        (OpCode::JumpIfTrue, Some((ParameterMode::Immediate, target)))
+        | (OpCode::JumpIfFalse, Some((ParameterMode::Immediate, target))) => Some(*target as usize),
+        _ => None,
+    }
+}
+
+fn is_jump(instruction: &Instruction) -> bool {
+    instruction.op_code == OpCode::JumpIfTrue || instruction.op_code == OpCode::JumpIfFalse
+}
+
+fn end_of(instruction: &Instruction) -> usize {
+    instruction.address + 1 + instruction.parameters.len()
+}
+
+/// Pseudo-code of an instruction other than a jump
+fn statement(instruction: &Instruction) -> String {
+    let p = &instruction.parameters;
+    let op = |i: usize| operand(p[i].0, p[i].1);
+    match instruction.op_code {
+        OpCode::Add => format!("{} = {}", op(2), sum(&op(0), p[1])),
+        OpCode::Mult => format!("{} = {} * {}", op(2), op(0), op(1)),
+        OpCode::LessThan => format!("{} = {} < {}", op(2), op(0), op(1)),
+        OpCode::Equals => format!("{} = {} == {}", op(2), op(0), op(1)),
+        OpCode::Input => format!("{} = input()", op(0)),
+        OpCode::Output => format!("output({})", op(0)),
+        OpCode::AdjustRelativeBase => match p[0] {
+            (ParameterMode::Immediate, value) if value < 0 => format!("rb -= {}", -value),
+            _ => format!("rb += {}", op(0)),
+        },
+        OpCode::Exit => "halt".to_string(),
+        OpCode::JumpIfTrue | OpCode::JumpIfFalse => unreachable!(),
+    }
+}
+
+impl Decompiler {
+    fn emit(&mut self, depth: usize, line: String) {
+        self.lines.push(format!("{}{}", INDENT.repeat(depth), line));
+    }
+
+    /// The furthest backward jump to `head` within `start..end`, closing a loop
+    fn loop_end(&self, head: usize, end: usize) -> Option<usize> {
+        self.instructions
+            .range(head..end)
+            .filter(|(_, i)| jump_target(i) == Some(head) && end_of(i) <= end)
+            .map(|(address, _)| *address)
+            .next_back()
+    }
+
+    /// Jump out of the structured code: `break`, `continue` or `goto`
+    fn jump(&mut self, target: usize, enclosing: Option<Loop>) -> String {
+        match enclosing {
+            Some(l) if target == l.exit => "break".to_string(),
+            Some(l) if target == l.head => "continue".to_string(),
+            _ => {
+                self.labels.insert(target);
+                format!("goto L{}", target)
+            }
+        }
+    }
+
+    /// Writes the pseudo-code of the instructions in `start..end`
+    fn structure(&mut self, start: usize, end: usize, depth: usize, enclosing: Option<Loop>) {
+        let mut address = start;
+        while let Some((&at, instruction)) = self.instructions.range(address..end).next() {
+            let instruction = instruction.clone();
+            if self.labels.contains(&at) {
+                self.lines.push(format!("L{}:", at));
+            }
+
+            // Loop closed by a backward jump to this instruction, unless it's the head of
+            // the loop being structured
+            let in_loop_head = enclosing.is_some_and(|l| l.head == at);
+            if let Some(back) = self.loop_end(at, end).filter(|_| !in_loop_head) {
+                let back_jump = self.instructions[&back].clone();
+                let exit = end_of(&back_jump);
+                let current = Some(Loop { head: at, exit });
+                match jump_condition(&back_jump) {
+                    Some(condition) => {
+                        self.emit(depth, "do {".to_string());
+                        self.structure(at, back, depth + 1, current);
+                        self.emit(depth, format!("}} while ({})", condition));
+                    }
+                    None => {
+                        self.emit(depth, "loop {".to_string());
+                        self.structure(at, back, depth + 1, current);
+                        self.emit(depth, "}".to_string());
+                    }
+                }
+                address = exit;
+                continue;
+            }
+
+            let next = end_of(&instruction);
+            address = next;
+            if !is_jump(&instruction) {
+                let line = statement(&instruction);
+                self.emit(depth, line);
+                continue;
+            }
+
+            let condition = jump_condition(&instruction);
+            let target = match jump_target(&instruction) {
+                Some(target) => target,
+                None => {
+                    let (mode, value) = instruction.parameters[1];
+                    let line = match condition {
+                        Some(condition) => {
+                            format!("if ({}) goto *{}", condition, operand(mode, value))
+                        }
+                        None => format!("goto *{}", operand(mode, value)),
+                    };
+                    self.emit(depth, line);
+                    continue;
+                }
+            };
+
+            match condition {
+                // Conditional jump forward, over the body of an if
+                Some(condition)
+                    if target > next
+                        && target <= end
+                        && enclosing.is_none_or(|l| target != l.exit) =>
+                {
+                    // The body ending with a jump forward over the else branch
+                    let last = self
+                        .instructions
+                        .range(next..target)
+                        .next_back()
+                        .map(|(_, i)| i.clone());
+                    let else_end = last
+                        .as_ref()
+                        .filter(|i| {
+                            is_jump(i) && end_of(i) == target && jump_condition(i).is_none()
+                        })
+                        .and_then(jump_target)
+                        .filter(|e| *e > target && *e <= end);
+                    self.emit(depth, format!("if ({}) {{", negate(&condition)));
+                    match (else_end, last) {
+                        (Some(else_end), Some(last)) => {
+                            self.structure(next, last.address, depth + 1, enclosing);
+                            self.emit(depth, "} else {".to_string());
+                            self.structure(target, else_end, depth + 1, enclosing);
+                            address = else_end;
+                        }
+                        _ => {
+                            self.structure(next, target, depth + 1, enclosing);
+                            address = target;
+                        }
+                    }
+                    self.emit(depth, "}".to_string());
+                }
+                Some(condition) => {
+                    let line = format!("if ({}) {}", condition, self.jump(target, enclosing));
+                    self.emit(depth, line);
+                }
+                // Jump to the instruction that follows anyway
+                None if self.instructions.range(next..end).next().map(|(a, _)| *a)
+                    == Some(target) => {}
+                None => {
+                    let line = self.jump(target, enclosing);
+                    self.emit(depth, line);
+                }
+            }
+        }
+    }
+}
+
+/// Address pushed by `add a, b, [rb+n]` (or `mul`) on constants, right before a jump:
+/// the way the puzzle programs call a function, passing it its return address
+fn return_address(instruction: &Instruction, next: Option<&Instruction>) -> Option<usize> {
+    let value = match (instruction.op_code, instruction.parameters.as_slice()) {
+        (
+            op_code,
+            [(ParameterMode::Immediate, a), (ParameterMode::Immediate, b), (ParameterMode::Relative, _)],
+        ) => match op_code {
+            OpCode::Add => a + b,
+            OpCode::Mult => a * b,
+            _ => return None,
+        },
+        _ => return None,
+    };
+    let call = next.filter(|n| jump_target(n).is_some() && jump_condition(n).is_none());
+    if value >= 0 && call.is_some() {
+        Some(value as usize)
+    } else {
+        None
+    }
+}
+
+/// Instructions reachable from the entry point, and from the return addresses of the
+/// function calls found along the way
+fn find_code(memory: &Memory) -> BTreeMap<usize, Instruction> {
+    let mut entries = vec![0];
+    loop {
+        let instructions = find_instructions_from(memory, &entries);
+        let returns: Vec<usize> = instructions
+            .values()
+            .filter_map(|i| return_address(i, instructions.get(&end_of(i))))
+            .filter(|a| *a < memory.image_len() && !entries.contains(a))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        if returns.is_empty() {
+            return instructions;
+        }
+        entries.extend(returns);
+    }
+}
+
+/// Pseudo-code of the program, recovering loops from backward jumps, and if/else blocks
+/// from forward jumps. The jumps that don't fit in these structures are left as `goto`,
+/// and the jumps to computed addresses as `goto *address`.
+pub fn decompile(memory: &Memory) -> String {
+    let instructions = find_code(memory);
+    let end = instructions.values().last().map_or(0, end_of);
+    let mut decompiler = Decompiler {
+        instructions,
+        labels: BTreeSet::new(),
+        lines: vec![],
+    };
+    // The labels are only known once all the gotos have been written
+    decompiler.structure(0, end, 0, None);
+    decompiler.lines.clear();
+    decompiler.structure(0, end, 0, None);
+    decompiler.lines.join("\n") + "\n"
+}
+
+#[cfg(test)]
+fn decompile_source(source: &str) -> String {
+    decompile(&Memory::new(crate::asm::assemble(source).unwrap()))
+}
+
+#[test]
+fn do_while_loop() {
+    let source = "
+        loop: out [counter]
+              add [counter], -1, [counter]
+              jnz [counter], loop
+              hlt
+        counter: .data 3
+    ";
+    let expected = "\
+do {
+    output(m[10])
+    m[10] = m[10] - 1
+} while (m[10] != 0)
+halt
+";
+    assert_eq!(expected, decompile_source(source));
+}
+
+#[test]
+fn if_else() {
+    // Outputs 1 if the input is less than 10, and 2 otherwise
+    let source = "
+              in [x]
+              lt [x], 10, [small]
+              jz [small], large
+              out 1
+              jz 0, done
+        large: out 2
+        done: hlt
+        x: .data 0
+        small: .data 0
+    ";
+    let expected = "\
+m[17] = input()
+m[18] = m[17] < 10
+if (m[18] != 0) {
+    output(1)
+} else {
+    output(2)
+}
+halt
+";
+    assert_eq!(expected, decompile_source(source));
+}
+
+#[test]
+fn infinite_loop_with_break() {
+    // Echoes the input until it reads 0
+    let source = "
+        loop: in [x]
+              jz [x], done
+              out [x]
+              jz 0, loop
+        done: hlt
+        x: .data 0
+    ";
+    let expected = "\
+loop {
+    m[11] = input()
+    if (m[11] == 0) break
+    output(m[11])
+}
+halt
+";
+    assert_eq!(expected, decompile_source(source));
+}
+
+#[test]
+fn gotos_and_computed_jumps() {
+    let source = "
+              jnz [x], skip
+              arb -3
+        skip: out [rb+1]
+              jnz 1, [rb]
+        x: .data 1
+    ";
+    let expected = "\
+if (m[10] == 0) {
+    rb -= 3
+}
+output(m[rb+1])
+goto *m[rb+0]
+";
+    assert_eq!(expected, decompile_source(source));
+}
+
+#[test]
+fn code_after_function_calls() {
+    // The function returns through the address pushed by the caller: the code following
+    // the call is only reached through that computed jump
+    let source = "
+              add back, 0, [rb+0]
+              jz 0, function
+        back: out 2
+              hlt
+        function: out 1
+              jz 0, [rb+0]
+    ";
+    let expected = "\
+m[rb+0] = 7 + 0
+goto L10
+output(2)
+halt
+L10:
+output(1)
+goto *m[rb+0]
+";
+    assert_eq!(expected, decompile_source(source));
+}
@@ -0,0 +1,154 @@
+use crate::memory::Value;
+use crate::switch::Packet;
+
+/// Decoders grouping raw VM outputs into the frames used by the puzzles
+#[allow(clippy::wrong_self_convention)]
+pub trait Frames: Iterator<Item = Value> + Sized {
+    /// (x, y, tile id) triples, as drawn by the arcade cabinet
+    fn as_tiles(self) -> Tiles<Self> {
+        Tiles { outputs: self }
+    }
+
+    /// (address, x, y) triples, as sent over the network
+    fn as_packets(self) -> Packets<Self> {
+        Packets { outputs: self }
+    }
+
+    /// Lines of ASCII text. A value that isn't an ASCII character (usually the puzzle
+    /// answer) is returned on its own, as an error; if it comes in the middle of a line,
+    /// that line is returned first.
+    fn as_ascii_lines(self) -> AsciiLines<Self> {
+        AsciiLines {
+            outputs: self,
+            non_ascii: None,
+        }
+    }
+}
+
+impl<I: Iterator<Item = Value>> Frames for I {}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub struct Tile {
+    pub x: Value,
+    pub y: Value,
+    pub id: Value,
+}
+
+pub struct Tiles<I> {
+    outputs: I,
+}
+
+impl<I: Iterator<Item = Value>> Iterator for Tiles<I> {
+    type Item = Tile;
+
+    fn next(&mut self) -> Option<Tile> {
+        let x = self.outputs.next()?;
+        let y = self.outputs.next()?;
+        let id = self.outputs.next()?;
+        Some(Tile { x, y, id })
+    }
+}
+
+pub struct Packets<I> {
+    outputs: I,
+}
+
+impl<I: Iterator<Item = Value>> Iterator for Packets<I> {
+    type Item = (Value, Packet);
+
+    fn next(&mut self) -> Option<(Value, Packet)> {
+        let addr = self.outputs.next()?;
+        let x = self.outputs.next()?;
+        let y = self.outputs.next()?;
+        Some((addr, Packet::new(x, y)))
+    }
+}
+
+pub struct AsciiLines<I> {
+    outputs: I,
+    /// Non-ASCII value that ended the previous line, returned next
+    non_ascii: Option<Value>,
+}
+
+impl<I: Iterator<Item = Value>> Iterator for AsciiLines<I> {
+    type Item = Result<String, Value>;
+
+    fn next(&mut self) -> Option<Result<String, Value>> {
+        if let Some(value) = self.non_ascii.take() {
+            return Some(Err(value));
+        }
+
+        let mut line = String::new();
+        loop {
+            match self.outputs.next() {
+                None if line.is_empty() => return None,
+                None | Some(10) => return Some(Ok(line)),
+                Some(c) if (0..128).contains(&c) => line.push(c as u8 as char),
+                Some(value) if line.is_empty() => return Some(Err(value)),
+                Some(value) => {
+                    self.non_ascii = Some(value);
+                    return Some(Ok(line));
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn decode_tiles() {
+    let outputs = vec![1, 2, 3, 6, 5, 4, -1, 0, 12345];
+    let tiles: Vec<Tile> = outputs.into_iter().as_tiles().collect();
+    assert_eq!(
+        vec![
+            Tile { x: 1, y: 2, id: 3 },
+            Tile { x: 6, y: 5, id: 4 },
+            Tile {
+                x: -1,
+                y: 0,
+                id: 12345
+            }
+        ],
+        tiles
+    );
+}
+
+#[test]
+fn decode_packets() {
+    let outputs = vec![255, 55691, 19463, 3, 10, 20, 7];
+    let packets: Vec<(Value, Value, Value)> = outputs
+        .into_iter()
+        .as_packets()
+        .map(|(a, p)| (a, p.x, p.y))
+        .collect();
+    assert_eq!(vec![(255, 55691, 19463), (3, 10, 20)], packets);
+}
+
+#[test]
+fn decode_ascii_lines() {
+    let outputs = "..#..\n..#..\n\nWalking...\n"
+        .bytes()
+        .map(|b| b as Value)
+        .chain(vec![1141869516])
+        .chain("\nend".bytes().map(|b| b as Value));
+    assert_eq!(
+        vec![
+            Ok("..#..".to_string()),
+            Ok("..#..".to_string()),
+            Ok("".to_string()),
+            Ok("Walking...".to_string()),
+            Err(1141869516),
+            Ok("".to_string()),
+            Ok("end".to_string()),
+        ],
+        outputs.as_ascii_lines().collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn non_ascii_value_ends_line() {
+    let outputs = "Score: ".bytes().map(|b| b as Value).chain(vec![1234, 10]);
+    assert_eq!(
+        vec![Ok("Score: ".to_string()), Err(1234), Ok("".to_string())],
+        outputs.as_ascii_lines().collect::<Vec<_>>()
+    );
+}
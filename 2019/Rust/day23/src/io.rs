@@ -0,0 +1,212 @@
+use crate::code::Computer;
+use crate::memory::Value;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::mpsc::{Receiver, Sender};
+
+/// Where a computer reads its input values from
+pub trait InputSource {
+    /// Next input value, or None if none is available yet
+    fn read(&mut self) -> Option<Value>;
+}
+
+/// Where a computer writes its output values, as they are output
+pub trait OutputSink {
+    fn write(&mut self, value: Value);
+}
+
+impl<F: FnMut() -> Option<Value>> InputSource for F {
+    fn read(&mut self) -> Option<Value> {
+        self()
+    }
+}
+
+impl<F: FnMut(Value)> OutputSink for F {
+    fn write(&mut self, value: Value) {
+        self(value)
+    }
+}
+
+/// Values queued in advance, read in order
+impl InputSource for VecDeque<Value> {
+    fn read(&mut self) -> Option<Value> {
+        self.pop_front()
+    }
+}
+
+/// Values of an iterator, only produced as they're read
+pub struct IterSource<I>(pub I);
+
+impl<I: Iterator<Item = Value>> InputSource for IterSource<I> {
+    fn read(&mut self) -> Option<Value> {
+        self.0.next()
+    }
+}
+
+/// The outputs of another computer, which runs each time a value is read until it outputs
+/// one. It stops providing values once it exits, waits for input or fails.
+impl InputSource for Computer {
+    fn read(&mut self) -> Option<Value> {
+        self.run_until_output().ok().flatten()
+    }
+}
+
+/// Comma or whitespace separated values
+pub fn parse_values(text: &str) -> Result<VecDeque<Value>, String> {
+    text.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|v| !v.is_empty())
+        .map(|v| {
+            v.parse::<Value>()
+                .map_err(|_| format!("Invalid input value: {}", v))
+        })
+        .collect()
+}
+
+/// Values of a file, comma or whitespace separated, queued to be read in order
+pub fn read_values_file(path: impl AsRef<Path>) -> Result<VecDeque<Value>, String> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    parse_values(&text)
+}
+
+/// Discards everything written
+impl OutputSink for () {
+    fn write(&mut self, _value: Value) {}
+}
+
+/// Buffer collecting the values, shared with the code reading them
+impl OutputSink for Rc<RefCell<Vec<Value>>> {
+    fn write(&mut self, value: Value) {
+        self.borrow_mut().push(value);
+    }
+}
+
+/// Writes the values as ASCII text: characters as they are, and any other value (usually
+/// the puzzle answer) as a number on its own line. Write errors are ignored.
+pub struct AsciiWriter<W>(pub W);
+
+impl<W: Write> OutputSink for AsciiWriter<W> {
+    fn write(&mut self, value: Value) {
+        let _ = match value {
+            0..=127 => self.0.write_all(&[value as u8]),
+            _ => writeln!(self.0, "{}", value),
+        };
+    }
+}
+
+/// Groups the values into tuples of N, such as the (x, y, tile id) drawn by the arcade
+/// cabinet or the (address, x, y) packets sent over the network, and passes each one on
+/// once complete
+pub struct Chunks<F, const N: usize> {
+    pending: [Value; N],
+    len: usize,
+    receiver: F,
+}
+
+impl<F: FnMut([Value; N]), const N: usize> Chunks<F, N> {
+    pub fn new(receiver: F) -> Chunks<F, N> {
+        Chunks {
+            pending: [0; N],
+            len: 0,
+            receiver,
+        }
+    }
+}
+
+impl<F: FnMut([Value; N]), const N: usize> OutputSink for Chunks<F, N> {
+    fn write(&mut self, value: Value) {
+        self.pending[self.len] = value;
+        self.len += 1;
+        if self.len == N {
+            self.len = 0;
+            (self.receiver)(self.pending);
+        }
+    }
+}
+
+/// Values sent on the channel; reading doesn't block
+impl InputSource for Receiver<Value> {
+    fn read(&mut self) -> Option<Value> {
+        self.try_recv().ok()
+    }
+}
+
+/// Values are forwarded to the receiver, and dropped once it's gone
+impl OutputSink for Sender<Value> {
+    fn write(&mut self, value: Value) {
+        let _ = self.send(value);
+    }
+}
+
+#[cfg(test)]
+use crate::memory::Memory;
+
+#[test]
+fn iterator_source() {
+    // Outputs the sum of its 2 inputs
+    let memory = Memory::parse("3,11,3,12,1,11,12,13,4,13,99");
+    let mut computer = Computer::new(0, memory, IterSource((1..).map(|i| i * 10)), ());
+    assert_eq!(Ok(Some(30)), computer.run_until_output());
+}
+
+#[test]
+fn values_file_source() {
+    let path = std::env::temp_dir().join(format!("day23_input_{}.txt", std::process::id()));
+    fs::write(&path, "4, -2\n7\n").unwrap();
+    let values = read_values_file(&path);
+    fs::remove_file(&path).unwrap();
+    assert_eq!(Ok(vec![4, -2, 7]), values.map(Vec::from));
+
+    assert_eq!(
+        Err("Invalid input value: x".to_string()),
+        parse_values("1,x")
+    );
+    assert!(read_values_file("/nonexistent/input.txt").is_err());
+}
+
+#[test]
+fn chained_computers() {
+    // Outputs 1, 2 and 3, then doubles each of its inputs
+    let counter = Computer::new(0, Memory::parse("104,1,104,2,104,3,99"), || None, ());
+    let double = Memory::parse("3,11,1002,11,2,11,4,11,1105,1,0");
+    let mut computer = Computer::new(1, double, counter, ());
+    assert_eq!(vec![2, 4, 6], computer.outputs().collect::<Vec<_>>());
+}
+
+#[test]
+fn output_sinks() {
+    // Outputs "Hi", then 1234
+    let program = Memory::parse("104,72,104,105,104,10,104,1234,99");
+
+    let buffer = Rc::new(RefCell::new(vec![]));
+    let mut computer = Computer::new(0, program.clone(), || None, buffer.clone());
+    computer.run_until_input_needed().unwrap();
+    assert_eq!(vec![72, 105, 10, 1234], *buffer.borrow());
+
+    let mut writer = AsciiWriter(vec![]);
+    for value in buffer.borrow().iter() {
+        writer.write(*value);
+    }
+    assert_eq!("Hi\n1234\n", String::from_utf8_lossy(&writer.0));
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut computer = Computer::new(0, program, || None, tx);
+    computer.run_until_input_needed().unwrap();
+    assert_eq!(vec![72, 105, 10, 1234], rx.try_iter().collect::<Vec<_>>());
+}
+
+#[test]
+fn chunked_outputs() {
+    let mut packets = vec![];
+    let mut chunks = Chunks::new(|[address, x, y]| packets.push((address, x, y)));
+    for value in 1..=8 {
+        chunks.write(value);
+    }
+    // The last 2 values wait for the third one
+    assert_eq!(vec![(1, 2, 3), (4, 5, 6)], packets);
+}
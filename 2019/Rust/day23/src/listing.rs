@@ -0,0 +1,212 @@
+use crate::asm::mnemonic;
+use crate::code::{Instruction, OpCode, ParameterMode};
+use crate::memory::{Memory, Value};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Addresses of the instructions reading and writing a memory cell
+#[derive(Default)]
+struct CrossReferences {
+    read_by: BTreeSet<usize>,
+    written_by: BTreeSet<usize>,
+}
+
+/// Instructions reachable from the entry point at address 0, following the jumps whose
+/// target is known. The rest of the program is considered data.
+pub fn find_instructions(memory: &Memory) -> BTreeMap<usize, Instruction> {
+    find_instructions_from(memory, &[0])
+}
+
+/// Instructions reachable from any of the entry points
+pub fn find_instructions_from(memory: &Memory, entries: &[usize]) -> BTreeMap<usize, Instruction> {
+    let mut instructions = BTreeMap::new();
+    let mut pending = entries.to_vec();
+    while let Some(address) = pending.pop() {
+        if address >= memory.image_len() || instructions.contains_key(&address) {
+            continue;
+        }
+        let instruction = match Instruction::decode(memory, address) {
+            Some(instruction) => instruction,
+            None => continue,
+        };
+
+        let next = address + 1 + instruction.parameters.len();
+        match (instruction.op_code, instruction.parameters.as_slice()) {
+            (OpCode::Exit, _) => (),
+            (OpCode::JumpIfTrue, [condition, target])
+            | (OpCode::JumpIfFalse, [condition, target]) => {
+                if let (ParameterMode::Immediate, target) = target {
+                    pending.push(*target as usize);
+                }
+                // Jumps on an immediate condition are either unconditional, or never taken
+                let always_taken = match condition {
+                    (ParameterMode::Immediate, value) => {
+                        (*value != 0) == (instruction.op_code == OpCode::JumpIfTrue)
+                    }
+                    _ => false,
+                };
+                if !always_taken {
+                    pending.push(next);
+                }
+            }
+            _ => pending.push(next),
+        }
+        instructions.insert(address, instruction);
+    }
+    instructions
+}
+
+fn jump_target(instruction: &Instruction) -> Option<usize> {
+    match (instruction.op_code, instruction.parameters.get(1)) {
+        (OpCode::JumpIfTrue, Some((ParameterMode::Immediate, target)))
+        | (OpCode::JumpIfFalse, Some((ParameterMode::Immediate, target))) => Some(*target as usize),
+        _ => None,
+    }
+}
+
+fn format_addresses(addresses: &BTreeSet<usize>) -> String {
+    addresses
+        .iter()
+        .map(|a| a.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Annotated disassembly of a program, in the syntax of the assembler. Jump targets are
+/// labeled `L<address>`, and the data cells accessed by the instructions `D<address>`.
+/// Each line ends with a comment giving its address, and the instructions reading or
+/// writing it.
+pub fn listing(memory: &Memory) -> String {
+    let instructions = find_instructions(memory);
+    let is_code = |address: usize| {
+        instructions
+            .range(..=address)
+            .next_back()
+            .is_some_and(|(start, i)| address < start + 1 + i.parameters.len())
+    };
+
+    let mut references: BTreeMap<usize, CrossReferences> = BTreeMap::new();
+    for (address, instruction) in &instructions {
+        let count = instruction.parameters.len();
+        for (i, (mode, value)) in instruction.parameters.iter().enumerate() {
+            if *mode != ParameterMode::Position {
+                continue;
+            }
+            let cell = references.entry(*value as usize).or_default();
+            if instruction.op_code.writes() && i == count - 1 {
+                cell.written_by.insert(*address);
+            } else {
+                cell.read_by.insert(*address);
+            }
+        }
+    }
+
+    let mut labels = BTreeMap::new();
+    for instruction in instructions.values() {
+        if let Some(target) = jump_target(instruction) {
+            if instructions.contains_key(&target) {
+                labels.insert(target, format!("L{}", target));
+            }
+        }
+    }
+    for address in references.keys() {
+        if *address < memory.image_len() && !is_code(*address) {
+            labels.insert(*address, format!("D{}", address));
+        }
+    }
+
+    let name = |value: Value| match labels.get(&(value as usize)) {
+        Some(label) if value >= 0 => label.clone(),
+        _ => value.to_string(),
+    };
+    let annotation = |address: usize| {
+        let mut text = format!("; {:>5}", address);
+        if let Some(cell) = references.get(&address) {
+            if !cell.read_by.is_empty() {
+                text += &format!("  read by {}", format_addresses(&cell.read_by));
+            }
+            if !cell.written_by.is_empty() {
+                text += &format!("  written by {}", format_addresses(&cell.written_by));
+            }
+        }
+        text
+    };
+
+    let mut lines = vec![];
+    let mut address = 0;
+    while address < memory.image_len() {
+        let label = labels
+            .get(&address)
+            .map_or(String::new(), |l| format!("{}:", l));
+        let (text, size) = match instructions.get(&address) {
+            Some(instruction) => {
+                let target = jump_target(instruction);
+                let parameters: Vec<_> = instruction
+                    .parameters
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (mode, value))| match mode {
+                        ParameterMode::Position => format!("[{}]", name(*value)),
+                        ParameterMode::Immediate if i == 1 && target.is_some() => name(*value),
+                        ParameterMode::Immediate => value.to_string(),
+                        ParameterMode::Relative => format!("[rb{:+}]", value),
+                    })
+                    .collect();
+                let text = format!(
+                    "{} {}",
+                    mnemonic(instruction.op_code),
+                    parameters.join(", ")
+                );
+                (text.trim_end().to_string(), 1 + parameters.len())
+            }
+            None => (format!(".data {}", memory[address]), 1),
+        };
+        let mut line = format!("{:<8}{:<32}{}", label, text, annotation(address));
+        // Writes into the parameters of an instruction, i.e. self-modifying code
+        for parameter in address + 1..address + size {
+            if references.contains_key(&parameter) {
+                line += &format!("\n{:<40}{}", "", annotation(parameter));
+            }
+        }
+        lines.push(line);
+        address += size;
+    }
+    lines.join("\n") + "\n"
+}
+
+#[cfg(test)]
+const COUNTDOWN: &str = "
+    loop: out [counter]
+          add [counter], -1, [counter]
+          jnz [counter], loop
+          hlt
+    counter: .data 3
+";
+
+#[test]
+fn annotated_listing() {
+    let memory = Memory::new(crate::asm::assemble(COUNTDOWN).unwrap());
+    let expected = [
+        "L0:     out [D10]                       ;     0",
+        "        add [D10], -1, [D10]            ;     2",
+        "        jnz [D10], L0                   ;     6",
+        "        hlt                             ;     9",
+        "D10:    .data 3                         ;    10  read by 0, 2, 6  written by 2",
+        "",
+    ]
+    .join("\n");
+    assert_eq!(expected, listing(&memory));
+}
+
+#[test]
+fn listing_reassembles() {
+    let image = crate::asm::assemble(COUNTDOWN).unwrap();
+    let text = listing(&Memory::new(image.clone()));
+    assert_eq!(image, crate::asm::assemble(&text).unwrap());
+
+    // Unreachable code is listed as data, and writes into instructions are annotated
+    let image = vec![1101, 1, 1, 6, 1105, 1, 9, 42, 42, 99];
+    let text = listing(&Memory::new(image.clone()));
+    assert!(text.contains(".data 42"));
+    assert!(text.contains(";     6  written by 0"));
+    assert_eq!(image, crate::asm::assemble(&text).unwrap());
+}
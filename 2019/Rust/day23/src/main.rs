@@ -1,24 +1,52 @@
-use crate::code::ExecutionResult::Exit;
+use crate::builder::{IntcodeBuilder, IoMode};
+use crate::chaos::FlakyIo;
+use crate::cluster::{Scheduler, VmStatus};
 use crate::code::*;
-use crate::memory::Memory;
+use crate::frames::Frames;
+use crate::io::{parse_values, AsciiWriter, Chunks, OutputSink};
+use crate::memory::{BackendKind, Memory, Value};
+use crate::monitor::Monitor;
+use crate::switch::Nat;
+use crate::switch::NetworkConfig;
 use crate::switch::Packet;
 use crate::switch::Switch;
-use std::cell::RefCell;
+use crate::trace::JsonTrace;
+use std::cell::{Cell, RefCell};
 use std::collections::HashSet;
+use std::collections::VecDeque;
 use std::env;
+use std::fs::File;
+use std::future;
+use std::io::BufWriter;
+use std::process;
 use std::rc::Rc;
+use std::time::Duration;
+use std::time::Instant;
 
-#[cfg(unix)]
-extern crate ncurses;
+#[macro_use]
+mod strict;
 
+mod asm;
+mod builder;
+mod bus;
+mod chaos;
+mod cluster;
 mod code;
+mod decompile;
+mod frames;
+// Sources and sinks for any program, of which the network only needs some
+#[allow(dead_code)]
+mod io;
+mod listing;
 mod memory;
+mod monitor;
 mod switch;
+mod trace;
 
 type Result<T> = ::std::result::Result<T, Box<dyn ::std::error::Error>>;
 
-#[derive(Eq, PartialEq, Hash, Clone, Copy)]
-struct Pos(i32, i32);
+// Exit code for Intcode VM errors, shared with the runner
+const EXIT_VM_ERROR: i32 = 4;
 
 // struct NetworkedComputer<'a> {
 //     address: i64,
@@ -57,103 +85,419 @@ enum InputStatus {
     ReadingPacket(Packet),
 }
 
-enum OutputStatus {
-    Idle,
-    WritingPacket(i64),
-}
-
 fn main() -> Result<()> {
     let file_name = env::args().nth(1).expect("Enter a file name");
+    strict::enable_from_args(env::args());
+
+    if env::args().any(|a| a == "--assemble") {
+        let source = std::fs::read_to_string(&file_name)?;
+        println!("{}", asm::to_image(&asm::assemble(&source)?));
+        return Ok(());
+    }
 
-    const COMPUTER_COUNT: usize = 50;
-    let switch = Rc::new(RefCell::new(Switch::new(COMPUTER_COUNT)));
-    let memory = Memory::load_from_file(&file_name)?;
+    let config = NetworkConfig::from_args(env::args())?;
+    let switch = Rc::new(RefCell::new(Switch::new(config)));
+    let mut memory = Memory::load_from_file(&file_name)?;
+    // --memory dense|sparse|auto: backend storing the values the computers write. Auto
+    // switches to the sparse one once they write far beyond the program; with dense, such
+    // a write is a VM error.
+    if let Some(kind) = env::args().skip_while(|a| a != "--memory").nth(1) {
+        memory.set_backend(match kind.as_str() {
+            "dense" => BackendKind::Dense,
+            "sparse" => BackendKind::Sparse,
+            "auto" => BackendKind::Auto,
+            _ => {
+                return Err(format!(
+                    "Unknown memory backend: {} (expected dense, sparse or auto)",
+                    kind
+                )
+                .into())
+            }
+        });
+    }
 
+    if env::args().any(|a| a == "--decompile") {
+        print!("{}", decompile::decompile(&memory));
+        return Ok(());
+    }
+
+    if env::args().any(|a| a == "--listing") {
+        print!("{}", listing::listing(&memory));
+        return Ok(());
+    }
+
+    if env::args().any(|a| a == "--run") {
+        return run_program(memory);
+    }
+
+    if env::args().any(|a| a == "--bench-scheduler") {
+        benchmark_scheduler(&memory);
+        return Ok(());
+    }
+
+    if env::args().any(|a| a == "--bench-memory") {
+        benchmark_memory(&memory);
+        return Ok(());
+    }
+
+    let chaos_seed = match env::args().skip_while(|a| a != "--chaos").nth(1) {
+        Some(seed) => Some(seed.parse::<u64>()?),
+        None => None,
+    };
+    let deadline = match env::args().skip_while(|a| a != "--time-limit").nth(1) {
+        Some(seconds) => Some(Instant::now() + Duration::from_secs_f64(seconds.parse()?)),
+        None => None,
+    };
+
+    let startup = Instant::now();
     let mut computers: Vec<Computer> = vec![];
-    for i in 0..COMPUTER_COUNT {
+    // Number of values each computer has output of its current packet, for the strict mode
+    // invariants: a computer only reads its input or exits between two packets
+    let mut packets_progress: Vec<Rc<Cell<usize>>> = vec![];
+    for i in 0..config.computers {
+        // The puzzle program only supports 50 addresses, and crashes when booted with
+        // another one: the extra computers of a larger network reuse them, which keeps
+        // them busy sending packets to the first 50
+        let boot_address = i % NetworkConfig::DEFAULT_COMPUTERS;
         let r1 = switch.clone();
         let r2 = switch.clone();
+        let read: Box<dyn Fn() -> Option<Packet>> = Box::new(move || r1.borrow().read(i));
+        let write: Box<dyn FnMut([Value; 3])> = Box::new(move |[addr, x, y]: [Value; 3]| {
+            r2.borrow().write(addr as usize, Packet::new(x, y));
+        });
+        let (read, write) = match chaos_seed {
+            Some(seed) => {
+                let flaky = FlakyIo::new(seed.wrapping_add(i as u64), 0.2, 0.05);
+                let read: Box<dyn Fn() -> Option<Packet>> =
+                    Box::new(flaky.clone().wrap_input(read));
+                let write: Box<dyn FnMut([Value; 3])> = Box::new(flaky.wrap_output(write));
+                (read, write)
+            }
+            None => (read, write),
+        };
+
+        let progress = Rc::new(Cell::new(0));
+        let output_progress = progress.clone();
+        let mut packets = Chunks::new(write);
+        let output = move |value| {
+            output_progress.set((output_progress.get() + 1) % 3);
+            packets.write(value);
+        };
+
+        let input_progress = progress.clone();
         let input_status = RefCell::new(InputStatus::WaitingForFirstRead);
-        let output_status = RefCell::new(OutputStatus::Idle);
-
-        computers.push(Computer::new(
-            i,
-            memory.clone(),
-            Box::new(move || {
-                let mut status = input_status.borrow_mut();
-                match *status {
-                    InputStatus::WaitingForFirstRead => {
-                        *status = InputStatus::Idle;
-                        Some(i as i64)
-                    }
-                    InputStatus::Idle => match r1.borrow().read(i as usize) {
-                        Some(packet) => {
-                            *status = InputStatus::ReadingPacket(packet);
-                            Some(packet.x)
-                        }
-                        None => None,
-                    },
-                    InputStatus::ReadingPacket(packet) => {
-                        *status = InputStatus::Idle;
-                        Some(packet.y)
-                    }
+        let input = move || {
+            invariant!(
+                input_progress.get() == 0,
+                "Computer {} reads its input in the middle of a packet ({} of 3 values sent)",
+                i,
+                input_progress.get()
+            );
+            let mut status = input_status.borrow_mut();
+            match *status {
+                InputStatus::WaitingForFirstRead => {
+                    *status = InputStatus::Idle;
+                    Some(boot_address as Value)
                 }
-            }),
-            Box::new(move |addr, data| {
-                let mut status = output_status.borrow_mut();
-                match *status {
-                    OutputStatus::Idle => {
-                        *status = OutputStatus::WritingPacket(data);
-                    }
-                    OutputStatus::WritingPacket(x) => {
-                        *status = OutputStatus::Idle;
-                        let packet = Packet::new(x, data);
-                        r2.borrow().write(addr as usize, packet);
-                    }
+                InputStatus::Idle => {
+                    let packet = read()?;
+                    *status = InputStatus::ReadingPacket(packet);
+                    Some(packet.x)
+                }
+                InputStatus::ReadingPacket(packet) => {
+                    *status = InputStatus::Idle;
+                    Some(packet.y)
                 }
-            }),
-        ));
+            }
+        };
+        let computer = IntcodeBuilder::new(memory.clone())
+            .id(i)
+            .input_source(input)
+            .on_empty_input(EmptyInput::Default(-1))
+            .output(output)
+            .deadline(deadline)
+            .build();
+        computers.push(computer);
+        packets_progress.push(progress);
     }
+    println!(
+        "Started {} computers in {:?}; NAT at address {}",
+        config.computers,
+        startup.elapsed(),
+        config.nat_address()
+    );
 
-    let mut completed: HashSet<usize> = HashSet::new();
-    let mut previous_nat_packet: Option<Packet> = None;
+    let mut monitor = if env::args().any(|a| a == "--monitor") {
+        Some(Monitor::new(config.computers))
+    } else {
+        None
+    };
 
-    while completed.len() < COMPUTER_COUNT {
-        let switch_activity = switch.borrow().get_activity();
-        let switch_was_quiet = switch.borrow().is_quiet();
+    // --trace <file>: writes every executed instruction to the file, as JSON Lines
+    let mut trace = match env::args().skip_while(|a| a != "--trace").nth(1) {
+        Some(file_name) => Some(JsonTrace::new(BufWriter::new(File::create(file_name)?))),
+        None => None,
+    };
 
+    let mut completed: HashSet<usize> = HashSet::new();
+    let mut timed_out = false;
+    let mut failure = None;
+    let switch = switch.borrow();
+    let result = Nat::new().run(&switch, || {
         // TODO: CHEATING!!!
         // We should detect instead that all computers have been attempting to read without writing anything
         for _ in 0..1000 {
             for computer in computers.iter_mut() {
                 if !completed.contains(&computer.id()) {
                     //println!("Computer {} is running...", computer.id());
-                    if computer.execute_single_instruction() == Exit {
-                        println!("Computer {} has exited", computer.id());
-                        completed.insert(computer.id());
+                    let result = match trace.as_mut() {
+                        Some(trace) => trace.step(computer),
+                        None => computer.execute_single_instruction(),
+                    };
+                    match result {
+                        Ok(ExecutionResult::Exit) => {
+                            let progress = packets_progress[computer.id()].get();
+                            invariant!(
+                                progress == 0,
+                                "Computer {} exits in the middle of a packet ({} of 3 values sent)",
+                                computer.id(),
+                                progress
+                            );
+                            println!("Computer {} has exited", computer.id());
+                            completed.insert(computer.id());
+                        }
+                        Ok(ExecutionResult::TimedOut) => {
+                            timed_out = true;
+                            return false;
+                        }
+                        Ok(_) => (),
+                        Err(e) => {
+                            failure = Some((computer.id(), e));
+                            return false;
+                        }
                     }
                 }
             }
         }
+        if let Some(line) = monitor.as_mut().and_then(|m| m.observe(&switch)) {
+            println!("{}", line);
+        }
+        completed.len() < config.computers
+    });
 
-        let switch = switch.borrow();
-        if switch_activity == switch.get_activity() && switch_was_quiet && switch.is_quiet() {
-            // No activity detected
-            // println!("Writing nat packet");
-            if let Some(packet) = switch.pop_nat_packet() {
-                println!("Writing NAT Packet {}", packet);
-                switch.write(0, packet);
-
-                if let Some(p) = previous_nat_packet {
-                    if p.y == packet.y {
-                        println!("Found it!! Y = {}", p.y);
-                        break;
-                    }
-                }
+    if let Some(trace) = trace {
+        let steps = trace.steps();
+        trace.finish()?;
+        println!("Traced {} instructions", steps);
+    }
 
-                previous_nat_packet = Some(packet);
+    if let Some(y) = result {
+        println!("Found it!! Y = {}", y);
+    } else if timed_out {
+        println!("Time limit exceeded; stopped all computers");
+    } else if let Some((id, e)) = failure {
+        eprintln!("VM error: computer {} failed: {}", id, e);
+        process::exit(EXIT_VM_ERROR);
+    }
+    Ok(())
+}
+
+/// Runs the program on its own rather than as a network computer, patched first by
+/// `--noun-verb <noun>,<verb>`. Its input is the text given by `--input <text>`, followed
+/// with `--stdin` by the lines typed on the standard input: numbers, or characters with
+/// `--ascii`. Its outputs are printed as they come: one value per line, or as text with
+/// `--ascii`. `--frames tiles|packets|lines` instead groups them once it stops. `--diff`
+/// then lists the values it wrote over the program.
+fn run_program(memory: Memory) -> Result<()> {
+    let ascii = env::args().any(|a| a == "--ascii");
+    let io_mode = if ascii {
+        IoMode::Ascii
+    } else {
+        IoMode::Numeric
+    };
+    let stdin = env::args().any(|a| a == "--stdin");
+    // Without --stdin, the whole input is known in advance: reading past it is an error
+    let empty_input = if stdin {
+        EmptyInput::Block
+    } else {
+        EmptyInput::Error
+    };
+    let mut builder = IntcodeBuilder::new(memory.clone())
+        .io_mode(io_mode)
+        .on_empty_input(empty_input);
+    if let Some(noun_verb) = env::args().skip_while(|a| a != "--noun-verb").nth(1) {
+        match Vec::from(parse_values(&noun_verb)?)[..] {
+            [noun, verb] => builder = builder.noun_verb(noun, verb),
+            _ => return Err(format!("Expected <noun>,<verb>, got {}", noun_verb).into()),
+        }
+    }
+    if let Some(text) = env::args().skip_while(|a| a != "--input").nth(1) {
+        builder = builder.input(&text)?;
+    }
+
+    let frames = env::args().skip_while(|a| a != "--frames").nth(1);
+    if let Some(frames) = frames
+        .as_deref()
+        .filter(|f| !["tiles", "packets", "lines"].contains(f))
+    {
+        return Err(format!(
+            "Unknown frames: {} (expected tiles, packets or lines)",
+            frames
+        )
+        .into());
+    }
+    // The outputs are printed as they come, so that they can be answered with --stdin,
+    // unless they're to be grouped into frames
+    let outputs = Rc::new(RefCell::new(vec![]));
+    builder = match (&frames, ascii) {
+        (Some(_), _) => builder.output(outputs.clone()),
+        (None, true) => builder.output(AsciiWriter(std::io::stdout())),
+        (None, false) => builder.output(|value: Value| println!("{}", value)),
+    };
+    let mut computer = builder.build();
+    let result = if stdin {
+        let mut typed = VecDeque::new();
+        block_on(computer.execute_async(|| future::ready(read_stdin_value(&mut typed, io_mode))))
+    } else {
+        computer.run_until_input_needed()
+    };
+
+    let values = outputs.take().into_iter();
+    match frames.as_deref() {
+        Some("tiles") => {
+            for tile in values.as_tiles() {
+                println!("({}, {}): {}", tile.x, tile.y, tile.id);
             }
         }
+        Some("packets") => {
+            for (address, packet) in values.as_packets() {
+                println!("{} -> {}", packet, address);
+            }
+        }
+        Some("lines") => {
+            for line in values.as_ascii_lines() {
+                match line {
+                    Ok(line) => println!("{}", line),
+                    Err(value) => println!("{}", value),
+                }
+            }
+        }
+        _ => (),
+    }
+
+    if env::args().any(|a| a == "--diff") {
+        // Both memories share the program image, so only the written values are compared
+        let changes = computer.memory().diff(&memory);
+        println!(
+            "{} values changed, in {} pages",
+            changes.len(),
+            computer.memory().dirty_pages().len()
+        );
+        for (address, value, initial) in changes {
+            println!("[{}] {} -> {}", address, initial, value);
+        }
+    }
+
+    match result {
+        Ok(ExecutionResult::MoreInputNeeded) => {
+            println!("Waiting for input at ip {}", computer.ip())
+        }
+        Ok(_) => (),
+        Err(e) => {
+            eprintln!("VM error: {}", e);
+            process::exit(EXIT_VM_ERROR);
+        }
     }
     Ok(())
 }
+
+/// Next value typed on the standard input, reading new lines as needed. Returns None at the
+/// end of the input.
+fn read_stdin_value(typed: &mut VecDeque<Value>, io_mode: IoMode) -> Option<Value> {
+    while typed.is_empty() {
+        let mut line = String::new();
+        if std::io::stdin().read_line(&mut line).ok()? == 0 {
+            return None;
+        }
+        match io_mode.encode(&line) {
+            Ok(values) => *typed = values,
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+    typed.pop_front()
+}
+
+/// Measures the scheduler throughput for various slice sizes, running 1000 isolated
+/// computers with an instruction budget each. The program only supports 50 addresses,
+/// so they are reused across the computers.
+fn benchmark_scheduler(memory: &Memory) {
+    const VM_COUNT: usize = 1000;
+    const BUDGET: u64 = 10_000;
+
+    for slice_size in &[1, 10, 100, 1000, 10_000] {
+        let mut scheduler = Scheduler::new(*slice_size);
+        for i in 0..VM_COUNT {
+            let computer = IntcodeBuilder::new(memory.clone())
+                .id(i)
+                .input_values(&[(i % NetworkConfig::DEFAULT_COMPUTERS) as Value])
+                .on_empty_input(EmptyInput::Default(-1))
+                .budget(BUDGET)
+                .build();
+            scheduler.add(computer, None);
+        }
+
+        let start = Instant::now();
+        let executed = scheduler.run();
+        let elapsed = start.elapsed();
+        let out_of_budget = scheduler
+            .statuses()
+            .iter()
+            .filter(|(_, status)| *status == VmStatus::OutOfBudget)
+            .count();
+        println!(
+            "Slice size {:>6}: {} instructions in {:?} ({:.0} instructions/s), {} of {} VMs out of budget",
+            slice_size,
+            executed,
+            elapsed,
+            executed as f64 / elapsed.as_secs_f64(),
+            out_of_budget,
+            VM_COUNT
+        );
+    }
+}
+
+/// Compares starting and running 1000 computers on clones of the copy-on-write memory,
+/// which share the program image, with starting them on their own copy of the image.
+fn benchmark_memory(memory: &Memory) {
+    const VM_COUNT: usize = 1000;
+    const BUDGET: u64 = 10_000;
+
+    let image: Vec<Value> = (0..memory.image_len()).map(|a| memory[a]).collect();
+    let strategies: [(&str, &dyn Fn() -> Memory); 2] = [
+        ("Shared image", &|| memory.clone()),
+        ("Copied image", &|| Memory::new(image.clone())),
+    ];
+    for (name, new_memory) in strategies.iter() {
+        let start = Instant::now();
+        let mut scheduler = Scheduler::new(1000);
+        for i in 0..VM_COUNT {
+            let computer = IntcodeBuilder::new(new_memory())
+                .id(i)
+                .input_values(&[(i % NetworkConfig::DEFAULT_COMPUTERS) as Value])
+                .on_empty_input(EmptyInput::Default(-1))
+                .budget(BUDGET)
+                .build();
+            scheduler.add(computer, None);
+        }
+        let started = start.elapsed();
+        let executed = scheduler.run();
+        println!(
+            "{}: {} computers started in {:?}, {} instructions run in {:?}",
+            name,
+            VM_COUNT,
+            started,
+            executed,
+            start.elapsed() - started
+        );
+    }
+}
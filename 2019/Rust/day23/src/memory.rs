@@ -4,15 +4,183 @@ use std::io::Error;
 use std::io::Read;
 use std::ops::Index;
 use std::ops::IndexMut;
+use std::sync::Arc;
 
+pub const PAGE_SIZE: usize = 256;
+
+/// Type of the memory cells: i64, or i128 with the `wide-cells` feature for programs
+/// working with bigger values
+#[cfg(not(feature = "wide-cells"))]
+pub type Value = i64;
+#[cfg(feature = "wide-cells")]
+pub type Value = i128;
+
+/// Highest address the dense backend grows to: beyond it, an `Auto` memory is promoted to
+/// the sparse backend, and a `Dense` one can't be written to
+pub const DENSE_LIMIT: usize = 64 * 1024;
+
+/// Storage for the values written to a memory
+pub trait MemoryBackend {
+    /// Value at the address, if it has been written to
+    fn get(&self, address: usize) -> Option<&Value>;
+    /// Value at the address, set to `initial` first if it hasn't been written to yet
+    fn get_or_insert(&mut self, address: usize, initial: Value) -> &mut Value;
+    /// Addresses written to, in increasing order
+    fn addresses(&self) -> Vec<usize>;
+}
+
+/// Values stored in a vec indexed by address; best when the program only writes
+/// close to its image
+#[derive(Clone, Default)]
+pub struct DenseBackend {
+    values: Vec<Option<Value>>,
+}
+
+impl MemoryBackend for DenseBackend {
+    fn get(&self, address: usize) -> Option<&Value> {
+        self.values.get(address).and_then(|v| v.as_ref())
+    }
+
+    fn get_or_insert(&mut self, address: usize, initial: Value) -> &mut Value {
+        if address >= self.values.len() {
+            self.values.resize(address + 1, None);
+        }
+        self.values[address].get_or_insert(initial)
+    }
+
+    fn addresses(&self) -> Vec<usize> {
+        (0..self.values.len())
+            .filter(|&a| self.values[a].is_some())
+            .collect()
+    }
+}
+
+/// Values stored in pages of `PAGE_SIZE` addresses, allocated on the first write;
+/// for programs writing far beyond their image
+#[derive(Clone, Default)]
+pub struct SparseBackend {
+    pages: HashMap<usize, Box<[Option<Value>]>>,
+}
+
+impl MemoryBackend for SparseBackend {
+    fn get(&self, address: usize) -> Option<&Value> {
+        self.pages
+            .get(&(address / PAGE_SIZE))
+            .and_then(|page| page[address % PAGE_SIZE].as_ref())
+    }
+
+    fn get_or_insert(&mut self, address: usize, initial: Value) -> &mut Value {
+        let page = self
+            .pages
+            .entry(address / PAGE_SIZE)
+            .or_insert_with(|| vec![None; PAGE_SIZE].into_boxed_slice());
+        page[address % PAGE_SIZE].get_or_insert(initial)
+    }
+
+    fn addresses(&self) -> Vec<usize> {
+        let mut addresses: Vec<usize> = self
+            .pages
+            .iter()
+            .flat_map(|(index, page)| {
+                (0..PAGE_SIZE)
+                    .filter(move |&i| page[i].is_some())
+                    .map(move |i| index * PAGE_SIZE + i)
+            })
+            .collect();
+        addresses.sort();
+        addresses
+    }
+}
+
+/// Which backend a memory uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackendKind {
+    /// Dense only, up to `DENSE_LIMIT`
+    Dense,
+    Sparse,
+    /// Dense, until an address beyond `DENSE_LIMIT` is written to
+    Auto,
+}
+
+#[derive(Clone)]
+enum Overlay {
+    Dense(DenseBackend),
+    Sparse(SparseBackend),
+}
+
+impl Overlay {
+    fn backend(&self) -> &dyn MemoryBackend {
+        match self {
+            Overlay::Dense(backend) => backend,
+            Overlay::Sparse(backend) => backend,
+        }
+    }
+
+    fn backend_mut(&mut self) -> &mut dyn MemoryBackend {
+        match self {
+            Overlay::Dense(backend) => backend,
+            Overlay::Sparse(backend) => backend,
+        }
+    }
+}
+
+/// Copy-on-write memory: all the clones share the same initial image, and only
+/// keep track of the addresses they've written to. Cloning it is therefore cheap,
+/// as long as it hasn't been written to much.
 #[derive(Clone)]
 pub struct Memory {
-    _values: HashMap<usize, i64>,
+    image: Arc<Vec<Value>>,
+    overlay: Overlay,
+    kind: BackendKind,
 }
 
 impl Memory {
-    pub fn new(values: HashMap<usize, i64>) -> Memory {
-        Memory { _values: values }
+    pub fn new(values: Vec<Value>) -> Memory {
+        Memory {
+            image: Arc::new(values),
+            overlay: Overlay::Dense(DenseBackend::default()),
+            kind: BackendKind::Auto,
+        }
+    }
+
+    /// Moves the values written so far to the given backend. Values written beyond
+    /// `DENSE_LIMIT` are never moved to the dense backend: they stay in the sparse one.
+    pub fn set_backend(&mut self, kind: BackendKind) {
+        self.kind = kind;
+        match (kind, &self.overlay) {
+            (BackendKind::Sparse, Overlay::Dense(_)) => {
+                self.move_overlay(Overlay::Sparse(SparseBackend::default()))
+            }
+            (BackendKind::Dense, Overlay::Sparse(sparse))
+                if sparse.addresses().last().is_none_or(|&a| a <= DENSE_LIMIT) =>
+            {
+                self.move_overlay(Overlay::Dense(DenseBackend::default()))
+            }
+            _ => {}
+        }
+    }
+
+    /// Whether the address can be written to: a `Dense` memory doesn't grow beyond
+    /// `DENSE_LIMIT`
+    pub fn can_write(&self, address: usize) -> bool {
+        self.kind != BackendKind::Dense || address <= DENSE_LIMIT
+    }
+
+    /// Backend currently in use; a memory reports the sparse one once it's been promoted to it
+    pub fn backend_kind(&self) -> BackendKind {
+        match self.overlay {
+            Overlay::Dense(_) => BackendKind::Dense,
+            Overlay::Sparse(_) => BackendKind::Sparse,
+        }
+    }
+
+    fn move_overlay(&mut self, mut overlay: Overlay) {
+        let current = self.overlay.backend();
+        for address in current.addresses() {
+            let value = current.get(address).copied().unwrap_or(0);
+            *overlay.backend_mut().get_or_insert(address, value) = value;
+        }
+        self.overlay = overlay;
     }
 
     pub fn load_from_file(file_name: &str) -> Result<Memory, Error> {
@@ -24,29 +192,153 @@ impl Memory {
         Ok(Memory::parse(&content))
     }
     pub fn parse(string: &str) -> Memory {
-        let memory: HashMap<usize, i64> = string
+        let memory: Vec<Value> = string
+            .trim()
             .split(",")
             .map(|x| {
-                x.parse::<i64>()
-                    .expect(format!("Failed to parse {}", x).as_str())
+                x.parse::<Value>()
+                    .unwrap_or_else(|_| panic!("Failed to parse {}", x))
             })
-            .enumerate()
             .collect();
 
         Memory::new(memory)
     }
+
+    /// Number of values the program was loaded with
+    pub fn image_len(&self) -> usize {
+        self.image.len()
+    }
+
+    /// Indices of the pages that have been written to since the memory was loaded
+    pub fn dirty_pages(&self) -> Vec<usize> {
+        let mut pages: Vec<usize> = self
+            .overlay
+            .backend()
+            .addresses()
+            .into_iter()
+            .map(|a| a / PAGE_SIZE)
+            .collect();
+        pages.dedup();
+        pages
+    }
+
+    /// Addresses whose values differ between the 2 memories, along with the value in
+    /// each of them. If both memories share the same image, only their overlays
+    /// need to be compared.
+    pub fn diff(&self, other: &Memory) -> Vec<(usize, Value, Value)> {
+        let mut addresses: Vec<usize> = if Arc::ptr_eq(&self.image, &other.image) {
+            let mut addresses = self.overlay.backend().addresses();
+            addresses.extend(other.overlay.backend().addresses());
+            addresses
+        } else {
+            (0..self.image.len().max(other.image.len()))
+                .chain(self.overlay.backend().addresses())
+                .chain(other.overlay.backend().addresses())
+                .collect()
+        };
+        addresses.sort();
+        addresses.dedup();
+
+        addresses
+            .into_iter()
+            .map(|a| (a, self[a], other[a]))
+            .filter(|(_, x, y)| x != y)
+            .collect()
+    }
 }
 
 impl Index<usize> for Memory {
-    type Output = i64;
+    type Output = Value;
 
     fn index(&self, index: usize) -> &Self::Output {
-        &self._values.get(&index).unwrap_or(&0)
+        match self.overlay.backend().get(index) {
+            Some(value) => value,
+            None => self.image.get(index).unwrap_or(&0),
+        }
     }
 }
 
+/// Writing beyond `DENSE_LIMIT` promotes the memory to the sparse backend, even a `Dense`
+/// one: the dense backend would grow to the address. The VM checks `can_write` first.
 impl IndexMut<usize> for Memory {
     fn index_mut(&mut self, index: usize) -> &mut Self::Output {
-        self._values.entry(index).or_insert(0)
+        if index > DENSE_LIMIT && self.backend_kind() == BackendKind::Dense {
+            self.move_overlay(Overlay::Sparse(SparseBackend::default()));
+        }
+        let initial = self.image.get(index).copied().unwrap_or(0);
+        self.overlay.backend_mut().get_or_insert(index, initial)
+    }
+}
+
+#[test]
+fn diff_between_clones() {
+    let mut memory = Memory::parse("1,2,3,4");
+    let initial = memory.clone();
+    memory[1] = 5;
+    memory[2] = 3;
+    memory[PAGE_SIZE * 2] = 7;
+
+    assert_eq!(vec![0, 2], memory.dirty_pages());
+    assert_eq!(
+        vec![(1, 5, 2), (PAGE_SIZE * 2, 7, 0)],
+        memory.diff(&initial)
+    );
+    assert_eq!(
+        vec![(1, 2, 5)],
+        Memory::parse("1,2,3,4").diff(&Memory::parse("1,5,3,4"))
+    );
+}
+
+#[test]
+fn backends() {
+    for &kind in &[BackendKind::Dense, BackendKind::Sparse] {
+        let mut memory = Memory::parse("1,2,3,4");
+        memory.set_backend(kind);
+        memory[2] = 8;
+        memory[PAGE_SIZE * 3 + 1] = 9;
+
+        assert_eq!(kind, memory.backend_kind());
+        assert_eq!(8, memory[2]);
+        assert_eq!(9, memory[PAGE_SIZE * 3 + 1]);
+        assert_eq!(4, memory[3]);
+        assert_eq!(0, memory[PAGE_SIZE * 3]);
+        assert_eq!(vec![0, 3], memory.dirty_pages());
     }
 }
+
+#[test]
+fn auto_promotion() {
+    let mut memory = Memory::parse("1,2,3,4");
+    memory[1] = 5;
+    memory[100] = 6;
+    assert_eq!(BackendKind::Dense, memory.backend_kind());
+
+    memory[DENSE_LIMIT * 2] = 7;
+    assert_eq!(BackendKind::Sparse, memory.backend_kind());
+    assert_eq!(5, memory[1]);
+    assert_eq!(6, memory[100]);
+    assert_eq!(7, memory[DENSE_LIMIT * 2]);
+
+    // The value beyond the limit keeps the memory sparse
+    memory.set_backend(BackendKind::Dense);
+    assert_eq!(BackendKind::Sparse, memory.backend_kind());
+    assert_eq!(7, memory[DENSE_LIMIT * 2]);
+}
+
+#[test]
+fn dense_limit() {
+    let mut memory = Memory::parse("1,2,3,4");
+    memory.set_backend(BackendKind::Dense);
+    memory[DENSE_LIMIT] = 5;
+    assert_eq!(BackendKind::Dense, memory.backend_kind());
+    assert!(memory.can_write(DENSE_LIMIT));
+    assert!(!memory.can_write(DENSE_LIMIT + 1));
+
+    // Only the VM checks the limit; a direct write is moved to the sparse backend
+    memory[DENSE_LIMIT * 2] = 6;
+    assert_eq!(BackendKind::Sparse, memory.backend_kind());
+    assert_eq!(5, memory[DENSE_LIMIT]);
+    assert!(!memory.can_write(DENSE_LIMIT * 2));
+
+    assert!(Memory::parse("1,2,3,4").can_write(DENSE_LIMIT * 2));
+}
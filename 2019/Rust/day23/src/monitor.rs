@@ -0,0 +1,103 @@
+use crate::switch::Switch;
+
+/// Most lanes shown on a line; larger networks group several computers per lane
+const MAX_LANES: usize = 50;
+
+/// Characters showing the load of a lane, from idle to busiest
+const LOAD_RAMP: &[u8] = b" .:-=+*#%@";
+
+/// Text view of the network, enabled with `--monitor`: one line per round, with a lane
+/// per computer showing the packets waiting to be read. On large networks, each lane
+/// aggregates a group of consecutive computers, and only one round in `sample_every`
+/// is shown, as the rounds are that many times faster to go by than the lines.
+pub struct Monitor {
+    computers_per_lane: usize,
+    sample_every: usize,
+    round: usize,
+}
+
+impl Monitor {
+    pub fn new(computers: usize) -> Monitor {
+        let computers_per_lane = computers.div_ceil(MAX_LANES);
+        Monitor {
+            computers_per_lane,
+            sample_every: computers_per_lane,
+            round: 0,
+        }
+    }
+
+    /// Total of the queue lengths of each lane
+    pub fn lane_loads(&self, queue_lengths: &[usize]) -> Vec<usize> {
+        queue_lengths
+            .chunks(self.computers_per_lane)
+            .map(|lane| lane.iter().sum())
+            .collect()
+    }
+
+    /// Lanes of a round, each shown as a character of the ramp. The scale is logarithmic,
+    /// so a lane stays readable whether it queues a few packets or thousands.
+    pub fn render(&self, queue_lengths: &[usize]) -> String {
+        self.lane_loads(queue_lengths)
+            .iter()
+            .map(|&load| {
+                let level = (usize::BITS - load.leading_zeros()) as usize;
+                LOAD_RAMP[level.min(LOAD_RAMP.len() - 1)] as char
+            })
+            .collect()
+    }
+
+    /// Line describing the round that just ended, if it's one of the sampled rounds
+    pub fn observe(&mut self, switch: &Switch) -> Option<String> {
+        self.round += 1;
+        if !(self.round - 1).is_multiple_of(self.sample_every) {
+            return None;
+        }
+        let queue_lengths = switch.queue_lengths();
+        Some(format!(
+            "Round {:>6} |{}| {} queued, {} dropped",
+            self.round,
+            self.render(&queue_lengths),
+            queue_lengths.iter().sum::<usize>(),
+            switch.dropped()
+        ))
+    }
+}
+
+#[test]
+fn lanes_scale_with_the_network() {
+    let monitor = Monitor::new(50);
+    assert_eq!(50, monitor.lane_loads(&[0; 50]).len());
+
+    let monitor = Monitor::new(5000);
+    let mut queues = vec![0; 5000];
+    queues[0] = 1;
+    queues[99] = 2;
+    queues[4999] = 1000;
+    let loads = monitor.lane_loads(&queues);
+    assert_eq!(50, loads.len());
+    assert_eq!((3, 1000), (loads[0], loads[49]));
+
+    // 11 computers per lane, the last one only has 3
+    assert_eq!(48, Monitor::new(520).lane_loads(&[0; 520]).len());
+}
+
+#[test]
+fn loads_rendered_on_a_log_scale() {
+    let monitor = Monitor::new(5);
+    assert_eq!(" .:-@", monitor.render(&[0, 1, 2, 4, 1_000_000]));
+}
+
+#[test]
+fn rounds_sampled_on_large_networks() {
+    use crate::switch::NetworkConfig;
+
+    let config = NetworkConfig::new(500, None).unwrap();
+    let switch = Switch::new(config);
+    let mut monitor = Monitor::new(config.computers);
+    let shown = (0..100).filter_map(|_| monitor.observe(&switch)).count();
+    assert_eq!(10, shown);
+
+    let mut monitor = Monitor::new(50);
+    let line = monitor.observe(&switch).unwrap();
+    assert!(line.starts_with("Round      1 |"), "{}", line);
+}
@@ -1,44 +1,126 @@
+use crate::bus::{IntcodeBus, Route, Router};
+use crate::memory::Value;
 use std::cell::Cell;
 use std::cell::RefCell;
-use std::collections::VecDeque;
 use std::fmt;
 
+/// Size of the network: the computers have the addresses from 0 to `computers - 1`, and
+/// the NAT the highest address that fits in `address_bits` bits (255 in the puzzle)
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct NetworkConfig {
+    pub computers: usize,
+    pub address_bits: u32,
+}
+
+impl NetworkConfig {
+    pub const DEFAULT_COMPUTERS: usize = 50;
+    const MIN_ADDRESS_BITS: u32 = 8;
+
+    /// Network of the given size; the address width defaults to the smallest one of at
+    /// least 8 bits leaving an address for the NAT
+    pub fn new(computers: usize, address_bits: Option<u32>) -> Result<NetworkConfig, String> {
+        if computers == 0 {
+            return Err("The network needs at least one computer".to_string());
+        }
+        let needed = (usize::BITS - computers.leading_zeros()).max(Self::MIN_ADDRESS_BITS);
+        let address_bits = address_bits.unwrap_or(needed);
+        if address_bits < needed || address_bits >= usize::BITS {
+            return Err(format!(
+                "{} computers and the NAT don't fit in {}-bit addresses",
+                computers, address_bits
+            ));
+        }
+        Ok(NetworkConfig {
+            computers,
+            address_bits,
+        })
+    }
+
+    /// Network described by `--computers <count>` and `--address-bits <width>`
+    pub fn from_args(args: impl Iterator<Item = String>) -> Result<NetworkConfig, String> {
+        let args: Vec<String> = args.collect();
+        let option = |name: &str| args.iter().skip_while(|a| *a != name).nth(1);
+        let computers = match option("--computers") {
+            Some(count) => count
+                .parse()
+                .map_err(|_| format!("Invalid number of computers: {}", count))?,
+            None => Self::DEFAULT_COMPUTERS,
+        };
+        let address_bits = match option("--address-bits") {
+            Some(width) => Some(
+                width
+                    .parse()
+                    .map_err(|_| format!("Invalid address width: {}", width))?,
+            ),
+            None => None,
+        };
+        NetworkConfig::new(computers, address_bits)
+    }
+
+    pub fn nat_address(&self) -> usize {
+        (1 << self.address_bits) - 1
+    }
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        NetworkConfig::new(Self::DEFAULT_COMPUTERS, None).unwrap()
+    }
+}
+
+/// Sends the packets to the computer at their destination address, and those for the
+/// NAT out of the bus
+struct AddressRouter {
+    nat_address: usize,
+}
+
+impl Router<(usize, Packet)> for AddressRouter {
+    fn route(&mut self, &(address, _): &(usize, Packet)) -> Vec<Route> {
+        if address == self.nat_address {
+            vec![Route::External]
+        } else {
+            vec![Route::Computer(address)]
+        }
+    }
+}
+
+/// The network: a bus carrying the packets along with their destination address
 pub struct Switch {
-    values: RefCell<Vec<VecDeque<Packet>>>,
-    activity: RefCell<i64>,
+    bus: RefCell<IntcodeBus<(usize, Packet), AddressRouter>>,
     nat_packet: Cell<Option<Packet>>,
 }
 
 impl Switch {
-    pub fn new(size: usize) -> Self {
-        let values = (0..size).map(|_| VecDeque::new()).collect();
+    pub fn new(config: NetworkConfig) -> Self {
+        let router = AddressRouter {
+            nat_address: config.nat_address(),
+        };
         Switch {
-            values: RefCell::new(values),
-            activity: RefCell::new(0),
+            bus: RefCell::new(IntcodeBus::new(config.computers, router)),
             nat_packet: Cell::new(None),
         }
     }
 
-    pub fn get_activity(&self) -> i64 {
-        *self.activity.borrow()
+    pub fn get_activity(&self) -> Value {
+        self.bus.borrow().activity() as Value
     }
 
-    pub fn write(&self, addr: usize, data: Packet) -> () {
+    pub fn write(&self, addr: usize, data: Packet) {
         //println!("Addr {}: Writing {}", addr, data);
-        if addr < self.values.borrow().len() {
-            self.values.borrow_mut()[addr].push_back(data);
-            *self.activity.borrow_mut() += 1;
-        } else {
-            //println!("NAT packet: {}", data);
-            self.nat_packet.set(Some(data));
+        let mut bus = self.bus.borrow_mut();
+        bus.send((addr, data));
+        if let Some((_, packet)) = bus.take_external().pop() {
+            //println!("NAT packet: {}", packet);
+            self.nat_packet.set(Some(packet));
         }
     }
 
     pub fn read(&self, addr: usize) -> Option<Packet> {
-        let read = self.values.borrow_mut()[addr].pop_front();
-        if read.is_some() {
-            *self.activity.borrow_mut() += 1;
-        }
+        let read = self
+            .bus
+            .borrow_mut()
+            .receive(addr)
+            .map(|(_, packet)| packet);
         //println!("Addr {}: Reading {:?}", addr, read);
         read
     }
@@ -50,18 +132,75 @@ impl Switch {
     }
 
     pub fn is_quiet(&self) -> bool {
-        self.values.borrow().iter().all(|s| s.is_empty())
+        self.bus.borrow().is_quiet()
+    }
+
+    /// Number of packets waiting to be read, for each computer
+    pub fn queue_lengths(&self) -> Vec<usize> {
+        self.bus.borrow().queue_lengths()
+    }
+
+    /// Packets sent to an address without any computer
+    pub fn dropped(&self) -> usize {
+        self.bus.borrow().dropped()
+    }
+}
+
+/// Monitors the network, and sends the last packet received at the NAT address (255 by
+/// default) to the computer at address 0 whenever the network is idle
+pub struct Nat {
+    previous_packet: Option<Packet>,
+}
+
+impl Nat {
+    pub fn new() -> Nat {
+        Nat {
+            previous_packet: None,
+        }
+    }
+
+    /// Runs the network until the NAT sends the same Y value twice in a row, and
+    /// returns it. `run_round` gives all the computers a chance to run for a while,
+    /// and returns false once they have all exited.
+    pub fn run(&mut self, switch: &Switch, mut run_round: impl FnMut() -> bool) -> Option<Value> {
+        loop {
+            let switch_activity = switch.get_activity();
+            let switch_was_quiet = switch.is_quiet();
+
+            if !run_round() {
+                return None;
+            }
+
+            if switch_activity == switch.get_activity() && switch_was_quiet && switch.is_quiet() {
+                // No activity detected
+                if let Some(y) = self.wake_up(switch) {
+                    return Some(y);
+                }
+            }
+        }
+    }
+
+    fn wake_up(&mut self, switch: &Switch) -> Option<Value> {
+        let packet = switch.pop_nat_packet()?;
+        println!("Writing NAT Packet {}", packet);
+        switch.write(0, packet);
+
+        let previous_packet = self.previous_packet.replace(packet);
+        match previous_packet {
+            Some(p) if p.y == packet.y => Some(p.y),
+            _ => None,
+        }
     }
 }
 
 #[derive(Clone, Copy, Debug)]
 pub struct Packet {
-    pub x: i64,
-    pub y: i64,
+    pub x: Value,
+    pub y: Value,
 }
 
 impl Packet {
-    pub fn new(x: i64, y: i64) -> Packet {
+    pub fn new(x: Value, y: Value) -> Packet {
         Packet { x, y }
     }
 }
@@ -71,3 +210,100 @@ impl fmt::Display for Packet {
         write!(f, "(X: {}, Y: {})", self.x, self.y)
     }
 }
+
+#[cfg(test)]
+fn run_scripted(rounds: Vec<Vec<(usize, Packet)>>) -> (Option<Value>, Vec<Packet>) {
+    // Each round, the fake computers empty their queue, and send the scripted packets
+    let switch = Switch::new(NetworkConfig::new(2, None).unwrap());
+    let received = RefCell::new(vec![]);
+    let mut rounds = rounds.into_iter();
+    let result = Nat::new().run(&switch, || {
+        for addr in 0..2 {
+            while let Some(packet) = switch.read(addr) {
+                if addr == 0 {
+                    received.borrow_mut().push(packet);
+                }
+            }
+        }
+        match rounds.next() {
+            Some(packets) => {
+                for (addr, packet) in packets {
+                    switch.write(addr, packet);
+                }
+                true
+            }
+            None => false,
+        }
+    });
+
+    (result, received.into_inner())
+}
+
+#[test]
+fn packets_to_255_go_to_the_nat() {
+    let switch = Switch::new(NetworkConfig::new(2, None).unwrap());
+    switch.write(255, Packet::new(1, 2));
+    switch.write(255, Packet::new(3, 4));
+    switch.write(100, Packet::new(5, 6));
+
+    assert!(switch.is_quiet());
+    assert_eq!(Some((3, 4)), switch.pop_nat_packet().map(|p| (p.x, p.y)));
+    assert!(switch.pop_nat_packet().is_none());
+    assert_eq!(1, switch.dropped());
+}
+
+#[test]
+fn network_sizes() {
+    assert_eq!(255, NetworkConfig::default().nat_address());
+    let config = NetworkConfig::new(5000, None).unwrap();
+    assert_eq!((13, 8191), (config.address_bits, config.nat_address()));
+    // Addresses 0 to 255 are taken, so the NAT needs a wider one
+    assert_eq!(9, NetworkConfig::new(256, None).unwrap().address_bits);
+    assert!(NetworkConfig::new(500, Some(8)).is_err());
+    assert!(NetworkConfig::new(0, None).is_err());
+
+    let args = |s: &str| s.split(' ').map(|a| a.to_string()).collect::<Vec<_>>();
+    let config =
+        NetworkConfig::from_args(args("input.txt --computers 500 --address-bits 16").into_iter());
+    assert_eq!(
+        Ok((500, 65535)),
+        config.map(|c| (c.computers, c.nat_address()))
+    );
+    assert!(NetworkConfig::from_args(args("input.txt --computers many").into_iter()).is_err());
+}
+
+#[test]
+fn nat_waits_for_idle_network() {
+    let (result, received) = run_scripted(vec![
+        vec![(255, Packet::new(1, 10)), (1, Packet::new(0, 0))],
+        vec![(1, Packet::new(0, 0))],
+        vec![],
+        vec![],
+    ]);
+
+    // Round 4 is the first one with no activity at all, starting with empty queues
+    assert_eq!(None, result);
+    assert_eq!(vec![10], received.iter().map(|p| p.y).collect::<Vec<_>>());
+}
+
+#[test]
+fn nat_detects_duplicate_y() {
+    let (result, received) = run_scripted(vec![
+        vec![(255, Packet::new(1, 10))],
+        vec![],
+        vec![(255, Packet::new(2, 20))],
+        vec![],
+        vec![],
+        vec![(255, Packet::new(3, 20))],
+        vec![],
+        vec![],
+        vec![],
+    ]);
+
+    // The second packet with Y = 20 is detected as it's sent, before being received
+    assert_eq!(Some(20), result);
+    assert_eq!(
+        vec![10, 20],
+        received.iter().map(|p| p.y).collect::<Vec<_>>()
+    );
+}
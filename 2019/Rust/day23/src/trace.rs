@@ -0,0 +1,190 @@
+use crate::code::{Computer, ExecutionResult, Instruction, IntcodeError, ParameterMode};
+use crate::memory::Value;
+use std::io::{self, Write};
+
+/// Writes the instructions executed by computers as JSON Lines: one object per
+/// instruction, with its address, op code, operands, the values they resolve to, and
+/// the memory it reads and writes. Runs can then be compared line by line with external
+/// tools.
+pub struct JsonTrace<W: Write> {
+    out: W,
+    steps: u64,
+    /// First error writing the trace; nothing is written after it
+    error: Option<io::Error>,
+}
+
+impl<W: Write> JsonTrace<W> {
+    pub fn new(out: W) -> JsonTrace<W> {
+        JsonTrace {
+            out,
+            steps: 0,
+            error: None,
+        }
+    }
+
+    /// Executes a single instruction of the computer, like
+    /// `Computer::execute_single_instruction`, and traces it if it was executed
+    pub fn step(&mut self, computer: &mut Computer) -> Result<ExecutionResult, IntcodeError> {
+        // The operands are resolved before the instruction overwrites anything
+        let decoded = computer.decode().map(|instruction| {
+            let values = resolve(computer, &instruction);
+            (instruction, values)
+        });
+        let id = computer.id();
+        let step = computer.step()?;
+        if let (Some(_), Some((instruction, values))) = (step.instruction, decoded) {
+            let line = format_step(
+                self.steps,
+                id,
+                &instruction,
+                &values,
+                step.reads,
+                step.write,
+                step.output(),
+            );
+            self.steps += 1;
+            if self.error.is_none() {
+                self.error = writeln!(self.out, "{}", line).err();
+            }
+        }
+        Ok(step.result)
+    }
+
+    /// Number of instructions traced so far
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Flushes the trace, returning the first error met while writing it
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+/// Value of each operand as the instruction sees it: immediate operands are their own
+/// value, the others the content of the memory cell they point to. None for an operand
+/// pointing before address 0, or beyond the range of a memory cell.
+fn resolve(computer: &Computer, instruction: &Instruction) -> Vec<Option<Value>> {
+    let memory = computer.memory();
+    instruction
+        .parameters
+        .iter()
+        .map(|(mode, value)| {
+            let address = match mode {
+                ParameterMode::Immediate => return Some(*value),
+                ParameterMode::Position => Some(*value),
+                ParameterMode::Relative => computer.relative_base().checked_add(*value),
+            };
+            address.filter(|a| *a >= 0).map(|a| memory[a as usize])
+        })
+        .collect()
+}
+
+fn format_step(
+    step: u64,
+    computer: usize,
+    instruction: &Instruction,
+    values: &[Option<Value>],
+    reads: &[usize],
+    write: Option<(usize, Value)>,
+    output: Option<Value>,
+) -> String {
+    let list = |items: Vec<String>| format!("[{}]", items.join(","));
+    let modes = instruction.parameters.iter().map(|(mode, _)| {
+        match mode {
+            ParameterMode::Position => "\"position\"",
+            ParameterMode::Immediate => "\"immediate\"",
+            ParameterMode::Relative => "\"relative\"",
+        }
+        .to_string()
+    });
+    let operands = instruction.parameters.iter().map(|(_, v)| v.to_string());
+    let values = values.iter().map(|v| match v {
+        Some(v) => v.to_string(),
+        None => "null".to_string(),
+    });
+    let reads = reads.iter().map(|address| address.to_string());
+    let writes = write
+        .iter()
+        .map(|(address, value)| format!("{{\"address\":{},\"value\":{}}}", address, value));
+
+    let mut line = format!(
+        "{{\"step\":{},\"computer\":{},\"ip\":{},\"opcode\":\"{:?}\",\"modes\":{},\"operands\":{},\"values\":{},\"reads\":{},\"writes\":{}",
+        step,
+        computer,
+        instruction.address,
+        instruction.op_code,
+        list(modes.collect()),
+        list(operands.collect()),
+        list(values.collect()),
+        list(reads.collect()),
+        list(writes.collect())
+    );
+    if let Some(output) = output {
+        line += &format!(",\"output\":{}", output);
+    }
+    line + "}"
+}
+
+#[test]
+fn traced_instructions() {
+    use crate::memory::Memory;
+    use std::collections::VecDeque;
+
+    // Reads a value into address 9, doubles it into address 10, outputs it, and exits
+    let mut input = VecDeque::from(vec![21]);
+    let mut computer = Computer::new(
+        3,
+        Memory::parse("3,9,1002,9,2,10,4,10,99"),
+        move || input.pop_front(),
+        (),
+    );
+    let mut trace = JsonTrace::new(vec![]);
+    while trace.step(&mut computer).unwrap() != ExecutionResult::Exit {}
+    assert_eq!(4, trace.steps());
+
+    let text = String::from_utf8(trace.finish().unwrap()).unwrap();
+    let lines: Vec<_> = text.lines().collect();
+    assert_eq!(
+        vec![
+            r#"{"step":0,"computer":3,"ip":0,"opcode":"Input","modes":["position"],"operands":[9],"values":[0],"reads":[],"writes":[{"address":9,"value":21}]}"#,
+            r#"{"step":1,"computer":3,"ip":2,"opcode":"Mult","modes":["position","immediate","position"],"operands":[9,2,10],"values":[21,2,0],"reads":[9],"writes":[{"address":10,"value":42}]}"#,
+            r#"{"step":2,"computer":3,"ip":6,"opcode":"Output","modes":["position"],"operands":[10],"values":[42],"reads":[10],"writes":[],"output":42}"#,
+            r#"{"step":3,"computer":3,"ip":8,"opcode":"Exit","modes":[],"operands":[],"values":[],"reads":[],"writes":[]}"#,
+        ],
+        lines
+    );
+}
+
+#[test]
+fn unresolved_operands_are_null() {
+    use crate::memory::Memory;
+
+    let values = |computer: &Computer| resolve(computer, &computer.decode().unwrap());
+    let computer = |program: &str| Computer::new(0, Memory::parse(program), || None, ());
+    assert_eq!(vec![Some(204)], values(&computer("204,0,99")));
+    assert_eq!(vec![None], values(&computer("4,-1,99")));
+
+    // rb+1 is beyond the largest address
+    let mut computer = computer(&format!("109,{},204,1,99", Value::MAX));
+    computer.step().unwrap();
+    assert_eq!(vec![None], values(&computer));
+}
+
+#[test]
+fn waiting_for_input_isnt_traced() {
+    use crate::memory::Memory;
+
+    let mut computer = Computer::new(0, Memory::parse("3,0,99"), || None, ());
+    let mut trace = JsonTrace::new(vec![]);
+    assert_eq!(
+        Ok(ExecutionResult::MoreInputNeeded),
+        trace.step(&mut computer)
+    );
+    assert_eq!(0, trace.steps());
+    assert!(trace.finish().unwrap().is_empty());
+}
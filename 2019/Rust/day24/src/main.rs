@@ -1,4 +1,5 @@
 use array2d::Array2D;
+use neighbors::{Level, NEIGHBORS};
 use std::collections::VecDeque;
 use std::env;
 use std::error::Error;
@@ -7,6 +8,8 @@ use std::io::prelude::*;
 use std::io::BufReader;
 use std::result::Result;
 
+mod neighbors;
+
 const DISPLAY_GRIDS: bool = false;
 const TOTAL_MINUTES: u32 = 200;
 
@@ -67,7 +70,6 @@ fn evolve(grid_space: &mut GridSpace) -> () {
     }
 
     let original = original;
-    let max_depth = original.len() - 1;
 
     let get_bug = |x: usize, y: usize, d: usize| match original.get(d) {
         Some(grid) => *grid.get(y, x).unwrap_or(&false),
@@ -84,86 +86,16 @@ fn evolve(grid_space: &mut GridSpace) -> () {
         grid_space.get_mut(d).unwrap().set(y, x, bug).unwrap();
     };
 
-    let count_bugs_at = |x: usize, y: usize, d: usize| {
-        if get_bug(x, y, d) {
-            1usize
-        } else {
-            0usize
-        }
-    };
-
+    // Outer grids are at higher depths
     let nb_adjacent_bugs = |x: usize, y: usize, depth: usize| {
-        let bugs_left = match (x, y) {
-            (0, _) => {
-                if depth == max_depth {
-                    0
-                } else {
-                    count_bugs_at(1, 2, depth + 1)
-                }
-            }
-            (3, 2) => {
-                if depth == 0 {
-                    0
-                } else {
-                    (0..5).map(|y| count_bugs_at(4, y, depth - 1)).sum()
-                }
-            }
-            (x, y) => count_bugs_at(x - 1, y, depth),
-        };
-        let bugs_right = match (x, y) {
-            (4, _) => {
-                if depth == max_depth {
-                    0
-                } else {
-                    count_bugs_at(3, 2, depth + 1)
-                }
-            }
-            (1, 2) => {
-                if depth == 0 {
-                    0
-                } else {
-                    (0..5).map(|y| count_bugs_at(0, y, depth - 1)).sum()
-                }
-            }
-            (x, y) => count_bugs_at(x + 1, y, depth),
-        };
-        let bugs_top = match (x, y) {
-            (_, 0) => {
-                if depth == max_depth {
-                    0
-                } else {
-                    count_bugs_at(2, 1, depth + 1)
-                }
-            }
-            (2, 3) => {
-                if depth == 0 {
-                    0
-                } else {
-                    (0..5).map(|x| count_bugs_at(x, 4, depth - 1)).sum()
-                }
-            }
-            (x, y) => count_bugs_at(x, y - 1, depth),
-        };
-        let bugs_bottom = match (x, y) {
-            (_, 4) => {
-                if depth == max_depth {
-                    0
-                } else {
-                    count_bugs_at(2, 3, depth + 1)
-                }
-            }
-            (2, 1) => {
-                if depth == 0 {
-                    0
-                } else {
-                    (0..5).map(|x| count_bugs_at(x, 0, depth - 1)).sum()
-                }
-            }
-            (x, y) => count_bugs_at(x, y + 1, depth),
-        };
-        vec![bugs_left, bugs_right, bugs_top, bugs_bottom]
-            .into_iter()
-            .sum()
+        NEIGHBORS[y][x]
+            .iter()
+            .filter(|n| match n.level {
+                Level::Same => get_bug(n.x, n.y, depth),
+                Level::Outer => get_bug(n.x, n.y, depth + 1),
+                Level::Inner => depth > 0 && get_bug(n.x, n.y, depth - 1),
+            })
+            .count()
     };
 
     for depth in 0..original.len() {
@@ -0,0 +1,181 @@
+/// Grid a neighbour is in, relative to the grid of the cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Same,
+    /// The grid containing this one, in place of its middle cell
+    Outer,
+    /// The grid in place of this grid's middle cell
+    Inner,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Neighbor {
+    pub x: usize,
+    pub y: usize,
+    pub level: Level,
+}
+
+/// Cells next to the middle one have 3 neighbours in their grid, and 5 in the inner one
+pub const MAX_NEIGHBORS: usize = 8;
+
+#[derive(Clone, Copy)]
+pub struct Neighbors {
+    cells: [Neighbor; MAX_NEIGHBORS],
+    len: usize,
+}
+
+impl Neighbors {
+    pub fn iter(&self) -> impl Iterator<Item = &Neighbor> {
+        self.cells[..self.len].iter()
+    }
+}
+
+const NO_NEIGHBOR: Neighbor = Neighbor {
+    x: 0,
+    y: 0,
+    level: Level::Same,
+};
+
+/// Neighbours of each cell of the recursive grid, indexed by [y][x]. The middle cell has none.
+pub const NEIGHBORS: [[Neighbors; 5]; 5] = neighbor_table();
+
+const fn neighbor_table() -> [[Neighbors; 5]; 5] {
+    let empty = Neighbors {
+        cells: [NO_NEIGHBOR; MAX_NEIGHBORS],
+        len: 0,
+    };
+    let mut table = [[empty; 5]; 5];
+    // Left, right, up, down
+    let directions: [(i32, i32); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+
+    let mut y = 0;
+    while y < 5 {
+        let mut x = 0;
+        while x < 5 {
+            if x == 2 && y == 2 {
+                x += 1;
+                continue;
+            }
+            let mut cells = [NO_NEIGHBOR; MAX_NEIGHBORS];
+            let mut len = 0;
+            let mut d = 0;
+            while d < 4 {
+                let (dx, dy) = directions[d];
+                let nx = x as i32 + dx;
+                let ny = y as i32 + dy;
+                if nx < 0 || nx > 4 || ny < 0 || ny > 4 {
+                    // Off the edge: the outer grid's cell next to its middle, in that direction
+                    cells[len] = Neighbor {
+                        x: (2 + dx) as usize,
+                        y: (2 + dy) as usize,
+                        level: Level::Outer,
+                    };
+                    len += 1;
+                } else if nx == 2 && ny == 2 {
+                    // Into the middle: the whole facing edge of the inner grid
+                    let mut i = 0;
+                    while i < 5 {
+                        let (ix, iy) = match (dx, dy) {
+                            (1, 0) => (0, i),
+                            (-1, 0) => (4, i),
+                            (0, 1) => (i, 0),
+                            _ => (i, 4),
+                        };
+                        cells[len] = Neighbor {
+                            x: ix,
+                            y: iy,
+                            level: Level::Inner,
+                        };
+                        len += 1;
+                        i += 1;
+                    }
+                } else {
+                    cells[len] = Neighbor {
+                        x: nx as usize,
+                        y: ny as usize,
+                        level: Level::Same,
+                    };
+                    len += 1;
+                }
+                d += 1;
+            }
+            table[y][x] = Neighbors { cells, len };
+            x += 1;
+        }
+        y += 1;
+    }
+    table
+}
+
+/// Straightforward listing of the neighbours of (x, y), following the puzzle's description
+#[cfg(test)]
+fn reference_neighbors(x: usize, y: usize) -> Vec<Neighbor> {
+    let mut neighbors = vec![];
+    if (x, y) == (2, 2) {
+        return neighbors;
+    }
+    let same = |x, y| Neighbor {
+        x,
+        y,
+        level: Level::Same,
+    };
+    let outer = |x, y| Neighbor {
+        x,
+        y,
+        level: Level::Outer,
+    };
+    let inner = |x, y| Neighbor {
+        x,
+        y,
+        level: Level::Inner,
+    };
+
+    // Left
+    match (x, y) {
+        (0, _) => neighbors.push(outer(1, 2)),
+        (3, 2) => neighbors.extend((0..5).map(|y| inner(4, y))),
+        _ => neighbors.push(same(x - 1, y)),
+    }
+    // Right
+    match (x, y) {
+        (4, _) => neighbors.push(outer(3, 2)),
+        (1, 2) => neighbors.extend((0..5).map(|y| inner(0, y))),
+        _ => neighbors.push(same(x + 1, y)),
+    }
+    // Up
+    match (x, y) {
+        (_, 0) => neighbors.push(outer(2, 1)),
+        (2, 3) => neighbors.extend((0..5).map(|x| inner(x, 4))),
+        _ => neighbors.push(same(x, y - 1)),
+    }
+    // Down
+    match (x, y) {
+        (_, 4) => neighbors.push(outer(2, 3)),
+        (2, 1) => neighbors.extend((0..5).map(|x| inner(x, 0))),
+        _ => neighbors.push(same(x, y + 1)),
+    }
+    neighbors
+}
+
+#[test]
+fn table_matches_reference() {
+    for (y, row) in NEIGHBORS.iter().enumerate() {
+        for (x, neighbors) in row.iter().enumerate() {
+            let mut expected = reference_neighbors(x, y);
+            let mut actual: Vec<Neighbor> = neighbors.iter().cloned().collect();
+            expected.sort();
+            actual.sort();
+            assert_eq!(expected, actual, "neighbours of ({}, {})", x, y);
+        }
+    }
+}
+
+#[test]
+fn neighbor_counts() {
+    let count = |x: usize, y: usize| NEIGHBORS[y][x].iter().count();
+    // Puzzle examples: tile 19 has 4 neighbours, tile 14 has 8, and tile E has 4
+    assert_eq!(4, count(3, 3));
+    assert_eq!(8, count(3, 2));
+    assert_eq!(4, count(4, 0));
+    assert_eq!(0, count(2, 2));
+}
@@ -0,0 +1,200 @@
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Room {
+    pub name: String,
+    pub description: String,
+    pub doors: Vec<String>,
+    pub items: Vec<String>,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum Weight {
+    TooHeavy,
+    TooLight,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum Response {
+    /// We've entered a new room (or looked around the current one)
+    Room(Room),
+    /// The droid's weight was rejected by the pressure-sensitive floor, and it's back
+    /// in the room it came from
+    Ejected(Weight, Room),
+    /// The pressure-sensitive floor let us in, and gave us the airlock code
+    Airlock(String),
+    /// Anything else, like the result of taking or dropping an item
+    Message(String),
+}
+
+#[derive(PartialEq)]
+enum Section {
+    Description,
+    Doors,
+    Items,
+    Other,
+}
+
+/// Parses the output of the program after a command, up to the next prompt
+pub fn parse_response(output: &str) -> Response {
+    if let Some(code) = parse_airlock_code(output) {
+        return Response::Airlock(code);
+    }
+
+    let weight = if output.contains("are heavier than the detected value") {
+        Some(Weight::TooLight)
+    } else if output.contains("are lighter than the detected value") {
+        Some(Weight::TooHeavy)
+    } else {
+        None
+    };
+
+    // When ejected, the last room is the one we're back in
+    match (parse_rooms(output).pop(), weight) {
+        (Some(room), Some(weight)) => Response::Ejected(weight, room),
+        (Some(room), None) => Response::Room(room),
+        (None, _) => Response::Message(
+            output
+                .lines()
+                .map(|l| l.trim())
+                .filter(|l| !l.is_empty() && *l != "Command?")
+                .collect::<Vec<_>>()
+                .join(" "),
+        ),
+    }
+}
+
+fn parse_airlock_code(output: &str) -> Option<String> {
+    let text = output.split_whitespace().collect::<Vec<_>>();
+    let index = text.windows(2).position(|w| w == ["by", "typing"])?;
+    let code = text.get(index + 2)?;
+    if code.chars().all(|c| c.is_ascii_digit()) {
+        Some(code.to_string())
+    } else {
+        None
+    }
+}
+
+fn parse_rooms(output: &str) -> Vec<Room> {
+    let mut rooms: Vec<Room> = vec![];
+    let mut section = Section::Other;
+
+    for line in output.lines() {
+        let line = line.trim_end();
+        if line.starts_with("== ") && line.ends_with(" ==") {
+            rooms.push(Room {
+                name: line[3..line.len() - 3].to_string(),
+                description: String::new(),
+                doors: vec![],
+                items: vec![],
+            });
+            section = Section::Description;
+            continue;
+        }
+
+        let room = match rooms.last_mut() {
+            Some(room) => room,
+            None => continue,
+        };
+
+        match line {
+            "Doors here lead:" => section = Section::Doors,
+            "Items here:" => section = Section::Items,
+            "" if section == Section::Description => (),
+            "" => section = Section::Other,
+            _ => {
+                let list = match section {
+                    Section::Description => {
+                        // The description may be wrapped over several lines
+                        if !room.description.is_empty() {
+                            room.description.push(' ');
+                        }
+                        room.description.push_str(line.trim());
+                        continue;
+                    }
+                    Section::Doors => &mut room.doors,
+                    Section::Items => &mut room.items,
+                    Section::Other => continue,
+                };
+
+                match (line.strip_prefix("- "), list.last_mut()) {
+                    (Some(entry), _) => list.push(entry.trim().to_string()),
+                    // Continuation of a wrapped entry
+                    (None, Some(entry)) => {
+                        entry.push(' ');
+                        entry.push_str(line.trim());
+                    }
+                    (None, None) => section = Section::Other,
+                }
+            }
+        }
+    }
+
+    rooms
+}
+
+#[cfg(test)]
+fn room(name: &str, description: &str, doors: &[&str], items: &[&str]) -> Room {
+    Room {
+        name: name.to_string(),
+        description: description.to_string(),
+        doors: doors.iter().map(|d| d.to_string()).collect(),
+        items: items.iter().map(|i| i.to_string()).collect(),
+    }
+}
+
+#[test]
+fn parse_room() {
+    assert_eq!(
+        Response::Room(room(
+            "Hull Breach",
+            "You got in through a hole in the floor here. To keep your ship from also freezing, the hole has been sealed.",
+            &["north", "east", "west"],
+            &[]
+        )),
+        parse_response(include_str!("../corpus/room.txt"))
+    );
+}
+
+#[test]
+fn parse_wrapped_lines() {
+    assert_eq!(
+        Response::Room(room(
+            "Hallway",
+            "This area has been optimized for something; you're just not quite sure what.",
+            &["north", "south"],
+            &["whirled peas", "mug"]
+        )),
+        parse_response(include_str!("../corpus/items.txt"))
+    );
+}
+
+#[test]
+fn parse_message() {
+    assert_eq!(
+        Response::Message("You take the mug.".to_string()),
+        parse_response(include_str!("../corpus/take.txt"))
+    );
+}
+
+#[test]
+fn parse_ejection() {
+    assert_eq!(
+        Response::Ejected(
+            Weight::TooLight,
+            room(
+                "Security Checkpoint",
+                "In the next room, a pressure-sensitive floor will verify your identity.",
+                &["north", "south"],
+                &[]
+            )
+        ),
+        parse_response(include_str!("../corpus/ejected.txt"))
+    );
+}
+
+#[test]
+fn parse_airlock() {
+    assert_eq!(
+        Response::Airlock("2424308736".to_string()),
+        parse_response(include_str!("../corpus/airlock.txt"))
+    );
+}
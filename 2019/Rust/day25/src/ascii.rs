@@ -0,0 +1,53 @@
+use std::collections::VecDeque;
+
+/// Character of an output value; None for the values beyond the ASCII range, which the
+/// programs use to report results such as the amount of dust collected
+pub fn to_char(value: i64) -> Option<char> {
+    if (0..128).contains(&value) {
+        Some(value as u8 as char)
+    } else {
+        None
+    }
+}
+
+/// Input values of a line of text: its characters, then a new line
+pub fn encode_line(line: &str) -> Vec<i64> {
+    line.chars()
+        .filter(|c| *c != '\r' && *c != '\n')
+        .chain(Some('\n'))
+        .map(|c| c as i64)
+        .collect()
+}
+
+/// Text of output values, leaving out the ones that aren't characters
+pub fn decode(values: impl IntoIterator<Item = i64>) -> String {
+    values.into_iter().filter_map(to_char).collect()
+}
+
+/// Commands of a script file, one per line; blank lines and lines starting with `#` are
+/// left out
+pub fn script_commands(text: &str) -> VecDeque<String> {
+    text.lines()
+        .map(|line| line.trim_end())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.to_string())
+        .collect()
+}
+
+#[test]
+fn line_conversions() {
+    assert_eq!(vec![78, 79, 84, 10], encode_line("NOT"));
+    assert_eq!(vec![65, 10], encode_line("A\r\n"));
+    assert_eq!("Hi\n", decode(vec![72, 105, 10, 1234]));
+    assert_eq!(None, to_char(128));
+    assert_eq!(None, to_char(-1));
+}
+
+#[test]
+fn script_commands_skip_comments() {
+    let script = "# Walk\nnorth\n\ntake mug  \r\n  # done\ninv";
+    assert_eq!(
+        vec!["north", "take mug", "  # done", "inv"],
+        Vec::from(script_commands(script))
+    );
+}
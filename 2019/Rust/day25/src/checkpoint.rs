@@ -0,0 +1,266 @@
+use crate::adventure::{parse_response, Response, Room, Weight};
+use crate::ascii;
+use crate::dialogue::Vm;
+use crate::explore::{explore, Oracle, Space};
+use crate::retry::RetryPolicy;
+use crate::ExecutionContext;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Items that end the game or trap the droid when taken
+const HAZARDS: [&str; 5] = [
+    "escape pod",
+    "giant electromagnet",
+    "infinite loop",
+    "molten lava",
+    "photons",
+];
+
+/// Outcome of the search for the item combination accepted by the pressure-sensitive floor
+pub struct Analysis {
+    pub checkpoint: String,
+    pub airlock_code: Option<String>,
+    /// Items collected while exploring the ship
+    pub items: Vec<String>,
+    /// Items carried when the floor let the droid in
+    pub solution: Vec<String>,
+    /// Number of VMs forked from the checkpoint snapshot
+    pub forks: usize,
+    /// Combinations the floor rejected as too heavy, and as too light
+    pub rejections: (usize, usize),
+    /// Commands sent to explore the ship and reach the checkpoint; replaying the session
+    /// for each combination would send them again every time
+    pub setup_commands: usize,
+    pub setup_time: Duration,
+    pub search_time: Duration,
+}
+
+impl Analysis {
+    pub fn report(&self) -> String {
+        let saved = self.setup_time * self.forks.saturating_sub(1) as u32;
+        let mut lines = vec![
+            format!("Checkpoint: {}", self.checkpoint),
+            format!("Items collected: {}", self.items.join(", ")),
+            format!("Items accepted: {}", self.solution.join(", ")),
+            format!(
+                "Airlock code: {}",
+                self.airlock_code.as_deref().unwrap_or("not found")
+            ),
+            format!(
+                "Setup: {} commands in {:.1?}",
+                self.setup_commands, self.setup_time
+            ),
+            format!(
+                "Search: {} forks in {:.1?}; rejected {} too heavy, {} too light",
+                self.forks, self.search_time, self.rejections.0, self.rejections.1
+            ),
+        ];
+        lines.push(format!(
+            "Naive replay would have sent {} more commands; time saved: ~{:.1?}",
+            self.setup_commands * self.forks.saturating_sub(1),
+            saved
+        ));
+        lines.join("\n")
+    }
+}
+
+fn opposite(door: &str) -> &str {
+    match door {
+        "north" => "south",
+        "south" => "north",
+        "east" => "west",
+        "west" => "east",
+        other => other,
+    }
+}
+
+/// Sends a command to the droid, and parses the output up to the next prompt
+fn send(vm: &mut ExecutionContext, command: &str) -> Response {
+    vm.send_line(command);
+    vm.run();
+    parse_response(&ascii::decode(vm.take_output()))
+}
+
+/// The droid, as the oracle telling what's behind each door: it takes every safe item in
+/// the rooms it enters, and stops answering once the game is over
+struct Droid {
+    vm: ExecutionContext,
+    items: Vec<String>,
+    commands: usize,
+}
+
+impl Droid {
+    fn send(&mut self, command: &str) -> Response {
+        self.commands += 1;
+        send(&mut self.vm, command)
+    }
+
+    fn take_items(&mut self, room: &Room) {
+        for item in &room.items {
+            if !HAZARDS.contains(&item.as_str()) {
+                self.send(&format!("take {}", item));
+                self.items.push(item.clone());
+            }
+        }
+    }
+}
+
+impl Oracle<String, Response> for Droid {
+    fn query(&mut self, door: &String) -> Option<Response> {
+        if self.vm.ended {
+            return None;
+        }
+        let response = self.send(door);
+        if let Response::Room(room) = &response {
+            self.take_items(room);
+        }
+        Some(response)
+    }
+}
+
+/// Doors of the rooms visited so far, and the way to the pressure-sensitive floor
+#[derive(Default)]
+struct Ship {
+    doors: HashMap<String, Vec<String>>,
+    /// The checkpoint's name, and its door to the floor
+    checkpoint: Option<(String, String)>,
+    airlock_code: Option<String>,
+}
+
+impl Space<String, Response> for Ship {
+    type Node = String;
+
+    fn moves(&self, room: &String) -> Vec<String> {
+        self.doors.get(room).cloned().unwrap_or_default()
+    }
+
+    fn reverse(&self, door: &String) -> String {
+        opposite(door).to_string()
+    }
+
+    fn reached(&mut self, from: &String, door: &String, response: Response) -> Option<String> {
+        match response {
+            Response::Room(room) => {
+                self.doors.entry(room.name.clone()).or_insert(room.doors);
+                Some(room.name)
+            }
+            Response::Ejected(_, _) => {
+                self.checkpoint = Some((from.clone(), door.clone()));
+                None
+            }
+            // Lucky: everything we carry is the right weight
+            Response::Airlock(code) => {
+                self.checkpoint = Some((from.clone(), door.clone()));
+                self.airlock_code = Some(code);
+                None
+            }
+            Response::Message(_) => None,
+        }
+    }
+}
+
+/// Explores the ship, then snapshots the droid at the checkpoint once, and tries the
+/// combinations of items on VMs forked from that snapshot
+pub fn analyze(vm: &ExecutionContext) -> Option<Analysis> {
+    let start = Instant::now();
+    let mut vm = vm.clone();
+    vm.output.set_tee(false);
+    let room = match send(&mut vm, "") {
+        Response::Room(room) => room,
+        _ => return None,
+    };
+
+    let mut droid = Droid {
+        vm,
+        items: vec![],
+        commands: 0,
+    };
+    droid.take_items(&room);
+    let mut ship = Ship::default();
+    ship.doors.insert(room.name.clone(), room.doors);
+    let graph = explore(&mut ship, &mut droid, room.name.clone());
+    let (checkpoint, door) = ship.checkpoint.clone()?;
+    let mut analysis = Analysis {
+        checkpoint: checkpoint.clone(),
+        airlock_code: ship.airlock_code.clone(),
+        items: droid.items.clone(),
+        solution: droid.items.clone(),
+        forks: 0,
+        rejections: (0, 0),
+        setup_commands: droid.commands,
+        setup_time: Duration::default(),
+        search_time: Duration::default(),
+    };
+    if analysis.airlock_code.is_some() {
+        analysis.setup_time = start.elapsed();
+        return Some(analysis);
+    }
+
+    // Back at the start after exploring: walk to the checkpoint
+    for door in graph.path(&room.name, &checkpoint)? {
+        droid.send(&door);
+    }
+    analysis.setup_commands = droid.commands;
+    analysis.setup_time = start.elapsed();
+
+    // Each attempt starts over from the checkpoint, with the next combination
+    let start = Instant::now();
+    let items = analysis.items.clone();
+    let rejections = &mut analysis.rejections;
+    let result = droid
+        .vm
+        .retry(&RetryPolicy::new(1 << items.len()), |vm, n| {
+            let combination = n - 1;
+            let carried = |i: usize| combination & 1 << i != 0;
+            for (i, item) in items.iter().enumerate() {
+                if !carried(i) {
+                    send(vm, &format!("drop {}", item));
+                }
+            }
+            let response = send(vm, &door);
+            match &response {
+                Response::Airlock(code) => return Ok((n, code.clone(), combination)),
+                Response::Ejected(Weight::TooHeavy, _) => rejections.0 += 1,
+                Response::Ejected(Weight::TooLight, _) => rejections.1 += 1,
+                _ => (),
+            }
+            Err(response)
+        });
+    match result {
+        Ok((n, code, combination)) => {
+            analysis.forks = n as usize;
+            analysis.airlock_code = Some(code);
+            analysis.solution = items
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| combination & 1 << i != 0)
+                .map(|(_, item)| item.clone())
+                .collect();
+        }
+        Err(e) => analysis.forks = e.errors.len(),
+    }
+    analysis.search_time = start.elapsed();
+    Some(analysis)
+}
+
+#[test]
+fn opposite_doors() {
+    assert_eq!("south", opposite("north"));
+    assert_eq!("east", opposite("west"));
+}
+
+#[test]
+fn analyze_input() {
+    use crate::memory::Memory;
+
+    let memory = Memory::parse(include_str!("../input.txt"));
+    let analysis = analyze(&ExecutionContext::new(&memory)).unwrap();
+    assert_eq!("Security Checkpoint", analysis.checkpoint);
+    assert_eq!(8, analysis.items.len());
+    assert!(analysis.forks <= 256);
+    // Every combination before the right one was rejected
+    let (too_heavy, too_light) = analysis.rejections;
+    assert_eq!(analysis.forks - 1, too_heavy + too_light);
+    let code = analysis.airlock_code.unwrap();
+    assert!(code.chars().all(|c| c.is_ascii_digit()));
+}
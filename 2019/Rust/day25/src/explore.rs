@@ -0,0 +1,323 @@
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+
+/// Stateful source of truth about a graph that can't be observed directly: the only way
+/// to learn about it is to make moves from the current node and look at the responses
+pub trait Oracle<M, R> {
+    /// Makes the move; None once the oracle has stopped answering
+    fn query(&mut self, m: &M) -> Option<R>;
+}
+
+/// How the moves and responses of an oracle relate to the nodes of the graph
+pub trait Space<M, R> {
+    type Node: Clone + Eq + Hash;
+
+    /// Moves worth trying from the node
+    fn moves(&self, node: &Self::Node) -> Vec<M>;
+
+    /// Move undoing the given one
+    fn reverse(&self, m: &M) -> M;
+
+    /// Node reached by making the move from `from`, according to the response; None if
+    /// the move was refused, leaving the explorer where it was
+    fn reached(&mut self, from: &Self::Node, m: &M, response: R) -> Option<Self::Node>;
+}
+
+/// Graph learned by exploring a space
+pub struct Graph<N, M> {
+    /// Moves leading out of each node, with the node they reach
+    pub edges: HashMap<N, Vec<(M, N)>>,
+    /// Moves sent to the oracle
+    pub queries: usize,
+    /// False if the oracle stopped answering before every move could be tried
+    pub complete: bool,
+}
+
+impl<N: Clone + Eq + Hash, M: Clone> Graph<N, M> {
+    /// Fewest moves from `from` to each node it leads to
+    pub fn distances(&self, from: &N) -> HashMap<N, usize> {
+        let mut distances = HashMap::new();
+        let mut queue = VecDeque::new();
+        distances.insert(from.clone(), 0);
+        queue.push_back(from.clone());
+        while let Some(node) = queue.pop_front() {
+            let distance = distances[&node];
+            for (_, next) in self.edges.get(&node).into_iter().flatten() {
+                if !distances.contains_key(next) {
+                    distances.insert(next.clone(), distance + 1);
+                    queue.push_back(next.clone());
+                }
+            }
+        }
+        distances
+    }
+
+    /// Shortest sequence of moves from `from` to `to`
+    pub fn path(&self, from: &N, to: &N) -> Option<Vec<M>> {
+        let mut came_from: HashMap<N, (N, M)> = HashMap::new();
+        let mut queue = VecDeque::new();
+        queue.push_back(from.clone());
+        while let Some(node) = queue.pop_front() {
+            if node == *to {
+                let mut moves = vec![];
+                let mut node = node;
+                while let Some((previous, m)) = came_from.get(&node) {
+                    moves.push(m.clone());
+                    node = previous.clone();
+                }
+                moves.reverse();
+                return Some(moves);
+            }
+            for (m, next) in self.edges.get(&node).into_iter().flatten() {
+                if *next != *from && !came_from.contains_key(next) {
+                    came_from.insert(next.clone(), (node.clone(), m.clone()));
+                    queue.push_back(next.clone());
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Node on the path from the start, with the moves left to try from it
+struct Frame<N, M> {
+    node: N,
+    moves: Vec<M>,
+    /// Move that led to the node from the previous one on the path
+    arrival: Option<M>,
+}
+
+/// Explores the space depth-first from `start`, walking back after each new node to try
+/// the other moves. Ends back at the start, unless the oracle stops answering.
+pub fn explore<M, R, S, O>(space: &mut S, oracle: &mut O, start: S::Node) -> Graph<S::Node, M>
+where
+    M: Clone + PartialEq,
+    S: Space<M, R>,
+    O: Oracle<M, R>,
+{
+    let mut graph = Graph {
+        edges: HashMap::new(),
+        queries: 0,
+        complete: false,
+    };
+    graph.edges.insert(start.clone(), vec![]);
+
+    // The moves are popped from the end of the list
+    let frame = |space: &S, node: S::Node, arrival: Option<M>| {
+        let mut moves = space.moves(&node);
+        moves.reverse();
+        Frame {
+            node,
+            moves,
+            arrival,
+        }
+    };
+    let mut path = vec![frame(space, start, None)];
+
+    while let Some(top) = path.last_mut() {
+        let node = top.node.clone();
+        let m = match top.moves.pop() {
+            Some(m) => m,
+            None => {
+                // Done with this node: walk back to the previous one
+                let top = path.pop().unwrap();
+                if let (Some(arrival), Some(previous)) = (top.arrival, path.last()) {
+                    let back = space.reverse(&arrival);
+                    graph.queries += 1;
+                    let response = match oracle.query(&back) {
+                        Some(response) => response,
+                        None => return graph,
+                    };
+                    space.reached(&node, &back, response);
+                    let previous = previous.node.clone();
+                    graph.edges.get_mut(&node).unwrap().push((back, previous));
+                }
+                continue;
+            }
+        };
+
+        // The way back is already known, and taken once the node is done
+        if top.arrival.as_ref().map(|a| space.reverse(a)).as_ref() == Some(&m) {
+            continue;
+        }
+
+        graph.queries += 1;
+        let response = match oracle.query(&m) {
+            Some(response) => response,
+            None => return graph,
+        };
+        let next = match space.reached(&node, &m, response) {
+            Some(next) => next,
+            None => continue,
+        };
+        graph
+            .edges
+            .get_mut(&node)
+            .unwrap()
+            .push((m.clone(), next.clone()));
+        if graph.edges.contains_key(&next) {
+            // Already explored, or being explored further up the path: come back
+            let back = space.reverse(&m);
+            graph.queries += 1;
+            let response = match oracle.query(&back) {
+                Some(response) => response,
+                None => return graph,
+            };
+            space.reached(&next, &back, response);
+        } else {
+            graph.edges.insert(next.clone(), vec![]);
+            path.push(frame(space, next, Some(m)));
+        }
+    }
+
+    graph.complete = true;
+    graph
+}
+
+/// Droid moving on a grid of open cells, which answers whether each move succeeded
+#[cfg(test)]
+struct MockGrid {
+    open: std::collections::HashSet<(i32, i32)>,
+    position: (i32, i32),
+    /// Queries answered before the mock stops
+    budget: usize,
+}
+
+#[cfg(test)]
+fn step((x, y): (i32, i32), m: char) -> (i32, i32) {
+    match m {
+        'N' => (x, y - 1),
+        'S' => (x, y + 1),
+        'W' => (x - 1, y),
+        _ => (x + 1, y),
+    }
+}
+
+#[cfg(test)]
+impl MockGrid {
+    fn parse(text: &str) -> MockGrid {
+        let open = text
+            .lines()
+            .enumerate()
+            .flat_map(|(y, line)| {
+                line.chars()
+                    .enumerate()
+                    .filter(|(_, c)| *c == '.')
+                    .map(move |(x, _)| (x as i32, y as i32))
+            })
+            .collect();
+        MockGrid {
+            open,
+            position: (1, 1),
+            budget: usize::MAX,
+        }
+    }
+}
+
+#[cfg(test)]
+impl Oracle<char, bool> for MockGrid {
+    fn query(&mut self, m: &char) -> Option<bool> {
+        if self.budget == 0 {
+            return None;
+        }
+        self.budget -= 1;
+        let target = step(self.position, *m);
+        let moved = self.open.contains(&target);
+        if moved {
+            self.position = target;
+        }
+        Some(moved)
+    }
+}
+
+/// Space of the grid mock, remembering the walls hit
+#[cfg(test)]
+#[derive(Default)]
+struct GridSpace {
+    walls: std::collections::HashSet<(i32, i32)>,
+}
+
+#[cfg(test)]
+impl Space<char, bool> for GridSpace {
+    type Node = (i32, i32);
+
+    fn moves(&self, _: &(i32, i32)) -> Vec<char> {
+        vec!['N', 'S', 'W', 'E']
+    }
+
+    fn reverse(&self, m: &char) -> char {
+        match m {
+            'N' => 'S',
+            'S' => 'N',
+            'W' => 'E',
+            _ => 'W',
+        }
+    }
+
+    fn reached(&mut self, from: &(i32, i32), m: &char, moved: bool) -> Option<(i32, i32)> {
+        let target = step(*from, *m);
+        if moved {
+            Some(target)
+        } else {
+            self.walls.insert(target);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+const MAZE: &str = "\
+#######
+#...#.#
+#.#.#.#
+#.#...#
+#######";
+
+#[test]
+fn explores_the_whole_grid() {
+    let mut oracle = MockGrid::parse(MAZE);
+    let mut space = GridSpace::default();
+    let graph = explore(&mut space, &mut oracle, (1, 1));
+
+    assert!(graph.complete);
+    assert_eq!((1, 1), oracle.position);
+    let nodes: std::collections::HashSet<_> = graph.edges.keys().cloned().collect();
+    assert_eq!(oracle.open, nodes);
+    // Every wall next to an open cell has been hit
+    assert_eq!(18, space.walls.len());
+}
+
+#[test]
+fn paths_and_distances() {
+    let mut oracle = MockGrid::parse(MAZE);
+    let graph = explore(&mut GridSpace::default(), &mut oracle, (1, 1));
+
+    let path = graph.path(&(1, 1), &(5, 1)).unwrap();
+    assert_eq!("EESSEENN", path.iter().collect::<String>());
+    let distances = graph.distances(&(5, 1));
+    assert_eq!(Some(&8), distances.get(&(1, 1)));
+    // The far end of the dead end on the left
+    assert_eq!(Some(&10), distances.get(&(1, 3)));
+    assert_eq!(Some(10), distances.values().max().cloned());
+    assert_eq!(Some(vec![]), graph.path(&(3, 3), &(3, 3)));
+}
+
+#[test]
+fn arrival_move_not_retried() {
+    // A corridor: each cell's way back is known without asking
+    let mut oracle = MockGrid::parse("#####\n#...#\n#####");
+    let graph = explore(&mut GridSpace::default(), &mut oracle, (1, 1));
+    // 4 moves from the first cell, 3 from each of the others, 2 to walk back
+    assert_eq!(4 + 3 + 3 + 2, graph.queries);
+    assert_eq!(2, graph.edges[&(2, 1)].len());
+}
+
+#[test]
+fn oracle_stopping_early() {
+    let mut oracle = MockGrid::parse(MAZE);
+    oracle.budget = 5;
+    let graph = explore(&mut GridSpace::default(), &mut oracle, (1, 1));
+    assert!(!graph.complete);
+    assert_eq!(6, graph.queries);
+    assert!(graph.edges.len() < oracle.open.len());
+}
@@ -1,125 +1,200 @@
+use crate::breakpoints::{BreakReason, Breakpoints};
+use crate::dialogue::{Delimiter, Dialogue, FromOutput, Vm};
 use crate::memory::Memory;
-use std::collections::HashMap;
+use crate::retry::{retry, RetryError, RetryPolicy};
+use crate::sink::OutputSink;
+use crate::transcript::Transcript;
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::env;
 use std::fs::File;
 use std::io::Write;
 use std::io::{stdin, stdout, Read};
-use std::thread::sleep;
-use std::time::Duration;
-
-#[cfg(unix)]
-extern crate ncurses;
-
+use std::process;
+
+mod adventure;
+mod ascii;
+mod breakpoints;
+mod checkpoint;
+// Same dialogue as day15, which uses the rest of the module
+#[allow(dead_code)]
+mod dialogue;
+// Same explorer as day15, which uses the rest of the module
+#[allow(dead_code)]
+mod explore;
 mod memory;
+mod retry;
+mod sink;
+#[cfg(all(unix, feature = "ui"))]
+mod terminal;
+mod transcript;
 
 type Result<T> = ::std::result::Result<T, Box<dyn ::std::error::Error>>;
 
-#[derive(Eq, PartialEq, Hash, Clone, Copy)]
-struct Pos(i32, i32);
+// Exit code for Intcode VM errors, shared with the runner
+const EXIT_VM_ERROR: i32 = 4;
 
-enum Cell {
-    Robot(RobotStatus),
-    Empty,
-    Scaffold,
+/// Reports an error of the Intcode VM, and exits with the runner's code for VM errors
+fn vm_error(message: String) -> ! {
+    eprintln!("VM error: {}", message);
+    process::exit(EXIT_VM_ERROR);
 }
 
-impl Cell {
-    fn is_scaffold(&self) -> bool {
-        match self {
-            Cell::Scaffold => true,
-            _ => false,
-        }
+fn main() -> Result<()> {
+    // day25 convert <input> <output>: converts a program between text and binary images
+    if env::args().nth(1).as_deref() == Some("convert") {
+        let input = env::args().nth(2).ok_or("Enter the file to convert")?;
+        let output = env::args().nth(3).ok_or("Enter the output file")?;
+        return convert(&input, &output);
     }
-}
-
-enum RobotStatus {
-    Up,
-    Down,
-    Left,
-    Right,
-    Falling,
-}
 
-fn main() -> Result<()> {
     let file_name = env::args().nth(1).expect("Enter a file name");
 
-    let mut instructions = String::new();
-    File::open(file_name)?
-        .read_to_string(&mut instructions)
-        .expect("Failed to read input file");
+    let memory = load_program(&file_name)?;
 
-    init();
-    let memory = Memory::parse(&instructions);
+    if env::args().any(|a| a == "--analyze-checkpoint") {
+        let context = ExecutionContext::new(&memory);
+        match checkpoint::analyze(&context) {
+            Some(analysis) => println!("{}", analysis.report()),
+            None => println!("No security checkpoint found"),
+        }
+        return Ok(());
+    }
 
+    let export_file = env::args()
+        .skip_while(|a| a != "--export-transcript")
+        .nth(1);
+    let mut transcript = Transcript::new();
+    // The game prints a room or a message, then asks for the next command
     let mut context = ExecutionContext::new(&memory);
-    loop {
-        match execute_program(&mut context) {
-            ExecutionResult::Exit => break,
-            ExecutionResult::MoreInputNeeded => {
-                print!("Input: ");
-                stdout().flush().unwrap();
-                let mut input = String::new();
-                stdin().read_line(&mut input).unwrap();
-                context.input = input.replace("\r", "");
-                context.input_index = 0;
-            }
+    // Resumes a session from the core dump it left, in either form
+    if let Some(dump_file) = env::args().skip_while(|a| a != "--resume").nth(1) {
+        let mut bytes = vec![];
+        File::open(dump_file)?.read_to_end(&mut bytes)?;
+        let snapshot = if memory::is_image(&bytes) {
+            Snapshot::from_bytes(&bytes)?
+        } else {
+            Snapshot::deserialize(std::str::from_utf8(&bytes)?)?
+        };
+        context.restore(&snapshot);
+    }
+    context.breakpoints = Breakpoints::from_args(env::args());
+    let mut dialogue = Dialogue::new(context, Delimiter::InputRequest);
+    let mut output: Option<String> = dialogue.send(&[]);
+    let mut command: Option<String> = None;
+    let mut prompt = Prompt::new();
+    if let Some(script_file) = env::args().skip_while(|a| a != "--script").nth(1) {
+        let mut text = String::new();
+        File::open(script_file)?.read_to_string(&mut text)?;
+        prompt.script = ascii::script_commands(&text);
+    }
+    while let Some(text) = output {
+        transcript.record(command.as_deref(), &text);
+        if dialogue.ended() {
+            break;
         }
+
+        let line = match prompt.read_line("Input: ")? {
+            Some(line) => line,
+            // End of the session
+            None => break,
+        };
+        output = dialogue.send(&ascii::encode_line(&line));
+        command = Some(line);
+    }
+
+    if let Some(file_name) = export_file {
+        transcript.export(&file_name)?;
+        println!("Transcript exported to {}", file_name);
+    }
+
+    // State of the VM when the session ended, to resume it or inspect its memory:
+    // --core-dump writes its binary form, --core-dump-text its text form
+    if let Some(file_name) = env::args().skip_while(|a| a != "--core-dump").nth(1) {
+        let snapshot = dialogue.vm().snapshot();
+        File::create(&file_name)?.write_all(&snapshot.to_bytes())?;
+        println!("Core dump written to {}", file_name);
+    }
+    if let Some(file_name) = env::args().skip_while(|a| a != "--core-dump-text").nth(1) {
+        let snapshot = dialogue.vm().snapshot();
+        File::create(&file_name)?.write_all(snapshot.serialize().as_bytes())?;
+        println!("Core dump written to {}", file_name);
     }
 
     Ok(())
 }
 
-fn build_grid(chars: &Vec<i32>) -> HashMap<Pos, Cell> {
-    let mut map = HashMap::new();
-    let mut x = 0;
-    let mut y = 0;
+/// Reads the commands typed by the player, after the ones of the script given with
+/// `--script`. On a terminal, the keys are handled as they are typed, with line editing
+/// and a history; otherwise, the input is read line by line.
+struct Prompt {
+    script: VecDeque<String>,
+    #[cfg(all(unix, feature = "ui"))]
+    editor: Option<terminal::LineEditor>,
+}
+
+impl Prompt {
+    fn new() -> Prompt {
+        Prompt {
+            script: VecDeque::new(),
+            #[cfg(all(unix, feature = "ui"))]
+            editor: if std::io::IsTerminal::is_terminal(&stdin()) {
+                Some(terminal::LineEditor::new())
+            } else {
+                None
+            },
+        }
+    }
 
-    for v in chars {
-        match v {
-            10 => {
-                y += 1;
-                x = 0;
-            }
-            c => {
-                let cell = match *c as u8 as char {
-                    '.' => Cell::Empty,
-                    '#' => Cell::Scaffold,
-                    x => parse_robot_cell(x),
-                };
+    fn read_line(&mut self, prompt: &str) -> Result<Option<String>> {
+        if let Some(command) = self.script.pop_front() {
+            println!("{}{}", prompt, command);
+            return Ok(Some(command));
+        }
 
-                map.insert(Pos(x, y), cell);
-                x += 1;
-            }
+        #[cfg(all(unix, feature = "ui"))]
+        if let Some(editor) = &mut self.editor {
+            return Ok(editor.read_line(prompt)?);
         }
-    }
 
-    map
+        print!("{}", prompt);
+        stdout().flush()?;
+        let mut input = String::new();
+        if stdin().read_line(&mut input)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(input.trim_end_matches(&['\r', '\n'][..]).to_string()))
+    }
 }
 
-fn parse_robot_cell(ch: char) -> Cell {
-    let status = match ch {
-        '^' => RobotStatus::Up,
-        '>' => RobotStatus::Left,
-        'v' => RobotStatus::Down,
-        '<' => RobotStatus::Right,
-        'X' => RobotStatus::Falling,
-        x => panic!("Unknown char: {}", x),
-    };
-    Cell::Robot(status)
+/// Reads a program, either as comma-separated text or as a binary image
+fn load_program(file_name: &str) -> Result<Memory> {
+    let mut bytes = vec![];
+    File::open(file_name)?
+        .read_to_end(&mut bytes)
+        .expect("Failed to read input file");
+    if memory::is_image(&bytes) {
+        Ok(Memory::from_bytes(&bytes)?.0)
+    } else {
+        Ok(Memory::parse(std::str::from_utf8(&bytes)?.trim()))
+    }
 }
 
-fn draw_grid(chars: &Vec<i32>) {
-    clear();
-
-    for ch in chars {
-        match ch {
-            10 => println(&""),
-            c => print(&format!("{}", *c as u8 as char)),
+/// Writes the binary image of a text program, or the text of a binary image
+fn convert(input: &str, output: &str) -> Result<()> {
+    let mut bytes = vec![];
+    File::open(input)?.read_to_end(&mut bytes)?;
+    let converted = if memory::is_image(&bytes) {
+        let (memory, metadata) = Memory::from_bytes(&bytes)?;
+        for (key, value) in metadata {
+            println!("{}: {}", key, value);
         }
-    }
-    println("");
-    refresh();
-    sleep(Duration::from_millis(20));
+        memory.to_text().into_bytes()
+    } else {
+        Memory::parse(std::str::from_utf8(&bytes)?.trim()).to_bytes(&BTreeMap::new())
+    };
+    File::create(output)?.write_all(&converted)?;
+    println!("{} converted to {}", input, output);
+    Ok(())
 }
 
 #[derive(Clone)]
@@ -128,9 +203,12 @@ struct ExecutionContext {
     memory: Memory,
     ended: bool,
     relative_base: usize,
-    input: String,
-    input_index: usize,
-    output: Vec<i32>,
+    breakpoints: Breakpoints,
+    /// Watchpoint hit by the instruction being executed
+    watch_hit: Option<BreakReason>,
+    input: VecDeque<i64>,
+    /// Values output by the program, printed as it runs unless the tee is disabled
+    output: OutputSink,
 }
 
 impl ExecutionContext {
@@ -140,45 +218,241 @@ impl ExecutionContext {
             memory: memory.clone(),
             ended: false,
             relative_base: 0,
-            output: vec![],
-            input_index: 0,
-            input: String::new(),
+            breakpoints: Breakpoints::default(),
+            watch_hit: None,
+            output: OutputSink::new(true),
+            input: VecDeque::new(),
         }
     }
 
     fn read_input(&mut self) -> Option<i64> {
-        let index = self.input_index;
-        self.input_index += 1;
-        let res = self.input.chars().nth(index).map(|x| x as i64);
-
+        let res = self.input.pop_front();
         //println!("Reading input: {:?}", res);
         res
     }
 
     fn write_output(&mut self, value: i64) {
-        //println!("{}", value);
-        if value > 128 {
-            println!("Result: {}", value);
-            return;
+        self.output.push(value);
+    }
+
+    /// Queues a line of text as input, followed by a new line
+    fn send_line(&mut self, line: &str) {
+        self.input.extend(ascii::encode_line(line));
+    }
+}
+
+/// Saved state of a VM, to explore several branches from the same point without
+/// replaying all the input
+#[derive(Clone)]
+struct Snapshot(ExecutionContext);
+
+impl ExecutionContext {
+    fn snapshot(&self) -> Snapshot {
+        Snapshot(self.clone())
+    }
+
+    fn restore(&mut self, snapshot: &Snapshot) {
+        *self = snapshot.fork();
+    }
+
+    /// Tries a sequence on the VM until it succeeds, rolling the VM back to its current
+    /// state before each attempt. The VM is left in the state of the last attempt.
+    fn retry<T, E>(
+        &mut self,
+        policy: &RetryPolicy,
+        mut attempt: impl FnMut(&mut ExecutionContext, u32) -> std::result::Result<T, E>,
+    ) -> std::result::Result<T, RetryError<E>> {
+        let snapshot = self.snapshot();
+        retry(policy, |n| {
+            self.restore(&snapshot);
+            attempt(self, n)
+        })
+    }
+}
+
+impl Snapshot {
+    /// New VM starting from the saved state
+    fn fork(&self) -> ExecutionContext {
+        self.0.clone()
+    }
+
+    /// Text form of the snapshot: one `name: values` line per field, with the
+    /// memory written as `address=value` pairs
+    fn serialize(&self) -> String {
+        let context = &self.0;
+        let join = |values: Vec<String>| values.join(",");
+        format!(
+            "ip: {}\nrelative_base: {}\nended: {}\ninput: {}\noutput: {}\nmemory: {}\n",
+            context.ip,
+            context.relative_base,
+            context.ended,
+            join(context.input.iter().map(|v| v.to_string()).collect()),
+            join(
+                context
+                    .output
+                    .values()
+                    .iter()
+                    .map(|v| v.to_string())
+                    .collect()
+            ),
+            join(
+                context
+                    .memory
+                    .values()
+                    .iter()
+                    .map(|(a, v)| format!("{}={}", a, v))
+                    .collect()
+            ),
+        )
+    }
+
+    fn deserialize(text: &str) -> Result<Snapshot> {
+        let fields: HashMap<&str, &str> = text
+            .lines()
+            .filter_map(|l| {
+                let mut parts = l.splitn(2, ':');
+                Some((parts.next()?.trim(), parts.next()?.trim()))
+            })
+            .collect();
+
+        let mut memory = HashMap::new();
+        let values = fields
+            .get("memory")
+            .ok_or("Missing field in snapshot: memory")?;
+        for pair in values.split(',').filter(|v| !v.is_empty()) {
+            let mut parts = pair.splitn(2, '=');
+            let address = parts.next().unwrap_or("").parse()?;
+            let value = parts
+                .next()
+                .ok_or_else(|| format!("Invalid memory value: {}", pair))?
+                .parse()?;
+            memory.insert(address, value);
         }
-        print!("{}", value as u8 as char);
-        self.output.push(value as i32);
-        // if value == 10 && self.output[self.output.len() - 2] == 10 {
-        //     set_cursor_position(0, 0);
-        //     //sleep(Duration::from_millis(0));
-        // }
-        //self.output.clear();
+
+        Snapshot::from_fields(&fields, Memory::new(memory))
+    }
+
+    /// Binary form of the snapshot: the memory image, with the other fields in its
+    /// metadata
+    fn to_bytes(&self) -> Vec<u8> {
+        let context = &self.0;
+        let join = |values: Vec<String>| values.join(",");
+        let mut metadata = BTreeMap::new();
+        metadata.insert("ip".to_string(), context.ip.to_string());
+        metadata.insert(
+            "relative_base".to_string(),
+            context.relative_base.to_string(),
+        );
+        metadata.insert("ended".to_string(), context.ended.to_string());
+        metadata.insert(
+            "input".to_string(),
+            join(context.input.iter().map(|v| v.to_string()).collect()),
+        );
+        metadata.insert(
+            "output".to_string(),
+            join(
+                context
+                    .output
+                    .values()
+                    .iter()
+                    .map(|v| v.to_string())
+                    .collect(),
+            ),
+        );
+        context.memory.to_bytes(&metadata)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Snapshot> {
+        let (memory, metadata) = Memory::from_bytes(bytes)?;
+        let fields: HashMap<&str, &str> = metadata
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        Snapshot::from_fields(&fields, memory)
+    }
+
+    /// Snapshot of the VM whose fields other than the memory are given by name
+    fn from_fields(fields: &HashMap<&str, &str>, memory: Memory) -> Result<Snapshot> {
+        let field = |name: &str| {
+            fields
+                .get(name)
+                .copied()
+                .ok_or_else(|| format!("Missing field in snapshot: {}", name))
+        };
+        let list = |name: &str| -> Result<Vec<i64>> {
+            let values = field(name)?.split(',').filter(|v| !v.is_empty());
+            Ok(values
+                .map(|v| v.parse())
+                .collect::<std::result::Result<_, _>>()?)
+        };
+
+        Ok(Snapshot(ExecutionContext {
+            ip: field("ip")?.parse()?,
+            memory,
+            ended: field("ended")?.parse()?,
+            relative_base: field("relative_base")?.parse()?,
+            breakpoints: Breakpoints::default(),
+            watch_hit: None,
+            input: list("input")?.into_iter().collect(),
+            output: OutputSink::from_values(list("output")?, true),
+        }))
+    }
+}
+
+impl FromOutput for String {
+    fn from_output(output: &[i64]) -> String {
+        ascii::decode(output.iter().cloned())
+    }
+}
+
+impl Vm for ExecutionContext {
+    fn push_input(&mut self, values: &[i64]) {
+        self.input.extend(values);
+    }
+
+    fn run(&mut self) -> bool {
+        loop {
+            match execute_program(self) {
+                ExecutionResult::MoreInputNeeded => return true,
+                ExecutionResult::Exit => return false,
+                ExecutionResult::Breakpoint { ip, reason } => {
+                    println!(
+                        "[{} at ip {}; relative base {}]",
+                        reason, ip, self.relative_base
+                    );
+                    self.breakpoints.stopped(ip, &reason);
+                }
+            }
+        }
+    }
+
+    fn take_output(&mut self) -> Vec<i64> {
+        self.output.take()
     }
 }
 
 enum ExecutionResult {
     MoreInputNeeded,
     Exit,
+    /// Stopped at a breakpoint or watchpoint; `ip` is the address of the instruction that
+    /// is about to be executed, or that wrote to the watched cell
+    Breakpoint {
+        ip: usize,
+        reason: BreakReason,
+    },
 }
 
 fn execute_program(context: &mut ExecutionContext) -> ExecutionResult {
     // println!("Executing program; ip: {}", context.ip.get());
     loop {
+        let instruction_ip = context.ip;
+        if context.breakpoints.should_break(instruction_ip) {
+            return ExecutionResult::Breakpoint {
+                ip: instruction_ip,
+                reason: BreakReason::Instruction,
+            };
+        }
+
         match read_op_code(context) {
             (OpCode::Add, parameter_modes) => {
                 let (a, b, c) = extract_parameters3(context, parameter_modes);
@@ -206,27 +480,27 @@ fn execute_program(context: &mut ExecutionContext) -> ExecutionResult {
             }
             (OpCode::Output, parameter_modes) => {
                 let a = extract_parameter(context, parameter_modes);
-                let output = a.get(&context);
+                let output = a.get(context);
                 //println!("Output: {}", output);
                 context.write_output(output);
             }
             (OpCode::JumpIfTrue, parameter_modes) => {
                 let (a, b) = extract_parameters2(context, parameter_modes);
-                if a.get(&context) != 0 {
-                    let address = b.get(&context);
+                if a.get(context) != 0 {
+                    let address = b.get(context);
                     jump_to(&mut context.ip, address);
                 }
             }
             (OpCode::JumpIfFalse, parameter_modes) => {
                 let (a, b) = extract_parameters2(context, parameter_modes);
-                if a.get(&context) == 0 {
-                    let address = b.get(&context);
+                if a.get(context) == 0 {
+                    let address = b.get(context);
                     jump_to(&mut context.ip, address);
                 }
             }
             (OpCode::LessThan, parameter_modes) => {
                 let (a, b, c) = extract_parameters3(context, parameter_modes);
-                let value = if a.get(&context) < b.get(&context) {
+                let value = if a.get(context) < b.get(context) {
                     1
                 } else {
                     0
@@ -235,7 +509,7 @@ fn execute_program(context: &mut ExecutionContext) -> ExecutionResult {
             }
             (OpCode::Equals, parameter_modes) => {
                 let (a, b, c) = extract_parameters3(context, parameter_modes);
-                let value = if a.get(&context) == b.get(&context) {
+                let value = if a.get(context) == b.get(context) {
                     1
                 } else {
                     0
@@ -244,17 +518,25 @@ fn execute_program(context: &mut ExecutionContext) -> ExecutionResult {
             }
             (OpCode::AdjustRelativeBase, parameter_modes) => {
                 let a = extract_parameter(context, parameter_modes);
-                let adjustment = a.get(&context);
+                let adjustment = a.get(context);
                 context.relative_base = (context.relative_base as i64 + adjustment) as usize;
             }
             (OpCode::Exit, _) => {
-                println!("Exiting");
+                if context.output.tee() {
+                    println!("Exiting");
+                }
                 context.ended = true;
                 return ExecutionResult::Exit;
             }
         }
 
         // println!("Values: {:?}", memory);
+        if let Some(reason) = context.watch_hit.take() {
+            return ExecutionResult::Breakpoint {
+                ip: instruction_ip,
+                reason,
+            };
+        }
     }
 }
 
@@ -291,7 +573,7 @@ fn read_op_code(context: &mut ExecutionContext) -> (OpCode, u32) {
         8 => OpCode::Equals,
         9 => OpCode::AdjustRelativeBase,
         99 => OpCode::Exit,
-        x => panic!("Unknown op code: {}; ip: {}", x, context.ip),
+        x => vm_error(format!("Unknown op code: {}; ip: {}", x, context.ip)),
     };
 
     context.ip += 1;
@@ -330,7 +612,7 @@ fn get_parameter(context: &mut ExecutionContext, parameter_modes: &mut u32) -> P
         0 => ParameterMode::Position,
         1 => ParameterMode::Immediate,
         2 => ParameterMode::Relative,
-        x => panic!(format!("Incorrect parameter mode: {}", x)),
+        x => vm_error(format!("Incorrect parameter mode: {}", x)),
     };
     *parameter_modes /= 10;
 
@@ -352,7 +634,7 @@ enum Parameter {
     Reference(usize),
 }
 
-impl<'a> Parameter {
+impl Parameter {
     fn get(&self, context: &ExecutionContext) -> i64 {
         match self {
             Parameter::Reference(address) => context.memory[*address],
@@ -360,10 +642,20 @@ impl<'a> Parameter {
         }
     }
 
-    fn set(&self, value: i64, context: &mut ExecutionContext) -> () {
+    fn set(&self, value: i64, context: &mut ExecutionContext) {
         match self {
-            Parameter::Reference(address) => context.memory[*address] = value,
-            Parameter::ImmediateValue(value) => panic!(format!(
+            Parameter::Reference(address) => {
+                let old = context.memory[*address];
+                context.memory[*address] = value;
+                if context.breakpoints.is_watched(*address) {
+                    context.watch_hit = Some(BreakReason::Write {
+                        address: *address,
+                        old,
+                        new: value,
+                    });
+                }
+            }
+            Parameter::ImmediateValue(value) => vm_error(format!(
                 "Attempted to write value {} to an immediate parameter",
                 value
             )),
@@ -377,136 +669,120 @@ enum ParameterMode {
     Relative,
 }
 
-extern crate kernel32;
-extern crate winapi;
-
-#[cfg(windows)]
-use winapi::wincon::CONSOLE_SCREEN_BUFFER_INFO;
-#[cfg(windows)]
-use winapi::wincon::COORD;
-#[cfg(windows)]
-use winapi::wincon::SMALL_RECT;
-#[cfg(windows)]
-use winapi::DWORD;
-#[cfg(windows)]
-use winapi::HANDLE;
-#[cfg(windows)]
-use winapi::WORD;
-
-#[cfg(windows)]
-static mut CONSOLE_HANDLE: Option<HANDLE> = None;
-
-#[cfg(windows)]
-fn get_output_handle() -> HANDLE {
-    unsafe {
-        if let Some(handle) = CONSOLE_HANDLE {
-            return handle;
-        } else {
-            let handle = kernel32::GetStdHandle(winapi::STD_OUTPUT_HANDLE);
-            CONSOLE_HANDLE = Some(handle);
-            return handle;
+#[test]
+fn snapshot_restore() {
+    // Reads a value and outputs its double, until it reads 0
+    let program = Memory::parse("3,20,1005,20,8,99,0,0,1002,20,2,21,4,21,1105,1,0");
+    let mut context = ExecutionContext::new(&program);
+    context.push_input(&[3]);
+    assert!(context.run());
+    let snapshot = context.snapshot();
+
+    context.push_input(&[4]);
+    context.run();
+    assert_eq!(vec![6, 8], context.take_output());
+
+    context.restore(&snapshot);
+    context.push_input(&[5]);
+    context.run();
+    assert_eq!(vec![6, 10], context.take_output());
+}
+
+#[test]
+fn retry_rolls_back() {
+    let program = Memory::parse("3,20,1005,20,8,99,0,0,1002,20,2,21,4,21,1105,1,0");
+    let mut context = ExecutionContext::new(&program);
+    context.push_input(&[3]);
+    context.run();
+    context.take_output();
+
+    // The first 2 attempts fail after sending a value; each one starts from the same state
+    let result = context.retry(&RetryPolicy::new(5), |vm, n| {
+        vm.push_input(&[n as i64]);
+        vm.run();
+        match vm.take_output() {
+            output if n < 3 => Err(output),
+            output => Ok(output),
         }
-    }
-}
-
-#[cfg(windows)]
-fn get_buffer_info() -> winapi::CONSOLE_SCREEN_BUFFER_INFO {
-    let handle = get_output_handle();
-    if handle == winapi::INVALID_HANDLE_VALUE {
-        panic!("NoConsole")
-    }
-    let mut buffer = CONSOLE_SCREEN_BUFFER_INFO {
-        dwSize: COORD { X: 0, Y: 0 },
-        dwCursorPosition: COORD { X: 0, Y: 0 },
-        wAttributes: 0 as WORD,
-        srWindow: SMALL_RECT {
-            Left: 0,
-            Top: 0,
-            Right: 0,
-            Bottom: 0,
-        },
-        dwMaximumWindowSize: COORD { X: 0, Y: 0 },
-    };
-    unsafe {
-        kernel32::GetConsoleScreenBufferInfo(handle, &mut buffer);
-    }
-    buffer
-}
-
-#[cfg(windows)]
-fn init() {}
-
-#[cfg(unix)]
-fn init() {
-    ncurses::initscr();
-}
-
-#[cfg(unix)]
-fn clear() {
-    //ncurses::clear();
-    ncurses::mv(0, 0);
-}
-
-#[cfg(windows)]
-fn print(msg: &str) {
-    print!("{}", msg);
-}
-
-#[cfg(unix)]
-fn print(msg: &str) {
-    ncurses::printw(msg);
-}
-
-#[cfg(windows)]
-fn println(msg: &str) {
-    println!("{}", msg);
-}
-
-#[cfg(unix)]
-fn println(msg: &str) {
-    ncurses::addstr(msg);
-    ncurses::addstr("\n");
-}
-
-#[cfg(windows)]
-fn refresh() {}
-
-#[cfg(unix)]
-fn refresh() {
-    ncurses::refresh();
-}
-
-#[cfg(windows)]
-fn clear() {
-    let handle = get_output_handle();
-    if handle == winapi::INVALID_HANDLE_VALUE {
-        panic!("NoConsole")
-    }
-
-    let screen_buffer = get_buffer_info();
-    let console_size: DWORD = screen_buffer.dwSize.X as u32 * screen_buffer.dwSize.Y as u32;
-    let coord_screen = COORD { X: 0, Y: 0 };
-
-    let mut amount_chart_written: DWORD = 0;
-    unsafe {
-        kernel32::FillConsoleOutputCharacterW(
-            handle,
-            32 as winapi::WCHAR,
-            console_size,
-            coord_screen,
-            &mut amount_chart_written,
-        );
-    }
-    set_cursor_position(0, 0);
-}
-
-#[cfg(windows)]
-fn set_cursor_position(y: i16, x: i16) {
-    let handle = get_output_handle();
-    if handle == winapi::INVALID_HANDLE_VALUE {
-        panic!("NoConsole")
-    }
-    unsafe {
-        kernel32::SetConsoleCursorPosition(handle, COORD { X: x, Y: y });
-    }
+    });
+    assert_eq!(vec![6], result.unwrap());
+
+    let result: std::result::Result<(), _> = context.retry(&RetryPolicy::new(2), |vm, n| {
+        vm.push_input(&[10 * n as i64]);
+        vm.run();
+        Err(vm.take_output())
+    });
+    assert_eq!(vec![vec![20], vec![40]], result.unwrap_err().errors);
+}
+
+#[test]
+fn snapshot_serialization() {
+    let program = Memory::parse("3,20,1005,20,8,99,0,0,1002,20,2,21,4,21,1105,1,0");
+    let mut context = ExecutionContext::new(&program);
+    context.push_input(&[7]);
+    context.run();
+    let text = context.snapshot().serialize();
+
+    let mut restored = ExecutionContext::new(&Memory::parse("99"));
+    restored.restore(&Snapshot::deserialize(&text).unwrap());
+    assert_eq!(text, restored.snapshot().serialize());
+    restored.push_input(&[2]);
+    restored.run();
+    assert_eq!(vec![14, 4], restored.take_output());
+    assert!(Snapshot::deserialize("ip: 3").is_err());
+}
+
+#[test]
+fn breakpoints_and_watchpoints() {
+    // Reads a value and outputs its double, until it reads 0
+    let program = Memory::parse("3,20,1005,20,8,99,0,0,1002,20,2,21,4,21,1105,1,0");
+    let mut context = ExecutionContext::new(&program);
+    context.breakpoints.add_breakpoint(12);
+    context.breakpoints.add_watchpoint(21);
+    context.push_input(&[3]);
+
+    let result = execute_program(&mut context);
+    assert!(matches!(
+        result,
+        ExecutionResult::Breakpoint {
+            ip: 8,
+            reason: BreakReason::Write {
+                address: 21,
+                old: 0,
+                new: 6
+            }
+        }
+    ));
+    let result = execute_program(&mut context);
+    assert!(matches!(
+        result,
+        ExecutionResult::Breakpoint {
+            ip: 12,
+            reason: BreakReason::Instruction
+        }
+    ));
+    assert!(context.run());
+    assert_eq!(vec![6], context.take_output());
+}
+
+#[test]
+fn snapshot_binary_form() {
+    let program = Memory::parse("3,20,1005,20,8,99,0,0,1002,20,2,21,4,21,1105,1,0");
+    let mut context = ExecutionContext::new(&program);
+    context.push_input(&[7]);
+    context.run();
+    let bytes = context.snapshot().to_bytes();
+    assert!(memory::is_image(&bytes));
+
+    let mut restored = Snapshot::from_bytes(&bytes).unwrap().fork();
+    assert_eq!(
+        context.snapshot().serialize(),
+        restored.snapshot().serialize()
+    );
+    restored.push_input(&[2]);
+    restored.run();
+    assert_eq!(vec![14, 4], restored.take_output());
+
+    // A plain image has no registers
+    assert!(Snapshot::from_bytes(&program.to_bytes(&BTreeMap::new())).is_err());
 }
@@ -1,4 +1,5 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryInto;
 use std::ops::Index;
 use std::ops::IndexMut;
 
@@ -17,20 +18,112 @@ impl Memory {
             .split(",")
             .map(|x| {
                 x.parse::<i64>()
-                    .expect(format!("Failed to parse {}", x).as_str())
+                    .unwrap_or_else(|_| panic!("Failed to parse {}", x))
             })
             .enumerate()
             .collect();
 
         Memory::new(memory)
     }
+
+    /// Non-zero values, by increasing address
+    pub fn values(&self) -> Vec<(usize, i64)> {
+        let mut values: Vec<(usize, i64)> = self
+            ._values
+            .iter()
+            .filter(|(_, v)| **v != 0)
+            .map(|(a, v)| (*a, *v))
+            .collect();
+        values.sort();
+        values
+    }
+
+    /// Number of values up to the highest address set
+    pub fn image_len(&self) -> usize {
+        self._values.keys().max().map_or(0, |a| a + 1)
+    }
+
+    /// Comma-separated values, in the format of the puzzle inputs
+    pub fn to_text(&self) -> String {
+        (0..self.image_len())
+            .map(|a| self[a].to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// Binary image: the magic number, the number of values as a u64, the values as i64,
+    /// then optionally the length of the metadata as a u32 and the metadata as
+    /// `key=value` lines. All the numbers are little-endian.
+    pub fn to_bytes(&self, metadata: &BTreeMap<String, String>) -> Vec<u8> {
+        let len = self.image_len();
+        let mut bytes = Vec::with_capacity(IMAGE_MAGIC.len() + 8 * (len + 1));
+        bytes.extend_from_slice(IMAGE_MAGIC);
+        bytes.extend_from_slice(&(len as u64).to_le_bytes());
+        for address in 0..len {
+            bytes.extend_from_slice(&self[address].to_le_bytes());
+        }
+        if !metadata.is_empty() {
+            let text: String = metadata
+                .iter()
+                .map(|(k, v)| format!("{}={}\n", k, v))
+                .collect();
+            bytes.extend_from_slice(&(text.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(text.as_bytes());
+        }
+        bytes
+    }
+
+    /// Reads a binary image written by `to_bytes`, with its metadata
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Memory, BTreeMap<String, String>), String> {
+        if !is_image(bytes) {
+            return Err("Not a memory image".to_string());
+        }
+        let mut rest = &bytes[IMAGE_MAGIC.len()..];
+        let mut take = |count: usize| -> Result<&[u8], String> {
+            if rest.len() < count {
+                return Err("Truncated memory image".to_string());
+            }
+            let (taken, remaining) = rest.split_at(count);
+            rest = remaining;
+            Ok(taken)
+        };
+
+        let len = u64::from_le_bytes(take(8)?.try_into().unwrap()) as usize;
+        let mut values = HashMap::new();
+        for address in 0..len {
+            values.insert(address, i64::from_le_bytes(take(8)?.try_into().unwrap()));
+        }
+
+        let mut metadata = BTreeMap::new();
+        if let Ok(metadata_len) = take(4) {
+            let metadata_len = u32::from_le_bytes(metadata_len.try_into().unwrap());
+            let text = std::str::from_utf8(take(metadata_len as usize)?)
+                .map_err(|_| "Invalid metadata in memory image".to_string())?;
+            for line in text.lines() {
+                let mut parts = line.splitn(2, '=');
+                let key = parts.next().unwrap_or("");
+                let value = parts
+                    .next()
+                    .ok_or_else(|| format!("Invalid metadata: {}", line))?;
+                metadata.insert(key.to_string(), value.to_string());
+            }
+        }
+        Ok((Memory::new(values), metadata))
+    }
+}
+
+/// First bytes of a binary memory image
+pub const IMAGE_MAGIC: &[u8; 4] = b"ICIM";
+
+pub fn is_image(bytes: &[u8]) -> bool {
+    bytes.starts_with(IMAGE_MAGIC)
 }
 
 impl Index<usize> for Memory {
     type Output = i64;
 
     fn index(&self, index: usize) -> &Self::Output {
-        &self._values.get(&index).unwrap_or(&0)
+        self._values.get(&index).unwrap_or(&0)
     }
 }
 
@@ -39,3 +132,31 @@ impl IndexMut<usize> for Memory {
         self._values.entry(index).or_insert(0)
     }
 }
+
+#[test]
+fn binary_image_round_trip() {
+    let memory = Memory::parse("1,0,-3,99,0");
+    let bytes = memory.to_bytes(&BTreeMap::new());
+    assert_eq!(b"ICIM", &bytes[..4]);
+    assert_eq!(4 + 8 + 5 * 8, bytes.len());
+    assert_eq!([253, 255, 255, 255, 255, 255, 255, 255], bytes[28..36]);
+
+    let (loaded, metadata) = Memory::from_bytes(&bytes).unwrap();
+    assert_eq!("1,0,-3,99,0", loaded.to_text());
+    assert!(metadata.is_empty());
+
+    let mut metadata = BTreeMap::new();
+    metadata.insert("ip".to_string(), "2".to_string());
+    let bytes = memory.to_bytes(&metadata);
+    assert_eq!(metadata, Memory::from_bytes(&bytes).unwrap().1);
+}
+
+#[test]
+fn invalid_binary_images() {
+    assert!(Memory::from_bytes(b"1,2,3").is_err());
+    let bytes = Memory::parse("1,2,3").to_bytes(&BTreeMap::new());
+    assert_eq!(
+        Some("Truncated memory image".to_string()),
+        Memory::from_bytes(&bytes[..bytes.len() - 1]).err()
+    );
+}
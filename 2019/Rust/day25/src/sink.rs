@@ -0,0 +1,74 @@
+use crate::ascii;
+
+/// Where the values output by the VM go: they are all captured, and can also be echoed
+/// to the console as they are output
+#[derive(Clone, Debug)]
+pub struct OutputSink {
+    values: Vec<i64>,
+    tee: bool,
+}
+
+impl OutputSink {
+    pub fn new(tee: bool) -> OutputSink {
+        OutputSink::from_values(vec![], tee)
+    }
+
+    /// Sink holding values captured earlier, such as the ones of a snapshot
+    pub fn from_values(values: Vec<i64>, tee: bool) -> OutputSink {
+        OutputSink { values, tee }
+    }
+
+    /// Whether the values are echoed to the console
+    pub fn tee(&self) -> bool {
+        self.tee
+    }
+
+    pub fn set_tee(&mut self, tee: bool) {
+        self.tee = tee;
+    }
+
+    pub fn push(&mut self, value: i64) {
+        if self.tee {
+            print!("{}", echo_text(value));
+        }
+        self.values.push(value);
+    }
+
+    /// Values captured and not taken yet
+    pub fn values(&self) -> &[i64] {
+        &self.values
+    }
+
+    pub fn take(&mut self) -> Vec<i64> {
+        std::mem::take(&mut self.values)
+    }
+}
+
+/// Console text of an output value: ASCII characters are shown as they are, and the
+/// other values, which are the results, on their own line
+fn echo_text(value: i64) -> String {
+    match ascii::to_char(value) {
+        Some(c) => c.to_string(),
+        None => format!("Result: {}\n", value),
+    }
+}
+
+#[test]
+fn all_values_captured() {
+    for &tee in &[false, true] {
+        let mut sink = OutputSink::new(tee);
+        sink.push(72);
+        sink.push(10);
+        sink.push(2_424_308_736);
+        assert_eq!(&[72, 10, 2_424_308_736], sink.values());
+        assert_eq!(vec![72, 10, 2_424_308_736], sink.take());
+        assert!(sink.values().is_empty());
+    }
+}
+
+#[test]
+fn results_echoed_on_their_own_line() {
+    assert_eq!("H", echo_text(72));
+    assert_eq!("\n", echo_text(10));
+    assert_eq!("Result: 1234\n", echo_text(1234));
+}
@@ -0,0 +1,258 @@
+use crossterm::cursor::MoveToColumn;
+use crossterm::event::{read, Event, KeyCode, KeyModifiers};
+use crossterm::queue;
+use crossterm::style::Print;
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode, Clear, ClearType};
+use std::io::{stdout, Write};
+
+/// Keys the interactive adapter understands
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Key {
+    Char(char),
+    Enter,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    /// Ctrl-D on an empty line, or a failure to read the terminal
+    Eof,
+}
+
+/// Puts the terminal into raw mode while it lives, so the keystrokes are read as soon as
+/// they are typed instead of when the line is complete
+pub struct RawTerminal;
+
+impl RawTerminal {
+    pub fn new() -> std::io::Result<RawTerminal> {
+        enable_raw_mode().map_err(|e| std::io::Error::other(e.to_string()))?;
+        Ok(RawTerminal)
+    }
+
+    /// Waits for the next key press
+    pub fn read_key(&self) -> Key {
+        loop {
+            let event = match read() {
+                Ok(Event::Key(event)) => event,
+                Ok(_) => continue,
+                Err(_) => return Key::Eof,
+            };
+            let ctrl = event.modifiers.contains(KeyModifiers::CONTROL);
+            return match event.code {
+                KeyCode::Char('c') | KeyCode::Char('d') if ctrl => Key::Eof,
+                KeyCode::Char(c) => Key::Char(c),
+                KeyCode::Enter => Key::Enter,
+                KeyCode::Backspace => Key::Backspace,
+                KeyCode::Delete => Key::Delete,
+                KeyCode::Left => Key::Left,
+                KeyCode::Right => Key::Right,
+                KeyCode::Up => Key::Up,
+                KeyCode::Down => Key::Down,
+                KeyCode::Home => Key::Home,
+                KeyCode::End => Key::End,
+                _ => continue,
+            };
+        }
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        disable_raw_mode().ok();
+    }
+}
+
+/// Outcome of a key press on the line being edited
+#[derive(Debug, Eq, PartialEq)]
+pub enum Edit {
+    Pending,
+    Line(String),
+    Eof,
+}
+
+/// Line being typed at the prompt, with a cursor and the history of the previous lines.
+/// The game only reads a command once it's complete, so the keys are applied here and
+/// the line is sent on Enter.
+#[derive(Default)]
+pub struct LineEditor {
+    line: Vec<char>,
+    cursor: usize,
+    history: Vec<String>,
+    /// Position in the history while browsing it with the arrows
+    recalled: Option<usize>,
+}
+
+impl LineEditor {
+    pub fn new() -> LineEditor {
+        LineEditor::default()
+    }
+
+    pub fn line(&self) -> String {
+        self.line.iter().collect()
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn apply(&mut self, key: Key) -> Edit {
+        match key {
+            Key::Char(c) => {
+                self.line.insert(self.cursor, c);
+                self.cursor += 1;
+            }
+            Key::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.line.remove(self.cursor);
+            }
+            Key::Delete if self.cursor < self.line.len() => {
+                self.line.remove(self.cursor);
+            }
+            Key::Left => self.cursor = self.cursor.saturating_sub(1),
+            Key::Right => self.cursor = (self.cursor + 1).min(self.line.len()),
+            Key::Home => self.cursor = 0,
+            Key::End => self.cursor = self.line.len(),
+            Key::Up => {
+                let index = match self.recalled {
+                    Some(index) => index.saturating_sub(1),
+                    None if self.history.is_empty() => return Edit::Pending,
+                    None => self.history.len() - 1,
+                };
+                self.recall(Some(index));
+            }
+            Key::Down => match self.recalled {
+                Some(index) if index + 1 < self.history.len() => self.recall(Some(index + 1)),
+                Some(_) => self.recall(None),
+                None => (),
+            },
+            Key::Enter => {
+                let line = self.line();
+                if !line.is_empty() && self.history.last() != Some(&line) {
+                    self.history.push(line.clone());
+                }
+                self.recall(None);
+                return Edit::Line(line);
+            }
+            Key::Eof if self.line.is_empty() => return Edit::Eof,
+            Key::Backspace | Key::Delete | Key::Eof => (),
+        }
+        Edit::Pending
+    }
+
+    /// Replaces the line with an entry of the history, or clears it
+    fn recall(&mut self, index: Option<usize>) {
+        self.recalled = index;
+        self.line = match index {
+            Some(index) => self.history[index].chars().collect(),
+            None => vec![],
+        };
+        self.cursor = self.line.len();
+    }
+
+    /// Reads a line in raw mode, redrawing it after each key press. Returns None at the
+    /// end of the session.
+    pub fn read_line(&mut self, prompt: &str) -> std::io::Result<Option<String>> {
+        let terminal = RawTerminal::new()?;
+        let mut out = stdout();
+        queue!(out, Print(prompt)).ok();
+        out.flush()?;
+        loop {
+            let edit = self.apply(terminal.read_key());
+            let column = (prompt.chars().count() + self.cursor()) as u16;
+            queue!(
+                out,
+                MoveToColumn(0),
+                Print(prompt),
+                Print(self.line()),
+                Clear(ClearType::UntilNewLine),
+                MoveToColumn(column)
+            )
+            .ok();
+            match edit {
+                Edit::Pending => out.flush()?,
+                Edit::Line(line) => {
+                    queue!(out, Print("\r\n")).ok();
+                    out.flush()?;
+                    return Ok(Some(line));
+                }
+                Edit::Eof => {
+                    queue!(out, Print("\r\n")).ok();
+                    out.flush()?;
+                    return Ok(None);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+fn type_keys(editor: &mut LineEditor, keys: &[Key]) -> Edit {
+    let mut edit = Edit::Pending;
+    for key in keys {
+        edit = editor.apply(*key);
+    }
+    edit
+}
+
+#[cfg(test)]
+fn type_text(text: &str) -> Vec<Key> {
+    text.chars().map(Key::Char).collect()
+}
+
+#[test]
+fn backspace_and_arrows_edit_the_line() {
+    let mut editor = LineEditor::new();
+    let mut keys = type_text("nroth");
+    keys.extend(&[Key::Backspace, Key::Backspace, Key::Backspace]);
+    keys.extend(type_text("th"));
+    keys.extend(&[Key::Home, Key::Right, Key::Delete, Key::End, Key::Left]);
+    assert_eq!(Edit::Pending, type_keys(&mut editor, &keys));
+    assert_eq!(("nth".to_string(), 2), (editor.line(), editor.cursor()));
+
+    let keys = [Key::Left, Key::Char('o'), Key::Char('r'), Key::Enter];
+    assert_eq!(
+        Edit::Line("north".to_string()),
+        type_keys(&mut editor, &keys)
+    );
+    assert_eq!(("".to_string(), 0), (editor.line(), editor.cursor()));
+}
+
+#[test]
+fn arrows_browse_the_history() {
+    let mut editor = LineEditor::new();
+    for command in &["north", "take mug", "take mug", "inv"] {
+        let mut keys = type_text(command);
+        keys.push(Key::Enter);
+        type_keys(&mut editor, &keys);
+    }
+    assert_eq!(Edit::Pending, editor.apply(Key::Up));
+    assert_eq!("inv", editor.line());
+    type_keys(&mut editor, &[Key::Up, Key::Up, Key::Up]);
+    assert_eq!("north", editor.line());
+    editor.apply(Key::Down);
+    assert_eq!("take mug", editor.line());
+
+    let keys = [Key::Backspace, Key::Backspace, Key::Backspace, Key::Enter];
+    assert_eq!(
+        Edit::Line("take ".to_string()),
+        type_keys(&mut editor, &keys)
+    );
+    type_keys(&mut editor, &[Key::Up, Key::Down]);
+    assert_eq!("", editor.line());
+}
+
+#[test]
+fn eof_only_on_an_empty_line() {
+    let mut editor = LineEditor::new();
+    assert_eq!(
+        Edit::Pending,
+        type_keys(&mut editor, &[Key::Char('n'), Key::Eof])
+    );
+    assert_eq!(
+        Edit::Eof,
+        type_keys(&mut editor, &[Key::Backspace, Key::Eof])
+    );
+}
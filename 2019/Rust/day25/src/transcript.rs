@@ -0,0 +1,184 @@
+use crate::adventure::{parse_response, Response, Room};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Write;
+
+struct Entry {
+    command: Option<String>,
+    output: String,
+    /// Set when the droid has moved to a new room
+    map: Option<String>,
+}
+
+/// Records the adventure session, to export it as an HTML page
+pub struct Transcript {
+    entries: Vec<Entry>,
+    current_room: Option<String>,
+    /// Known rooms, with the room each of their doors leads to, if visited
+    rooms: BTreeMap<String, BTreeMap<String, Option<String>>>,
+}
+
+impl Transcript {
+    pub fn new() -> Transcript {
+        Transcript {
+            entries: vec![],
+            current_room: None,
+            rooms: BTreeMap::new(),
+        }
+    }
+
+    /// Records the output of the program following a command (or the start of the game)
+    pub fn record(&mut self, command: Option<&str>, output: &str) {
+        let room = match parse_response(output) {
+            Response::Room(room) | Response::Ejected(_, room) => Some(room),
+            _ => None,
+        };
+
+        let mut map = None;
+        if let Some(room) = room {
+            if self.current_room.as_ref() != Some(&room.name) {
+                self.visit(command, &room);
+                map = Some(self.render_map());
+            }
+        }
+
+        self.entries.push(Entry {
+            command: command.map(|c| c.trim().to_string()),
+            output: output.to_string(),
+            map,
+        });
+    }
+
+    fn visit(&mut self, command: Option<&str>, room: &Room) {
+        let doors = self.rooms.entry(room.name.clone()).or_default();
+        for door in &room.doors {
+            doors.entry(door.clone()).or_insert(None);
+        }
+
+        if let (Some(previous), Some(door)) = (self.current_room.take(), command) {
+            let door = door.trim();
+            if let Some(destination) = self.rooms.get_mut(&previous).and_then(|d| d.get_mut(door)) {
+                *destination = Some(room.name.clone());
+            }
+            if let Some(way_back) = opposite(door) {
+                if let Some(destination) = self
+                    .rooms
+                    .get_mut(&room.name)
+                    .and_then(|d| d.get_mut(way_back))
+                {
+                    *destination = Some(previous);
+                }
+            }
+        }
+        self.current_room = Some(room.name.clone());
+    }
+
+    fn render_map(&self) -> String {
+        let mut html = String::from("<ul class=\"map\">\n");
+        for (name, doors) in &self.rooms {
+            let class = if self.current_room.as_ref() == Some(name) {
+                " class=\"current\""
+            } else {
+                ""
+            };
+            let doors: Vec<String> = doors
+                .iter()
+                .map(|(door, destination)| match destination {
+                    Some(d) => format!("{} &rarr; {}", door, escape(d)),
+                    None => format!("{} &rarr; ?", door),
+                })
+                .collect();
+            html.push_str(&format!(
+                "<li{}><b>{}</b>: {}</li>\n",
+                class,
+                escape(name),
+                doors.join(", ")
+            ));
+        }
+        html.push_str("</ul>\n");
+        html
+    }
+
+    pub fn to_html(&self) -> String {
+        let mut toc = String::new();
+        let mut body = String::new();
+        for (i, entry) in self.entries.iter().enumerate() {
+            body.push_str(&format!("<section id=\"step{}\">\n", i));
+            if let Some(command) = &entry.command {
+                body.push_str(&format!(
+                    "<pre class=\"command\">&gt; {}</pre>\n",
+                    escape(command)
+                ));
+            }
+            body.push_str(&format!("<pre>{}</pre>\n", escape(entry.output.trim())));
+            if let Some(map) = &entry.map {
+                body.push_str(map);
+                if let Some(room) = self.room_name(i) {
+                    toc.push_str(&format!(
+                        "<li><a href=\"#step{}\">{}</a></li>\n",
+                        i,
+                        escape(&room)
+                    ));
+                }
+            }
+            body.push_str("</section>\n");
+        }
+
+        format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>Day 25 transcript</title>\n<style>\n\
+             nav {{ position: fixed; right: 0; top: 0; width: 20em; }}\n\
+             main {{ margin-right: 21em; }}\n\
+             .command {{ color: #0a0; font-weight: bold; }}\n\
+             .map {{ font-size: small; color: #666; }}\n\
+             .current {{ color: #000; }}\n\
+             </style>\n</head>\n<body>\n<nav>\n<h2>Rooms</h2>\n<ol>\n{}</ol>\n</nav>\n\
+             <main>\n{}</main>\n</body>\n</html>\n",
+            toc, body
+        )
+    }
+
+    fn room_name(&self, index: usize) -> Option<String> {
+        match parse_response(&self.entries[index].output) {
+            Response::Room(room) | Response::Ejected(_, room) => Some(room.name),
+            _ => None,
+        }
+    }
+
+    pub fn export(&self, file_name: &str) -> std::io::Result<()> {
+        File::create(file_name)?.write_all(self.to_html().as_bytes())
+    }
+}
+
+fn opposite(door: &str) -> Option<&'static str> {
+    match door {
+        "north" => Some("south"),
+        "south" => Some("north"),
+        "east" => Some("west"),
+        "west" => Some("east"),
+        _ => None,
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[test]
+fn export_session() {
+    let mut transcript = Transcript::new();
+    transcript.record(None, include_str!("../corpus/room.txt"));
+    transcript.record(Some("north\n"), include_str!("../corpus/items.txt"));
+    transcript.record(Some("take mug\n"), include_str!("../corpus/take.txt"));
+
+    let html = transcript.to_html();
+    assert!(html.contains("<a href=\"#step0\">Hull Breach</a>"));
+    assert!(html.contains("<a href=\"#step1\">Hallway</a>"));
+    assert!(!html.contains("#step2"));
+    assert!(html.contains("&gt; take mug"));
+    assert!(html.contains("north &rarr; Hallway"));
+    assert!(html.contains("south &rarr; Hull Breach"));
+}
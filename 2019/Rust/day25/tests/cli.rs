@@ -0,0 +1,29 @@
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+/// Time allowed for the binary to start and report a missing input, which it does
+/// without setting up the terminal. Generous, as it includes spawning the process.
+const STARTUP_BUDGET: Duration = Duration::from_millis(500);
+
+#[test]
+fn startup_leaves_the_terminal_alone() {
+    let start = Instant::now();
+    let output = Command::new(env!("CARGO_BIN_EXE_day25"))
+        .arg("missing_input.txt")
+        .current_dir(std::env::temp_dir())
+        .output()
+        .expect("Failed to run day25");
+    let elapsed = start.elapsed();
+
+    assert_eq!(Some(1), output.status.code());
+    assert!(
+        !output.stdout.contains(&b'\x1b'),
+        "Terminal initialized at startup"
+    );
+    assert!(
+        elapsed < STARTUP_BUDGET,
+        "Startup took {:?}, over the {:?} budget",
+        elapsed,
+        STARTUP_BUDGET
+    );
+}